@@ -0,0 +1,293 @@
+use core::cell::Cell;
+use embassy_futures::join::join;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_time::{with_timeout, Duration, Timer};
+use embedded_io_async::{Error, ErrorKind, Read, Write};
+use crate::bridge::rfc2217::{ComPort, Item, TelnetDecoder, IAC, MAX_VALUE, SET_BAUDRATE, SERVER_OFFSET};
+use crate::channel::callback_enum::CallbackEnum;
+use crate::channel::SocketChannel;
+
+pub mod rfc2217;
+
+/// serial bridge config
+#[derive(Copy, Clone, Debug)]
+pub struct BridgeConfig {
+    /// serial data is sent to the tcp connection when nothing is received from the serial port for gap,
+    /// or when `WC_SZ` bytes have been received<br />
+    /// zero sends the data of every serial read
+    pub gap: Duration,
+    /// the tcp side speaks telnet with RFC 2217 com port control, 0xFF data bytes are escaped,
+    /// and the baud rate can be set by the tcp peer through [SerialControl]
+    pub rfc2217: bool,
+}
+
+/// support default
+impl Default for BridgeConfig {
+    #[inline]
+    fn default() -> Self {
+        Self::new(Duration::from_millis(10))
+    }
+}
+
+/// custom method
+impl BridgeConfig {
+    /// create raw bridge config with packet gap
+    #[inline]
+    pub const fn new(gap: Duration) -> Self {
+        Self { gap, rfc2217: false }
+    }
+
+    /// enable RFC 2217
+    #[inline]
+    pub const fn rfc2217(mut self) -> Self {
+        self.rfc2217 = true;
+        self
+    }
+}
+
+/// serial port control of RFC 2217
+pub trait SerialControl {
+    /// the tcp peer requests the baud rate, returns the baud rate in effect, None if it can not be changed
+    async fn set_baud_rate(&mut self, baud: u32) -> Option<u32>;
+}
+
+/// serial port without control, the baud rate can not be changed
+pub struct NoControl;
+
+/// support serial control
+impl SerialControl for NoControl {
+    #[inline]
+    async fn set_baud_rate(&mut self, _baud: u32) -> Option<u32> {
+        None
+    }
+}
+
+/// serial side statistics of the bridge, the tcp side is [SocketChannel::stats]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct BridgeStats {
+    /// bytes read from the serial port
+    pub serial_in: u64,
+    /// bytes written to the serial port
+    pub serial_out: u64,
+    /// packets sent to the tcp connection
+    pub packets: u32,
+    /// bytes discarded because there was no tcp connection or the write channel was full
+    pub discarded: u64,
+    /// serial read and write errors
+    pub serial_errors: u32,
+    /// last serial error
+    pub last_serial_error: Option<ErrorKind>,
+    /// baud rate set by the tcp peer, None if never set
+    pub baud_rate: Option<u32>,
+}
+
+/// transparent bridge between a serial port and the tcp connection of a socket channel<br />
+/// the socket channel is served by a tcp server runner or a tcp client runner, the bridge replaces the callback runner,
+/// with the multi-connection tcp server, serial data is sent to all connections<br />
+/// share it to other tasks by reference to read [SerialBridge::stats]
+pub struct SerialBridge<'d, const RC_SZ: usize, const WC_SZ: usize> {
+    /// socket channel
+    socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
+    /// bridge config
+    config: BridgeConfig,
+    /// statistics
+    stats: Mutex<CriticalSectionRawMutex, Cell<BridgeStats>>,
+}
+
+/// custom method
+impl<'d, const RC_SZ: usize, const WC_SZ: usize> SerialBridge<'d, RC_SZ, WC_SZ> {
+    /// create serial bridge
+    #[inline]
+    pub const fn new(socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>, config: BridgeConfig) -> Self {
+        Self {
+            socket_channel,
+            config,
+            stats: Mutex::new(Cell::new(BridgeStats {
+                serial_in: 0,
+                serial_out: 0,
+                packets: 0,
+                discarded: 0,
+                serial_errors: 0,
+                last_serial_error: None,
+                baud_rate: None,
+            })),
+        }
+    }
+
+    /// statistics snapshot
+    #[inline]
+    pub fn stats(&self) -> BridgeStats {
+        self.stats.lock(|s| s.get())
+    }
+
+    /// reset statistics, the baud rate is kept
+    #[inline]
+    pub fn reset_stats(&self) {
+        self.update(|s| *s = BridgeStats { baud_rate: s.baud_rate, ..Default::default() });
+    }
+
+    /// update statistics
+    #[inline]
+    fn update(&self, f: impl FnOnce(&mut BridgeStats)) {
+        self.stats.lock(|s| {
+            let mut stats = s.get();
+            f(&mut stats);
+            s.set(stats);
+        });
+    }
+
+    /// run the bridge without baud rate control, more see [SerialBridge::run_with_control]
+    #[inline]
+    pub async fn run<R: Read, W: Write>(&self, rx: &mut R, tx: &mut W) {
+        self.run_with_control(rx, tx, &mut NoControl).await
+    }
+
+    /// run the bridge in both directions, never returns<br />
+    /// rx and tx are the halves of the serial port, a serial read is cancelled when the packet gap is reached,
+    /// so the read must not lose data when cancelled, for example a buffered or ring buffered uart<br />
+    /// serial data read while there is no tcp connection is discarded
+    pub async fn run_with_control<R: Read, W: Write, C: SerialControl>(&self, rx: &mut R, tx: &mut W, control: &mut C) {
+        join(self.serial_to_tcp(rx), self.tcp_to_serial(tx, control)).await;
+    }
+
+    /// read the serial port and send packets to the tcp connection
+    async fn serial_to_tcp<R: Read>(&self, rx: &mut R) {
+        let mut buf = [0; WC_SZ];
+        let mut len = 0;
+        loop {
+            let read = match len {
+                0 => rx.read(&mut buf).await.map(Some),
+                _ => with_timeout(self.config.gap, rx.read(&mut buf[len..])).await.map_or(Ok(None), |read| read.map(Some)),
+            };
+            let failed = match read {
+                Ok(Some(n)) => {
+                    self.update(|s| s.serial_in = s.serial_in.saturating_add(n as u64));
+                    len += n;
+                    // the packet is sent when the gap is reached, the buf is full or the serial port is closed
+                    if n > 0 && len < WC_SZ { continue; }
+                    n == 0
+                }
+                Ok(None) => false,
+                Err(e) => {
+                    self.serial_error(e.kind());
+                    true
+                }
+            };
+
+            match len {
+                // a closed or failed serial port returns at once, wait a gap so that the loop does not spin
+                0 if failed => Timer::after(self.config.gap).await,
+                0 => {}
+                _ => self.send_packet(&buf[..len]).await,
+            }
+            len = 0;
+        }
+    }
+
+    /// send serial data to the tcp connection, 0xFF is escaped for RFC 2217<br />
+    /// an escaped pair is never split, so answers of the other direction can not break it
+    async fn send_packet(&self, data: &[u8]) {
+        if !self.config.rfc2217 { return self.send(data).await; }
+
+        let mut out = [0; WC_SZ];
+        let mut len = 0;
+        for b in data {
+            let need = if *b == IAC { 2 } else { 1 };
+            if len + need > WC_SZ {
+                self.send(&out[..len]).await;
+                len = 0;
+            }
+            out[len..len + need].fill(*b);
+            len += need;
+        }
+        self.send(&out[..len]).await
+    }
+
+    /// send bytes to the tcp connection and count them
+    async fn send(&self, bytes: &[u8]) {
+        match self.socket_channel.write_channel.send_bytes(bytes).await {
+            Ok(_) => self.update(|s| s.packets = s.packets.saturating_add(1)),
+            Err(_) => self.update(|s| s.discarded = s.discarded.saturating_add(bytes.len() as u64)),
+        }
+    }
+
+    /// read the tcp connection and write the data to the serial port, telnet commands are answered for RFC 2217<br />
+    /// the msg is borrowed in place until it is written, so a slow serial port pushes back to the tcp connection
+    async fn tcp_to_serial<W: Write, C: SerialControl>(&self, tx: &mut W, control: &mut C) {
+        let mut telnet = TelnetDecoder::new();
+        loop {
+            let msg = self.socket_channel.read_channel.wait_read_ref().await;
+            match msg.callback_enum() {
+                CallbackEnum::Recv => {}
+                CallbackEnum::Conn | CallbackEnum::Disconnect => {
+                    telnet.reset();
+                    continue;
+                }
+                // errors and drops are counted by the socket channel statistics
+                _ => continue,
+            }
+            if !self.config.rfc2217 {
+                self.serial_write(tx, msg.as_bytes()).await;
+                continue;
+            }
+
+            let mut bytes = msg.as_bytes();
+            while !bytes.is_empty() {
+                let (used, item) = telnet.decode(bytes);
+                bytes = &bytes[used..];
+                match item {
+                    Some(Item::Data(data)) => self.serial_write(tx, data).await,
+                    Some(Item::Negotiate(command, option)) => {
+                        if let Some(answer) = rfc2217::answer(command, option) { self.socket_channel.write_channel.send_bytes(&answer).await.ok(); }
+                    }
+                    Some(Item::ComPort(com_port)) => self.com_port(com_port, control).await,
+                    None => {}
+                }
+            }
+        }
+    }
+
+    /// write data to the serial port and count it
+    async fn serial_write<W: Write>(&self, tx: &mut W, data: &[u8]) {
+        match tx.write_all(data).await {
+            Ok(_) => self.update(|s| s.serial_out = s.serial_out.saturating_add(data.len() as u64)),
+            Err(e) => self.serial_error(e.kind()),
+        }
+    }
+
+    /// record serial error
+    #[inline]
+    fn serial_error(&self, kind: ErrorKind) {
+        self.update(|s| {
+            s.serial_errors = s.serial_errors.saturating_add(1);
+            s.last_serial_error = Some(kind);
+        });
+    }
+
+    /// handle com port command and answer it<br />
+    /// the baud rate is set through the serial control, other commands are answered with the requested value without being applied
+    async fn com_port<C: SerialControl>(&self, com_port: ComPort, control: &mut C) {
+        // answers to commands of the access server are not answered again
+        if com_port.command >= SERVER_OFFSET { return; }
+
+        let baud;
+        let value = match (com_port.command, com_port.value_u32()) {
+            (SET_BAUDRATE, Some(requested)) => {
+                let applied = match requested {
+                    0 => None,
+                    _ => control.set_baud_rate(requested).await,
+                };
+                if applied.is_some() { self.update(|s| s.baud_rate = applied); }
+                // a query or a refused baud rate is answered with the current baud rate, 0 if unknown
+                baud = applied.or(self.stats().baud_rate).unwrap_or_default().to_be_bytes();
+                &baud[..]
+            }
+            _ => com_port.value(),
+        };
+
+        let mut out = [0; 6 + MAX_VALUE * 2];
+        let len = rfc2217::encode_com_port(com_port.command, value, &mut out);
+        self.socket_channel.write_channel.send_bytes(&out[..len]).await.ok();
+    }
+}
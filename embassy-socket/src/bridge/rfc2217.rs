@@ -0,0 +1,212 @@
+/// interpret as command
+pub const IAC: u8 = 255;
+/// refuse the peer option
+pub const DONT: u8 = 254;
+/// request the peer option
+pub const DO: u8 = 253;
+/// refuse the option
+pub const WONT: u8 = 252;
+/// offer the option
+pub const WILL: u8 = 251;
+/// subnegotiation begin
+pub const SB: u8 = 250;
+/// subnegotiation end
+pub const SE: u8 = 240;
+/// binary transmission option
+pub const BINARY: u8 = 0;
+/// com port control option, RFC 2217
+pub const COM_PORT_OPTION: u8 = 44;
+/// set baud rate com port command, the value is 4 bytes big endian, 0 queries the current baud rate
+pub const SET_BAUDRATE: u8 = 1;
+/// the access server answers a client com port command with the command plus this offset
+pub const SERVER_OFFSET: u8 = 100;
+/// max com port command value len, longer values are truncated
+pub const MAX_VALUE: usize = 8;
+
+/// com port command of the client
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ComPort {
+    /// command, for example [SET_BAUDRATE]
+    pub command: u8,
+    /// value buf, IAC IAC has been unescaped
+    value: [u8; MAX_VALUE],
+    /// value len
+    len: usize,
+}
+
+/// custom method
+impl ComPort {
+    /// command value
+    #[inline]
+    pub fn value(&self) -> &[u8] {
+        &self.value[..self.len]
+    }
+
+    /// value as 4 bytes big endian number, None if the value is not 4 bytes
+    #[inline]
+    pub fn value_u32(&self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.value().try_into().ok()?))
+    }
+}
+
+/// decoded telnet item
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Item<'a> {
+    /// serial data, IAC IAC has been unescaped
+    Data(&'a [u8]),
+    /// option negotiation, the command is [WILL], [WONT], [DO] or [DONT]
+    Negotiate(u8, u8),
+    /// com port command, more see [ComPort]
+    ComPort(ComPort),
+}
+
+/// decoder state
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+    /// serial data
+    Data,
+    /// after IAC
+    Iac,
+    /// after IAC and negotiation command
+    Option(u8),
+    /// in subnegotiation
+    Sub,
+    /// after IAC in subnegotiation
+    SubIac,
+}
+
+/// telnet stream decoder, split serial data from telnet commands<br />
+/// commands can be split over several tcp msg, the decoder keeps the incomplete command
+pub struct TelnetDecoder {
+    /// decoder state
+    state: State,
+    /// subnegotiation buf, option, command and value
+    sub: [u8; MAX_VALUE + 2],
+    /// subnegotiation len, bytes after the buf is full are dropped
+    sub_len: usize,
+}
+
+/// support default
+impl Default for TelnetDecoder {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// custom method
+impl TelnetDecoder {
+    /// create telnet decoder
+    #[inline]
+    pub const fn new() -> Self {
+        Self { state: State::Data, sub: [0; MAX_VALUE + 2], sub_len: 0 }
+    }
+
+    /// drop the incomplete command, generally called when connection changes
+    #[inline]
+    pub fn reset(&mut self) {
+        self.state = State::Data;
+        self.sub_len = 0;
+    }
+
+    /// decode bytes, returns the number of bytes used and the decoded item<br />
+    /// if the item is None, all bytes have been used<br />
+    /// call this method repeatedly with the remaining bytes until all bytes have been used
+    pub fn decode<'a>(&mut self, bytes: &'a [u8]) -> (usize, Option<Item<'a>>) {
+        for (i, b) in bytes.iter().enumerate() {
+            match self.state {
+                State::Data => {
+                    if *b == IAC {
+                        self.state = State::Iac;
+                        continue;
+                    }
+                    let len = bytes[i..].iter().position(|b| *b == IAC).unwrap_or(bytes.len() - i);
+                    return (i + len, Some(Item::Data(&bytes[i..i + len])));
+                }
+                State::Iac => {
+                    self.state = State::Data;
+                    match *b {
+                        // escaped 0xFF, the second IAC is the data byte
+                        IAC => return (i + 1, Some(Item::Data(&bytes[i..i + 1]))),
+                        WILL..=DONT => self.state = State::Option(*b),
+                        SB => {
+                            self.state = State::Sub;
+                            self.sub_len = 0;
+                        }
+                        // NOP, break and other commands without option are ignored
+                        _ => {}
+                    }
+                }
+                State::Option(command) => {
+                    self.state = State::Data;
+                    return (i + 1, Some(Item::Negotiate(command, *b)));
+                }
+                State::Sub => match *b {
+                    IAC => self.state = State::SubIac,
+                    _ => self.push_sub(*b),
+                },
+                State::SubIac => match *b {
+                    IAC => {
+                        self.state = State::Sub;
+                        self.push_sub(IAC);
+                    }
+                    SE => {
+                        self.state = State::Data;
+                        if let Some(com_port) = self.com_port() { return (i + 1, Some(Item::ComPort(com_port))); }
+                    }
+                    // malformed subnegotiation is dropped
+                    _ => self.state = State::Data,
+                },
+            }
+        }
+        (bytes.len(), None)
+    }
+
+    /// add subnegotiation byte
+    #[inline]
+    fn push_sub(&mut self, b: u8) {
+        if self.sub_len == self.sub.len() { return; }
+        self.sub[self.sub_len] = b;
+        self.sub_len += 1;
+    }
+
+    /// finished subnegotiation as com port command, None if it is another option
+    fn com_port(&self) -> Option<ComPort> {
+        let [option, command, value @ ..] = &self.sub[..self.sub_len] else { return None; };
+        if *option != COM_PORT_OPTION { return None; }
+
+        let mut com_port = ComPort { command: *command, value: [0; MAX_VALUE], len: value.len() };
+        com_port.value[..value.len()].copy_from_slice(value);
+        Some(com_port)
+    }
+}
+
+/// answer of the access server to the option negotiation, None is no answer<br />
+/// binary transmission and com port control are accepted, other options are refused,
+/// refusals of the peer are not answered so that negotiation can not loop
+pub fn answer(command: u8, option: u8) -> Option<[u8; 3]> {
+    let command = match (command, option) {
+        (WILL, BINARY | COM_PORT_OPTION) => DO,
+        (DO, BINARY) => WILL,
+        (WILL, _) => DONT,
+        (DO, _) => WONT,
+        _ => return None,
+    };
+    Some([IAC, command, option])
+}
+
+/// encode the com port answer of the access server, IAC in the value is escaped, returns the encoded len
+pub fn encode_com_port(command: u8, value: &[u8], out: &mut [u8; 6 + MAX_VALUE * 2]) -> usize {
+    out[..4].copy_from_slice(&[IAC, SB, COM_PORT_OPTION, command.saturating_add(SERVER_OFFSET)]);
+    let mut len = 4;
+    for b in value.iter().take(MAX_VALUE) {
+        out[len] = *b;
+        len += 1;
+        if *b == IAC {
+            out[len] = IAC;
+            len += 1;
+        }
+    }
+    out[len..len + 2].copy_from_slice(&[IAC, SE]);
+    len + 2
+}
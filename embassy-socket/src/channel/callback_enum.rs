@@ -1,7 +1,8 @@
+use embassy_time::Duration;
 use crate::err::SocketErr;
 
 /// callback enum
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub enum CallbackEnum {
     /// socket connection
     Conn,
@@ -11,6 +12,16 @@ pub enum CallbackEnum {
     Recv,
     /// socket error
     Err(SocketErr),
+    /// data was dropped because the read channel was full, the value is the number of dropped msg<br />
+    /// more see [crate::channel::overflow_policy::OverflowPolicy]
+    Dropped(u32),
+    /// tcp client will reconnect after delay, attempt starts from 1
+    Reconnect {
+        /// reconnect attempt
+        attempt: u32,
+        /// delay before reconnecting
+        delay: Duration,
+    },
 }
 
 /// support default
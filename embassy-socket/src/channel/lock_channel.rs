@@ -1,78 +1,176 @@
+use core::cell::{Cell, RefCell};
+use core::future::poll_fn;
+use core::task::Poll;
 use embassy_net::IpEndpoint;
+use embassy_sync::blocking_mutex::Mutex;
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
-use embassy_sync::rwlock::RwLock;
 use embassy_sync::zerocopy_channel::Channel;
 use crate::channel::callback_enum::CallbackEnum;
+use crate::channel::overflow_policy::OverflowPolicy;
 use crate::channel::socket_msg::SocketMsg;
 
-/// socket lock channel<br />
-/// N is channel bytes len
+/// socket lock channel, the write side storage<br />
+/// N is channel bytes len<br />
+/// the channel is only locked while copying msg, waiting is done through the channel waker,
+/// so senders and receivers never block each other
 pub struct LockChannel<'d, const N: usize> {
     /// channel
-    pub channel: RwLock<CriticalSectionRawMutex, Channel<'d, CriticalSectionRawMutex, SocketMsg<N>>>,
+    channel: Mutex<CriticalSectionRawMutex, RefCell<Channel<'d, CriticalSectionRawMutex, SocketMsg<N>>>>,
+    /// overflow policy
+    policy: Mutex<CriticalSectionRawMutex, Cell<OverflowPolicy>>,
+    /// total number of dropped msg
+    dropped: Mutex<CriticalSectionRawMutex, Cell<u32>>,
+    /// max number of msg
+    capacity: usize,
 }
 
 /// custom method
 impl<'d, const N: usize> LockChannel<'d, N> {
-    /// create read channel
+    /// create lock channel
     #[inline]
     pub fn new(buf: &'d mut [SocketMsg<N>]) -> Self {
-        Self { channel: RwLock::new(Channel::new(buf)) }
+        let capacity = buf.len();
+        Self {
+            channel: Mutex::new(RefCell::new(Channel::new(buf))),
+            policy: Mutex::new(Cell::new(OverflowPolicy::default())),
+            dropped: Mutex::new(Cell::new(0)),
+            capacity,
+        }
+    }
+
+    /// lock channel and call f
+    #[inline]
+    fn with<R>(&self, f: impl FnOnce(&mut Channel<'d, CriticalSectionRawMutex, SocketMsg<N>>) -> R) -> R {
+        self.channel.lock(|ch| f(&mut ch.borrow_mut()))
     }
 
     /// channel is empty
     #[inline]
-    pub async fn is_empty(&self) -> bool {
-        self.channel.read().await.is_empty()
+    pub fn is_empty(&self) -> bool {
+        self.with(|ch| ch.is_empty())
     }
 
     /// channel is full
     #[inline]
-    pub async fn is_full(&self) -> bool {
-        self.channel.read().await.is_full()
+    pub fn is_full(&self) -> bool {
+        self.with(|ch| ch.is_full())
+    }
+
+    /// max number of msg
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
     }
 
-    /// clear channel
+    /// clear channel, must not be called while a msg is borrowed, only the write channel clears
     #[inline]
-    pub async fn clear(&self) {
-        self.channel.write().await.clear();
+    pub fn clear(&self) {
+        self.with(|ch| ch.clear());
     }
 
-    /// send bytes data and set callback logic
-    pub async fn send_bytes(&self, bytes: &[u8], endpoint: Option<IpEndpoint>) {
-        // split send
-        let mut bytes_iter = bytes.chunks_exact(N);
-        for byte in bytes_iter.by_ref() {
-            // if full stop send, if send will be stop task
-            let mut ch = self.channel.write().await;
-            if ch.is_full() { return; }
+    /// get overflow policy
+    #[inline]
+    pub fn policy(&self) -> OverflowPolicy {
+        self.policy.lock(|p| p.get())
+    }
 
-            let mut sender = ch.split().0;
-            let socket_msg = sender.send().await;
-            socket_msg.bytes.copy_from_slice(byte);
-            socket_msg.len = N;
-            socket_msg.callback_enum = CallbackEnum::Recv;
-            if let Some(endpoint) = endpoint {
-                socket_msg.endpoint = endpoint;
+    /// set overflow policy
+    #[inline]
+    pub fn set_policy(&self, policy: OverflowPolicy) {
+        self.policy.lock(|p| p.set(policy));
+    }
+
+    /// total number of dropped msg
+    #[inline]
+    pub fn dropped(&self) -> u32 {
+        self.dropped.lock(|d| d.get())
+    }
+
+    /// reset dropped counter
+    #[inline]
+    pub fn reset_dropped(&self) {
+        self.dropped.lock(|d| d.set(0));
+    }
+
+    /// add dropped counter
+    fn add_dropped(&self) {
+        self.dropped.lock(|d| d.set(d.get().saturating_add(1)));
+    }
+
+    /// wait until the channel is not empty
+    pub async fn wait_not_empty(&self) {
+        poll_fn(|cx| self.with(|ch| ch.split().1.poll_receive(cx).map(|_| ()))).await
+    }
+
+    /// receive one msg, wait until the channel is not empty
+    pub async fn receive<R>(&self, mut f: impl FnMut(&SocketMsg<N>) -> R) -> R {
+        poll_fn(|cx| self.with(|ch| {
+            let mut recv = ch.split().1;
+            let Poll::Ready(msg) = recv.poll_receive(cx) else { return Poll::Pending; };
+            let r = f(msg);
+            recv.receive_done();
+            Poll::Ready(r)
+        })).await
+    }
+
+    /// send bytes data and set callback logic, returns the number of dropped msg<br />
+    /// bytes larger than N will be split into multiple msg
+    pub async fn send_bytes(&self, bytes: &[u8], endpoint: Option<IpEndpoint>) -> u32 {
+        let policy = self.policy();
+        let mut dropped = 0;
+        for byte in bytes.chunks(N) {
+            dropped += self.send_msg(CallbackEnum::Recv, byte, endpoint, policy).await;
+        }
+        dropped
+    }
+
+    /// send callback logic without data, this will never be dropped
+    #[inline]
+    pub async fn send_logic(&self, callback_enum: CallbackEnum, endpoint: Option<IpEndpoint>) {
+        self.send_msg(callback_enum, &[], endpoint, OverflowPolicy::Block).await;
+    }
+
+    /// send one msg according to the overflow policy, returns the number of dropped msg
+    async fn send_msg(&self, callback_enum: CallbackEnum, bytes: &[u8], endpoint: Option<IpEndpoint>, policy: OverflowPolicy) -> u32 {
+        let mut dropped = 0;
+        poll_fn(|cx| self.with(|ch| {
+            // if full and no room can be made, drop the new data or wait for the receiver
+            if ch.is_full() && !self.try_make_room(ch, policy, &mut dropped) && policy == OverflowPolicy::DropNewest {
+                return Poll::Ready(());
             }
+
+            let mut sender = ch.split().0;
+            let Poll::Ready(msg) = sender.poll_send(cx) else { return Poll::Pending; };
+            msg.bytes[..bytes.len()].copy_from_slice(bytes);
+            msg.len = bytes.len();
+            msg.callback_enum = callback_enum;
+            msg.endpoint = endpoint.unwrap_or(SocketMsg::<N>::NO_ENDPOINT);
             sender.send_done();
-            drop(ch);
-        }
+            Poll::Ready(())
+        })).await;
+        dropped
+    }
+
+    /// try to make room in a full channel, returns true if there is room now
+    fn try_make_room(&self, ch: &mut Channel<'d, CriticalSectionRawMutex, SocketMsg<N>>, policy: OverflowPolicy, dropped: &mut u32) -> bool {
+        match policy {
+            OverflowPolicy::Block => false,
+            OverflowPolicy::DropNewest => {
+                self.add_dropped();
+                *dropped += 1;
+                false
+            }
+            OverflowPolicy::DropOldest => {
+                let mut recv = ch.split().1;
+                // only data can be dropped, connection and error notifications must be kept
+                let can_drop = recv.try_receive().is_some_and(|msg| matches!(msg.callback_enum, CallbackEnum::Recv));
+                if !can_drop { return false; }
 
-        // send last bytes
-        let byte = bytes_iter.remainder();
-        if byte.is_empty() { return; }
-        // if full stop send, if send will be stop task
-        let mut ch = self.channel.write().await;
-        if ch.is_full() { return; }
-        let mut sender = ch.split().0;
-        let msg = sender.send().await;
-        msg.bytes[..byte.len()].copy_from_slice(byte);
-        msg.len = byte.len();
-        msg.callback_enum = CallbackEnum::Recv;
-        if let Some(endpoint) = endpoint {
-            msg.endpoint = endpoint;
+                recv.receive_done();
+                self.add_dropped();
+                *dropped += 1;
+                true
+            }
         }
-        sender.send_done();
     }
 }
@@ -1,12 +1,16 @@
-use crate::channel::read_channel::ReadChannel;
+use crate::channel::read_channel::{ReadBuf, ReadChannel};
 use crate::channel::socket_msg::SocketMsg;
+use crate::channel::stats::SocketStats;
 use crate::channel::write_channel::WriteChannel;
 
 pub(crate) mod lock_channel;
+pub(crate) mod ring_channel;
 pub mod write_channel;
 pub mod read_channel;
 pub mod socket_msg;
 pub mod callback_enum;
+pub mod overflow_policy;
+pub mod stats;
 
 /// socket channel
 pub struct SocketChannel<'d, const RC_SZ: usize, const WC_SZ: usize> {
@@ -18,9 +22,26 @@ pub struct SocketChannel<'d, const RC_SZ: usize, const WC_SZ: usize> {
 
 /// custom method
 impl<'d, const RC_SZ: usize, const WC_SZ: usize> SocketChannel<'d, RC_SZ, WC_SZ> {
-    /// create socket channel
+    /// create socket channel, more see [ReadBuf]
     #[inline]
-    pub fn new(read_buf: &'d mut [SocketMsg<RC_SZ>], write_buf: &'d mut [SocketMsg<WC_SZ>]) -> Self {
+    pub fn new<const M: usize>(read_buf: &'d mut ReadBuf<RC_SZ, M>, write_buf: &'d mut [SocketMsg<WC_SZ>]) -> Self {
         Self { read_channel: ReadChannel::new(read_buf), write_channel: WriteChannel::new(write_buf) }
     }
+
+    /// socket statistics snapshot, counted since creation or the last [SocketChannel::reset_stats]<br />
+    /// callbacks can hold a `&SocketChannel` to read it, generally used for field diagnostics and tuning `RC_SZ`/`WC_SZ`
+    pub fn stats(&self) -> SocketStats {
+        let mut stats = self.read_channel.stats();
+        stats.bytes_out = self.write_channel.written();
+        stats.read_dropped = self.read_channel.dropped();
+        stats.write_dropped = self.write_channel.dropped();
+        stats
+    }
+
+    /// reset statistics, include dropped counters, the current connection uptime is kept
+    #[inline]
+    pub fn reset_stats(&self) {
+        self.read_channel.reset_stats();
+        self.write_channel.reset_stats();
+    }
 }
@@ -0,0 +1,13 @@
+/// channel overflow policy, decide what to do when the channel is full
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// wait until the channel has free space<br />
+    /// for the read channel, this means the socket will stop reading until the callback consumes data
+    Block,
+    /// drop the oldest data in the channel, then send the new data<br />
+    /// connection and error notifications will never be dropped, if the oldest is one of them, wait like [OverflowPolicy::Block]
+    DropOldest,
+    /// drop the new data, this is the default policy
+    #[default]
+    DropNewest,
+}
@@ -1,121 +1,315 @@
 use embassy_net::IpEndpoint;
+use embassy_time::Duration;
 use crate::channel::callback_enum::CallbackEnum;
-use crate::channel::lock_channel::LockChannel;
+use crate::channel::overflow_policy::OverflowPolicy;
+use crate::channel::ring_channel::{MsgRef, RingChannel};
 use crate::channel::socket_msg::SocketMsg;
+use crate::channel::stats::{SocketStats, StatsCounter};
 use crate::err::SocketErr;
 
+/// head of a msg in the read channel
+#[derive(Copy, Clone, Debug)]
+pub struct MsgHead {
+    /// data offset
+    pub(crate) start: usize,
+    /// data len
+    pub(crate) len: usize,
+    /// read channel logic enum
+    pub(crate) callback_enum: CallbackEnum,
+    /// ip addr, more see [SocketMsg::endpoint]
+    pub(crate) endpoint: IpEndpoint,
+}
+
+/// support default
+impl Default for MsgHead {
+    #[inline]
+    fn default() -> Self {
+        Self { start: 0, len: 0, callback_enum: CallbackEnum::Disconnect, endpoint: SocketMsg::<0>::NO_ENDPOINT }
+    }
+}
+
+/// read channel buf, N bytes shared by up to M msg<br />
+/// one msg can use all N bytes, so that one socket read is one msg however large it is
+pub struct ReadBuf<const N: usize, const M: usize> {
+    /// msg data
+    bytes: [u8; N],
+    /// msg heads
+    heads: [MsgHead; M],
+}
+
+/// support default
+impl<const N: usize, const M: usize> Default for ReadBuf<N, M> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// custom method
+impl<const N: usize, const M: usize> ReadBuf<N, M> {
+    /// create read channel buf
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            bytes: [0; N],
+            heads: [MsgHead { start: 0, len: 0, callback_enum: CallbackEnum::Disconnect, endpoint: SocketMsg::<0>::NO_ENDPOINT }; M],
+        }
+    }
+}
+
 /// socket read channel <br />
-/// N is channel bytes len
+/// N is channel bytes len, one msg is at most N bytes
 pub struct ReadChannel<'d, const N: usize> {
     /// channel
-    channel: LockChannel<'d, N>,
+    channel: RingChannel<'d, N>,
+    /// statistics, updated by connection events
+    stats: StatsCounter,
 }
 
 /// custom method
 impl<'d, const N: usize> ReadChannel<'d, N> {
     /// create read channel
     #[inline]
-    pub fn new(buf: &'d mut [SocketMsg<N>]) -> Self {
-        Self { channel: LockChannel::new(buf) }
+    pub fn new<const M: usize>(buf: &'d mut ReadBuf<N, M>) -> Self {
+        Self::from_parts(&mut buf.bytes, &mut buf.heads)
     }
 
-    /// channel is empty
+    /// create read channel from msg data and msg heads, the number of heads is the max number of msg
+    #[inline]
+    pub fn from_parts(bytes: &'d mut [u8; N], heads: &'d mut [MsgHead]) -> Self {
+        Self { channel: RingChannel::new(bytes, heads), stats: StatsCounter::new() }
+    }
+
+    /// channel is empty, unreported dropped msg is also regarded as not empty
     #[inline]
     pub async fn is_empty(&self) -> bool {
-        self.channel.is_empty().await
+        self.channel.unreported() == 0 && self.channel.is_empty()
     }
 
     /// channel is full
     #[inline]
     pub async fn is_full(&self) -> bool {
-        self.channel.is_full().await
+        self.channel.is_full()
+    }
+
+    /// set overflow policy, default is [OverflowPolicy::DropNewest]<br />
+    /// connection and error notifications are never dropped
+    #[inline]
+    pub fn overflow_policy(&self, policy: OverflowPolicy) {
+        self.channel.set_policy(policy);
+    }
+
+    /// total number of msg dropped because the channel was full
+    #[inline]
+    pub fn dropped(&self) -> u32 {
+        self.channel.dropped()
+    }
+
+    /// reset dropped counter
+    #[inline]
+    pub fn reset_dropped(&self) {
+        self.channel.reset_dropped();
+    }
+
+    /// statistics of the read side, dropped and bytes_out are not included<br />
+    /// more see [crate::channel::SocketChannel::stats]
+    #[inline]
+    pub(crate) fn stats(&self) -> SocketStats {
+        self.stats.get()
+    }
+
+    /// reset statistics and dropped counter
+    #[inline]
+    pub(crate) fn reset_stats(&self) {
+        self.stats.reset();
+        self.channel.reset_dropped();
+    }
+
+    /// record connect attempt
+    #[inline]
+    pub(crate) fn connect_attempt(&self) {
+        self.stats.connect_attempt();
+    }
+
+    /// record rtt sample, used by protocols with request and response<br />
+    /// more see [SocketStats::rtt]
+    #[inline]
+    pub fn rtt_sample(&self, rtt: Duration) {
+        self.stats.rtt_sample(rtt);
     }
 
     /// change callback logic and addr
+    #[inline]
     pub(crate) async fn callback_logic_addr(&self, callback_enum: CallbackEnum, endpoint: IpEndpoint) {
-        let mut ch = self.channel.channel.write().await;
-        let mut send = ch.split().0;
-        let socket_msg = send.send().await;
-        socket_msg.callback_enum = callback_enum;
-        socket_msg.endpoint = endpoint;
-        send.send_done();
+        self.channel.send_logic(callback_enum, Some(endpoint)).await
     }
 
     /// change callback logic
+    #[inline]
     pub(crate) async fn callback_logic(&self, callback_enum: CallbackEnum) {
-        let mut ch = self.channel.channel.write().await;
-        let mut send = ch.split().0;
-        let socket_msg = send.send().await;
-        socket_msg.callback_enum = callback_enum;
-        send.send_done();
+        self.channel.send_logic(callback_enum, None).await
     }
 
     /// socket connection
     #[inline]
     pub async fn conn(&self) {
+        self.stats.connected();
         self.callback_logic(CallbackEnum::Conn).await
     }
 
     /// socket connection
     #[inline]
     pub async fn conn_addr(&self, endpoint: IpEndpoint) {
+        self.stats.connected();
         self.callback_logic_addr(CallbackEnum::Conn, endpoint).await
     }
 
     /// socket dis connection
     #[inline]
     pub async fn dis_conn(&self) {
+        self.stats.disconnected();
         self.callback_logic(CallbackEnum::Disconnect).await
     }
 
     /// socket dis connection
     #[inline]
     pub async fn dis_conn_addr(&self, endpoint: IpEndpoint) {
+        self.stats.disconnected();
         self.callback_logic_addr(CallbackEnum::Disconnect, endpoint).await
     }
 
     /// socket error
     #[inline]
     pub async fn err(&self, socket_err: SocketErr) {
+        self.stats.error(socket_err);
         self.callback_logic(CallbackEnum::Err(socket_err)).await
     }
 
+    /// socket error of the connection of the endpoint
+    #[inline]
+    pub(crate) async fn err_addr(&self, socket_err: SocketErr, endpoint: IpEndpoint) {
+        self.stats.error(socket_err);
+        self.callback_logic_addr(CallbackEnum::Err(socket_err), endpoint).await
+    }
+
+    /// socket reconnect
+    #[inline]
+    pub async fn reconnect(&self, attempt: u32, delay: Duration) {
+        self.callback_logic(CallbackEnum::Reconnect { attempt, delay }).await
+    }
+
     /// socket recv
     #[inline]
     pub async fn recv(&self, bytes: &[u8]) {
-        self.channel.send_bytes(bytes, None).await
+        self.stats.add_in(bytes.len());
+        self.channel.send_bytes(bytes, None).await;
     }
 
     /// socket recv
     #[inline]
     pub async fn recv_addr(&self, bytes: &[u8], endpoint: IpEndpoint) {
-        self.channel.send_bytes(bytes, Some(endpoint)).await
+        self.stats.add_in(bytes.len());
+        self.channel.send_bytes(bytes, Some(endpoint)).await;
     }
 
-    /// read data, returns read results, true=success, false=fail
-    pub async fn read(&self, socket_msg: &mut SocketMsg<N>) -> bool {
-        let mut ch = self.channel.channel.write().await;
-        // fixed the issue where receive().await may be locked all the time, resulting in the channel being unable to be rewritten
-        if ch.is_empty() { return false; }
-        let mut recv = ch.split().1;
-        let msg = recv.receive().await;
-        *socket_msg = *msg;
-        recv.receive_done();
+    /// copy bytes to the channel as one msg without waiting, returns the number of bytes used<br />
+    /// all bytes are used if they fit, otherwise the largest free part of the channel is used<br />
+    /// returns 0 if the channel is full and no room can be made, then wait with [ReadChannel::wait_room]<br />
+    /// used by the runners to read socket data in place, so that one socket read is exactly one msg
+    #[inline]
+    pub fn try_recv(&self, bytes: &[u8]) -> usize {
+        self.try_recv_addr(bytes, None)
+    }
+
+    /// copy bytes with endpoint to the channel without waiting, more see [ReadChannel::try_recv]
+    pub fn try_recv_addr(&self, bytes: &[u8], endpoint: Option<IpEndpoint>) -> usize {
+        let used = self.channel.try_send_bytes(bytes, endpoint);
+        self.stats.add_in(used);
+        used
+    }
+
+    /// wait until the channel is not full
+    #[inline]
+    pub async fn wait_room(&self) {
+        self.channel.wait_not_full().await
+    }
+
+    /// if any msg was dropped, read [CallbackEnum::Dropped] first, returns true if read
+    fn read_dropped(&self, socket_msg: &mut SocketMsg<N>) -> bool {
+        let dropped = self.channel.take_unreported();
+        if dropped == 0 { return false; }
+
+        socket_msg.len = 0;
+        socket_msg.callback_enum = CallbackEnum::Dropped(dropped);
         true
     }
 
-    /// read data and addr, returns read results, true=success, false=fail
+    /// wait until there is data to read, this method will not take data
+    pub async fn wait(&self) {
+        if self.channel.unreported() > 0 { return; }
+        self.channel.wait_not_empty().await
+    }
+
+    /// read data, returns read results, true=success, false=channel is empty
+    #[inline]
+    pub async fn read(&self, socket_msg: &mut SocketMsg<N>) -> bool {
+        self.read_addr(socket_msg).await
+    }
+
+    /// read data and addr, returns read results, true=success, false=channel is empty
     pub async fn read_addr(&self, socket_msg: &mut SocketMsg<N>) -> bool {
-        let mut ch = self.channel.channel.write().await;
-        // fixed the issue where receive().await may be locked all the time, resulting in the channel being unable to be rewritten
-        if ch.is_empty() { return false; }
-        let mut recv = ch.split().1;
-        let msg = recv.receive().await;
-        socket_msg.bytes.copy_from_slice(&msg.bytes);
-        socket_msg.len = msg.len;
-        socket_msg.callback_enum = msg.callback_enum;
-        socket_msg.endpoint = msg.endpoint;
-        recv.receive_done();
-        true
+        if self.read_dropped(socket_msg) { return true; }
+
+        self.channel.try_receive(|head, bytes| socket_msg.copy_head(head, bytes)).is_some()
+    }
+
+    /// wait and read data and addr, wait until there is data to read
+    pub async fn wait_read(&self, socket_msg: &mut SocketMsg<N>) {
+        if self.read_dropped(socket_msg) { return; }
+
+        self.channel.receive(|head, bytes| socket_msg.copy_head(head, bytes)).await
+    }
+
+    /// wait and borrow the msg in place, wait until there is data to read<br />
+    /// the msg is not copied, it stays in the channel and is released when the returned ref is dropped,
+    /// so drop it as soon as the msg is handled, the runner can not reuse the slot while it is borrowed
+    pub async fn wait_read_ref(&self) -> ReadRef<'_, 'd, N> {
+        let dropped = self.channel.take_unreported();
+        if dropped > 0 { return ReadRef { msg: None, dropped }; }
+
+        ReadRef { msg: Some(self.channel.receive_ref().await), dropped: 0 }
+    }
+}
+
+/// msg borrowed in place from the read channel, released when dropped<br />
+/// more see [ReadChannel::wait_read_ref]
+pub struct ReadRef<'a, 'd, const N: usize> {
+    /// borrowed msg, None is dropped notification
+    msg: Option<MsgRef<'a, 'd, N>>,
+    /// number of dropped msg
+    dropped: u32,
+}
+
+/// custom method
+impl<const N: usize> ReadRef<'_, '_, N> {
+    /// callback logic
+    #[inline]
+    pub fn callback_enum(&self) -> CallbackEnum {
+        match &self.msg {
+            Some(msg) => msg.head().callback_enum,
+            None => CallbackEnum::Dropped(self.dropped),
+        }
+    }
+
+    /// real bytes data
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.msg.as_ref().map(|msg| msg.as_bytes()).unwrap_or_default()
+    }
+
+    /// endpoint, more see [SocketMsg::endpoint]
+    #[inline]
+    pub fn endpoint(&self) -> IpEndpoint {
+        self.msg.as_ref().map(|msg| msg.head().endpoint).unwrap_or(SocketMsg::<N>::NO_ENDPOINT)
     }
 }
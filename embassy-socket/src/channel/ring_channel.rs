@@ -0,0 +1,415 @@
+use core::cell::{Cell, RefCell};
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+use core::task::{Context, Poll};
+use embassy_net::IpEndpoint;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::waitqueue::MultiWakerRegistration;
+use crate::channel::callback_enum::CallbackEnum;
+use crate::channel::overflow_policy::OverflowPolicy;
+use crate::channel::read_channel::MsgHead;
+use crate::channel::socket_msg::SocketMsg;
+
+/// ring of variable length msg, the data of one msg is always contiguous<br />
+/// the msg data is kept as a raw pointer, every msg data slice is made over its own range,
+/// so that filling the free part never reborrows the data of the borrowed front msg
+struct Ring<'d, const N: usize> {
+    /// start of the msg data, N bytes
+    bytes: NonNull<u8>,
+    /// the msg data is borrowed for 'd
+    _bytes: PhantomData<&'d mut [u8; N]>,
+    /// msg heads, in order from front
+    heads: &'d mut [MsgHead],
+    /// index of the front head
+    front: usize,
+    /// number of msg
+    count: usize,
+    /// offset of the next msg data
+    tail: usize,
+    /// the newer msg data continues from the start of bytes
+    wrapped: bool,
+    /// the front msg is borrowed by [RingChannel::receive_ref]
+    borrowed: bool,
+    /// wakers of the receivers, also woken when the borrowed msg is released
+    receivers: MultiWakerRegistration<4>,
+    /// wakers of the senders waiting for room
+    senders: MultiWakerRegistration<4>,
+}
+
+// the msg data pointer comes from `&'d mut [u8; N]`, which is Send
+/// support send
+unsafe impl<const N: usize> Send for Ring<'_, N> {}
+
+/// custom method
+impl<const N: usize> Ring<'_, N> {
+    /// front msg head
+    #[inline]
+    fn front(&self) -> Option<&MsgHead> {
+        (self.count > 0).then(|| &self.heads[self.front])
+    }
+
+    /// free contiguous data at tail and at the start of bytes
+    fn free(&self) -> (usize, usize) {
+        match self.front() {
+            None => (N - self.tail, 0),
+            Some(front) if self.wrapped => (front.start - self.tail, 0),
+            Some(front) => (N - self.tail, front.start),
+        }
+    }
+
+    /// the largest msg data that can be pushed now
+    #[inline]
+    fn room(&self) -> usize {
+        if self.count == self.heads.len() { return 0; }
+        let (tail, start) = self.free();
+        tail.max(start)
+    }
+
+    /// no msg can be pushed
+    #[inline]
+    fn is_full(&self) -> bool {
+        self.count == self.heads.len()
+    }
+
+    /// push msg with len bytes data, returns the data to fill, None if it does not fit
+    fn push(&mut self, len: usize, callback_enum: CallbackEnum, endpoint: Option<IpEndpoint>) -> Option<&mut [u8]> {
+        if self.is_full() { return None; }
+        let (tail, start) = self.free();
+        let offset = match len {
+            _ if len <= tail => self.tail,
+            _ if len <= start => {
+                self.wrapped = true;
+                0
+            }
+            _ => return None,
+        };
+
+        let index = (self.front + self.count) % self.heads.len();
+        self.heads[index] = MsgHead { start: offset, len, callback_enum, endpoint: endpoint.unwrap_or(SocketMsg::<N>::NO_ENDPOINT) };
+        self.count += 1;
+        self.tail = offset + len;
+        self.receivers.wake();
+        // the range is free space, no msg data slice overlaps it
+        Some(unsafe { core::slice::from_raw_parts_mut(self.bytes.as_ptr().add(offset), len) })
+    }
+
+    /// pop the front msg
+    fn pop(&mut self) {
+        let Some(&old) = self.front() else { return; };
+        self.front = (self.front + 1) % self.heads.len();
+        self.count -= 1;
+        match self.front() {
+            // everything is free, start from the beginning again
+            None => {
+                self.tail = 0;
+                self.wrapped = false;
+            }
+            // the front has crossed the wrap point
+            Some(front) if front.start < old.start => self.wrapped = false,
+            Some(_) => {}
+        }
+        self.senders.wake();
+    }
+
+    /// msg data of the head, the data is not written until the msg is popped
+    #[inline]
+    fn msg_bytes(&self, head: &MsgHead) -> NonNull<[u8]> {
+        // head.start + head.len <= N, checked by push
+        NonNull::slice_from_raw_parts(unsafe { self.bytes.add(head.start) }, head.len)
+    }
+
+    /// front msg data
+    #[inline]
+    fn front_bytes(&self, head: &MsgHead) -> &[u8] {
+        unsafe { self.msg_bytes(head).as_ref() }
+    }
+}
+
+/// socket ring channel, the read side storage<br />
+/// N is channel bytes len, one msg can use all of it, so that one socket read is stored as one msg<br />
+/// the channel is only locked while copying msg, so senders and receivers never block each other
+pub(crate) struct RingChannel<'d, const N: usize> {
+    /// ring
+    ring: Mutex<CriticalSectionRawMutex, RefCell<Ring<'d, N>>>,
+    /// overflow policy
+    policy: Mutex<CriticalSectionRawMutex, Cell<OverflowPolicy>>,
+    /// total number of dropped msg
+    dropped: Mutex<CriticalSectionRawMutex, Cell<u32>>,
+    /// number of dropped msg that have not been reported
+    unreported: Mutex<CriticalSectionRawMutex, Cell<u32>>,
+}
+
+/// custom method
+impl<'d, const N: usize> RingChannel<'d, N> {
+    /// create ring channel, heads is the max number of msg
+    #[inline]
+    pub fn new(bytes: &'d mut [u8; N], heads: &'d mut [MsgHead]) -> Self {
+        assert!(!heads.is_empty(), "read channel needs at least one msg head");
+        Self {
+            ring: Mutex::new(RefCell::new(Ring {
+                bytes: NonNull::from(bytes).cast(),
+                _bytes: PhantomData,
+                heads,
+                front: 0,
+                count: 0,
+                tail: 0,
+                wrapped: false,
+                borrowed: false,
+                receivers: MultiWakerRegistration::new(),
+                senders: MultiWakerRegistration::new(),
+            })),
+            policy: Mutex::new(Cell::new(OverflowPolicy::default())),
+            dropped: Mutex::new(Cell::new(0)),
+            unreported: Mutex::new(Cell::new(0)),
+        }
+    }
+
+    /// lock ring and call f
+    #[inline]
+    fn with<R>(&self, f: impl FnOnce(&mut Ring<'d, N>) -> R) -> R {
+        self.ring.lock(|ring| f(&mut ring.borrow_mut()))
+    }
+
+    /// channel is empty
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.with(|ring| ring.count == 0)
+    }
+
+    /// channel is full, no msg data can be pushed
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.with(|ring| ring.room() == 0)
+    }
+
+    /// get overflow policy
+    #[inline]
+    pub fn policy(&self) -> OverflowPolicy {
+        self.policy.lock(|p| p.get())
+    }
+
+    /// set overflow policy
+    #[inline]
+    pub fn set_policy(&self, policy: OverflowPolicy) {
+        self.policy.lock(|p| p.set(policy));
+    }
+
+    /// total number of dropped msg
+    #[inline]
+    pub fn dropped(&self) -> u32 {
+        self.dropped.lock(|d| d.get())
+    }
+
+    /// reset dropped counter
+    #[inline]
+    pub fn reset_dropped(&self) {
+        self.dropped.lock(|d| d.set(0));
+        self.unreported.lock(|u| u.set(0));
+    }
+
+    /// number of dropped msg that have not been reported
+    #[inline]
+    pub fn unreported(&self) -> u32 {
+        self.unreported.lock(|u| u.get())
+    }
+
+    /// take the number of dropped msg that have not been reported, and mark them reported
+    #[inline]
+    pub fn take_unreported(&self) -> u32 {
+        self.unreported.lock(|u| u.replace(0))
+    }
+
+    /// add dropped counter
+    fn add_dropped(&self) {
+        self.dropped.lock(|d| d.set(d.get().saturating_add(1)));
+        self.unreported.lock(|u| u.set(u.get().saturating_add(1)));
+    }
+
+    /// poll the front msg that is not borrowed, register the waker if there is none
+    fn poll_front(ring: &mut Ring<'d, N>, cx: &mut Context) -> Option<MsgHead> {
+        match ring.front() {
+            Some(head) if !ring.borrowed => Some(*head),
+            _ => {
+                ring.receivers.register(cx.waker());
+                None
+            }
+        }
+    }
+
+    /// wait until the channel is not empty
+    pub async fn wait_not_empty(&self) {
+        poll_fn(|cx| self.with(|ring| {
+            if ring.count > 0 { return Poll::Ready(()); }
+            ring.receivers.register(cx.waker());
+            Poll::Pending
+        })).await
+    }
+
+    /// receive one msg, wait until the channel is not empty
+    pub async fn receive<R>(&self, mut f: impl FnMut(&MsgHead, &[u8]) -> R) -> R {
+        poll_fn(|cx| self.with(|ring| {
+            let Some(head) = Self::poll_front(ring, cx) else { return Poll::Pending; };
+            let r = f(&head, ring.front_bytes(&head));
+            ring.pop();
+            Poll::Ready(r)
+        })).await
+    }
+
+    /// try receive one msg, returns None if the channel is empty or the front msg is borrowed
+    pub fn try_receive<R>(&self, f: impl FnOnce(&MsgHead, &[u8]) -> R) -> Option<R> {
+        self.with(|ring| {
+            if ring.borrowed { return None; }
+            let head = *ring.front()?;
+            let r = f(&head, ring.front_bytes(&head));
+            ring.pop();
+            Some(r)
+        })
+    }
+
+    /// borrow the front msg in place, wait until the channel is not empty<br />
+    /// the msg is released when the returned ref is dropped, only one msg can be borrowed at a time
+    pub async fn receive_ref(&self) -> MsgRef<'_, 'd, N> {
+        poll_fn(|cx| self.with(|ring| {
+            let Some(head) = Self::poll_front(ring, cx) else { return Poll::Pending; };
+            ring.borrowed = true;
+            Poll::Ready(MsgRef { channel: self, head, bytes: ring.msg_bytes(&head) })
+        })).await
+    }
+
+    /// release the borrowed front msg
+    fn release(&self) {
+        self.with(|ring| {
+            ring.borrowed = false;
+            ring.pop();
+            ring.receivers.wake();
+        });
+    }
+
+    /// wait until the channel is not full
+    pub async fn wait_not_full(&self) {
+        poll_fn(|cx| self.with(|ring| {
+            if ring.room() > 0 { return Poll::Ready(()); }
+            ring.senders.register(cx.waker());
+            Poll::Pending
+        })).await
+    }
+
+    /// copy bytes to one msg without waiting, returns the number of bytes used<br />
+    /// all bytes are one msg if they fit, otherwise the largest free part of the channel is used<br />
+    /// if the channel is full, [OverflowPolicy::Block] uses nothing,
+    /// [OverflowPolicy::DropNewest] drops the bytes and [OverflowPolicy::DropOldest] makes room if possible
+    pub fn try_send_bytes(&self, bytes: &[u8], endpoint: Option<IpEndpoint>) -> usize {
+        let policy = self.policy();
+        self.with(|ring| {
+            if policy == OverflowPolicy::DropOldest {
+                while ring.room() < bytes.len() && self.try_make_room(ring, policy, &mut 0) {}
+            }
+            let len = bytes.len().min(ring.room());
+            if len == 0 {
+                if policy != OverflowPolicy::DropNewest { return 0; }
+                self.add_dropped();
+                return bytes.len();
+            }
+
+            let Some(data) = ring.push(len, CallbackEnum::Recv, endpoint) else { return 0; };
+            data.copy_from_slice(&bytes[..len]);
+            len
+        })
+    }
+
+    /// send bytes data and set callback logic, returns the number of dropped msg<br />
+    /// bytes larger than N will be split into multiple msg
+    pub async fn send_bytes(&self, bytes: &[u8], endpoint: Option<IpEndpoint>) -> u32 {
+        let policy = self.policy();
+        let mut dropped = 0;
+        for byte in bytes.chunks(N) {
+            dropped += self.send_msg(CallbackEnum::Recv, byte, endpoint, policy).await;
+        }
+        dropped
+    }
+
+    /// send callback logic without data, this will never be dropped
+    #[inline]
+    pub async fn send_logic(&self, callback_enum: CallbackEnum, endpoint: Option<IpEndpoint>) {
+        self.send_msg(callback_enum, &[], endpoint, OverflowPolicy::Block).await;
+    }
+
+    /// send one msg according to the overflow policy, returns the number of dropped msg
+    async fn send_msg(&self, callback_enum: CallbackEnum, bytes: &[u8], endpoint: Option<IpEndpoint>, policy: OverflowPolicy) -> u32 {
+        let mut dropped = 0;
+        poll_fn(|cx| self.with(|ring| {
+            // if there is no room and no room can be made, drop the new data or wait for the receiver
+            loop {
+                if let Some(data) = ring.push(bytes.len(), callback_enum, endpoint) {
+                    data.copy_from_slice(bytes);
+                    return Poll::Ready(());
+                }
+                if self.try_make_room(ring, policy, &mut dropped) { continue; }
+                if policy == OverflowPolicy::DropNewest { return Poll::Ready(()); }
+
+                ring.senders.register(cx.waker());
+                return Poll::Pending;
+            }
+        })).await;
+        dropped
+    }
+
+    /// try to make room in a full channel, returns true if a msg was removed
+    fn try_make_room(&self, ring: &mut Ring<'d, N>, policy: OverflowPolicy, dropped: &mut u32) -> bool {
+        match policy {
+            OverflowPolicy::Block => false,
+            OverflowPolicy::DropNewest => {
+                self.add_dropped();
+                *dropped += 1;
+                false
+            }
+            OverflowPolicy::DropOldest => {
+                // only data can be dropped, connection and error notifications must be kept, borrowed msg must be kept too
+                let can_drop = !ring.borrowed && ring.front().is_some_and(|head| matches!(head.callback_enum, CallbackEnum::Recv));
+                if !can_drop { return false; }
+
+                ring.pop();
+                self.add_dropped();
+                *dropped += 1;
+                true
+            }
+        }
+    }
+}
+
+/// msg borrowed in place from the channel, released when dropped
+pub(crate) struct MsgRef<'a, 'd, const N: usize> {
+    /// channel
+    channel: &'a RingChannel<'d, N>,
+    /// head of the front msg
+    head: MsgHead,
+    /// data of the front msg
+    bytes: NonNull<[u8]>,
+}
+
+/// custom method
+impl<const N: usize> MsgRef<'_, '_, N> {
+    /// msg head
+    #[inline]
+    pub fn head(&self) -> &MsgHead {
+        &self.head
+    }
+
+    /// msg data
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        // the front msg data is not written by senders or dropped by the overflow policy until it is released,
+        // and the channel bytes live for 'd
+        unsafe { self.bytes.as_ref() }
+    }
+}
+
+/// support drop
+impl<const N: usize> Drop for MsgRef<'_, '_, N> {
+    #[inline]
+    fn drop(&mut self) {
+        self.channel.release();
+    }
+}
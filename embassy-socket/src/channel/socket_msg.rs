@@ -1,5 +1,6 @@
 use embassy_net::{IpAddress, IpEndpoint};
 use crate::channel::callback_enum::CallbackEnum;
+use crate::channel::read_channel::MsgHead;
 
 /// socket msg
 #[derive(Copy, Clone)]
@@ -11,7 +12,7 @@ pub struct SocketMsg<const N: usize> {
     /// read channel logic enum
     pub callback_enum: CallbackEnum,
     /// ip addr, tcp server need this attribute<br />
-    /// default is 0.0.0.0:0, if this happens, please consider it invalid ip
+    /// default is [SocketMsg::NO_ENDPOINT], if this happens, please consider it invalid ip
     pub endpoint: IpEndpoint,
 }
 
@@ -25,10 +26,14 @@ impl<const N: usize> Default for SocketMsg<N> {
 
 /// custom method
 impl<const N: usize> SocketMsg<N> {
+    /// no endpoint, 0.0.0.0:0<br />
+    /// use [SocketMsg::has_endpoint] to check, do not compare with this, because `[::]:0` is no endpoint too
+    pub const NO_ENDPOINT: IpEndpoint = IpEndpoint::new(IpAddress::v4(0, 0, 0, 0), 0);
+
     /// create socket msg
     #[inline]
     pub const fn new(bytes: [u8; N], len: usize) -> Self {
-        Self { bytes, len, callback_enum: CallbackEnum::Disconnect, endpoint: IpEndpoint::new(IpAddress::v4(0, 0, 0, 0), 0) }
+        Self { bytes, len, callback_enum: CallbackEnum::Disconnect, endpoint: Self::NO_ENDPOINT }
     }
 
     /// get real bytes data
@@ -36,4 +41,28 @@ impl<const N: usize> SocketMsg<N> {
     pub fn as_bytes(&self) -> &[u8] {
         &self.bytes[..self.len]
     }
+
+    /// msg has a valid endpoint, port 0 or unspecified address (`0.0.0.0` or `::`) is regarded as no endpoint
+    #[inline]
+    pub fn has_endpoint(&self) -> bool {
+        self.endpoint.port != 0 && !self.endpoint.addr.is_unspecified()
+    }
+
+    /// copy other msg to this msg, only real bytes are copied
+    #[inline]
+    pub(crate) fn copy_from(&mut self, other: &Self) {
+        self.bytes[..other.len].copy_from_slice(other.as_bytes());
+        self.len = other.len;
+        self.callback_enum = other.callback_enum;
+        self.endpoint = other.endpoint;
+    }
+
+    /// copy msg of the read channel to this msg
+    #[inline]
+    pub(crate) fn copy_head(&mut self, head: &MsgHead, bytes: &[u8]) {
+        self.bytes[..bytes.len()].copy_from_slice(bytes);
+        self.len = bytes.len();
+        self.callback_enum = head.callback_enum;
+        self.endpoint = head.endpoint;
+    }
 }
@@ -0,0 +1,134 @@
+use core::cell::Cell;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_time::{Duration, Instant};
+use crate::err::SocketErr;
+
+/// socket statistics, more see [crate::channel::SocketChannel::stats]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SocketStats {
+    /// bytes read from the socket
+    pub bytes_in: u64,
+    /// bytes written to the socket
+    pub bytes_out: u64,
+    /// msg dropped because the read channel was full, generally means `RC_SZ` or the read buf is too small
+    pub read_dropped: u32,
+    /// msg dropped because the write channel was full, generally means `WC_SZ` or the write buf is too small
+    pub write_dropped: u32,
+    /// connect attempts, tcp server counts accepts, udp counts binds
+    pub connect_attempts: u32,
+    /// successful connections
+    pub connect_successes: u32,
+    /// last error passed to the callback
+    pub last_error: Option<SocketErr>,
+    /// uptime of the current connection, None if not connected<br />
+    /// for the multi-connection tcp server, it is the time since the table became non-empty
+    pub uptime: Option<Duration>,
+    /// last rtt sample<br />
+    /// embassy-net does not expose the tcp rtt, so samples come from protocols with request and response,
+    /// such as mqtt pingreq and modbus requests, or from [crate::channel::read_channel::ReadChannel::rtt_sample]
+    pub rtt: Option<Duration>,
+    /// min rtt sample
+    pub rtt_min: Option<Duration>,
+    /// max rtt sample
+    pub rtt_max: Option<Duration>,
+}
+
+/// statistics counter of the read channel, all connection events pass through the read channel
+pub(crate) struct StatsCounter {
+    /// counters, dropped and bytes_out are held by the channels
+    stats: Mutex<CriticalSectionRawMutex, Cell<SocketStats>>,
+    /// open connections and the time the first one was opened
+    connected: Mutex<CriticalSectionRawMutex, Cell<(u32, Option<Instant>)>>,
+}
+
+/// custom method
+impl StatsCounter {
+    /// create statistics counter
+    #[inline]
+    pub(crate) const fn new() -> Self {
+        Self {
+            stats: Mutex::new(Cell::new(SocketStats {
+                bytes_in: 0,
+                bytes_out: 0,
+                read_dropped: 0,
+                write_dropped: 0,
+                connect_attempts: 0,
+                connect_successes: 0,
+                last_error: None,
+                uptime: None,
+                rtt: None,
+                rtt_min: None,
+                rtt_max: None,
+            })),
+            connected: Mutex::new(Cell::new((0, None))),
+        }
+    }
+
+    /// update counters
+    #[inline]
+    fn update(&self, f: impl FnOnce(&mut SocketStats)) {
+        self.stats.lock(|s| {
+            let mut stats = s.get();
+            f(&mut stats);
+            s.set(stats);
+        });
+    }
+
+    /// statistics snapshot, uptime is calculated now
+    pub(crate) fn get(&self) -> SocketStats {
+        let mut stats = self.stats.lock(|s| s.get());
+        stats.uptime = self.connected.lock(|c| c.get().1).map(|since| Instant::now() - since);
+        stats
+    }
+
+    /// reset counters, the current connection uptime is kept
+    #[inline]
+    pub(crate) fn reset(&self) {
+        self.stats.lock(|s| s.set(SocketStats::default()));
+    }
+
+    /// add read bytes
+    #[inline]
+    pub(crate) fn add_in(&self, len: usize) {
+        self.update(|s| s.bytes_in = s.bytes_in.saturating_add(len as u64));
+    }
+
+    /// add connect attempt
+    #[inline]
+    pub(crate) fn connect_attempt(&self) {
+        self.update(|s| s.connect_attempts = s.connect_attempts.saturating_add(1));
+    }
+
+    /// connection opened
+    pub(crate) fn connected(&self) {
+        self.update(|s| s.connect_successes = s.connect_successes.saturating_add(1));
+        self.connected.lock(|c| {
+            let (count, since) = c.get();
+            c.set((count.saturating_add(1), since.or_else(|| Some(Instant::now()))));
+        });
+    }
+
+    /// connection closed
+    pub(crate) fn disconnected(&self) {
+        self.connected.lock(|c| {
+            let count = c.get().0.saturating_sub(1);
+            c.set((count, if count == 0 { None } else { c.get().1 }));
+        });
+    }
+
+    /// record last error
+    #[inline]
+    pub(crate) fn error(&self, err: SocketErr) {
+        self.update(|s| s.last_error = Some(err));
+    }
+
+    /// record rtt sample
+    pub(crate) fn rtt_sample(&self, rtt: Duration) {
+        self.update(|s| {
+            s.rtt = Some(rtt);
+            s.rtt_min = Some(s.rtt_min.map_or(rtt, |min| min.min(rtt)));
+            s.rtt_max = Some(s.rtt_max.map_or(rtt, |max| max.max(rtt)));
+        });
+    }
+}
@@ -1,9 +1,30 @@
-use embassy_net::tcp;
+use embassy_net::{tcp, udp, IpEndpoint};
+use embassy_net::udp::UdpSocket;
+use core::cell::{Cell, RefCell};
+use core::future::poll_fn;
+use core::task::Poll;
+use embassy_sync::blocking_mutex::Mutex;
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 use embassy_sync::rwlock::RwLock;
+use embassy_sync::waitqueue::MultiWakerRegistration;
+use embedded_io_async::Write;
+use crate::channel::callback_enum::CallbackEnum;
 use crate::channel::lock_channel::LockChannel;
+use crate::channel::overflow_policy::OverflowPolicy;
 use crate::channel::socket_msg::SocketMsg;
 use crate::connection::TcpConnection;
+use crate::connection::udp_connection::UdpConnection;
+use crate::err::{SocketErr, SocketResult};
+
+/// flush state, more see [WriteChannel::flush]
+struct FlushState {
+    /// a taken msg is being written by the runner
+    busy: bool,
+    /// number of failed writes, the channel being disabled also counts as a failure
+    failed: u32,
+    /// wakers of the tasks waiting for flush
+    wakers: MultiWakerRegistration<4>,
+}
 
 /// socket write channel<br />
 /// N is channel len
@@ -12,6 +33,10 @@ pub struct WriteChannel<'d, const N: usize> {
     channel: LockChannel<'d, N>,
     /// can send data
     can_send: RwLock<CriticalSectionRawMutex, bool>,
+    /// bytes written to the socket
+    written: Mutex<CriticalSectionRawMutex, Cell<u64>>,
+    /// flush state
+    flush: Mutex<CriticalSectionRawMutex, RefCell<FlushState>>,
 }
 
 /// custom method
@@ -19,7 +44,12 @@ impl<'d, const N: usize> WriteChannel<'d, N> {
     /// create write channel
     #[inline]
     pub fn new(buf: &'d mut [SocketMsg<N>]) -> Self {
-        Self { channel: LockChannel::new(buf), can_send: RwLock::new(false) }
+        Self {
+            channel: LockChannel::new(buf),
+            can_send: RwLock::new(false),
+            written: Mutex::new(Cell::new(0)),
+            flush: Mutex::new(RefCell::new(FlushState { busy: false, failed: 0, wakers: MultiWakerRegistration::new() })),
+        }
     }
 
     /// enable channel, allow channels to send data
@@ -28,63 +58,229 @@ impl<'d, const N: usize> WriteChannel<'d, N> {
         *self.can_send.write().await = true;
     }
 
-    /// disable channel, disable channel from sending data
+    /// disable channel, disable channel from sending data<br />
+    /// queued data is discarded, waiting [WriteChannel::flush] returns [SocketErr::NotConnected]
     pub async fn disable(&self) {
         *self.can_send.write().await = false;
-        self.channel.clear().await;
+        self.channel.clear();
+        self.flush.lock(|f| {
+            let mut f = f.borrow_mut();
+            f.busy = false;
+            f.failed = f.failed.wrapping_add(1);
+            f.wakers.wake();
+        });
+    }
+
+    /// set overflow policy, default is [OverflowPolicy::DropNewest]<br />
+    /// use [OverflowPolicy::Block] to wait until the runner has written queued data
+    #[inline]
+    pub fn overflow_policy(&self, policy: OverflowPolicy) {
+        self.channel.set_policy(policy);
+    }
+
+    /// total number of msg dropped because the channel was full
+    #[inline]
+    pub fn dropped(&self) -> u32 {
+        self.channel.dropped()
+    }
+
+    /// reset dropped counter
+    #[inline]
+    pub fn reset_dropped(&self) {
+        self.channel.reset_dropped();
+    }
+
+    /// total bytes written to the socket
+    #[inline]
+    pub fn written(&self) -> u64 {
+        self.written.lock(|w| w.get())
+    }
+
+    /// add written bytes, runners writing the socket themselves call this
+    #[inline]
+    pub(crate) fn add_written(&self, len: usize) {
+        self.written.lock(|w| w.set(w.get().saturating_add(len as u64)));
+    }
+
+    /// reset written and dropped counter
+    #[inline]
+    pub(crate) fn reset_stats(&self) {
+        self.written.lock(|w| w.set(0));
+        self.channel.reset_dropped();
     }
 
     /// channel is empty
     #[inline]
     pub async fn is_empty(&self) -> bool {
-        self.channel.is_empty().await
+        self.channel.is_empty()
     }
 
     /// channel is full
     #[inline]
     pub async fn is_full(&self) -> bool {
-        self.channel.is_full().await
+        self.channel.is_full()
+    }
+
+    /// wait until there is data to write, this method will not take data
+    #[inline]
+    pub async fn wait(&self) {
+        self.channel.wait_not_empty().await
     }
 
-    /// try tcp write data
-    pub async fn try_tcp_write<const CN: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize>(
+    /// take one queued msg to msg cache, wait until there is data to write<br />
+    /// the msg is in flight until [WriteChannel::write_done] is called, [WriteChannel::flush] waits for it
+    #[inline]
+    pub async fn take(&self, msg: &mut SocketMsg<N>) {
+        self.channel.receive(|m| {
+            msg.copy_from(m);
+            self.flush.lock(|f| f.borrow_mut().busy = true);
+        }).await
+    }
+
+    /// finish the msg taken by [WriteChannel::take], written is false if writing failed<br />
+    /// runners writing the socket themselves call this after every taken msg
+    pub fn write_done(&self, written: bool) {
+        self.flush.lock(|f| {
+            let mut f = f.borrow_mut();
+            f.busy = false;
+            if !written { f.failed = f.failed.wrapping_add(1); }
+            f.wakers.wake();
+        });
+    }
+
+    /// wait until all queued data was written and flushed to the socket<br />
+    /// data queued while waiting is waited too<br />
+    /// returns [SocketErr::NotConnected] if the channel is disabled or any write failed while waiting,
+    /// the write error itself is reported to the callback,
+    /// returns [SocketErr::QueueFull] if any msg was dropped while waiting
+    pub async fn flush(&self) -> SocketResult<()> {
+        if !*self.can_send.read().await { return Err(SocketErr::NotConnected); }
+
+        let failed = self.flush.lock(|f| f.borrow().failed);
+        let dropped = self.dropped();
+        poll_fn(|cx| self.flush.lock(|f| {
+            let mut f = f.borrow_mut();
+            if f.failed != failed { return Poll::Ready(Err(SocketErr::NotConnected)); }
+            if f.busy || !self.channel.is_empty() {
+                f.wakers.register(cx.waker());
+                return Poll::Pending;
+            }
+
+            Poll::Ready(if self.dropped() == dropped { Ok(()) } else { Err(SocketErr::QueueFull) })
+        })).await
+    }
+
+    /// try write one queued msg to writer and flush, wait until there is data to write<br />
+    /// msg is the write cache, the queued msg is copied to it so that the channel is not locked while writing<br />
+    /// close request will not write anything, check `msg.callback_enum` is [CallbackEnum::Disconnect] to close the connection
+    pub async fn try_write<W: Write>(&self, writer: &mut W, msg: &mut SocketMsg<N>) -> Result<(), W::Error> {
+        self.take(msg).await;
+        let result = Self::write_msg(writer, msg).await;
+        if result.is_ok() { self.add_written(msg.len); }
+        self.write_done(result.is_ok());
+        result
+    }
+
+    /// write msg to writer and flush
+    async fn write_msg<W: Write>(writer: &mut W, msg: &SocketMsg<N>) -> Result<(), W::Error> {
+        writer.write_all(msg.as_bytes()).await?;
+        writer.flush().await
+    }
+
+    /// try tcp write data, wait until there is data to write<br />
+    /// the write cache is on the stack, use [WriteChannel::try_write] to provide your own cache
+    #[inline]
+    pub async fn try_tcp_write(
         &self,
-        conn: &mut TcpConnection<'_, CN, TX_SZ, RX_SZ, BUF_SIZE>) -> Result<(), tcp::Error> {
-        let mut ch = self.channel.channel.write().await;
-        let mut recv = ch.split().1;
-        let msg = recv.receive().await;
-
-        if let Err(e) = Self::try_conn_write(conn, msg.as_bytes()).await {
-            recv.receive_done();
-            return Err(e);
-        }
-        recv.receive_done();
-        Ok(())
+        conn: &mut TcpConnection<'_>) -> Result<(), tcp::Error> {
+        self.try_write(&mut conn.socket, &mut SocketMsg::default()).await
+    }
+
+    /// tcp write data, the same as [WriteChannel::try_tcp_write] with the error converted to [SocketErr]
+    #[inline]
+    pub async fn tcp_write(
+        &self, conn: &mut TcpConnection<'_>) -> SocketResult<()> {
+        Ok(self.try_tcp_write(conn).await?)
+    }
+
+    /// try send one queued datagram to the endpoint specified when sending, wait until there is data to write<br />
+    /// msg is the write cache
+    pub async fn try_send_to(&self, socket: &UdpSocket<'_>, msg: &mut SocketMsg<N>) -> Result<(), udp::SendError> {
+        self.take(msg).await;
+        // udp has no connection to close
+        let result = match msg.callback_enum {
+            CallbackEnum::Recv => socket.send_to(msg.as_bytes(), msg.endpoint).await,
+            _ => Ok(()),
+        };
+        if result.is_ok() { self.add_written(msg.len); }
+        self.write_done(result.is_ok());
+        result
+    }
+
+    /// try udp write data, the datagram is sent to the endpoint specified when sending<br />
+    /// the write cache is on the stack, use [WriteChannel::try_send_to] to provide your own cache
+    #[inline]
+    pub async fn try_udp_write(&self, conn: &UdpConnection<'_>) -> Result<(), udp::SendError> {
+        self.try_send_to(&conn.socket, &mut SocketMsg::default()).await
     }
 
-    /// try conn write data
-    async fn try_conn_write<const CN: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize>(
-        conn: &mut TcpConnection<'_, CN, TX_SZ, RX_SZ, BUF_SIZE>, buf: &[u8]) -> Result<(), tcp::Error> {
-        conn.socket.write(buf).await?;
-        conn.socket.flush().await
+    /// udp write data, the same as [WriteChannel::try_udp_write] with the error converted to [SocketErr]
+    #[inline]
+    pub async fn udp_write(&self, conn: &UdpConnection<'_>) -> SocketResult<()> {
+        Ok(self.try_udp_write(conn).await?)
     }
 
+    /// send bytes data, returns Ok if all data is queued, it does not mean the data was written, use [WriteChannel::flush] to wait for that<br />
+    /// returns [SocketErr::NotConnected] if the channel is disabled,
+    /// [SocketErr::PayloadTooLarge] if bytes can never fit in the channel, nothing is queued then,
+    /// and [SocketErr::QueueFull] if any msg was dropped, more see [OverflowPolicy]<br />
+    /// for the multi-connection tcp server, data is sent to all connections
     #[inline]
-    pub async fn tcp_write<const CN: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize>(
-        &self, conn: &mut TcpConnection<'_, CN, TX_SZ, RX_SZ, BUF_SIZE>) {
-        self.try_tcp_write(conn).await.ok();
+    pub async fn send_bytes(&self, bytes: &[u8]) -> SocketResult<()> {
+        self.send_logic(bytes, None).await
     }
 
-    /// send bytes data
-    pub async fn send_bytes(&self, bytes: &[u8]) {
-        if !*self.can_send.read().await { return; }
+    /// send str data, more see [WriteChannel::send_bytes]
+    #[inline]
+    pub async fn send_str(&self, s: &str) -> SocketResult<()> {
+        self.send_bytes(s.as_bytes()).await
+    }
 
-        self.channel.send_bytes(bytes, None).await;
+    /// send bytes data to endpoint, more see [WriteChannel::send_bytes]<br />
+    /// for udp, bytes larger than N will be split into multiple datagrams<br />
+    /// for the multi-connection tcp server, data is sent to the connection of the endpoint<br />
+    /// other tcp runners ignore the endpoint
+    #[inline]
+    pub async fn send_bytes_to(&self, bytes: &[u8], endpoint: IpEndpoint) -> SocketResult<()> {
+        self.send_logic(bytes, Some(endpoint)).await
     }
 
-    /// send str data
+    /// send str data to endpoint, more see [WriteChannel::send_bytes]
     #[inline]
-    pub async fn send_str(&self, s: &str) {
-        self.send_bytes(s.as_bytes()).await;
+    pub async fn send_str_to(&self, s: &str, endpoint: IpEndpoint) -> SocketResult<()> {
+        self.send_bytes_to(s.as_bytes(), endpoint).await
+    }
+
+    /// send bytes data logic<br />
+    /// only [OverflowPolicy::Block] can queue bytes larger than the whole channel, other policies would drop part of them
+    async fn send_logic(&self, bytes: &[u8], endpoint: Option<IpEndpoint>) -> SocketResult<()> {
+        if !*self.can_send.read().await { return Err(SocketErr::NotConnected); }
+        if self.channel.policy() != OverflowPolicy::Block && bytes.len() > self.channel.capacity() * N {
+            return Err(SocketErr::PayloadTooLarge);
+        }
+
+        if self.channel.send_bytes(bytes, endpoint).await > 0 { return Err(SocketErr::QueueFull); }
+        Ok(())
+    }
+
+    /// request the runner to close the connection after the queued data is written<br />
+    /// for the multi-connection tcp server, the connection of the endpoint is closed,
+    /// other tcp runners ignore the endpoint, udp ignores close requests<br />
+    /// returns [SocketErr::NotConnected] if the channel is disabled
+    pub async fn close(&self, endpoint: Option<IpEndpoint>) -> SocketResult<()> {
+        if !*self.can_send.read().await { return Err(SocketErr::NotConnected); }
+
+        self.channel.send_logic(CallbackEnum::Disconnect, endpoint).await;
+        Ok(())
     }
 }
@@ -1,28 +1,30 @@
-use core::ptr::NonNull;
 use embassy_net::Stack;
-use embassy_net::tcp::{Error, TcpSocket};
-use crate::connection::socket_state::SocketState;
+use embassy_net::tcp::{Error, TcpReader, TcpSocket, TcpWriter};
+use crate::connection::socket_state::{TcpBufs, TcpState};
 
 pub mod socket_state;
+pub mod slab_state;
+pub mod udp_state;
+pub mod udp_connection;
 pub(crate) mod pool;
 
 /// tcp connection
-pub struct TcpConnection<'d, const N: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize> {
+pub struct TcpConnection<'d> {
     /// tcp socket
     pub socket: TcpSocket<'d>,
     /// socket state, memory pool
-    state: &'d SocketState<N, TX_SZ, RX_SZ, BUF_SIZE>,
+    state: &'d dyn TcpState,
     /// memory buf
-    bufs: NonNull<([u8; TX_SZ], [u8; RX_SZ], [u8; BUF_SIZE])>,
+    bufs: TcpBufs,
 }
 
 /// custom method
-impl<'d, const N: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize> TcpConnection<'d, N, TX_SZ, RX_SZ, BUF_SIZE> {
+impl<'d> TcpConnection<'d> {
     /// create tcp connection
-    pub fn new(stack: Stack<'d>, state: &'d SocketState<N, TX_SZ, RX_SZ, BUF_SIZE>) -> Result<Self, Error> {
-        let mut bufs = state.pool.alloc().ok_or(Error::ConnectionReset)?;
+    pub fn new(stack: Stack<'d>, state: &'d dyn TcpState) -> Result<Self, Error> {
+        let mut bufs = state.alloc().ok_or(Error::ConnectionReset)?;
         Ok(Self {
-            socket: unsafe { TcpSocket::new(stack, &mut bufs.as_mut().1, &mut bufs.as_mut().0) },
+            socket: unsafe { TcpSocket::new(stack, bufs.rx.as_mut(), bufs.tx.as_mut()) },
             state,
             bufs,
         })
@@ -30,18 +32,74 @@ impl<'d, const N: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE:
 
     /// try read to buf
     pub async fn try_read(&mut self) -> Result<&[u8], Error> {
-        let bytes = unsafe { &mut self.bufs.as_mut().2 };
+        let bytes = unsafe { self.bufs.buf.as_mut() };
         let len = self.socket.read(bytes).await?;
         if len == 0 { return Err(Error::ConnectionReset); }
 
         Ok(&bytes[0..len])
     }
+
+    /// try read data in place from the socket rx buffer, f returns the number of bytes used<br />
+    /// wait until there is at least one byte available, the pool buf is not used
+    pub async fn try_read_with(&mut self, f: impl FnOnce(&[u8]) -> usize) -> Result<usize, Error> {
+        self.socket.read_with(|data| {
+            let len = f(data);
+            (len, len)
+        }).await
+    }
+
+    /// split connection into reader and writer, so that reading and writing can be done concurrently
+    pub fn split(&mut self) -> (TcpConnReader<'_>, TcpWriter<'_>) {
+        let buf = unsafe { self.bufs.buf.as_mut() };
+        let (reader, writer) = self.socket.split();
+        (TcpConnReader { reader, buf }, writer)
+    }
+
+    /// get tcp socket and connection read buf, used to wrap the socket in a tls session
+    #[cfg(feature = "tls")]
+    #[inline]
+    pub(crate) fn socket_buf(&mut self) -> (&mut TcpSocket<'d>, &mut [u8]) {
+        (&mut self.socket, unsafe { self.bufs.buf.as_mut() })
+    }
+}
+
+/// tcp connection reader, read data to the connection read buf
+pub struct TcpConnReader<'a> {
+    /// tcp reader
+    reader: TcpReader<'a>,
+    /// read data buf
+    buf: &'a mut [u8],
+}
+
+/// custom method
+impl TcpConnReader<'_> {
+    /// read data to buf, wait until there is at least one byte available<br />
+    /// returns empty data if the remote side has closed the connection
+    pub async fn read(&mut self) -> Result<&[u8], Error> {
+        let len = self.reader.read(self.buf).await?;
+        Ok(&self.buf[0..len])
+    }
+
+    /// read data in place from the socket rx buffer, f returns the number of bytes used<br />
+    /// wait until there is at least one byte available, the read buf is not used<br />
+    /// returns None if the remote side has closed the connection
+    pub async fn read_with(&mut self, f: impl FnOnce(&[u8]) -> usize) -> Result<Option<usize>, Error> {
+        let result = self.reader.read_with(|data| {
+            let len = f(data);
+            (len, len)
+        }).await;
+        match result {
+            Ok(len) => Ok(Some(len)),
+            // read_with can not tell eof from reset, read can, and there is no data left to read here
+            Err(_) => self.reader.read(&mut [0; 1]).await.map(|_| None),
+        }
+    }
 }
 
 /// support drop
-impl<'d, const N: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize> Drop for TcpConnection<'d, N, TX_SZ, RX_SZ, BUF_SIZE> {
+impl Drop for TcpConnection<'_> {
     fn drop(&mut self) {
         self.socket.close();
-        self.state.pool.free(self.bufs);
+        self.state.free(&self.bufs);
     }
 }
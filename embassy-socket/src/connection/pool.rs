@@ -12,32 +12,40 @@ pub struct Pool<T, const N: usize> {
 
 /// custom method
 impl<T, const N: usize> Pool<T, N> {
-    const VALUE: Cell<bool> = Cell::new(false);
-    const UNINIT: UnsafeCell<MaybeUninit<T>> = UnsafeCell::new(MaybeUninit::uninit());
-
     /// create a memory pool
     pub const fn new() -> Self {
         Self {
-            used: [Self::VALUE; N],
-            data: [Self::UNINIT; N],
+            used: [const { Cell::new(false) }; N],
+            data: [const { UnsafeCell::new(MaybeUninit::uninit()) }; N],
         }
     }
 
     /// alloc memory
+    #[inline]
     pub fn alloc(&self) -> Option<NonNull<T>> {
+        self.alloc_slot().map(|(_, p)| p)
+    }
+
+    /// alloc memory, returns the slot and the memory
+    pub fn alloc_slot(&self) -> Option<(usize, NonNull<T>)> {
         for n in 0..N {
             // this can't race because Pool is not Sync.
             if !self.used[n].get() {
                 self.used[n].set(true);
                 let p = self.data[n].get() as *mut T;
-                return Some(unsafe { NonNull::new_unchecked(p) });
+                return Some((n, unsafe { NonNull::new_unchecked(p) }));
             }
         }
         None
     }
 
-    /// safety: p must be a pointer obtained from self.alloc that hasn't been freed yet.
+    /// free memory of the slot obtained from self.alloc_slot
+    #[inline]
+    pub fn free_slot(&self, n: usize) {
+        self.used[n].set(false);
+    }
 
+    /// safety: p must be a pointer obtained from self.alloc that hasn't been freed yet.
     pub fn free(&self, p: NonNull<T>) {
         let origin = self.data.as_ptr() as *mut T;
         let n = unsafe { p.as_ptr().offset_from(origin) };
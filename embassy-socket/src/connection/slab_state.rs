@@ -0,0 +1,125 @@
+use core::cell::{Cell, RefCell};
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+use crate::connection::socket_state::{TcpBufs, TcpState};
+
+/// tcp socket state built from a user provided slab at runtime<br />
+/// every connection takes `tx + rx + buf` bytes, up to 32 connections<br />
+/// unlike [crate::connection::socket_state::SocketState], the sizes are not const generics,
+/// so states with different sizes can be carved from one [SlabPool]
+pub struct SlabState<'d> {
+    /// start of the slab
+    slab: NonNull<u8>,
+    /// connection number
+    count: usize,
+    /// socket tx size
+    tx: usize,
+    /// socket rx size
+    rx: usize,
+    /// read data buf size
+    buf: usize,
+    /// used connections, one bit per connection
+    used: Cell<u32>,
+    /// the slab is borrowed for 'd
+    _slab: PhantomData<&'d mut [u8]>,
+}
+
+/// custom method
+impl<'d> SlabState<'d> {
+    /// create state of count connections from slab<br />
+    /// returns None if count is 0 or greater than 32, or the slab is smaller than `count * (tx + rx + buf)`
+    pub fn new(slab: &'d mut [u8], count: usize, tx: usize, rx: usize, buf: usize) -> Option<Self> {
+        if count == 0 || count > 32 || slab.len() < Self::size_of(count, tx, rx, buf)? { return None; }
+
+        Some(Self { slab: NonNull::from(slab).cast(), count, tx, rx, buf, used: Cell::new(0), _slab: PhantomData })
+    }
+
+    /// slab size required by count connections, None if it overflows
+    #[inline]
+    pub const fn size_of(count: usize, tx: usize, rx: usize, buf: usize) -> Option<usize> {
+        match tx.checked_add(rx) {
+            Some(n) => match n.checked_add(buf) {
+                Some(n) => count.checked_mul(n),
+                None => None,
+            },
+            None => None,
+        }
+    }
+
+    /// connection number
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// connections in use
+    #[inline]
+    pub fn used(&self) -> usize {
+        self.used.get().count_ones() as usize
+    }
+
+    /// slice of the slab
+    #[inline]
+    fn slice(&self, offset: usize, len: usize) -> NonNull<[u8]> {
+        NonNull::slice_from_raw_parts(unsafe { self.slab.add(offset) }, len)
+    }
+}
+
+/// support tcp state
+impl TcpState for SlabState<'_> {
+    fn alloc(&self) -> Option<TcpBufs> {
+        let used = self.used.get();
+        let slot = (0..self.count).find(|n| used & (1 << n) == 0)?;
+        self.used.set(used | (1 << slot));
+
+        // every connection takes tx, rx and buf in order, the size is checked when creating
+        let start = slot * (self.tx + self.rx + self.buf);
+        Some(TcpBufs {
+            slot,
+            tx: self.slice(start, self.tx),
+            rx: self.slice(start + self.tx, self.rx),
+            buf: self.slice(start + self.tx + self.rx, self.buf),
+        })
+    }
+
+    #[inline]
+    fn free(&self, bufs: &TcpBufs) {
+        self.used.set(self.used.get() & !(1 << bufs.slot));
+    }
+}
+
+/// slab shared by tcp states with different sizes<br />
+/// states are carved from the front of the slab and never returned, generally created once at startup
+pub struct SlabPool<'d> {
+    /// rest of the slab
+    rest: RefCell<&'d mut [u8]>,
+}
+
+/// custom method
+impl<'d> SlabPool<'d> {
+    /// create slab pool
+    #[inline]
+    pub fn new(slab: &'d mut [u8]) -> Self {
+        Self { rest: RefCell::new(slab) }
+    }
+
+    /// remaining bytes of the slab
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.rest.borrow().len()
+    }
+
+    /// carve state of count connections from the slab, more see [SlabState::new]<br />
+    /// returns None if the remaining slab is too small, nothing is taken in that case
+    pub fn state(&self, count: usize, tx: usize, rx: usize, buf: usize) -> Option<SlabState<'d>> {
+        if count == 0 || count > 32 { return None; }
+
+        let size = SlabState::size_of(count, tx, rx, buf)?;
+        let mut rest = self.rest.borrow_mut();
+        if rest.len() < size { return None; }
+
+        let (slab, tail) = core::mem::take(&mut *rest).split_at_mut(size);
+        *rest = tail;
+        SlabState::new(slab, count, tx, rx, buf)
+    }
+}
@@ -1,5 +1,30 @@
+use core::ptr::NonNull;
 use crate::connection::pool::Pool;
 
+/// buffers of one tcp connection, tx buf, rx buf and read data buf<br />
+/// allocated by [TcpState::alloc] and returned by [TcpState::free]
+pub struct TcpBufs {
+    /// slot of the state
+    pub(crate) slot: usize,
+    /// socket tx buf
+    pub(crate) tx: NonNull<[u8]>,
+    /// socket rx buf
+    pub(crate) rx: NonNull<[u8]>,
+    /// read data buf
+    pub(crate) buf: NonNull<[u8]>,
+}
+
+/// tcp socket memory of the runners<br />
+/// implemented by [SocketState] with const sizes and [crate::connection::slab_state::SlabState] with runtime sizes,
+/// the runners only hold `&dyn TcpState`, so the buffer sizes do not appear in their types
+pub trait TcpState {
+    /// alloc buffers of one connection, None if all connections are used
+    fn alloc(&self) -> Option<TcpBufs>;
+
+    /// free buffers of one connection
+    fn free(&self, bufs: &TcpBufs);
+}
+
 /// socket connection<br />
 /// N is socket number<br />
 /// TX_SZ is socket tx size<br />
@@ -10,6 +35,14 @@ pub struct SocketState<const N: usize, const TX_SZ: usize, const RX_SZ: usize, c
     pub pool: Pool<([u8; TX_SZ], [u8; RX_SZ], [u8; BUF_SIZE]), N>,
 }
 
+/// support default
+impl<const N: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize> Default for SocketState<N, TX_SZ, RX_SZ, BUF_SIZE> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// custom method
 impl<const N: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize> SocketState<N, TX_SZ, RX_SZ, BUF_SIZE> {
     /// create socket connection
@@ -17,3 +50,17 @@ impl<const N: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usi
         Self { pool: Pool::new() }
     }
 }
+
+/// support tcp state
+impl<const N: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize> TcpState for SocketState<N, TX_SZ, RX_SZ, BUF_SIZE> {
+    fn alloc(&self) -> Option<TcpBufs> {
+        let (slot, mut bufs) = self.pool.alloc_slot()?;
+        let (tx, rx, buf) = unsafe { bufs.as_mut() };
+        Some(TcpBufs { slot, tx: NonNull::from(&mut tx[..]), rx: NonNull::from(&mut rx[..]), buf: NonNull::from(&mut buf[..]) })
+    }
+
+    #[inline]
+    fn free(&self, bufs: &TcpBufs) {
+        self.pool.free_slot(bufs.slot);
+    }
+}
@@ -0,0 +1,48 @@
+use embassy_net::{IpEndpoint, Stack};
+use embassy_net::udp::{BindError, RecvError, UdpSocket};
+use crate::connection::udp_state::{UdpBufs, UdpState};
+
+/// udp connection
+pub struct UdpConnection<'d> {
+    /// udp socket
+    pub socket: UdpSocket<'d>,
+    /// socket state, memory pool
+    state: &'d dyn UdpState,
+    /// memory buf
+    bufs: UdpBufs,
+}
+
+/// custom method
+impl<'d> UdpConnection<'d> {
+    /// create udp connection<br />
+    /// if the memory pool is exhausted, [BindError::InvalidState] will be returned
+    pub fn new(stack: Stack<'d>, state: &'d dyn UdpState) -> Result<Self, BindError> {
+        let mut bufs = state.alloc().ok_or(BindError::InvalidState)?;
+        let (tx_meta, tx, rx_meta, rx) = unsafe { (bufs.tx_meta.as_mut(), bufs.tx.as_mut(), bufs.rx_meta.as_mut(), bufs.rx.as_mut()) };
+        Ok(Self {
+            socket: UdpSocket::new(stack, rx_meta, rx, tx_meta, tx),
+            state,
+            bufs,
+        })
+    }
+
+    /// try recv datagram to buf, returns datagram and remote endpoint
+    pub async fn try_recv_from(&mut self) -> Result<(&[u8], IpEndpoint), RecvError> {
+        let bytes = unsafe { self.bufs.buf.as_mut() };
+        let (len, meta) = self.socket.recv_from(bytes).await?;
+        Ok((&bytes[0..len], meta.endpoint))
+    }
+
+    /// split connection into socket and read datagram buf, so that reading and writing can be done concurrently
+    pub fn split(&mut self) -> (&UdpSocket<'d>, &mut [u8]) {
+        (&self.socket, unsafe { self.bufs.buf.as_mut() })
+    }
+}
+
+/// support drop
+impl Drop for UdpConnection<'_> {
+    fn drop(&mut self) {
+        self.socket.close();
+        self.state.free(&self.bufs);
+    }
+}
@@ -0,0 +1,87 @@
+use core::ptr::NonNull;
+use embassy_net::udp::PacketMetadata;
+use crate::connection::pool::Pool;
+
+/// udp socket memory, tx meta, tx buf, rx meta, rx buf, read data buf
+pub type UdpBuf<const META: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize> =
+([PacketMetadata; META], [u8; TX_SZ], [PacketMetadata; META], [u8; RX_SZ], [u8; BUF_SIZE]);
+
+/// buffers of one udp socket, tx meta, tx buf, rx meta, rx buf and read datagram buf<br />
+/// allocated by [UdpState::alloc] and returned by [UdpState::free]
+pub struct UdpBufs {
+    /// slot of the state
+    pub(crate) slot: usize,
+    /// socket tx packet metadata
+    pub(crate) tx_meta: NonNull<[PacketMetadata]>,
+    /// socket tx buf
+    pub(crate) tx: NonNull<[u8]>,
+    /// socket rx packet metadata
+    pub(crate) rx_meta: NonNull<[PacketMetadata]>,
+    /// socket rx buf
+    pub(crate) rx: NonNull<[u8]>,
+    /// read datagram buf
+    pub(crate) buf: NonNull<[u8]>,
+}
+
+/// udp socket memory of the runners<br />
+/// implemented by [UdpSocketState] with const sizes,
+/// the runners only hold `&dyn UdpState`, so the buffer sizes do not appear in their types
+pub trait UdpState {
+    /// alloc buffers of one socket, None if all sockets are used<br />
+    /// the packet metadata is initialized
+    fn alloc(&self) -> Option<UdpBufs>;
+
+    /// free buffers of one socket
+    fn free(&self, bufs: &UdpBufs);
+}
+
+/// udp socket state<br />
+/// N is socket number<br />
+/// META is packet metadata number, that is, the maximum number of datagrams cached by tx or rx<br />
+/// TX_SZ is socket tx size<br />
+/// RX_SZ is socket rx size<br />
+/// BUF_SIZE is read datagram buf size, datagrams larger than this will be reported as truncated
+pub struct UdpSocketState<const N: usize, const META: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize> {
+    /// memory pool
+    pub pool: Pool<UdpBuf<META, TX_SZ, RX_SZ, BUF_SIZE>, N>,
+}
+
+/// support default
+impl<const N: usize, const META: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize> Default for UdpSocketState<N, META, TX_SZ, RX_SZ, BUF_SIZE> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// custom method
+impl<const N: usize, const META: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize> UdpSocketState<N, META, TX_SZ, RX_SZ, BUF_SIZE> {
+    /// create udp socket state
+    pub const fn new() -> Self {
+        Self { pool: Pool::new() }
+    }
+}
+
+/// support udp state
+impl<const N: usize, const META: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize> UdpState for UdpSocketState<N, META, TX_SZ, RX_SZ, BUF_SIZE> {
+    fn alloc(&self) -> Option<UdpBufs> {
+        let (slot, mut bufs) = self.pool.alloc_slot()?;
+        let (tx_meta, tx, rx_meta, rx, buf) = unsafe { bufs.as_mut() };
+        // packet metadata must be initialized before use
+        *tx_meta = [PacketMetadata::EMPTY; META];
+        *rx_meta = [PacketMetadata::EMPTY; META];
+        Some(UdpBufs {
+            slot,
+            tx_meta: NonNull::from(&mut tx_meta[..]),
+            tx: NonNull::from(&mut tx[..]),
+            rx_meta: NonNull::from(&mut rx_meta[..]),
+            rx: NonNull::from(&mut rx[..]),
+            buf: NonNull::from(&mut buf[..]),
+        })
+    }
+
+    #[inline]
+    fn free(&self, bufs: &UdpBufs) {
+        self.pool.free_slot(bufs.slot);
+    }
+}
@@ -0,0 +1,146 @@
+use core::cell::Cell;
+use core::future::pending;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use crate::channel::write_channel::WriteChannel;
+use crate::tcp_client::target::Target;
+
+/// runner state
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum State {
+    /// waiting for the network, connecting or accepting
+    Connecting,
+    /// connected
+    Connected,
+    /// waiting before the next reconnect, more see [crate::tcp_client::reconnect::ReconnectPolicy]
+    Backoff,
+    /// disconnected by [Control::disconnect], waiting for [Control::connect] or [Control::reconnect_to]
+    Paused,
+    /// the runner has returned
+    Stopped,
+}
+
+/// runner command
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum Command {
+    /// close the connection and pause
+    Disconnect,
+    /// resume connecting
+    Connect,
+    /// close the connection and connect to the new target
+    ReconnectTo(Target, u16),
+    /// flush the write queue, close the connection and return
+    Stop,
+}
+
+/// runtime control handle of a runner, share it to other tasks by reference<br />
+/// supported by the tcp client runner, the tcp server runners and the udp runner,
+/// the tcp server runners close the current connections on disconnect and ignore connect and reconnect,
+/// the udp runner unbinds the socket on disconnect and binds it again on connect or reconnect, the target is ignored<br />
+/// only the latest command is kept, a new command replaces the one the runner has not handled yet
+pub struct Control {
+    /// command signal
+    signal: Signal<CriticalSectionRawMutex, Command>,
+    /// runner state
+    state: Mutex<CriticalSectionRawMutex, Cell<State>>,
+}
+
+/// support default
+impl Default for Control {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// custom method
+impl Control {
+    /// create control handle
+    #[inline]
+    pub const fn new() -> Self {
+        Self { signal: Signal::new(), state: Mutex::new(Cell::new(State::Connecting)) }
+    }
+
+    /// current runner state
+    #[inline]
+    pub fn state(&self) -> State {
+        self.state.lock(|s| s.get())
+    }
+
+    /// close the connection now and do not reconnect until [Control::connect] or [Control::reconnect_to]<br />
+    /// queued write data is discarded
+    #[inline]
+    pub fn disconnect(&self) {
+        self.signal.signal(Command::Disconnect);
+    }
+
+    /// resume connecting after [Control::disconnect], the reconnect attempts are reset
+    #[inline]
+    pub fn connect(&self) {
+        self.signal.signal(Command::Connect);
+    }
+
+    /// close the connection and connect to the new target immediately, the reconnect attempts are reset
+    #[inline]
+    pub fn reconnect_to(&self, target: impl Into<Target>, port: u16) {
+        self.signal.signal(Command::ReconnectTo(target.into(), port));
+    }
+
+    /// write the queued data, close the connection and let the runner return
+    #[inline]
+    pub fn stop(&self) {
+        self.signal.signal(Command::Stop);
+    }
+
+    /// set runner state
+    #[inline]
+    pub(crate) fn set_state(&self, state: State) {
+        self.state.lock(|s| s.set(state));
+    }
+
+    /// wait for the next command
+    #[inline]
+    pub(crate) async fn wait(&self) -> Command {
+        self.signal.wait().await
+    }
+}
+
+/// set runner state if there is a control handle
+#[inline]
+pub(crate) fn set_state(control: Option<&Control>, state: State) {
+    if let Some(control) = control { control.set_state(state); }
+}
+
+/// wait for the next command, never returns without a control handle
+pub(crate) async fn wait(control: Option<&Control>) -> Command {
+    match control {
+        Some(control) => control.wait().await,
+        None => pending().await,
+    }
+}
+
+/// handle commands while connected, the command is recorded to `command` for the runner<br />
+/// returns when the connection should be closed now, stop queues a close request
+/// and keeps waiting, so that the write logic returns after flushing the queue<br />
+/// reconnect_to is the command closes the connection, false for the servers
+pub(crate) async fn serve<const N: usize>(control: Option<&Control>, wch: &WriteChannel<'_, N>, command: &Cell<Option<Command>>, reconnect_to: bool) {
+    loop {
+        let cmd = wait(control).await;
+        match cmd {
+            Command::Stop => {
+                command.set(Some(cmd));
+                wch.close(None).await.ok();
+            }
+            Command::Disconnect => {
+                command.set(Some(cmd));
+                return;
+            }
+            Command::ReconnectTo(..) if reconnect_to => {
+                command.set(Some(cmd));
+                return;
+            }
+            Command::ReconnectTo(..) | Command::Connect => {}
+        }
+    }
+}
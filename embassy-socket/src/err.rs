@@ -1,5 +1,13 @@
-use embassy_net::tcp;
+use embassy_net::{tcp, udp};
 use embassy_sync::channel::TryReceiveError;
+#[cfg(feature = "modbus")]
+use crate::modbus::ModbusErr;
+#[cfg(feature = "mqtt")]
+use crate::mqtt::MqttErr;
+#[cfg(feature = "sntp")]
+use crate::sntp::SntpErr;
+#[cfg(feature = "websocket")]
+use crate::websocket::WsErr;
 
 /// socket result
 pub type SocketResult<T> = Result<T, SocketErr>;
@@ -15,6 +23,66 @@ pub enum SocketErr {
     TryReceiveError(TryReceiveError),
     /// tcp accept error
     AcceptError(tcp::AcceptError),
+    /// udp bind error
+    UdpBindError(udp::BindError),
+    /// udp send error
+    UdpSendError(udp::SendError),
+    /// udp recv error
+    UdpRecvError(udp::RecvError),
+    /// the write channel is disabled because there is no connection, or the connection was lost while flushing,
+    /// more see [crate::channel::write_channel::WriteChannel]
+    NotConnected,
+    /// the write channel is full and data was dropped, more see [crate::channel::overflow_policy::OverflowPolicy]
+    QueueFull,
+    /// payload is larger than the whole write channel and can not be queued,
+    /// more see [crate::channel::write_channel::WriteChannel::send_bytes]
+    PayloadTooLarge,
+    /// tcp client reconnect attempts exhausted, more see [crate::tcp_client::reconnect::ReconnectPolicy]
+    ReconnectExhausted,
+    /// heartbeat timed out, the connection is closed, more see [crate::heartbeat::Heartbeat]
+    HeartbeatTimeout,
+    /// the peer did not take the write msg in time, the connection is closed,
+    /// more see [crate::tcp_server::multi_runner::MultiReadRunner::write_timeout]
+    WriteTimeout,
+    /// the connection table is full, the connection is closed,
+    /// more see [crate::tcp_server::multi_callback_runner::MultiCallbackRunner]
+    TooManyConnections,
+    /// frame is larger than the frame buf, the frame is discarded<br />
+    /// reported by the frame decoders of the `framing`, `mqtt`, `modbus` and `websocket` modules
+    FrameTooLarge,
+    /// frame can not be decoded, the frame is discarded<br />
+    /// reported by the frame decoders of the `framing`, `mqtt`, `modbus` and `websocket` modules
+    InvalidFrame,
+    /// no response within the timeout, more see [crate::request::Requester::request]
+    #[cfg(feature = "request")]
+    RequestTimeout,
+    /// the connection was lost before the response, more see [crate::request::Requester::request]
+    #[cfg(feature = "request")]
+    RequestAborted,
+    /// too many outstanding requests, more see [crate::request::Requester]
+    #[cfg(feature = "request")]
+    TooManyRequests,
+    /// mqtt error, more see [crate::mqtt]
+    #[cfg(feature = "mqtt")]
+    MqttError(MqttErr),
+    /// modbus error, more see [crate::modbus]
+    #[cfg(feature = "modbus")]
+    ModbusError(ModbusErr),
+    /// websocket error, more see [crate::websocket]
+    #[cfg(feature = "websocket")]
+    WsError(WsErr),
+    /// sntp error, more see [crate::sntp]
+    #[cfg(feature = "sntp")]
+    SntpError(SntpErr),
+    /// tls error, more see [crate::tls]
+    #[cfg(feature = "tls")]
+    TlsError(embedded_tls::TlsError),
+    /// dns resolve error, more see [crate::tcp_client::target::Target]
+    #[cfg(feature = "dns")]
+    DnsError(embassy_net::dns::Error),
+    /// multicast group error, more see [crate::mdns]
+    #[cfg(feature = "mdns")]
+    MulticastError(embassy_net::MulticastError),
 }
 
 /// custom method
@@ -58,3 +126,90 @@ impl From<tcp::AcceptError> for SocketErr {
         Self::AcceptError(value)
     }
 }
+
+/// support udp bind error to socket err
+impl From<udp::BindError> for SocketErr {
+    #[inline]
+    fn from(value: udp::BindError) -> Self {
+        Self::UdpBindError(value)
+    }
+}
+
+/// support udp send error to socket err
+impl From<udp::SendError> for SocketErr {
+    #[inline]
+    fn from(value: udp::SendError) -> Self {
+        Self::UdpSendError(value)
+    }
+}
+
+/// support udp recv error to socket err
+impl From<udp::RecvError> for SocketErr {
+    #[inline]
+    fn from(value: udp::RecvError) -> Self {
+        Self::UdpRecvError(value)
+    }
+}
+
+/// support mqtt error to socket err
+#[cfg(feature = "mqtt")]
+impl From<MqttErr> for SocketErr {
+    #[inline]
+    fn from(value: MqttErr) -> Self {
+        Self::MqttError(value)
+    }
+}
+
+/// support modbus error to socket err
+#[cfg(feature = "modbus")]
+impl From<ModbusErr> for SocketErr {
+    #[inline]
+    fn from(value: ModbusErr) -> Self {
+        Self::ModbusError(value)
+    }
+}
+
+/// support websocket error to socket err
+#[cfg(feature = "websocket")]
+impl From<WsErr> for SocketErr {
+    #[inline]
+    fn from(value: WsErr) -> Self {
+        Self::WsError(value)
+    }
+}
+
+/// support sntp error to socket err
+#[cfg(feature = "sntp")]
+impl From<SntpErr> for SocketErr {
+    #[inline]
+    fn from(value: SntpErr) -> Self {
+        Self::SntpError(value)
+    }
+}
+
+/// support tls error to socket err
+#[cfg(feature = "tls")]
+impl From<embedded_tls::TlsError> for SocketErr {
+    #[inline]
+    fn from(value: embedded_tls::TlsError) -> Self {
+        Self::TlsError(value)
+    }
+}
+
+/// support dns error to socket err
+#[cfg(feature = "dns")]
+impl From<embassy_net::dns::Error> for SocketErr {
+    #[inline]
+    fn from(value: embassy_net::dns::Error) -> Self {
+        Self::DnsError(value)
+    }
+}
+
+/// support multicast error to socket err
+#[cfg(feature = "mdns")]
+impl From<embassy_net::MulticastError> for SocketErr {
+    #[inline]
+    fn from(value: embassy_net::MulticastError) -> Self {
+        Self::MulticastError(value)
+    }
+}
@@ -0,0 +1,270 @@
+use crate::err::{SocketErr, SocketResult};
+use crate::framing::{Endian, Framing};
+
+/// slip frame end
+const SLIP_END: u8 = 0xC0;
+/// slip frame escape
+const SLIP_ESC: u8 = 0xDB;
+/// slip escaped frame end
+const SLIP_ESC_END: u8 = 0xDC;
+/// slip escaped frame escape
+const SLIP_ESC_ESC: u8 = 0xDD;
+
+/// frame decoder, reassemble socket data into whole frames<br />
+/// N is max frame size, larger frames will be reported as [SocketErr::FrameTooLarge] and discarded
+pub struct FrameDecoder<const N: usize> {
+    /// framing
+    framing: Framing,
+    /// frame buf
+    buf: [u8; N],
+    /// frame buf len
+    len: usize,
+    /// length prefix cache
+    header: [u8; 4],
+    /// length prefix cache len
+    header_len: usize,
+    /// expected frame len, length prefix and fixed size only
+    expect: Option<usize>,
+    /// number of bytes to skip, length prefix and fixed size only
+    skip: usize,
+    /// discard data until the frame end, delimiter/cobs/slip only
+    discard: bool,
+    /// number of delimiter bytes matched, they are not stored in buf, delimiter only
+    matched: usize,
+    /// slip escape flag
+    escape: bool,
+    /// the frame in buf has been returned, and buf should be cleared on next decode
+    ready: bool,
+}
+
+/// custom method
+impl<const N: usize> FrameDecoder<N> {
+    /// create frame decoder<br />
+    /// panics if the delimiter is empty
+    #[inline]
+    pub const fn new(framing: Framing) -> Self {
+        if let Framing::Delimiter(delimiter) = framing { assert!(!delimiter.is_empty(), "empty delimiter"); }
+        Self {
+            framing,
+            buf: [0; N],
+            len: 0,
+            header: [0; 4],
+            header_len: 0,
+            expect: None,
+            skip: 0,
+            discard: false,
+            matched: 0,
+            escape: false,
+            ready: false,
+        }
+    }
+
+    /// get framing
+    #[inline]
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    /// drop the incomplete frame, generally called when connection changes or data was lost
+    pub fn reset(&mut self) {
+        self.len = 0;
+        self.header_len = 0;
+        self.expect = None;
+        self.skip = 0;
+        self.discard = false;
+        self.matched = 0;
+        self.escape = false;
+        self.ready = false;
+    }
+
+    /// decode bytes, returns the number of bytes used and the decoded frame<br />
+    /// if the frame is None, all bytes have been used<br />
+    /// call this method repeatedly with the remaining bytes until all bytes have been used
+    pub fn decode(&mut self, bytes: &[u8]) -> (usize, Option<SocketResult<&[u8]>>) {
+        if self.ready {
+            self.ready = false;
+            self.len = 0;
+        }
+
+        match self.framing {
+            Framing::LengthPrefix { .. } | Framing::FixedSize(_) => self.decode_sized(bytes),
+            Framing::Delimiter(delimiter) => self.decode_delimiter(bytes, delimiter),
+            Framing::Cobs => self.decode_end(bytes, 0),
+            Framing::Slip => self.decode_end(bytes, SLIP_END),
+        }
+    }
+
+    /// decode length prefix or fixed size frame
+    fn decode_sized(&mut self, bytes: &[u8]) -> (usize, Option<SocketResult<&[u8]>>) {
+        let mut used = 0;
+        if self.skip > 0 {
+            used = self.skip.min(bytes.len());
+            self.skip -= used;
+        }
+
+        let expect = match self.expect {
+            Some(expect) => expect,
+            None => {
+                let Some(expect) = self.read_header(bytes, &mut used) else { return (used, None); };
+                if expect > N {
+                    self.skip = expect;
+                    let skip = self.skip.min(bytes.len() - used);
+                    self.skip -= skip;
+                    return (used + skip, Some(Err(SocketErr::FrameTooLarge)));
+                }
+                self.expect = Some(expect);
+                expect
+            }
+        };
+
+        let copy = (expect - self.len).min(bytes.len() - used);
+        self.buf[self.len..self.len + copy].copy_from_slice(&bytes[used..used + copy]);
+        self.len += copy;
+        used += copy;
+        if self.len < expect { return (used, None); }
+
+        self.expect = None;
+        (used, Some(self.take_frame(expect)))
+    }
+
+    /// read frame len, returns None if the length prefix is incomplete
+    fn read_header(&mut self, bytes: &[u8], used: &mut usize) -> Option<usize> {
+        let (width, endian) = match self.framing {
+            Framing::LengthPrefix { width, endian } => (width.size(), endian),
+            // zero size frame is meaningless, regarded as 1
+            Framing::FixedSize(size) => return (*used < bytes.len()).then_some(size.max(1)),
+            _ => return None,
+        };
+
+        let copy = (width - self.header_len).min(bytes.len() - *used);
+        self.header[self.header_len..self.header_len + copy].copy_from_slice(&bytes[*used..*used + copy]);
+        self.header_len += copy;
+        *used += copy;
+        if self.header_len < width { return None; }
+
+        self.header_len = 0;
+        let header = &self.header[..width];
+        let len = match endian {
+            Endian::Big => header.iter().fold(0usize, |len, b| (len << 8) | *b as usize),
+            Endian::Little => header.iter().rev().fold(0usize, |len, b| (len << 8) | *b as usize),
+        };
+        Some(len)
+    }
+
+    /// decode delimiter frame, the delimiter is matched without storing it, so a frame of N bytes fits
+    fn decode_delimiter(&mut self, bytes: &[u8], delimiter: &[u8]) -> (usize, Option<SocketResult<&[u8]>>) {
+        for (i, b) in bytes.iter().enumerate() {
+            let matched = self.matched;
+            let data = self.match_delimiter(*b, delimiter);
+            if self.matched == delimiter.len() {
+                self.matched = 0;
+                if self.discard {
+                    self.discard = false;
+                    continue;
+                }
+                return (i + 1, Some(self.take_frame(self.len)));
+            }
+            if self.discard || data == 0 { continue; }
+
+            if self.len + data > N {
+                self.len = 0;
+                self.discard = true;
+                return (i + 1, Some(Err(SocketErr::FrameTooLarge)));
+            }
+
+            // the data is the first matched delimiter bytes, followed by the byte if nothing is matched now
+            let copy = data.min(matched);
+            self.buf[self.len..self.len + copy].copy_from_slice(&delimiter[..copy]);
+            if data > copy { self.buf[self.len + copy] = *b; }
+            self.len += data;
+        }
+        (bytes.len(), None)
+    }
+
+    /// match one byte with the delimiter, returns the number of bytes that turned out to be frame data,
+    /// they are the first bytes of the previously matched delimiter bytes followed by the byte
+    fn match_delimiter(&mut self, b: u8, delimiter: &[u8]) -> usize {
+        if delimiter[self.matched] == b {
+            self.matched += 1;
+            return 0;
+        }
+
+        // the longest tail of the matched bytes and the byte that is still a delimiter prefix
+        let pending = self.matched + 1;
+        let matched = (1..pending).rev()
+            .find(|&k| delimiter[k - 1] == b && delimiter[..k - 1] == delimiter[pending - k..self.matched])
+            .unwrap_or(0);
+        self.matched = matched;
+        pending - matched
+    }
+
+    /// decode cobs or slip frame, end is frame end byte
+    fn decode_end(&mut self, bytes: &[u8], end: u8) -> (usize, Option<SocketResult<&[u8]>>) {
+        for (i, b) in bytes.iter().enumerate() {
+            if *b == end {
+                // empty frame is just a frame separator
+                if self.discard || self.len == 0 {
+                    self.discard = false;
+                    continue;
+                }
+
+                let frame = match self.framing {
+                    Framing::Cobs => self.cobs_decode(),
+                    _ => Ok(self.len),
+                };
+                self.escape = false;
+                return (i + 1, Some(frame.and_then(|len| self.take_frame(len))));
+            }
+            if self.discard { continue; }
+
+            if self.len == N {
+                self.len = 0;
+                self.escape = false;
+                self.discard = true;
+                return (i + 1, Some(Err(SocketErr::FrameTooLarge)));
+            }
+
+            self.buf[self.len] = match (self.framing, self.escape, *b) {
+                (Framing::Slip, false, SLIP_ESC) => {
+                    self.escape = true;
+                    continue;
+                }
+                (Framing::Slip, true, SLIP_ESC_END) => SLIP_END,
+                (Framing::Slip, true, SLIP_ESC_ESC) => SLIP_ESC,
+                // RFC 1055: if the escape is followed by any other byte, just keep the byte
+                (_, _, b) => b,
+            };
+            self.escape = false;
+            self.len += 1;
+        }
+        (bytes.len(), None)
+    }
+
+    /// decode cobs frame in place, returns decoded len
+    fn cobs_decode(&mut self) -> SocketResult<usize> {
+        let (mut read, mut write) = (0, 0);
+        while read < self.len {
+            let code = self.buf[read] as usize;
+            if code == 0 || read + code > self.len {
+                self.len = 0;
+                return Err(SocketErr::InvalidFrame);
+            }
+
+            self.buf.copy_within(read + 1..read + code, write);
+            write += code - 1;
+            read += code;
+            if code != 0xFF && read < self.len {
+                self.buf[write] = 0;
+                write += 1;
+            }
+        }
+        Ok(write)
+    }
+
+    /// mark frame ready and returns frame
+    #[inline]
+    fn take_frame(&mut self, len: usize) -> SocketResult<&[u8]> {
+        self.ready = true;
+        Ok(&self.buf[..len])
+    }
+}
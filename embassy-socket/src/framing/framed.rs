@@ -0,0 +1,143 @@
+use embassy_net::IpEndpoint;
+use embassy_time::Duration;
+use crate::channel::write_channel::WriteChannel;
+use crate::err::SocketErr;
+use crate::framing::decoder::FrameDecoder;
+use crate::framing::Framing;
+use crate::tcp_client::callback::TcpClientCallBack;
+use crate::tcp_server::callback::TcpServerCallBack;
+
+/// framed tcp client callback<br />
+/// reassemble tcp data into whole frames, and then pass every frame to the inner callback [TcpClientCallBack::recv]<br />
+/// F_SZ is max frame size, larger frames will be passed to [TcpClientCallBack::err] as [SocketErr::FrameTooLarge]
+pub struct FramedClient<CB: TcpClientCallBack, const F_SZ: usize> {
+    /// frame decoder
+    decoder: FrameDecoder<F_SZ>,
+    /// inner callback
+    pub cb: CB,
+}
+
+/// custom method
+impl<CB: TcpClientCallBack, const F_SZ: usize> FramedClient<CB, F_SZ> {
+    /// create framed tcp client callback
+    #[inline]
+    pub const fn new(framing: Framing, cb: CB) -> Self {
+        Self { decoder: FrameDecoder::new(framing), cb }
+    }
+}
+
+/// support tcp client callback
+impl<CB: TcpClientCallBack, const F_SZ: usize> TcpClientCallBack for FramedClient<CB, F_SZ> {
+    type T = CB::T;
+
+    async fn conn<const CN: usize>(&mut self, wch: &WriteChannel<'_, CN>, t: &mut Self::T) {
+        self.decoder.reset();
+        self.cb.conn(wch, t).await;
+    }
+
+    async fn dis_conn(&mut self, t: &mut Self::T) {
+        self.decoder.reset();
+        self.cb.dis_conn(t).await;
+    }
+
+    async fn recv<const CN: usize>(&mut self, mut buf: &[u8], wch: &WriteChannel<'_, CN>, t: &mut Self::T) {
+        while !buf.is_empty() {
+            let (used, frame) = self.decoder.decode(buf);
+            buf = &buf[used..];
+            match frame {
+                Some(Ok(frame)) => self.cb.recv(frame, wch, t).await,
+                Some(Err(e)) => self.cb.err(e, t).await,
+                None => {}
+            }
+        }
+    }
+
+    #[inline]
+    async fn err(&mut self, err: SocketErr, t: &mut Self::T) {
+        self.cb.err(err, t).await;
+    }
+
+    #[inline]
+    async fn reconnect(&mut self, attempt: u32, delay: Duration) {
+        self.cb.reconnect(attempt, delay).await;
+    }
+
+    /// the incomplete frame is dropped too, because part of it has been lost
+    async fn dropped<const CN: usize>(&mut self, count: u32, wch: &WriteChannel<'_, CN>, t: &mut Self::T) {
+        self.decoder.reset();
+        self.cb.dropped(count, wch, t).await;
+    }
+}
+
+/// framed tcp server connection data, hold the frame decoder of the connection and the inner callback data
+pub struct FramedState<T, const F_SZ: usize> {
+    /// frame decoder
+    decoder: FrameDecoder<F_SZ>,
+    /// inner callback data
+    pub t: T,
+}
+
+/// custom method
+impl<T, const F_SZ: usize> FramedState<T, F_SZ> {
+    /// create framed tcp server connection data
+    #[inline]
+    pub const fn new(framing: Framing, t: T) -> Self {
+        Self { decoder: FrameDecoder::new(framing), t }
+    }
+}
+
+/// framed tcp server callback<br />
+/// reassemble tcp data into whole frames, and then pass every frame to the inner callback [TcpServerCallBack::recv]<br />
+/// every connection runner needs its own [FramedState] created with the framing<br />
+/// F_SZ is max frame size, larger frames will be passed to [TcpServerCallBack::err] as [SocketErr::FrameTooLarge]
+pub struct FramedServer<CB: TcpServerCallBack, const F_SZ: usize> {
+    /// inner callback
+    pub cb: CB,
+}
+
+/// custom method
+impl<CB: TcpServerCallBack, const F_SZ: usize> FramedServer<CB, F_SZ> {
+    /// create framed tcp server callback
+    #[inline]
+    pub const fn new(cb: CB) -> Self {
+        Self { cb }
+    }
+}
+
+/// support tcp server callback
+impl<CB: TcpServerCallBack, const F_SZ: usize> TcpServerCallBack for FramedServer<CB, F_SZ> {
+    type T = FramedState<CB::T, F_SZ>;
+
+    async fn conn<const CN: usize>(&self, endpoint: IpEndpoint, wch: &WriteChannel<'_, CN>, t: &mut Self::T) {
+        t.decoder.reset();
+        self.cb.conn(endpoint, wch, &mut t.t).await;
+    }
+
+    async fn dis_conn(&self, endpoint: IpEndpoint, t: &mut Self::T) {
+        t.decoder.reset();
+        self.cb.dis_conn(endpoint, &mut t.t).await;
+    }
+
+    async fn recv<const CN: usize>(&self, endpoint: IpEndpoint, mut buf: &[u8], wch: &WriteChannel<'_, CN>, t: &mut Self::T) {
+        while !buf.is_empty() {
+            let (used, frame) = t.decoder.decode(buf);
+            buf = &buf[used..];
+            match frame {
+                Some(Ok(frame)) => self.cb.recv(endpoint, frame, wch, &mut t.t).await,
+                Some(Err(e)) => self.cb.err(e, &mut t.t).await,
+                None => {}
+            }
+        }
+    }
+
+    #[inline]
+    async fn err(&self, err: SocketErr, t: &mut Self::T) {
+        self.cb.err(err, &mut t.t).await;
+    }
+
+    /// the incomplete frame is dropped too, because part of it has been lost
+    async fn dropped<const CN: usize>(&self, endpoint: IpEndpoint, count: u32, wch: &WriteChannel<'_, CN>, t: &mut Self::T) {
+        t.decoder.reset();
+        self.cb.dropped(endpoint, count, wch, &mut t.t).await;
+    }
+}
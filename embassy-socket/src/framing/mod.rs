@@ -0,0 +1,74 @@
+pub mod decoder;
+pub mod framed;
+
+/// length prefix width
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PrefixWidth {
+    /// 1 byte
+    U8,
+    /// 2 bytes
+    U16,
+    /// 4 bytes
+    U32,
+}
+
+/// length prefix byte order
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Endian {
+    /// big endian, network byte order
+    Big,
+    /// little endian
+    Little,
+}
+
+/// socket data framing
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Framing {
+    /// frame starts with a length prefix, the length does not include the prefix itself<br />
+    /// the frame passed to the callback does not contain the prefix
+    LengthPrefix {
+        /// prefix width
+        width: PrefixWidth,
+        /// prefix byte order
+        endian: Endian,
+    },
+    /// frame ends with the delimiter, for example `b"\r\n"`, the delimiter must not be empty<br />
+    /// the frame passed to the callback does not contain the delimiter
+    Delimiter(&'static [u8]),
+    /// every frame has a fixed size, the size must be greater than 0
+    FixedSize(usize),
+    /// consistent overhead byte stuffing, frame ends with 0x00<br />
+    /// the frame passed to the callback is decoded
+    Cobs,
+    /// serial line internet protocol, RFC 1055, frame ends with 0xC0<br />
+    /// the frame passed to the callback is unescaped
+    Slip,
+}
+
+/// custom method
+impl PrefixWidth {
+    /// prefix bytes size
+    #[inline]
+    pub const fn size(&self) -> usize {
+        match self {
+            Self::U8 => 1,
+            Self::U16 => 2,
+            Self::U32 => 4,
+        }
+    }
+}
+
+/// custom method
+impl Framing {
+    /// u16 big endian length prefix, the most common length prefix
+    #[inline]
+    pub const fn u16_be() -> Self {
+        Self::LengthPrefix { width: PrefixWidth::U16, endian: Endian::Big }
+    }
+
+    /// `\r\n` delimiter
+    #[inline]
+    pub const fn crlf() -> Self {
+        Self::Delimiter(b"\r\n")
+    }
+}
@@ -0,0 +1,116 @@
+use core::cell::Cell;
+use core::future::pending;
+use embassy_time::{Duration, Instant, Timer};
+use crate::channel::write_channel::WriteChannel;
+
+/// application-level heartbeat of tcp connections<br />
+/// tcp keep alive only detects a dead peer network stack, the heartbeat detects a dead peer application<br />
+/// when nothing is received for `idle`, the ping is queued to the write channel,
+/// if the response does not arrive within `timeout`, the connection is closed with [crate::err::SocketErr::HeartbeatTimeout]<br />
+/// without ping, the connection is closed as soon as nothing is received for `idle`
+#[derive(Copy, Clone, Debug)]
+pub struct Heartbeat {
+    /// idle time without received data before sending the ping
+    pub idle: Duration,
+    /// time to wait for the response after sending the ping
+    pub timeout: Duration,
+    /// ping payload, empty is no ping, only idle timeout
+    pub ping: &'static [u8],
+    /// expected response matcher, None means any received data is the response<br />
+    /// data is still passed to the callback whether it matches or not
+    pub matcher: Option<fn(&[u8]) -> bool>,
+}
+
+/// custom method
+impl Heartbeat {
+    /// close the connection when nothing is received for idle
+    #[inline]
+    pub const fn idle_timeout(idle: Duration) -> Self {
+        Self { idle, timeout: Duration::from_ticks(0), ping: &[], matcher: None }
+    }
+
+    /// send ping when nothing is received for idle, close the connection when there is no response within timeout
+    #[inline]
+    pub const fn ping(idle: Duration, timeout: Duration, ping: &'static [u8]) -> Self {
+        Self { idle, timeout, ping, matcher: None }
+    }
+
+    /// set expected response matcher
+    #[inline]
+    pub const fn matcher(mut self, matcher: fn(&[u8]) -> bool) -> Self {
+        self.matcher = Some(matcher);
+        self
+    }
+}
+
+/// heartbeat action when the deadline is reached
+pub(crate) enum Action {
+    /// send the ping
+    Ping(&'static [u8]),
+    /// close the connection
+    Timeout,
+}
+
+/// heartbeat supervisor of one connection
+pub(crate) struct Supervisor {
+    /// heartbeat config, None is disabled
+    heartbeat: Option<Heartbeat>,
+    /// last recv time
+    last_recv: Cell<Instant>,
+    /// ping send time, waiting for the response
+    ping_at: Cell<Option<Instant>>,
+}
+
+/// custom method
+impl Supervisor {
+    /// create supervisor of a new connection
+    #[inline]
+    pub(crate) fn new(heartbeat: Option<Heartbeat>) -> Self {
+        Self { heartbeat, last_recv: Cell::new(Instant::now()), ping_at: Cell::new(None) }
+    }
+
+    /// data received
+    pub(crate) fn recv(&self, bytes: &[u8]) {
+        let Some(heartbeat) = self.heartbeat else { return; };
+        self.last_recv.set(Instant::now());
+        if self.ping_at.get().is_some() && heartbeat.matcher.is_none_or(|matcher| matcher(bytes)) {
+            self.ping_at.set(None);
+        }
+    }
+
+    /// next deadline, [Instant::MAX] if disabled
+    pub(crate) fn deadline(&self) -> Instant {
+        let Some(heartbeat) = self.heartbeat else { return Instant::MAX; };
+        match self.ping_at.get() {
+            Some(ping_at) => ping_at + heartbeat.timeout,
+            None => self.last_recv.get() + heartbeat.idle,
+        }
+    }
+
+    /// check the deadline, returns the action if it is reached
+    pub(crate) fn poll(&self) -> Option<Action> {
+        let heartbeat = self.heartbeat?;
+        let now = Instant::now();
+        if now < self.deadline() { return None; }
+
+        if self.ping_at.get().is_some() || heartbeat.ping.is_empty() { return Some(Action::Timeout); }
+        self.ping_at.set(Some(now));
+        Some(Action::Ping(heartbeat.ping))
+    }
+
+    /// supervise the connection, ping is queued to the write channel<br />
+    /// returns when the heartbeat times out, never returns if disabled
+    pub(crate) async fn run<const N: usize>(&self, wch: &WriteChannel<'_, N>) {
+        if self.heartbeat.is_none() { return pending().await; }
+
+        loop {
+            Timer::at(self.deadline()).await;
+            match self.poll() {
+                // if the ping is not queued, the connection is closed by the timeout
+                Some(Action::Ping(ping)) => { wch.send_bytes(ping).await.ok(); }
+                Some(Action::Timeout) => return,
+                None => {}
+            }
+        }
+    }
+}
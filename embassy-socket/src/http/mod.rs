@@ -0,0 +1,222 @@
+use embassy_net::IpEndpoint;
+use crate::channel::overflow_policy::OverflowPolicy;
+use crate::channel::write_channel::WriteChannel;
+use crate::err::SocketErr;
+use crate::http::request::{Method, ParseErr, Request};
+use crate::http::response::Response;
+use crate::tcp_server::callback::TcpServerCallBack;
+
+pub mod request;
+pub mod response;
+
+/// route path match
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RoutePath {
+    /// path must be equal
+    Exact(&'static str),
+    /// path must start with the prefix
+    Prefix(&'static str),
+}
+
+/// http route, a route table is a static slice of routes, the first matched route is used
+#[derive(Copy, Clone, Debug)]
+pub struct Route<R: Copy> {
+    /// method
+    pub method: Method,
+    /// path
+    pub path: RoutePath,
+    /// route value passed to the handler
+    pub route: R,
+}
+
+/// custom method
+impl<R: Copy> Route<R> {
+    /// create route with exact path
+    #[inline]
+    pub const fn new(method: Method, path: &'static str, route: R) -> Self {
+        Self { method, path: RoutePath::Exact(path), route }
+    }
+
+    /// create route with path prefix
+    #[inline]
+    pub const fn prefix(method: Method, prefix: &'static str, route: R) -> Self {
+        Self { method, path: RoutePath::Prefix(prefix), route }
+    }
+
+    /// path matched
+    #[inline]
+    fn path_matched(&self, path: &str) -> bool {
+        match self.path {
+            RoutePath::Exact(p) => p == path,
+            RoutePath::Prefix(p) => path.starts_with(p),
+        }
+    }
+}
+
+/// http handler
+pub trait HttpHandler {
+    /// route value, generally an enum of pages and apis
+    type Route: Copy + 'static;
+
+    /// handle matched route, write the response through resp<br />
+    /// if no response is written, the server responds 500
+    async fn handle<const CN: usize>(&self, route: Self::Route, req: &Request<'_>, resp: &mut Response<'_, '_, CN>);
+
+    /// socket err will call this, default do nothing
+    async fn err(&self, _err: SocketErr) {}
+}
+
+/// http server connection data, hold the request buf of the connection<br />
+/// N is max request size, include request line, headers and body
+pub struct HttpState<const N: usize> {
+    /// request buf
+    buf: [u8; N],
+    /// request buf len
+    len: usize,
+}
+
+/// support default
+impl<const N: usize> Default for HttpState<N> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// custom method
+impl<const N: usize> HttpState<N> {
+    /// create http server connection data
+    #[inline]
+    pub const fn new() -> Self {
+        Self { buf: [0; N], len: 0 }
+    }
+
+    /// drop the incomplete request
+    #[inline]
+    pub fn reset(&mut self) {
+        self.len = 0;
+    }
+}
+
+/// http/1.1 server callback<br />
+/// parse requests from tcp data, dispatch them by the route table,
+/// and write responses through the write channel of the connection<br />
+/// keep-alive and pipelined requests are supported, request body must have `Content-Length`<br />
+/// every connection needs its own [HttpState], the multi-connection runner keeps one per connection,
+/// more see [crate::tcp_server::multi_callback_runner::MultiCallbackRunner]<br />
+/// responses are written in pieces, so the write channel is set to [OverflowPolicy::Block] when connected
+pub struct HttpServer<H: HttpHandler, const N: usize> {
+    /// route table
+    routes: &'static [Route<H::Route>],
+    /// handler
+    pub handler: H,
+}
+
+/// custom method
+impl<H: HttpHandler, const N: usize> HttpServer<H, N> {
+    /// create http server callback
+    #[inline]
+    pub const fn new(routes: &'static [Route<H::Route>], handler: H) -> Self {
+        Self { routes, handler }
+    }
+
+    /// handle all complete requests in the request buf, returns false if the connection should be closed
+    async fn handle_requests<const CN: usize>(&self, endpoint: IpEndpoint, wch: &WriteChannel<'_, CN>, t: &mut HttpState<N>) -> bool {
+        loop {
+            let Some(head_len) = t.buf[..t.len].windows(4).position(|w| w == b"\r\n\r\n") else {
+                if t.len == N { return self.send_err(endpoint, wch, 431).await; }
+                return true;
+            };
+
+            let mut req = match Request::parse(&t.buf[..head_len]) {
+                Ok(req) => req,
+                Err(e) => return self.send_err(endpoint, wch, e.status()).await,
+            };
+            if req.header("Transfer-Encoding").is_some() { return self.send_err(endpoint, wch, ParseErr::NotImplemented.status()).await; }
+            let body_len = match req.content_length() {
+                Ok(len) => len,
+                Err(e) => return self.send_err(endpoint, wch, e.status()).await,
+            };
+
+            let total = head_len + 4 + body_len;
+            if total > N { return self.send_err(endpoint, wch, 413).await; }
+            if total > t.len { return true; }
+
+            req.body = &t.buf[head_len + 4..total];
+            let keep_alive = req.keep_alive();
+            let mut resp = Response::new(wch, endpoint, keep_alive, req.method == Method::Head);
+            self.dispatch(&req, &mut resp).await;
+            let keep_alive = resp.keep_alive();
+
+            t.buf.copy_within(total..t.len, 0);
+            t.len -= total;
+            if !keep_alive {
+                wch.close(Some(endpoint)).await.ok();
+                return false;
+            }
+        }
+    }
+
+    /// dispatch request by the route table
+    async fn dispatch<const CN: usize>(&self, req: &Request<'_>, resp: &mut Response<'_, '_, CN>) {
+        let mut path_matched = false;
+        for route in self.routes.iter().filter(|route| route.path_matched(req.path)) {
+            // HEAD is answered by the GET route
+            if route.method == req.method || (req.method == Method::Head && route.method == Method::Get) {
+                self.handler.handle(route.route, req, resp).await;
+                if !resp.is_started() { resp.send_status(500).await; }
+                return;
+            }
+            path_matched = true;
+        }
+
+        resp.send_status(if path_matched { 405 } else { 404 }).await;
+    }
+
+    /// send error response and close the connection, always returns false
+    async fn send_err<const CN: usize>(&self, endpoint: IpEndpoint, wch: &WriteChannel<'_, CN>, status: u16) -> bool {
+        Response::new(wch, endpoint, false, false).send_status(status).await;
+        wch.close(Some(endpoint)).await.ok();
+        false
+    }
+}
+
+/// support tcp server callback
+impl<H: HttpHandler, const N: usize> TcpServerCallBack for HttpServer<H, N> {
+    type T = HttpState<N>;
+
+    async fn conn<const CN: usize>(&self, _endpoint: IpEndpoint, wch: &WriteChannel<'_, CN>, t: &mut Self::T) {
+        wch.overflow_policy(OverflowPolicy::Block);
+        t.reset();
+    }
+
+    #[inline]
+    async fn dis_conn(&self, _endpoint: IpEndpoint, t: &mut Self::T) {
+        t.reset();
+    }
+
+    async fn recv<const CN: usize>(&self, endpoint: IpEndpoint, mut buf: &[u8], wch: &WriteChannel<'_, CN>, t: &mut Self::T) {
+        while !buf.is_empty() {
+            let copy = (N - t.len).min(buf.len());
+            t.buf[t.len..t.len + copy].copy_from_slice(&buf[..copy]);
+            t.len += copy;
+            buf = &buf[copy..];
+
+            if !self.handle_requests(endpoint, wch, t).await {
+                // the rest data belongs to a closed connection
+                t.reset();
+                return;
+            }
+        }
+    }
+
+    #[inline]
+    async fn err(&self, err: SocketErr, _t: &mut Self::T) {
+        self.handler.err(err).await;
+    }
+
+    /// the incomplete request is dropped too, because part of it has been lost
+    async fn dropped<const CN: usize>(&self, _endpoint: IpEndpoint, _count: u32, _wch: &WriteChannel<'_, CN>, t: &mut Self::T) {
+        t.reset();
+    }
+}
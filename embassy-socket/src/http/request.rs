@@ -0,0 +1,157 @@
+/// max number of request headers
+pub const MAX_HEADERS: usize = 16;
+
+/// http method
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Method {
+    /// GET
+    Get,
+    /// HEAD, the response body is not sent
+    Head,
+    /// POST
+    Post,
+    /// PUT
+    Put,
+    /// DELETE
+    Delete,
+    /// PATCH
+    Patch,
+    /// OPTIONS
+    Options,
+}
+
+/// custom method
+impl Method {
+    /// parse method, returns None if the method is not supported
+    pub fn parse(method: &str) -> Option<Self> {
+        let method = match method {
+            "GET" => Self::Get,
+            "HEAD" => Self::Head,
+            "POST" => Self::Post,
+            "PUT" => Self::Put,
+            "DELETE" => Self::Delete,
+            "PATCH" => Self::Patch,
+            "OPTIONS" => Self::Options,
+            _ => return None,
+        };
+        Some(method)
+    }
+}
+
+/// request parse error, answered with the status code
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ParseErr {
+    /// 400 bad request
+    BadRequest,
+    /// 431 request header fields too large
+    TooManyHeaders,
+    /// 501 not implemented
+    NotImplemented,
+    /// 505 http version not supported
+    VersionNotSupported,
+}
+
+/// custom method
+impl ParseErr {
+    /// response status code
+    #[inline]
+    pub const fn status(&self) -> u16 {
+        match self {
+            Self::BadRequest => 400,
+            Self::TooManyHeaders => 431,
+            Self::NotImplemented => 501,
+            Self::VersionNotSupported => 505,
+        }
+    }
+}
+
+/// http request, all data is borrowed from the connection request buf
+#[derive(Debug)]
+pub struct Request<'a> {
+    /// method
+    pub method: Method,
+    /// path, without query
+    pub path: &'a str,
+    /// query, without `?`
+    pub query: Option<&'a str>,
+    /// http minor version, 0 is HTTP/1.0, 1 is HTTP/1.1
+    pub minor_version: u8,
+    /// headers
+    headers: [(&'a str, &'a str); MAX_HEADERS],
+    /// headers len
+    header_len: usize,
+    /// body
+    pub body: &'a [u8],
+}
+
+/// custom method
+impl<'a> Request<'a> {
+    /// parse request head, head does not contain the empty line
+    pub fn parse(head: &'a [u8]) -> Result<Self, ParseErr> {
+        let head = core::str::from_utf8(head).map_err(|_| ParseErr::BadRequest)?;
+        let mut lines = head.split("\r\n");
+
+        let mut request_line = lines.next().unwrap_or_default().split(' ');
+        let (Some(method), Some(target), Some(version), None) = (request_line.next(), request_line.next(), request_line.next(), request_line.next())
+        else { return Err(ParseErr::BadRequest); };
+        let method = Method::parse(method).ok_or(ParseErr::NotImplemented)?;
+        let minor_version = match version {
+            "HTTP/1.1" => 1,
+            "HTTP/1.0" => 0,
+            _ => return Err(ParseErr::VersionNotSupported),
+        };
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (target, None),
+        };
+
+        let mut headers = [("", ""); MAX_HEADERS];
+        let mut header_len = 0;
+        for line in lines {
+            let (name, value) = line.split_once(':').ok_or(ParseErr::BadRequest)?;
+            let header = headers.get_mut(header_len).ok_or(ParseErr::TooManyHeaders)?;
+            *header = (name.trim(), value.trim());
+            header_len += 1;
+        }
+
+        Ok(Self { method, path, query, minor_version, headers, header_len, body: &[] })
+    }
+
+    /// get header value, name is case-insensitive
+    pub fn header(&self, name: &str) -> Option<&'a str> {
+        self.headers().iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| *v)
+    }
+
+    /// all headers
+    #[inline]
+    pub fn headers(&self) -> &[(&'a str, &'a str)] {
+        &self.headers[..self.header_len]
+    }
+
+    /// content length, 0 if there is no content length header
+    pub fn content_length(&self) -> Result<usize, ParseErr> {
+        match self.header("Content-Length") {
+            Some(len) => len.parse().map_err(|_| ParseErr::BadRequest),
+            None => Ok(0),
+        }
+    }
+
+    /// keep the connection alive after the response<br />
+    /// HTTP/1.1 keeps alive unless `Connection: close`, HTTP/1.0 closes unless `Connection: keep-alive`
+    pub fn keep_alive(&self) -> bool {
+        match self.header("Connection") {
+            Some(conn) if conn.eq_ignore_ascii_case("close") => false,
+            Some(conn) if conn.eq_ignore_ascii_case("keep-alive") => true,
+            _ => self.minor_version == 1,
+        }
+    }
+
+    /// get query param value, the value is not url decoded
+    pub fn query_param(&self, name: &str) -> Option<&'a str> {
+        self.query?.split('&').find_map(|kv| match kv.split_once('=') {
+            Some((k, v)) if k == name => Some(v),
+            None if kv == name => Some(""),
+            _ => None,
+        })
+    }
+}
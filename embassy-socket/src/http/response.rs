@@ -0,0 +1,206 @@
+use embassy_net::IpEndpoint;
+use crate::channel::write_channel::WriteChannel;
+
+/// response body state
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum BodyState {
+    /// head has not been written
+    NotStarted,
+    /// fixed body with content length
+    Fixed,
+    /// chunked body
+    Chunked,
+    /// response has been finished
+    Finished,
+}
+
+/// http response, write status, headers and body through the write channel of the connection<br />
+/// call [Response::write_head] first, then [Response::write_body] any times, and [Response::finish] at last,
+/// or use [Response::send] to do all of them<br />
+/// data is packed into msg of N bytes, and sent when the msg is full or the response is finished<br />
+/// `Connection` header is added by the server, do not add it yourself
+pub struct Response<'a, 'd, const N: usize> {
+    /// write channel
+    wch: &'a WriteChannel<'d, N>,
+    /// connection endpoint
+    endpoint: IpEndpoint,
+    /// keep the connection alive after the response
+    keep_alive: bool,
+    /// HEAD request, the body is not sent
+    head_only: bool,
+    /// body state
+    state: BodyState,
+    /// write cache
+    buf: [u8; N],
+    /// write cache len
+    len: usize,
+}
+
+/// custom method
+impl<'a, 'd, const N: usize> Response<'a, 'd, N> {
+    /// create http response
+    #[inline]
+    pub(crate) fn new(wch: &'a WriteChannel<'d, N>, endpoint: IpEndpoint, keep_alive: bool, head_only: bool) -> Self {
+        Self { wch, endpoint, keep_alive, head_only, state: BodyState::NotStarted, buf: [0; N], len: 0 }
+    }
+
+    /// response has been started
+    #[inline]
+    pub fn is_started(&self) -> bool {
+        self.state != BodyState::NotStarted
+    }
+
+    /// keep the connection alive after the response
+    #[inline]
+    pub fn keep_alive(&self) -> bool {
+        self.keep_alive
+    }
+
+    /// close the connection after the response
+    #[inline]
+    pub fn close_after(&mut self) {
+        self.keep_alive = false;
+    }
+
+    /// write status line and headers<br />
+    /// if content_length is None, the body is sent with chunked transfer encoding<br />
+    /// returns false if the head has been written or the data can not be queued, more see [WriteChannel::send_bytes_to]
+    pub async fn write_head(&mut self, status: u16, headers: &[(&str, &str)], content_length: Option<usize>) -> bool {
+        if self.state != BodyState::NotStarted { return false; }
+
+        let mut num = [0; 20];
+        let mut ok = self.put(b"HTTP/1.1 ").await
+            && self.put(fmt_dec(status as usize, &mut num)).await
+            && self.put(b" ").await
+            && self.put(reason(status).as_bytes()).await
+            && self.put(b"\r\n").await;
+        for (name, value) in headers {
+            ok = ok && self.put_header(name, value.as_bytes()).await;
+        }
+        ok = ok && match content_length {
+            Some(len) => self.put_header("Content-Length", fmt_dec(len, &mut num)).await,
+            None => self.put_header("Transfer-Encoding", b"chunked").await,
+        };
+        let conn: &[u8] = if self.keep_alive { b"keep-alive" } else { b"close" };
+        ok = ok && self.put_header("Connection", conn).await && self.put(b"\r\n").await;
+
+        self.state = if content_length.is_some() { BodyState::Fixed } else { BodyState::Chunked };
+        ok
+    }
+
+    /// write body data, chunked body is sent as one chunk<br />
+    /// returns false if the head has not been written, the response has been finished or the data can not be queued
+    pub async fn write_body(&mut self, bytes: &[u8]) -> bool {
+        match self.state {
+            BodyState::Fixed if self.head_only => true,
+            BodyState::Fixed => self.put(bytes).await,
+            BodyState::Chunked if self.head_only || bytes.is_empty() => true,
+            BodyState::Chunked => {
+                let mut num = [0; 20];
+                self.put(fmt_hex(bytes.len(), &mut num)).await
+                    && self.put(b"\r\n").await
+                    && self.put(bytes).await
+                    && self.put(b"\r\n").await
+            }
+            BodyState::NotStarted | BodyState::Finished => false,
+        }
+    }
+
+    /// finish the response, chunked body is terminated by the last chunk
+    pub async fn finish(&mut self) -> bool {
+        let state = core::mem::replace(&mut self.state, BodyState::Finished);
+        let ok = match state {
+            BodyState::Chunked if !self.head_only => self.put(b"0\r\n\r\n").await,
+            BodyState::NotStarted => false,
+            _ => true,
+        };
+        ok && self.flush().await
+    }
+
+    /// send the cached data now, generally used to stream a slow body
+    pub async fn flush(&mut self) -> bool {
+        let len = core::mem::take(&mut self.len);
+        len == 0 || self.wch.send_bytes_to(&self.buf[..len], self.endpoint).await.is_ok()
+    }
+
+    /// send whole response with fixed body
+    pub async fn send(&mut self, status: u16, content_type: &str, body: &[u8]) -> bool {
+        self.write_head(status, &[("Content-Type", content_type)], Some(body.len())).await
+            && self.write_body(body).await
+            && self.finish().await
+    }
+
+    /// send response without body
+    #[inline]
+    pub async fn send_status(&mut self, status: u16) -> bool {
+        self.write_head(status, &[], Some(0)).await && self.finish().await
+    }
+
+    /// write one header
+    async fn put_header(&mut self, name: &str, value: &[u8]) -> bool {
+        self.put(name.as_bytes()).await && self.put(b": ").await && self.put(value).await && self.put(b"\r\n").await
+    }
+
+    /// write bytes to the cache, the cache is sent when full
+    async fn put(&mut self, mut bytes: &[u8]) -> bool {
+        while !bytes.is_empty() {
+            let copy = (N - self.len).min(bytes.len());
+            self.buf[self.len..self.len + copy].copy_from_slice(&bytes[..copy]);
+            self.len += copy;
+            bytes = &bytes[copy..];
+            if self.len == N && !self.flush().await { return false; }
+        }
+        true
+    }
+}
+
+/// format decimal number
+fn fmt_dec(mut num: usize, buf: &mut [u8; 20]) -> &[u8] {
+    let mut i = buf.len();
+    loop {
+        i -= 1;
+        buf[i] = b'0' + (num % 10) as u8;
+        num /= 10;
+        if num == 0 { return &buf[i..]; }
+    }
+}
+
+/// format hex number
+fn fmt_hex(mut num: usize, buf: &mut [u8; 20]) -> &[u8] {
+    let mut i = buf.len();
+    loop {
+        i -= 1;
+        buf[i] = b"0123456789ABCDEF"[num % 16];
+        num /= 16;
+        if num == 0 { return &buf[i..]; }
+    }
+}
+
+/// status reason phrase
+pub const fn reason(status: u16) -> &'static str {
+    match status {
+        100 => "Continue",
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        411 => "Length Required",
+        413 => "Content Too Large",
+        415 => "Unsupported Media Type",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        503 => "Service Unavailable",
+        505 => "HTTP Version Not Supported",
+        _ => "",
+    }
+}
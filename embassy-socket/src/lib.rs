@@ -7,11 +7,35 @@ pub use embassy_time;
 pub use embassy_sync;
 pub use embedded_hal;
 pub use embedded_hal_async;
+pub use embedded_io_async;
 
 pub mod tcp_client;
 pub mod tcp_server;
+pub mod udp;
 pub mod connection;
 pub mod channel;
 pub mod err;
 pub mod wait;
+pub mod heartbeat;
+pub mod control;
+#[cfg(feature = "framing")]
+pub mod framing;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+#[cfg(feature = "modbus")]
+pub mod modbus;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(feature = "request")]
+pub mod request;
+#[cfg(feature = "websocket")]
+pub mod websocket;
+#[cfg(feature = "bridge")]
+pub mod bridge;
+#[cfg(feature = "sntp")]
+pub mod sntp;
+#[cfg(feature = "mdns")]
+pub mod mdns;
+#[cfg(feature = "tls")]
+pub mod tls;
 pub mod socket_build;
\ No newline at end of file
@@ -0,0 +1,197 @@
+/// dns header len
+pub const HEADER_LEN: usize = 12;
+/// ipv4 address record type
+pub const TYPE_A: u16 = 1;
+/// pointer record type
+pub const TYPE_PTR: u16 = 12;
+/// text record type
+pub const TYPE_TXT: u16 = 16;
+/// service record type
+pub const TYPE_SRV: u16 = 33;
+/// any record type
+pub const TYPE_ANY: u16 = 255;
+/// internet class
+pub const CLASS_IN: u16 = 1;
+/// any class
+pub const CLASS_ANY: u16 = 255;
+/// top bit of the class, unicast response in questions, cache flush in records
+pub const CLASS_FLAG: u16 = 0x8000;
+/// max labels followed in one name, protects against pointer loops
+const MAX_LABELS: usize = 128;
+
+/// read u16 at offset
+#[inline]
+pub fn read_u16(msg: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*msg.get(offset)?, *msg.get(offset + 1)?]))
+}
+
+/// name in a dns message, labels may be compressed with pointers
+#[derive(Copy, Clone, Debug)]
+pub struct Name<'a> {
+    /// whole message
+    msg: &'a [u8],
+    /// name offset
+    offset: usize,
+}
+
+/// custom method
+impl<'a> Name<'a> {
+    /// name at offset of the message
+    #[inline]
+    pub const fn new(msg: &'a [u8], offset: usize) -> Self {
+        Self { msg, offset }
+    }
+
+    /// offset after the name, None if the name is malformed
+    pub fn end(&self) -> Option<usize> {
+        let mut offset = self.offset;
+        loop {
+            let len = *self.msg.get(offset)? as usize;
+            match len {
+                0 => return Some(offset + 1),
+                _ if len & 0xC0 == 0xC0 => return (offset + 2 <= self.msg.len()).then_some(offset + 2),
+                _ => offset += 1 + len,
+            }
+        }
+    }
+
+    /// name equals the dotted parts, ascii case insensitive, for example `["device", "local"]` is `device.local`
+    pub fn matches(&self, parts: &[&str]) -> bool {
+        let mut expected = parts.iter().flat_map(|part| part.split('.')).filter(|label| !label.is_empty());
+        let mut offset = self.offset;
+        for _ in 0..MAX_LABELS {
+            let Some(len) = self.msg.get(offset).map(|len| *len as usize) else { return false; };
+            if len == 0 { return expected.next().is_none(); }
+            if len & 0xC0 == 0xC0 {
+                let Some(pointer) = read_u16(self.msg, offset) else { return false; };
+                offset = (pointer & 0x3FFF) as usize;
+                continue;
+            }
+
+            let Some(label) = self.msg.get(offset + 1..offset + 1 + len) else { return false; };
+            if !expected.next().is_some_and(|e| e.as_bytes().eq_ignore_ascii_case(label)) { return false; }
+            offset += 1 + len;
+        }
+        false
+    }
+}
+
+/// question of a query
+#[derive(Copy, Clone, Debug)]
+pub struct Question<'a> {
+    /// question name
+    pub name: Name<'a>,
+    /// question type
+    pub qtype: u16,
+    /// unicast response is requested
+    pub unicast: bool,
+}
+
+/// parse the questions of a query, returns the offset after the question section<br />
+/// None if the message is a response or malformed
+pub fn questions<'a>(msg: &'a [u8], mut f: impl FnMut(Question<'a>)) -> Option<usize> {
+    let flags = read_u16(msg, 2)?;
+    // responses and other opcodes are not answered
+    if flags & 0xF800 != 0 { return None; }
+
+    let mut offset = HEADER_LEN;
+    for _ in 0..read_u16(msg, 4)? {
+        let name = Name::new(msg, offset);
+        offset = name.end()?;
+        let (qtype, class) = (read_u16(msg, offset)?, read_u16(msg, offset + 2)?);
+        offset += 4;
+        if matches!(class & !CLASS_FLAG, CLASS_IN | CLASS_ANY) {
+            f(Question { name, qtype, unicast: class & CLASS_FLAG != 0 });
+        }
+    }
+    Some(offset)
+}
+
+/// dns message writer, the record that does not fit is dropped
+pub struct Writer<'a> {
+    /// output buf
+    buf: &'a mut [u8],
+    /// written len
+    len: usize,
+    /// a write did not fit
+    overflow: bool,
+}
+
+/// custom method
+impl<'a> Writer<'a> {
+    /// create writer, the header is written by [Writer::header]
+    #[inline]
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        let overflow = buf.len() < HEADER_LEN;
+        Self { buf, len: HEADER_LEN, overflow }
+    }
+
+    /// written len
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// nothing is written after the header
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == HEADER_LEN
+    }
+
+    /// write header, the counts are questions, answers and additional records
+    pub fn header(&mut self, id: u16, flags: u16, counts: [u16; 3]) {
+        if self.buf.len() < HEADER_LEN { return; }
+        let [qd, an, ar] = counts;
+        for (i, v) in [id, flags, qd, an, 0, ar].iter().enumerate() {
+            self.buf[i * 2..i * 2 + 2].copy_from_slice(&v.to_be_bytes());
+        }
+    }
+
+    /// write bytes
+    pub fn put(&mut self, bytes: &[u8]) {
+        if self.overflow || self.len + bytes.len() > self.buf.len() {
+            self.overflow = true;
+            return;
+        }
+        self.buf[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+
+    /// write u16
+    #[inline]
+    pub fn u16(&mut self, v: u16) {
+        self.put(&v.to_be_bytes())
+    }
+
+    /// write uncompressed name of the dotted parts
+    pub fn name(&mut self, parts: &[&str]) {
+        for label in parts.iter().flat_map(|part| part.split('.')).filter(|label| !label.is_empty()) {
+            // labels are at most 63 bytes
+            let label = &label.as_bytes()[..label.len().min(63)];
+            self.put(&[label.len() as u8]);
+            self.put(label);
+        }
+        self.put(&[0]);
+    }
+
+    /// write record, rdata writes the record data, returns false if the record does not fit
+    pub fn record(&mut self, name: &[&str], rtype: u16, flush: bool, ttl: u32, rdata: impl FnOnce(&mut Self)) -> bool {
+        let start = self.len;
+        self.name(name);
+        self.u16(rtype);
+        self.u16(if flush { CLASS_IN | CLASS_FLAG } else { CLASS_IN });
+        self.put(&ttl.to_be_bytes());
+        self.u16(0);
+        let data = self.len;
+        rdata(self);
+
+        if self.overflow {
+            // the previous records are kept, later records are dropped
+            self.len = start;
+            return false;
+        }
+        let rdlength = ((self.len - data) as u16).to_be_bytes();
+        self.buf[data - 2..data].copy_from_slice(&rdlength);
+        true
+    }
+}
@@ -0,0 +1,283 @@
+use embassy_futures::join::join;
+use embassy_futures::select::select;
+use embassy_net::{IpAddress, IpEndpoint, Ipv4Address, Stack};
+use embassy_net::udp::UdpSocket;
+use embassy_time::{Duration, Timer};
+use crate::connection::udp_connection::UdpConnection;
+use crate::connection::udp_state::UdpState;
+use crate::err::SocketResult;
+use crate::mdns::message::{questions, Writer, HEADER_LEN, TYPE_A, TYPE_ANY, TYPE_PTR, TYPE_SRV, TYPE_TXT};
+
+pub mod message;
+
+/// mdns port
+pub const MDNS_PORT: u16 = 5353;
+/// mdns ipv4 multicast group
+pub const MDNS_GROUP: Ipv4Address = Ipv4Address::new(224, 0, 0, 251);
+/// ttl of the host records, address and srv
+pub const HOST_TTL: u32 = 120;
+/// ttl of the other records
+pub const SERVICE_TTL: u32 = 4500;
+/// max ttl of the answers to legacy unicast queries
+pub const LEGACY_TTL: u32 = 10;
+/// max advertised services, more services are ignored
+pub const MAX_SERVICES: usize = 32;
+/// dns-sd service type enumeration name
+pub const SERVICES_NAME: &str = "_services._dns-sd._udp.local";
+/// response flags, response and authoritative answer
+const RESPONSE_FLAGS: u16 = 0x8400;
+/// delay before binding again after an error
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// dns-sd service advertised by the responder
+#[derive(Copy, Clone, Debug)]
+pub struct Service<'d> {
+    /// service instance name, None is the host name
+    pub instance: Option<&'d str>,
+    /// service type and protocol, for example `_http._tcp`
+    pub service: &'d str,
+    /// port served by the tcp server
+    pub port: u16,
+    /// txt record strings, for example `path=/`
+    pub txt: &'d [&'d str],
+}
+
+/// custom method
+impl<'d> Service<'d> {
+    /// create service of the port, the instance name is the host name
+    #[inline]
+    pub const fn new(service: &'d str, port: u16) -> Self {
+        Self { instance: None, service, port, txt: &[] }
+    }
+
+    /// set instance name
+    #[inline]
+    pub const fn instance(mut self, instance: &'d str) -> Self {
+        self.instance = Some(instance);
+        self
+    }
+
+    /// set txt record strings
+    #[inline]
+    pub const fn txt(mut self, txt: &'d [&'d str]) -> Self {
+        self.txt = txt;
+        self
+    }
+}
+
+/// records to answer in one response, service bit i is service i
+#[derive(Copy, Clone, Debug, Default)]
+struct Answers {
+    /// host address
+    a: bool,
+    /// service type enumeration
+    services: bool,
+    /// service instance pointers
+    ptr: u32,
+    /// service srv records
+    srv: u32,
+    /// service txt records
+    txt: u32,
+}
+
+/// records of the host, the `<hostname>.local` address and the dns-sd services
+#[derive(Copy, Clone, Debug)]
+pub struct Records<'d> {
+    /// host name without `.local`
+    pub hostname: &'d str,
+    /// advertised services, at most [MAX_SERVICES]
+    pub services: &'d [Service<'d>],
+}
+
+/// custom method
+impl<'d> Records<'d> {
+    /// create records
+    #[inline]
+    pub const fn new(hostname: &'d str, services: &'d [Service<'d>]) -> Self {
+        Self { hostname, services }
+    }
+
+    /// answer the query with the host address, returns the response len and whether it is sent to the source only<br />
+    /// None if nothing is asked about this host<br />
+    /// legacy is a query not from port 5353, the answer repeats the query id and questions, and the ttl is limited
+    pub fn answer(&self, query: &[u8], addr: Ipv4Address, legacy: bool, out: &mut [u8]) -> Option<(usize, bool)> {
+        let mut answers = Answers::default();
+        let mut unicast = legacy;
+        let end = questions(query, |q| {
+            let any = q.qtype == TYPE_ANY;
+            let before = (answers.a, answers.services, answers.ptr, answers.srv, answers.txt);
+            if (any || q.qtype == TYPE_A) && q.name.matches(&[self.hostname, "local"]) { answers.a = true; }
+            if (any || q.qtype == TYPE_PTR) && q.name.matches(&[SERVICES_NAME]) { answers.services = true; }
+            for (i, s) in self.services.iter().enumerate().take(MAX_SERVICES) {
+                if (any || q.qtype == TYPE_PTR) && q.name.matches(&[s.service, "local"]) { answers.ptr |= 1 << i; }
+                if !q.name.matches(&[self.instance(s), s.service, "local"]) { continue; }
+                if any || q.qtype == TYPE_SRV { answers.srv |= 1 << i; }
+                if any || q.qtype == TYPE_TXT { answers.txt |= 1 << i; }
+            }
+            // the unicast bit only counts for the questions this host answers
+            unicast |= q.unicast && before != (answers.a, answers.services, answers.ptr, answers.srv, answers.txt);
+        })?;
+
+        let mut w = Writer::new(out);
+        let (id, qd) = match legacy {
+            true => {
+                // the questions are copied at the same offset, so compressed names stay valid
+                w.put(&query[HEADER_LEN..end]);
+                (u16::from_be_bytes([query[0], query[1]]), u16::from_be_bytes([query[4], query[5]]))
+            }
+            false => (0, 0),
+        };
+        let an = self.write(&mut w, answers, addr, legacy);
+        if an == 0 { return None; }
+
+        // the records the peer will ask next are sent as additional records
+        let srv = answers.srv | answers.ptr;
+        let additional = Answers {
+            a: !answers.a && srv != 0,
+            services: false,
+            ptr: 0,
+            srv: answers.ptr & !answers.srv,
+            txt: answers.ptr & !answers.txt,
+        };
+        let ar = self.write(&mut w, additional, addr, legacy);
+        w.header(id, RESPONSE_FLAGS, [qd, an, ar]);
+        Some((w.len(), unicast))
+    }
+
+    /// announce all records, returns the response len
+    pub fn announce(&self, addr: Ipv4Address, out: &mut [u8]) -> usize {
+        let all = match self.services.len().min(MAX_SERVICES) {
+            MAX_SERVICES => u32::MAX,
+            len => (1 << len) - 1,
+        };
+        let mut w = Writer::new(out);
+        let an = self.write(&mut w, Answers { a: true, services: all != 0, ptr: all, srv: all, txt: all }, addr, false);
+        w.header(0, RESPONSE_FLAGS, [0, an, 0]);
+        w.len()
+    }
+
+    /// instance name of the service
+    #[inline]
+    fn instance(&self, service: &Service<'d>) -> &'d str {
+        service.instance.unwrap_or(self.hostname)
+    }
+
+    /// write the records, returns the number of records written
+    fn write(&self, w: &mut Writer<'_>, answers: Answers, addr: Ipv4Address, legacy: bool) -> u16 {
+        let ttl = |ttl: u32| if legacy { ttl.min(LEGACY_TTL) } else { ttl };
+        // the cache flush bit must not be set in legacy answers
+        let flush = !legacy;
+        let mut count = 0;
+        let host = [self.hostname, "local"];
+        if answers.a {
+            count += w.record(&host, TYPE_A, flush, ttl(HOST_TTL), |w| w.put(&addr.octets())) as u16;
+        }
+        for (i, s) in self.services.iter().enumerate().take(MAX_SERVICES) {
+            let bit = 1 << i;
+            let instance = [self.instance(s), s.service, "local"];
+            // a service type is enumerated once, even if several instances have it
+            let first = !self.services[..i].iter().any(|other| other.service.eq_ignore_ascii_case(s.service));
+            if answers.services && first {
+                count += w.record(&[SERVICES_NAME], TYPE_PTR, false, ttl(SERVICE_TTL), |w| w.name(&[s.service, "local"])) as u16;
+            }
+            if answers.ptr & bit != 0 {
+                count += w.record(&[s.service, "local"], TYPE_PTR, false, ttl(SERVICE_TTL), |w| w.name(&instance)) as u16;
+            }
+            if answers.srv & bit != 0 {
+                count += w.record(&instance, TYPE_SRV, flush, ttl(HOST_TTL), |w| {
+                    // priority and weight
+                    w.u16(0);
+                    w.u16(0);
+                    w.u16(s.port);
+                    w.name(&host);
+                }) as u16;
+            }
+            if answers.txt & bit != 0 {
+                count += w.record(&instance, TYPE_TXT, flush, ttl(SERVICE_TTL), |w| {
+                    // txt record must contain at least one string
+                    if s.txt.is_empty() { w.put(&[0]); }
+                    for txt in s.txt {
+                        let txt = &txt.as_bytes()[..txt.len().min(255)];
+                        w.put(&[txt.len() as u8]);
+                        w.put(txt);
+                    }
+                }) as u16;
+            }
+        }
+        count
+    }
+}
+
+/// mdns responder, answer address queries of `<hostname>.local` and dns-sd queries of the services<br />
+/// runs as a single task on the net stack with its own udp socket, the socket is bound again after the link is up again<br />
+/// ipv4 only, the host name is not probed for conflicts, so it must be unique on the network, for example contain the mac
+pub struct MdnsResponder<'d, const OUT_SZ: usize> {
+    /// net stack
+    stack: Stack<'d>,
+    /// udp state, memory pool
+    state: &'d dyn UdpState,
+    /// host records
+    records: Records<'d>,
+}
+
+/// custom method
+impl<'d, const OUT_SZ: usize> MdnsResponder<'d, OUT_SZ> {
+    /// create mdns responder, OUT_SZ is the max response size
+    #[inline]
+    pub fn new(stack: Stack<'d>, state: &'d dyn UdpState, records: Records<'d>) -> Self {
+        Self { stack, state, records }
+    }
+
+    /// run mdns responder, never returns
+    pub async fn run(&self) {
+        loop {
+            if self.run_logic().await.is_err() { Timer::after(RETRY_DELAY).await; }
+        }
+    }
+
+    /// run logic, bind and join the group, announce and answer until the network link is down
+    async fn run_logic(&self) -> SocketResult<()> {
+        self.stack.wait_link_up().await;
+        self.stack.wait_config_up().await;
+
+        let mut conn = UdpConnection::new(self.stack, self.state)?;
+        conn.socket.bind(MDNS_PORT)?;
+        if !self.stack.has_multicast_group(MDNS_GROUP) { self.stack.join_multicast_group(MDNS_GROUP)?; }
+
+        let (socket, buf) = conn.split();
+        select(join(self.announce(socket), self.answer_logic(socket, buf)), self.stack.wait_link_down()).await;
+        Ok(())
+    }
+
+    /// current ipv4 address
+    #[inline]
+    fn address(&self) -> Option<Ipv4Address> {
+        self.stack.config_v4().map(|config| config.address.address())
+    }
+
+    /// announce the records twice, one second apart
+    async fn announce(&self, socket: &UdpSocket<'_>) {
+        let mut out = [0; OUT_SZ];
+        for i in 0..2 {
+            if i > 0 { Timer::after(Duration::from_secs(1)).await; }
+            let Some(addr) = self.address() else { return; };
+            let len = self.records.announce(addr, &mut out);
+            socket.send_to(&out[..len], IpEndpoint::new(IpAddress::Ipv4(MDNS_GROUP), MDNS_PORT)).await.ok();
+        }
+    }
+
+    /// answer queries, the answer is sent to the group unless the source asked for a unicast answer
+    async fn answer_logic(&self, socket: &UdpSocket<'_>, buf: &mut [u8]) {
+        let mut out = [0; OUT_SZ];
+        loop {
+            // truncated or failed reads are ignored, the peer asks again
+            let Ok((len, meta)) = socket.recv_from(buf).await else { continue; };
+            let Some(addr) = self.address() else { continue; };
+            let legacy = meta.endpoint.port != MDNS_PORT;
+            let Some((len, unicast)) = self.records.answer(&buf[..len], addr, legacy, &mut out) else { continue; };
+
+            let endpoint = if unicast { meta.endpoint } else { IpEndpoint::new(IpAddress::Ipv4(MDNS_GROUP), MDNS_PORT) };
+            socket.send_to(&out[..len], endpoint).await.ok();
+        }
+    }
+}
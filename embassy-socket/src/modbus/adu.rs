@@ -0,0 +1,100 @@
+use crate::err::{SocketErr, SocketResult};
+use crate::modbus::{MAX_ADU, MBAP_LEN};
+
+/// modbus adu
+#[derive(Copy, Clone, Debug)]
+pub struct Adu<'a> {
+    /// transaction id
+    pub transaction_id: u16,
+    /// unit id
+    pub unit_id: u8,
+    /// pdu, function code and data
+    pub pdu: &'a [u8],
+}
+
+/// custom method
+impl Adu<'_> {
+    /// encode adu to buf, returns the adu len<br />
+    /// the pdu must not be longer than [crate::modbus::MAX_PDU]
+    pub fn encode(transaction_id: u16, unit_id: u8, pdu: &[u8], buf: &mut [u8; MAX_ADU]) -> usize {
+        buf[0..2].copy_from_slice(&transaction_id.to_be_bytes());
+        buf[2..4].copy_from_slice(&[0, 0]);
+        buf[4..6].copy_from_slice(&(pdu.len() as u16 + 1).to_be_bytes());
+        buf[6] = unit_id;
+        buf[MBAP_LEN..MBAP_LEN + pdu.len()].copy_from_slice(pdu);
+        MBAP_LEN + pdu.len()
+    }
+}
+
+/// modbus adu decoder, reassemble tcp data into whole adu by the mbap header
+pub struct AduDecoder {
+    /// adu buf
+    buf: [u8; MAX_ADU],
+    /// adu buf len
+    len: usize,
+    /// the adu in buf has been returned, and buf should be cleared on next decode
+    ready: bool,
+}
+
+/// support default
+impl Default for AduDecoder {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// custom method
+impl AduDecoder {
+    /// create adu decoder
+    #[inline]
+    pub const fn new() -> Self {
+        Self { buf: [0; MAX_ADU], len: 0, ready: false }
+    }
+
+    /// drop the incomplete adu, generally called when connection changes or data was lost
+    #[inline]
+    pub fn reset(&mut self) {
+        self.len = 0;
+        self.ready = false;
+    }
+
+    /// decode bytes, returns the number of bytes used and the decoded adu<br />
+    /// if the adu is None, all bytes have been used<br />
+    /// invalid mbap header is reported as [SocketErr::InvalidFrame], the stream can not be resynchronized,
+    /// so the connection should be closed<br />
+    /// call this method repeatedly with the remaining bytes until all bytes have been used
+    pub fn decode(&mut self, bytes: &[u8]) -> (usize, Option<SocketResult<Adu<'_>>>) {
+        if self.ready { self.reset(); }
+
+        // read mbap header first, then the pdu
+        let mut used = 0;
+        let expect = loop {
+            let expect = if self.len < MBAP_LEN { MBAP_LEN } else {
+                // the mbap length includes the unit id, the pdu has at least the function code
+                let len = u16::from_be_bytes([self.buf[4], self.buf[5]]) as usize;
+                let protocol_id = u16::from_be_bytes([self.buf[2], self.buf[3]]);
+                if protocol_id != 0 || len < 2 || MBAP_LEN - 1 + len > MAX_ADU {
+                    self.reset();
+                    return (used, Some(Err(SocketErr::InvalidFrame)));
+                }
+                MBAP_LEN - 1 + len
+            };
+
+            let copy = (expect - self.len).min(bytes.len() - used);
+            self.buf[self.len..self.len + copy].copy_from_slice(&bytes[used..used + copy]);
+            self.len += copy;
+            used += copy;
+            if self.len < expect { return (used, None); }
+            if expect > MBAP_LEN { break expect; }
+        };
+
+        self.ready = true;
+        let adu = Adu {
+            transaction_id: u16::from_be_bytes([self.buf[0], self.buf[1]]),
+            unit_id: self.buf[6],
+            pdu: &self.buf[MBAP_LEN..expect],
+        };
+        (used, Some(Ok(adu)))
+    }
+}
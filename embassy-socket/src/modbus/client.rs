@@ -0,0 +1,268 @@
+use embassy_futures::select::{select, Either};
+use embassy_time::{Duration, Instant, Timer};
+use crate::channel::callback_enum::CallbackEnum;
+use crate::channel::SocketChannel;
+use crate::err::SocketResult;
+use crate::modbus::adu::{Adu, AduDecoder};
+use crate::modbus::{Coils, Exception, ModbusErr, MAX_ADU, MAX_PDU, MAX_READ_BITS, MAX_READ_REGISTERS, MAX_WRITE_BITS,
+                    MAX_WRITE_REGISTERS, READ_COILS, READ_DISCRETE_INPUTS, READ_HOLDING_REGISTERS, READ_INPUT_REGISTERS,
+                    WRITE_MULTIPLE_COILS, WRITE_MULTIPLE_REGISTERS, WRITE_SINGLE_COIL, WRITE_SINGLE_REGISTER};
+
+/// modbus tcp client, send one request at a time and wait for the response with the same transaction id<br />
+/// responses of timed out requests are discarded<br />
+/// default unit id is 1, default timeout is 1 second
+pub struct ModbusClient<'d, const RC_SZ: usize, const WC_SZ: usize> {
+    /// socket channel
+    socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
+    /// unit id
+    unit_id: u8,
+    /// response timeout
+    timeout: Duration,
+    /// last transaction id
+    transaction_id: u16,
+    /// adu decoder
+    decoder: AduDecoder,
+}
+
+/// custom method
+impl<'d, const RC_SZ: usize, const WC_SZ: usize> ModbusClient<'d, RC_SZ, WC_SZ> {
+    /// create modbus tcp client
+    #[inline]
+    pub fn new(socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>) -> Self {
+        Self {
+            socket_channel,
+            unit_id: 1,
+            timeout: Duration::from_secs(1),
+            transaction_id: 0,
+            decoder: AduDecoder::new(),
+        }
+    }
+
+    /// set unit id of the following requests
+    #[inline]
+    pub fn unit_id(&mut self, unit_id: u8) {
+        self.unit_id = unit_id;
+    }
+
+    /// set response timeout
+    #[inline]
+    pub fn timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// read coils from addr, the number of coils is the len of coils, function code 1
+    #[inline]
+    pub async fn read_coils(&mut self, addr: u16, coils: &mut [bool]) -> SocketResult<()> {
+        self.read_bits(READ_COILS, addr, coils).await
+    }
+
+    /// read discrete inputs from addr, the number of inputs is the len of inputs, function code 2
+    #[inline]
+    pub async fn read_discrete_inputs(&mut self, addr: u16, inputs: &mut [bool]) -> SocketResult<()> {
+        self.read_bits(READ_DISCRETE_INPUTS, addr, inputs).await
+    }
+
+    /// read holding registers from addr, the number of registers is the len of regs, function code 3
+    #[inline]
+    pub async fn read_holding_registers(&mut self, addr: u16, regs: &mut [u16]) -> SocketResult<()> {
+        self.read_registers(READ_HOLDING_REGISTERS, addr, regs).await
+    }
+
+    /// read input registers from addr, the number of registers is the len of regs, function code 4
+    #[inline]
+    pub async fn read_input_registers(&mut self, addr: u16, regs: &mut [u16]) -> SocketResult<()> {
+        self.read_registers(READ_INPUT_REGISTERS, addr, regs).await
+    }
+
+    /// write single coil, function code 5
+    pub async fn write_single_coil(&mut self, addr: u16, value: bool) -> SocketResult<()> {
+        let value: u16 = if value { 0xFF00 } else { 0x0000 };
+        self.write_single(WRITE_SINGLE_COIL, addr, value).await
+    }
+
+    /// write single holding register, function code 6
+    #[inline]
+    pub async fn write_single_register(&mut self, addr: u16, value: u16) -> SocketResult<()> {
+        self.write_single(WRITE_SINGLE_REGISTER, addr, value).await
+    }
+
+    /// write coils from addr, function code 15
+    pub async fn write_multiple_coils(&mut self, addr: u16, coils: &[bool]) -> SocketResult<()> {
+        check_range(addr, coils.len(), MAX_WRITE_BITS)?;
+        let count = coils.len().div_ceil(8);
+        let mut pdu = [0; MAX_PDU];
+        let len = write_header(&mut pdu, WRITE_MULTIPLE_COILS, addr, coils.len() as u16);
+        pdu[len] = count as u8;
+        let mut bits = Coils::new(&mut pdu[len + 1..len + 1 + count], coils.len());
+        for (i, coil) in coils.iter().enumerate() { bits.set(i, *coil); }
+
+        let mut resp = [0; MAX_PDU];
+        let resp_len = self.request(&pdu[..len + 1 + count], &mut resp).await?;
+        check_echo(&resp[..resp_len], &pdu[..len])
+    }
+
+    /// write holding registers from addr, function code 16
+    pub async fn write_multiple_registers(&mut self, addr: u16, regs: &[u16]) -> SocketResult<()> {
+        check_range(addr, regs.len(), MAX_WRITE_REGISTERS)?;
+        let mut pdu = [0; MAX_PDU];
+        let len = write_header(&mut pdu, WRITE_MULTIPLE_REGISTERS, addr, regs.len() as u16);
+        pdu[len] = (regs.len() * 2) as u8;
+        for (i, reg) in regs.iter().enumerate() {
+            pdu[len + 1 + i * 2..len + 3 + i * 2].copy_from_slice(&reg.to_be_bytes());
+        }
+
+        let mut resp = [0; MAX_PDU];
+        let resp_len = self.request(&pdu[..len + 1 + regs.len() * 2], &mut resp).await?;
+        check_echo(&resp[..resp_len], &pdu[..len])
+    }
+
+    /// read coils or discrete inputs
+    async fn read_bits(&mut self, fc: u8, addr: u16, out: &mut [bool]) -> SocketResult<()> {
+        check_range(addr, out.len(), MAX_READ_BITS)?;
+        let mut pdu = [0; 5];
+        write_header(&mut pdu, fc, addr, out.len() as u16);
+
+        let mut resp = [0; MAX_PDU];
+        let len = self.request(&pdu, &mut resp).await?;
+        let count = out.len().div_ceil(8);
+        if len != 2 + count || resp[1] as usize != count { return Err(ModbusErr::InvalidResponse.into()); }
+
+        let bits = Coils::new(&mut resp[2..2 + count], out.len());
+        for (i, bit) in out.iter_mut().enumerate() { *bit = bits.get(i); }
+        Ok(())
+    }
+
+    /// read holding registers or input registers
+    async fn read_registers(&mut self, fc: u8, addr: u16, out: &mut [u16]) -> SocketResult<()> {
+        check_range(addr, out.len(), MAX_READ_REGISTERS)?;
+        let mut pdu = [0; 5];
+        write_header(&mut pdu, fc, addr, out.len() as u16);
+
+        let mut resp = [0; MAX_PDU];
+        let len = self.request(&pdu, &mut resp).await?;
+        if len != 2 + out.len() * 2 || resp[1] as usize != out.len() * 2 { return Err(ModbusErr::InvalidResponse.into()); }
+
+        for (i, reg) in out.iter_mut().enumerate() {
+            *reg = u16::from_be_bytes([resp[2 + i * 2], resp[3 + i * 2]]);
+        }
+        Ok(())
+    }
+
+    /// write single coil or single register, the response echoes the request
+    async fn write_single(&mut self, fc: u8, addr: u16, value: u16) -> SocketResult<()> {
+        let mut pdu = [0; 5];
+        write_header(&mut pdu, fc, addr, value);
+
+        let mut resp = [0; MAX_PDU];
+        let len = self.request(&pdu, &mut resp).await?;
+        check_echo(&resp[..len], &pdu)
+    }
+
+    /// send request pdu and wait for the response pdu, returns the response pdu len<br />
+    /// exception responses are returned as [ModbusErr::Exception]
+    async fn request(&mut self, pdu: &[u8], resp: &mut [u8; MAX_PDU]) -> SocketResult<usize> {
+        self.drain(resp).await;
+        self.transaction_id = self.transaction_id.wrapping_add(1);
+        let mut adu = [0; MAX_ADU];
+        let len = Adu::encode(self.transaction_id, self.unit_id, pdu, &mut adu);
+        self.socket_channel.write_channel.send_bytes(&adu[..len]).await?;
+
+        let start = Instant::now();
+        let deadline = start + self.timeout;
+        let resp_len = loop {
+            let read = self.socket_channel.read_channel.wait_read_ref();
+            let msg = match select(read, Timer::at(deadline)).await {
+                Either::First(msg) => msg,
+                Either::Second(_) => return Err(ModbusErr::Timeout.into()),
+            };
+
+            match msg.callback_enum() {
+                CallbackEnum::Recv => match self.recv_logic(msg.as_bytes(), resp) {
+                    Ok(Some(len)) => break len,
+                    Ok(None) => {}
+                    Err(e) => {
+                        // the stream can not be resynchronized
+                        drop(msg);
+                        self.socket_channel.write_channel.close(None).await.ok();
+                        return Err(e);
+                    }
+                },
+                CallbackEnum::Disconnect => {
+                    self.decoder.reset();
+                    return Err(ModbusErr::NotConnected.into());
+                }
+                CallbackEnum::Dropped(_) => self.decoder.reset(),
+                CallbackEnum::Err(e) => return Err(e),
+                CallbackEnum::Conn | CallbackEnum::Reconnect { .. } => {}
+            }
+        };
+        self.socket_channel.read_channel.rtt_sample(Instant::now() - start);
+
+        if resp[0] == pdu[0] | 0x80 {
+            let exception = Exception::from_code(resp[1]).ok_or(ModbusErr::InvalidResponse)?;
+            return Err(ModbusErr::Exception(exception).into());
+        }
+        if resp[0] != pdu[0] { return Err(ModbusErr::InvalidResponse.into()); }
+        Ok(resp_len)
+    }
+
+    /// handle the msg queued before the request, a disconnect or error seen here belongs to an earlier connection
+    /// and must not fail the request<br />
+    /// late responses of timed out requests are decoded and discarded, so that the stream stays in sync
+    async fn drain(&mut self, resp: &mut [u8; MAX_PDU]) {
+        while !self.socket_channel.read_channel.is_empty().await {
+            let msg = self.socket_channel.read_channel.wait_read_ref().await;
+            match msg.callback_enum() {
+                // responses carry earlier transaction ids, a decode error has reset the decoder
+                CallbackEnum::Recv => { self.recv_logic(msg.as_bytes(), resp).ok(); }
+                CallbackEnum::Disconnect | CallbackEnum::Dropped(_) => self.decoder.reset(),
+                CallbackEnum::Err(_) | CallbackEnum::Conn | CallbackEnum::Reconnect { .. } => {}
+            }
+        }
+    }
+
+    /// decode recv data, copy the pdu of the current transaction to resp and returns the len<br />
+    /// adu of other transactions are discarded
+    fn recv_logic(&mut self, mut buf: &[u8], resp: &mut [u8; MAX_PDU]) -> SocketResult<Option<usize>> {
+        let mut found = None;
+        while !buf.is_empty() {
+            let (used, adu) = self.decoder.decode(buf);
+            buf = &buf[used..];
+            match adu {
+                Some(Ok(adu)) if adu.transaction_id == self.transaction_id && adu.unit_id == self.unit_id => {
+                    resp[..adu.pdu.len()].copy_from_slice(adu.pdu);
+                    found = Some(adu.pdu.len());
+                }
+                Some(Err(e)) => {
+                    self.decoder.reset();
+                    return Err(e);
+                }
+                _ => {}
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// write function code, address and quantity or value to pdu, returns the len
+#[inline]
+fn write_header(pdu: &mut [u8], fc: u8, addr: u16, value: u16) -> usize {
+    pdu[0] = fc;
+    pdu[1..3].copy_from_slice(&addr.to_be_bytes());
+    pdu[3..5].copy_from_slice(&value.to_be_bytes());
+    5
+}
+
+/// check quantity and address range
+#[inline]
+fn check_range(addr: u16, qty: usize, max: usize) -> SocketResult<()> {
+    if qty == 0 || qty > max || addr as usize + qty > 0x10000 { return Err(ModbusErr::InvalidRequest.into()); }
+    Ok(())
+}
+
+/// check the write response echoes the request header
+#[inline]
+fn check_echo(resp: &[u8], header: &[u8]) -> SocketResult<()> {
+    if resp != header { return Err(ModbusErr::InvalidResponse.into()); }
+    Ok(())
+}
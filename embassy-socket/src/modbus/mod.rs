@@ -0,0 +1,166 @@
+use embassy_net::Stack;
+use crate::channel::overflow_policy::OverflowPolicy;
+use crate::channel::SocketChannel;
+use crate::connection::socket_state::TcpState;
+use crate::modbus::client::ModbusClient;
+use crate::tcp_client::read_runner::ReadRunner;
+use crate::tcp_client::target::Target;
+
+pub mod adu;
+pub mod server;
+pub mod client;
+
+/// mbap header len, include unit id
+pub const MBAP_LEN: usize = 7;
+/// max pdu len
+pub const MAX_PDU: usize = 253;
+/// max adu len
+pub const MAX_ADU: usize = MBAP_LEN + MAX_PDU;
+
+/// read coils function code
+pub const READ_COILS: u8 = 1;
+/// read discrete inputs function code
+pub const READ_DISCRETE_INPUTS: u8 = 2;
+/// read holding registers function code
+pub const READ_HOLDING_REGISTERS: u8 = 3;
+/// read input registers function code
+pub const READ_INPUT_REGISTERS: u8 = 4;
+/// write single coil function code
+pub const WRITE_SINGLE_COIL: u8 = 5;
+/// write single register function code
+pub const WRITE_SINGLE_REGISTER: u8 = 6;
+/// write multiple coils function code
+pub const WRITE_MULTIPLE_COILS: u8 = 15;
+/// write multiple registers function code
+pub const WRITE_MULTIPLE_REGISTERS: u8 = 16;
+
+/// max coils or discrete inputs of one read
+pub const MAX_READ_BITS: usize = 2000;
+/// max registers of one read
+pub const MAX_READ_REGISTERS: usize = 125;
+/// max coils of one write
+pub const MAX_WRITE_BITS: usize = 1968;
+/// max registers of one write
+pub const MAX_WRITE_REGISTERS: usize = 123;
+
+/// modbus exception code
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Exception {
+    /// function code is not supported
+    IllegalFunction = 0x01,
+    /// address is not allowed
+    IllegalDataAddress = 0x02,
+    /// value is not allowed
+    IllegalDataValue = 0x03,
+    /// unrecoverable error while performing the action
+    ServerDeviceFailure = 0x04,
+    /// request accepted, but it takes a long time to process
+    Acknowledge = 0x05,
+    /// server is busy
+    ServerDeviceBusy = 0x06,
+    /// gateway path is not available
+    GatewayPathUnavailable = 0x0A,
+    /// gateway target device failed to respond
+    GatewayTargetFailedToRespond = 0x0B,
+}
+
+/// custom method
+impl Exception {
+    /// get exception from exception code
+    pub const fn from_code(code: u8) -> Option<Self> {
+        let exception = match code {
+            0x01 => Self::IllegalFunction,
+            0x02 => Self::IllegalDataAddress,
+            0x03 => Self::IllegalDataValue,
+            0x04 => Self::ServerDeviceFailure,
+            0x05 => Self::Acknowledge,
+            0x06 => Self::ServerDeviceBusy,
+            0x0A => Self::GatewayPathUnavailable,
+            0x0B => Self::GatewayTargetFailedToRespond,
+            _ => return None,
+        };
+        Some(exception)
+    }
+}
+
+/// modbus error
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ModbusErr {
+    /// server responded with an exception
+    Exception(Exception),
+    /// server did not respond within the timeout
+    Timeout,
+    /// tcp connection was lost before the response
+    NotConnected,
+    /// request quantity or address is out of range, the request is not sent
+    InvalidRequest,
+    /// response does not match the request
+    InvalidResponse,
+}
+
+/// packed coils or discrete inputs, bit 0 of the first byte is the first coil
+pub struct Coils<'a> {
+    /// packed bits
+    bytes: &'a mut [u8],
+    /// number of coils
+    len: usize,
+}
+
+/// custom method
+impl<'a> Coils<'a> {
+    /// create coils, bytes must hold at least len bits
+    #[inline]
+    pub fn new(bytes: &'a mut [u8], len: usize) -> Self {
+        Self { bytes, len }
+    }
+
+    /// number of coils
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// coils is empty
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// get coil, index out of range returns false
+    #[inline]
+    pub fn get(&self, index: usize) -> bool {
+        index < self.len && self.bytes[index / 8] & (1 << (index % 8)) != 0
+    }
+
+    /// set coil, index out of range is ignored
+    pub fn set(&mut self, index: usize, value: bool) {
+        if index >= self.len { return; }
+
+        let mask = 1 << (index % 8);
+        if value { self.bytes[index / 8] |= mask; } else { self.bytes[index / 8] &= !mask; }
+    }
+
+    /// packed bytes
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len.div_ceil(8)]
+    }
+}
+
+/// build modbus tcp client<br />
+/// the tcp client read runner keeps the tcp connection and reconnects,
+/// the modbus client sends requests and waits for the responses on the read channel,
+/// so do not read the read channel yourself<br />
+/// lost data breaks the modbus stream, so both channels are set to [OverflowPolicy::Block]
+#[inline]
+pub fn build_client<'d, const RC_SZ: usize, const WC_SZ: usize>(
+    stack: Stack<'d>,
+    target: impl Into<Target>,
+    port: u16,
+    socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
+    state: &'d dyn TcpState)
+    -> (ReadRunner<'d, RC_SZ, WC_SZ>, ModbusClient<'d, RC_SZ, WC_SZ>) {
+    socket_channel.read_channel.overflow_policy(OverflowPolicy::Block);
+    socket_channel.write_channel.overflow_policy(OverflowPolicy::Block);
+    (ReadRunner::new(stack, target, port, socket_channel, state), ModbusClient::new(socket_channel))
+}
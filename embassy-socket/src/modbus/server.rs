@@ -0,0 +1,243 @@
+use embassy_net::IpEndpoint;
+use crate::channel::write_channel::WriteChannel;
+use crate::err::SocketErr;
+use crate::modbus::adu::{Adu, AduDecoder};
+use crate::modbus::{Coils, Exception, MAX_ADU, MAX_PDU, MAX_READ_BITS, MAX_READ_REGISTERS, MAX_WRITE_BITS, MAX_WRITE_REGISTERS,
+                    READ_COILS, READ_DISCRETE_INPUTS, READ_HOLDING_REGISTERS, READ_INPUT_REGISTERS, WRITE_MULTIPLE_COILS,
+                    WRITE_MULTIPLE_REGISTERS, WRITE_SINGLE_COIL, WRITE_SINGLE_REGISTER};
+use crate::tcp_server::callback::TcpServerCallBack;
+
+/// modbus register map, the modbus server dispatches requests to this trait<br />
+/// unit_id is the unit identifier of the request, it can be used to emulate multiple devices<br />
+/// every method returns [Exception::IllegalFunction] by default,
+/// return [Exception::IllegalDataAddress] if the address range is not supported
+pub trait RegisterMap {
+    /// read coils, function code 1<br />
+    /// coils are cleared before calling, set the requested coils from addr
+    async fn read_coils(&self, _unit_id: u8, _addr: u16, _coils: &mut Coils<'_>) -> Result<(), Exception> {
+        Err(Exception::IllegalFunction)
+    }
+
+    /// read discrete inputs, function code 2<br />
+    /// inputs are cleared before calling, set the requested inputs from addr
+    async fn read_discrete_inputs(&self, _unit_id: u8, _addr: u16, _inputs: &mut Coils<'_>) -> Result<(), Exception> {
+        Err(Exception::IllegalFunction)
+    }
+
+    /// read holding registers, function code 3
+    async fn read_holding_registers(&self, _unit_id: u8, _addr: u16, _regs: &mut [u16]) -> Result<(), Exception> {
+        Err(Exception::IllegalFunction)
+    }
+
+    /// read input registers, function code 4
+    async fn read_input_registers(&self, _unit_id: u8, _addr: u16, _regs: &mut [u16]) -> Result<(), Exception> {
+        Err(Exception::IllegalFunction)
+    }
+
+    /// write coils, function code 5 and 15
+    async fn write_coils(&self, _unit_id: u8, _addr: u16, _coils: &Coils<'_>) -> Result<(), Exception> {
+        Err(Exception::IllegalFunction)
+    }
+
+    /// write holding registers, function code 6 and 16
+    async fn write_registers(&self, _unit_id: u8, _addr: u16, _regs: &[u16]) -> Result<(), Exception> {
+        Err(Exception::IllegalFunction)
+    }
+
+    /// socket err will call this, default do nothing
+    async fn err(&self, _err: SocketErr) {}
+}
+
+/// modbus server connection data, hold the adu decoder of the connection
+#[derive(Default)]
+pub struct ModbusState {
+    /// adu decoder
+    decoder: AduDecoder,
+}
+
+/// custom method
+impl ModbusState {
+    /// create modbus server connection data
+    #[inline]
+    pub const fn new() -> Self {
+        Self { decoder: AduDecoder::new() }
+    }
+}
+
+/// modbus tcp server callback<br />
+/// parse mbap requests from tcp data, dispatch them to the register map,
+/// and write responses through the write channel of the connection<br />
+/// every connection needs its own [ModbusState], the multi-connection runner keeps one per connection,
+/// more see [crate::tcp_server::multi_callback_runner::MultiCallbackRunner]
+pub struct ModbusServer<M: RegisterMap> {
+    /// register map
+    pub map: M,
+}
+
+/// custom method
+impl<M: RegisterMap> ModbusServer<M> {
+    /// create modbus tcp server callback
+    #[inline]
+    pub const fn new(map: M) -> Self {
+        Self { map }
+    }
+
+    /// process request pdu, returns the response pdu len
+    async fn process(&self, unit_id: u8, pdu: &[u8], resp: &mut [u8; MAX_PDU]) -> usize {
+        let fc = pdu[0];
+        match self.dispatch(unit_id, pdu, resp).await {
+            Ok(len) => len,
+            Err(exception) => {
+                resp[0] = fc | 0x80;
+                resp[1] = exception as u8;
+                2
+            }
+        }
+    }
+
+    /// dispatch request by function code, returns the response pdu len
+    async fn dispatch(&self, unit_id: u8, pdu: &[u8], resp: &mut [u8; MAX_PDU]) -> Result<usize, Exception> {
+        let fc = pdu[0];
+        match fc {
+            READ_COILS..=WRITE_SINGLE_REGISTER if pdu.len() != 5 => return Err(Exception::IllegalDataValue),
+            READ_COILS..=WRITE_SINGLE_REGISTER | WRITE_MULTIPLE_COILS | WRITE_MULTIPLE_REGISTERS => {}
+            _ => return Err(Exception::IllegalFunction),
+        }
+
+        let addr = read_u16(pdu, 1)?;
+        let value = read_u16(pdu, 3)?;
+        resp[0] = fc;
+        match fc {
+            READ_COILS | READ_DISCRETE_INPUTS => {
+                let qty = check_range(addr, value, MAX_READ_BITS)?;
+                let count = qty.div_ceil(8);
+                resp[1] = count as u8;
+                resp[2..2 + count].fill(0);
+                let mut coils = Coils::new(&mut resp[2..2 + count], qty);
+                match fc {
+                    READ_COILS => self.map.read_coils(unit_id, addr, &mut coils).await?,
+                    _ => self.map.read_discrete_inputs(unit_id, addr, &mut coils).await?,
+                }
+                Ok(2 + count)
+            }
+            READ_HOLDING_REGISTERS | READ_INPUT_REGISTERS => {
+                let qty = check_range(addr, value, MAX_READ_REGISTERS)?;
+                let mut regs = [0; MAX_READ_REGISTERS];
+                match fc {
+                    READ_HOLDING_REGISTERS => self.map.read_holding_registers(unit_id, addr, &mut regs[..qty]).await?,
+                    _ => self.map.read_input_registers(unit_id, addr, &mut regs[..qty]).await?,
+                }
+                resp[1] = (qty * 2) as u8;
+                for (i, reg) in regs[..qty].iter().enumerate() {
+                    resp[2 + i * 2..4 + i * 2].copy_from_slice(&reg.to_be_bytes());
+                }
+                Ok(2 + qty * 2)
+            }
+            WRITE_SINGLE_COIL => {
+                let mut byte = match value {
+                    0xFF00 => [1],
+                    0x0000 => [0],
+                    _ => return Err(Exception::IllegalDataValue),
+                };
+                self.map.write_coils(unit_id, addr, &Coils::new(&mut byte, 1)).await?;
+                resp[1..5].copy_from_slice(&pdu[1..5]);
+                Ok(5)
+            }
+            WRITE_SINGLE_REGISTER => {
+                self.map.write_registers(unit_id, addr, &[value]).await?;
+                resp[1..5].copy_from_slice(&pdu[1..5]);
+                Ok(5)
+            }
+            WRITE_MULTIPLE_COILS => {
+                let qty = check_range(addr, value, MAX_WRITE_BITS)?;
+                let count = qty.div_ceil(8);
+                if pdu.get(5) != Some(&(count as u8)) || pdu.len() != 6 + count { return Err(Exception::IllegalDataValue); }
+
+                let mut bytes = [0; MAX_WRITE_BITS.div_ceil(8)];
+                bytes[..count].copy_from_slice(&pdu[6..]);
+                self.map.write_coils(unit_id, addr, &Coils::new(&mut bytes, qty)).await?;
+                resp[1..5].copy_from_slice(&pdu[1..5]);
+                Ok(5)
+            }
+            // write multiple registers, other function codes have been rejected
+            _ => {
+                let qty = check_range(addr, value, MAX_WRITE_REGISTERS)?;
+                if pdu.get(5) != Some(&(qty as u8 * 2)) || pdu.len() != 6 + qty * 2 { return Err(Exception::IllegalDataValue); }
+
+                let mut regs = [0; MAX_WRITE_REGISTERS];
+                for (i, reg) in regs[..qty].iter_mut().enumerate() {
+                    *reg = u16::from_be_bytes([pdu[6 + i * 2], pdu[7 + i * 2]]);
+                }
+                self.map.write_registers(unit_id, addr, &regs[..qty]).await?;
+                resp[1..5].copy_from_slice(&pdu[1..5]);
+                Ok(5)
+            }
+        }
+    }
+}
+
+/// read big endian u16 from pdu, missing data is illegal data value
+#[inline]
+fn read_u16(pdu: &[u8], index: usize) -> Result<u16, Exception> {
+    match pdu.get(index..index + 2) {
+        Some(&[h, l]) => Ok(u16::from_be_bytes([h, l])),
+        _ => Err(Exception::IllegalDataValue),
+    }
+}
+
+/// check quantity and address range, returns the quantity
+#[inline]
+fn check_range(addr: u16, qty: u16, max: usize) -> Result<usize, Exception> {
+    let qty = qty as usize;
+    if qty == 0 || qty > max { return Err(Exception::IllegalDataValue); }
+    if addr as usize + qty > 0x10000 { return Err(Exception::IllegalDataAddress); }
+    Ok(qty)
+}
+
+/// support tcp server callback
+impl<M: RegisterMap> TcpServerCallBack for ModbusServer<M> {
+    type T = ModbusState;
+
+    #[inline]
+    async fn conn<const CN: usize>(&self, _endpoint: IpEndpoint, _wch: &WriteChannel<'_, CN>, t: &mut Self::T) {
+        t.decoder.reset();
+    }
+
+    #[inline]
+    async fn dis_conn(&self, _endpoint: IpEndpoint, t: &mut Self::T) {
+        t.decoder.reset();
+    }
+
+    async fn recv<const CN: usize>(&self, endpoint: IpEndpoint, mut buf: &[u8], wch: &WriteChannel<'_, CN>, t: &mut Self::T) {
+        while !buf.is_empty() {
+            let (used, adu) = t.decoder.decode(buf);
+            buf = &buf[used..];
+            match adu {
+                Some(Ok(adu)) => {
+                    let mut resp = [0; MAX_PDU];
+                    let len = self.process(adu.unit_id, adu.pdu, &mut resp).await;
+                    let mut out = [0; MAX_ADU];
+                    let len = Adu::encode(adu.transaction_id, adu.unit_id, &resp[..len], &mut out);
+                    wch.send_bytes_to(&out[..len], endpoint).await.ok();
+                }
+                Some(Err(e)) => {
+                    // the stream can not be resynchronized
+                    self.map.err(e).await;
+                    wch.close(Some(endpoint)).await.ok();
+                    return;
+                }
+                None => {}
+            }
+        }
+    }
+
+    #[inline]
+    async fn err(&self, err: SocketErr, _t: &mut Self::T) {
+        self.map.err(err).await;
+    }
+
+    /// the incomplete adu is dropped too, because part of it has been lost
+    async fn dropped<const CN: usize>(&self, _endpoint: IpEndpoint, _count: u32, _wch: &WriteChannel<'_, CN>, t: &mut Self::T) {
+        t.decoder.reset();
+    }
+}
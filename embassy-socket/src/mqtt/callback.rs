@@ -0,0 +1,41 @@
+use embassy_time::Duration;
+use crate::err::SocketErr;
+use crate::mqtt::client::MqttClient;
+use crate::mqtt::QoS;
+
+/// mqtt callback
+pub trait MqttCallBack {
+    /// connection accepted by the broker call this, subscribe topics here<br />
+    /// if the session is present, qos 1 messages not acknowledged before can be sent again with [MqttClient::republish]
+    async fn conn<const CN: usize>(&mut self, session_present: bool, client: &MqttClient<'_, CN>);
+
+    /// connection lost call this
+    async fn dis_conn(&mut self);
+
+    /// recv publish message call this<br />
+    /// qos 1 message has been acknowledged before calling this
+    async fn publish<const CN: usize>(&mut self, topic: &str, payload: &[u8], qos: QoS, client: &MqttClient<'_, CN>);
+
+    /// socket err or mqtt err will call this<br />
+    /// only error notification will be made, no blocking and exit will be made<br />
+    /// please do not use endless loops in this method
+    async fn err(&mut self, err: SocketErr);
+
+    /// qos 1 publish acknowledged call this, default do nothing
+    async fn puback(&mut self, _packet_id: u16) {}
+
+    /// subscribe acknowledged call this, codes are the granted qos of every topic, 0x80 is failure<br />
+    /// default do nothing
+    async fn suback(&mut self, _packet_id: u16, _codes: &[u8]) {}
+
+    /// unsubscribe acknowledged call this, default do nothing
+    async fn unsuback(&mut self, _packet_id: u16) {}
+
+    /// tcp reconnect will call this before waiting delay, attempt starts from 1<br />
+    /// default do nothing
+    async fn reconnect(&mut self, _attempt: u32, _delay: Duration) {}
+
+    /// read data was dropped because the read channel was full, the connection will be closed<br />
+    /// more see [crate::channel::overflow_policy::OverflowPolicy], default do nothing
+    async fn dropped(&mut self, _count: u32) {}
+}
@@ -0,0 +1,281 @@
+use core::cell::Cell;
+use embassy_sync::blocking_mutex::Mutex as BlockingMutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::mutex::{Mutex, MutexGuard};
+use embassy_time::Instant;
+use crate::channel::write_channel::WriteChannel;
+use crate::err::SocketResult;
+use crate::mqtt::packet::{encode_len, CONNECT, DISCONNECT, PINGREQ, PUBACK, PUBLISH, SUBSCRIBE, UNSUBSCRIBE};
+use crate::mqtt::{MqttErr, MqttOptions, QoS};
+
+/// mqtt client, encode mqtt packets to the write channel<br />
+/// N is write channel len, packets are packed into msg of N bytes<br />
+/// the client can be shared to other tasks, packets sent by different tasks will not be interleaved
+pub struct MqttClient<'d, const N: usize> {
+    /// write channel
+    wch: &'d WriteChannel<'d, N>,
+    /// connect options
+    options: MqttOptions<'d>,
+    /// packet encode buf, also used as the send lock
+    buf: Mutex<CriticalSectionRawMutex, [u8; N]>,
+    /// last packet id
+    packet_id: BlockingMutex<CriticalSectionRawMutex, Cell<u16>>,
+    /// connack has been accepted
+    connected: BlockingMutex<CriticalSectionRawMutex, Cell<bool>>,
+    /// last packet send time
+    last_send: BlockingMutex<CriticalSectionRawMutex, Cell<Instant>>,
+}
+
+/// custom method
+impl<'d, const N: usize> MqttClient<'d, N> {
+    /// create mqtt client
+    #[inline]
+    pub fn new(wch: &'d WriteChannel<'d, N>, options: MqttOptions<'d>) -> Self {
+        Self {
+            wch,
+            options,
+            buf: Mutex::new([0; N]),
+            packet_id: BlockingMutex::new(Cell::new(0)),
+            connected: BlockingMutex::new(Cell::new(false)),
+            last_send: BlockingMutex::new(Cell::new(Instant::MIN)),
+        }
+    }
+
+    /// get connect options
+    #[inline]
+    pub fn options(&self) -> &MqttOptions<'d> {
+        &self.options
+    }
+
+    /// mqtt connection has been accepted by the broker
+    #[inline]
+    pub fn is_connected(&self) -> bool {
+        self.connected.lock(|c| c.get())
+    }
+
+    /// publish message, returns the packet id, qos 0 always returns 0<br />
+    /// qos 1 message is acknowledged by [crate::mqtt::callback::MqttCallBack::puback]<br />
+    /// the client keeps no in-flight messages, if the puback never arrives, for example the connection is lost,
+    /// the caller must keep the message and send it again with [MqttClient::republish] after
+    /// [crate::mqtt::callback::MqttCallBack::conn] reports a present session, or publish it as a new message otherwise
+    pub async fn publish(&self, topic: &str, payload: &[u8], qos: QoS, retain: bool) -> SocketResult<u16> {
+        self.check_connected()?;
+        let packet_id = match qos {
+            QoS::AtMostOnce => 0,
+            QoS::AtLeastOnce => self.next_packet_id(),
+        };
+        self.publish_packet(topic, payload, qos, retain, false, packet_id).await?;
+        Ok(packet_id)
+    }
+
+    /// send a qos 1 message again with the dup flag and the packet id returned by [MqttClient::publish]<br />
+    /// used when the puback was not received before the connection was lost and the broker kept the session
+    #[inline]
+    pub async fn republish(&self, packet_id: u16, topic: &str, payload: &[u8], retain: bool) -> SocketResult<()> {
+        self.check_connected()?;
+        self.publish_packet(topic, payload, QoS::AtLeastOnce, retain, true, packet_id).await
+    }
+
+    /// subscribe topics, returns the packet id<br />
+    /// acknowledged by [crate::mqtt::callback::MqttCallBack::suback]
+    pub async fn subscribe(&self, topics: &[(&str, QoS)]) -> SocketResult<u16> {
+        self.check_connected()?;
+        let packet_id = self.next_packet_id();
+        let mut len = 0;
+        for (topic, _) in topics { len += field_len(topic.as_bytes())? + 1; }
+
+        let mut writer = self.writer(SUBSCRIBE << 4 | 0x02, 2 + len).await?;
+        writer.put(&packet_id.to_be_bytes()).await?;
+        for (topic, qos) in topics {
+            writer.put_str(topic).await?;
+            writer.put(&[*qos as u8]).await?;
+        }
+        writer.finish().await?;
+        Ok(packet_id)
+    }
+
+    /// unsubscribe topics, returns the packet id<br />
+    /// acknowledged by [crate::mqtt::callback::MqttCallBack::unsuback]
+    pub async fn unsubscribe(&self, topics: &[&str]) -> SocketResult<u16> {
+        self.check_connected()?;
+        let packet_id = self.next_packet_id();
+        let mut len = 0;
+        for topic in topics { len += field_len(topic.as_bytes())?; }
+
+        let mut writer = self.writer(UNSUBSCRIBE << 4 | 0x02, 2 + len).await?;
+        writer.put(&packet_id.to_be_bytes()).await?;
+        for topic in topics {
+            writer.put_str(topic).await?;
+        }
+        writer.finish().await?;
+        Ok(packet_id)
+    }
+
+    /// send disconnect and close the tcp connection, the will message will not be published<br />
+    /// the tcp client runner will reconnect according to the reconnect policy
+    pub async fn disconnect(&self) -> SocketResult<()> {
+        self.check_connected()?;
+        self.writer(DISCONNECT << 4, 0).await?.finish().await?;
+        self.close().await;
+        Ok(())
+    }
+
+    /// send connect packet
+    pub(crate) async fn connect(&self) -> SocketResult<()> {
+        let options = &self.options;
+        let mut flags = (options.clean_session as u8) << 1;
+        let mut len = 10 + field_len(options.client_id.as_bytes())?;
+        if let Some(will) = options.will {
+            flags |= 0x04 | (will.qos as u8) << 3 | (will.retain as u8) << 5;
+            len += field_len(will.topic.as_bytes())? + field_len(will.payload)?;
+        }
+        if let Some(username) = options.username {
+            flags |= 0x80;
+            len += field_len(username.as_bytes())?;
+        }
+        if let Some(password) = options.password {
+            flags |= 0x40;
+            len += field_len(password)?;
+        }
+
+        let mut writer = self.writer(CONNECT << 4, len).await?;
+        writer.put_str("MQTT").await?;
+        writer.put(&[4, flags]).await?;
+        writer.put(&options.keep_alive.to_be_bytes()).await?;
+        writer.put_str(options.client_id).await?;
+        if let Some(will) = options.will {
+            writer.put_str(will.topic).await?;
+            writer.put_bytes(will.payload).await?;
+        }
+        if let Some(username) = options.username { writer.put_str(username).await?; }
+        if let Some(password) = options.password { writer.put_bytes(password).await?; }
+        writer.finish().await
+    }
+
+    /// send pingreq packet
+    #[inline]
+    pub(crate) async fn ping(&self) -> SocketResult<()> {
+        self.writer(PINGREQ << 4, 0).await?.finish().await
+    }
+
+    /// send puback packet
+    #[inline]
+    pub(crate) async fn puback(&self, packet_id: u16) -> SocketResult<()> {
+        let mut writer = self.writer(PUBACK << 4, 2).await?;
+        writer.put(&packet_id.to_be_bytes()).await?;
+        writer.finish().await
+    }
+
+    /// request the tcp runner to close the connection
+    #[inline]
+    pub(crate) async fn close(&self) {
+        self.wch.close(None).await.ok();
+    }
+
+    /// set connected state
+    #[inline]
+    pub(crate) fn set_connected(&self, connected: bool) {
+        self.connected.lock(|c| c.set(connected));
+    }
+
+    /// get last packet send time
+    #[inline]
+    pub(crate) fn last_send(&self) -> Instant {
+        self.last_send.lock(|l| l.get())
+    }
+
+    /// check mqtt is connected
+    #[inline]
+    fn check_connected(&self) -> SocketResult<()> {
+        if self.is_connected() { Ok(()) } else { Err(MqttErr::NotConnected.into()) }
+    }
+
+    /// get next packet id, packet id 0 is not allowed
+    fn next_packet_id(&self) -> u16 {
+        self.packet_id.lock(|id| {
+            let next = id.get().checked_add(1).unwrap_or(1);
+            id.set(next);
+            next
+        })
+    }
+
+    /// send publish packet, qos 0 has no packet id
+    async fn publish_packet(&self, topic: &str, payload: &[u8], qos: QoS, retain: bool, dup: bool, packet_id: u16) -> SocketResult<()> {
+        let id_len = if packet_id == 0 { 0 } else { 2 };
+        let header = PUBLISH << 4 | (dup as u8) << 3 | (qos as u8) << 1 | retain as u8;
+        let mut writer = self.writer(header, field_len(topic.as_bytes())? + id_len + payload.len()).await?;
+        writer.put_str(topic).await?;
+        if packet_id != 0 { writer.put(&packet_id.to_be_bytes()).await?; }
+        writer.put(payload).await?;
+        writer.finish().await
+    }
+
+    /// lock encode buf and write fixed header
+    async fn writer(&self, header: u8, remaining: usize) -> SocketResult<PacketWriter<'_, 'd, N>> {
+        let mut len_buf = [0; 4];
+        let used = encode_len(remaining, &mut len_buf)?;
+
+        let mut writer = PacketWriter { client: self, buf: self.buf.lock().await, len: 0 };
+        writer.put(&[header]).await?;
+        writer.put(&len_buf[..used]).await?;
+        Ok(writer)
+    }
+}
+
+/// length prefixed field len, the field can not be longer than u16::MAX
+#[inline]
+fn field_len(bytes: &[u8]) -> SocketResult<usize> {
+    if bytes.len() > u16::MAX as usize { return Err(MqttErr::PacketTooLarge.into()); }
+    Ok(2 + bytes.len())
+}
+
+/// packet writer, pack packet bytes into msg of N bytes
+struct PacketWriter<'a, 'd, const N: usize> {
+    /// mqtt client
+    client: &'a MqttClient<'d, N>,
+    /// locked encode buf
+    buf: MutexGuard<'a, CriticalSectionRawMutex, [u8; N]>,
+    /// encode buf len
+    len: usize,
+}
+
+/// custom method
+impl<const N: usize> PacketWriter<'_, '_, N> {
+    /// put bytes, the buf is sent when full
+    async fn put(&mut self, mut bytes: &[u8]) -> SocketResult<()> {
+        while !bytes.is_empty() {
+            let copy = (N - self.len).min(bytes.len());
+            self.buf[self.len..self.len + copy].copy_from_slice(&bytes[..copy]);
+            self.len += copy;
+            bytes = &bytes[copy..];
+            if self.len == N { self.send().await?; }
+        }
+        Ok(())
+    }
+
+    /// put length prefixed bytes, the len has been checked by [field_len]
+    async fn put_bytes(&mut self, bytes: &[u8]) -> SocketResult<()> {
+        self.put(&(bytes.len() as u16).to_be_bytes()).await?;
+        self.put(bytes).await
+    }
+
+    /// put length prefixed utf-8 string
+    #[inline]
+    async fn put_str(&mut self, s: &str) -> SocketResult<()> {
+        self.put_bytes(s.as_bytes()).await
+    }
+
+    /// send the remaining bytes and release the encode buf
+    async fn finish(mut self) -> SocketResult<()> {
+        if self.len > 0 { self.send().await?; }
+        Ok(())
+    }
+
+    /// send encode buf to the write channel
+    async fn send(&mut self) -> SocketResult<()> {
+        let len = core::mem::take(&mut self.len);
+        self.client.wch.send_bytes(&self.buf[..len]).await?;
+        self.client.last_send.lock(|l| l.set(Instant::now()));
+        Ok(())
+    }
+}
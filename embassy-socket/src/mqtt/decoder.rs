@@ -0,0 +1,107 @@
+use crate::err::{SocketErr, SocketResult};
+use crate::mqtt::MqttErr;
+use crate::mqtt::packet::Packet;
+
+/// mqtt packet decoder, reassemble tcp data into whole mqtt packets<br />
+/// N is max packet body size, larger packets will be reported as [SocketErr::FrameTooLarge] and discarded
+pub struct PacketDecoder<const N: usize> {
+    /// fixed header first byte
+    header: Option<u8>,
+    /// remaining length being decoded
+    remaining: usize,
+    /// remaining length bit shift
+    shift: u32,
+    /// packet body len, known after remaining length is decoded
+    expect: Option<usize>,
+    /// packet body buf
+    buf: [u8; N],
+    /// packet body buf len
+    len: usize,
+    /// number of bytes to skip
+    skip: usize,
+    /// the packet in buf has been returned, and buf should be cleared on next decode
+    ready: bool,
+}
+
+/// support default
+impl<const N: usize> Default for PacketDecoder<N> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// custom method
+impl<const N: usize> PacketDecoder<N> {
+    /// create mqtt packet decoder
+    #[inline]
+    pub const fn new() -> Self {
+        Self { header: None, remaining: 0, shift: 0, expect: None, buf: [0; N], len: 0, skip: 0, ready: false }
+    }
+
+    /// drop the incomplete packet, generally called when connection changes or data was lost
+    pub fn reset(&mut self) {
+        self.clear();
+        self.skip = 0;
+    }
+
+    /// clear the packet state, the skip state is kept
+    fn clear(&mut self) {
+        self.header = None;
+        self.remaining = 0;
+        self.shift = 0;
+        self.expect = None;
+        self.len = 0;
+        self.ready = false;
+    }
+
+    /// decode bytes, returns the number of bytes used and the decoded packet<br />
+    /// if the packet is None, all bytes have been used<br />
+    /// call this method repeatedly with the remaining bytes until all bytes have been used
+    pub fn decode(&mut self, bytes: &[u8]) -> (usize, Option<SocketResult<Packet<'_>>>) {
+        if self.ready { self.clear(); }
+
+        let mut used = self.skip.min(bytes.len());
+        self.skip -= used;
+
+        let expect = loop {
+            if let Some(expect) = self.expect { break expect; }
+            let Some(&byte) = bytes.get(used) else { return (used, None); };
+            used += 1;
+
+            if self.header.is_none() {
+                self.header = Some(byte);
+                continue;
+            }
+
+            self.remaining |= ((byte & 0x7F) as usize) << self.shift;
+            self.shift += 7;
+            if byte & 0x80 != 0 {
+                if self.shift >= 28 {
+                    self.clear();
+                    return (used, Some(Err(MqttErr::InvalidPacket.into())));
+                }
+                continue;
+            }
+
+            if self.remaining > N {
+                self.skip = self.remaining;
+                self.clear();
+                let skip = self.skip.min(bytes.len() - used);
+                self.skip -= skip;
+                return (used + skip, Some(Err(SocketErr::FrameTooLarge)));
+            }
+            self.expect = Some(self.remaining);
+        };
+
+        let copy = (expect - self.len).min(bytes.len() - used);
+        self.buf[self.len..self.len + copy].copy_from_slice(&bytes[used..used + copy]);
+        self.len += copy;
+        used += copy;
+        if self.len < expect { return (used, None); }
+
+        self.ready = true;
+        let header = self.header.unwrap_or_default();
+        (used, Some(Packet::parse(header, &self.buf[..self.len])))
+    }
+}
@@ -0,0 +1,129 @@
+use embassy_net::Stack;
+use crate::channel::overflow_policy::OverflowPolicy;
+use crate::channel::SocketChannel;
+use crate::connection::socket_state::TcpState;
+use crate::mqtt::callback::MqttCallBack;
+use crate::mqtt::client::MqttClient;
+use crate::mqtt::runner::MqttRunner;
+use crate::tcp_client::read_runner::ReadRunner;
+use crate::tcp_client::target::Target;
+
+pub mod packet;
+pub mod decoder;
+pub mod client;
+pub mod callback;
+pub mod runner;
+
+/// mqtt quality of service, only qos 0 and qos 1 are supported
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum QoS {
+    /// qos 0, fire and forget
+    AtMostOnce = 0,
+    /// qos 1, acknowledged by puback
+    AtLeastOnce = 1,
+}
+
+/// mqtt error
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MqttErr {
+    /// broker refused the connection, the value is the connack return code
+    ConnectRefused(u8),
+    /// mqtt is not connected, wait for [MqttCallBack::conn]
+    NotConnected,
+    /// packet can not be decoded, the connection will be closed
+    InvalidPacket,
+    /// packet is larger than the max remaining length of mqtt
+    PacketTooLarge,
+    /// broker did not answer connect or pingreq within keep alive, the connection will be closed
+    Timeout,
+}
+
+/// mqtt last will
+#[derive(Copy, Clone, Debug)]
+pub struct Will<'a> {
+    /// will topic
+    pub topic: &'a str,
+    /// will payload
+    pub payload: &'a [u8],
+    /// will qos
+    pub qos: QoS,
+    /// will retain
+    pub retain: bool,
+}
+
+/// mqtt connect options<br />
+/// default keep alive is 60 seconds, clean session, no credentials and no will
+#[derive(Copy, Clone, Debug)]
+pub struct MqttOptions<'a> {
+    /// client id
+    pub client_id: &'a str,
+    /// keep alive seconds, 0 is disable pingreq
+    pub keep_alive: u16,
+    /// clean session
+    pub clean_session: bool,
+    /// user name
+    pub username: Option<&'a str>,
+    /// password
+    pub password: Option<&'a [u8]>,
+    /// last will
+    pub will: Option<Will<'a>>,
+}
+
+/// custom method
+impl<'a> MqttOptions<'a> {
+    /// create mqtt connect options
+    #[inline]
+    pub const fn new(client_id: &'a str) -> Self {
+        Self { client_id, keep_alive: 60, clean_session: true, username: None, password: None, will: None }
+    }
+
+    /// set keep alive seconds, 0 is disable pingreq
+    #[inline]
+    pub const fn keep_alive(mut self, keep_alive: u16) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    /// set clean session
+    #[inline]
+    pub const fn clean_session(mut self, clean_session: bool) -> Self {
+        self.clean_session = clean_session;
+        self
+    }
+
+    /// set user name and password
+    #[inline]
+    pub const fn credentials(mut self, username: &'a str, password: &'a [u8]) -> Self {
+        self.username = Some(username);
+        self.password = Some(password);
+        self
+    }
+
+    /// set last will
+    #[inline]
+    pub const fn will(mut self, will: Will<'a>) -> Self {
+        self.will = Some(will);
+        self
+    }
+}
+
+/// build mqtt runner, the tcp client read runner keeps the tcp connection and reconnects,
+/// the mqtt runner sends connect, keeps alive and calls back<br />
+/// client is created with `socket_channel.write_channel`, and can be shared to other tasks to publish<br />
+/// mqtt is a byte stream protocol and lost data breaks the stream,
+/// so both channels are set to [OverflowPolicy::Block]<br />
+/// F_SZ is max incoming packet size, larger packets will be passed to [MqttCallBack::err] as [crate::err::SocketErr::FrameTooLarge]
+#[inline]
+pub fn build<'d, const RC_SZ: usize, const WC_SZ: usize, const F_SZ: usize, CB: MqttCallBack>(
+    stack: Stack<'d>,
+    target: impl Into<Target>,
+    port: u16,
+    socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
+    state: &'d dyn TcpState,
+    client: &'d MqttClient<'d, WC_SZ>,
+    cb: CB)
+    -> (ReadRunner<'d, RC_SZ, WC_SZ>, MqttRunner<'d, RC_SZ, WC_SZ, F_SZ, CB>) {
+    socket_channel.read_channel.overflow_policy(OverflowPolicy::Block);
+    socket_channel.write_channel.overflow_policy(OverflowPolicy::Block);
+    (ReadRunner::new(stack, target, port, socket_channel, state), MqttRunner::new(&socket_channel.read_channel, client, cb))
+}
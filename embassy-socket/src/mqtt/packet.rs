@@ -0,0 +1,133 @@
+use crate::err::SocketResult;
+use crate::mqtt::{MqttErr, QoS};
+
+/// connect packet type
+pub const CONNECT: u8 = 1;
+/// connack packet type
+pub const CONNACK: u8 = 2;
+/// publish packet type
+pub const PUBLISH: u8 = 3;
+/// puback packet type
+pub const PUBACK: u8 = 4;
+/// subscribe packet type
+pub const SUBSCRIBE: u8 = 8;
+/// suback packet type
+pub const SUBACK: u8 = 9;
+/// unsubscribe packet type
+pub const UNSUBSCRIBE: u8 = 10;
+/// unsuback packet type
+pub const UNSUBACK: u8 = 11;
+/// pingreq packet type
+pub const PINGREQ: u8 = 12;
+/// pingresp packet type
+pub const PINGRESP: u8 = 13;
+/// disconnect packet type
+pub const DISCONNECT: u8 = 14;
+
+/// max remaining length, 4 bytes variable length
+pub const MAX_REMAINING_LEN: usize = 268_435_455;
+
+/// incoming mqtt packet, only packets sent by the broker to the client are supported
+#[derive(Copy, Clone, Debug)]
+pub enum Packet<'a> {
+    /// connect acknowledgment
+    ConnAck {
+        /// session present flag
+        session_present: bool,
+        /// return code, 0 is accepted
+        code: u8,
+    },
+    /// publish message
+    Publish {
+        /// topic name
+        topic: &'a str,
+        /// payload
+        payload: &'a [u8],
+        /// qos
+        qos: QoS,
+        /// retain flag
+        retain: bool,
+        /// duplicate flag
+        dup: bool,
+        /// packet id, 0 for qos 0
+        packet_id: u16,
+    },
+    /// publish acknowledgment
+    PubAck(u16),
+    /// subscribe acknowledgment
+    SubAck {
+        /// packet id
+        packet_id: u16,
+        /// granted qos of every topic, 0x80 is failure
+        codes: &'a [u8],
+    },
+    /// unsubscribe acknowledgment
+    UnsubAck(u16),
+    /// ping response
+    PingResp,
+}
+
+/// custom method
+impl<'a> Packet<'a> {
+    /// parse packet from fixed header first byte and packet body
+    pub fn parse(header: u8, body: &'a [u8]) -> SocketResult<Self> {
+        let packet = match header >> 4 {
+            CONNACK if body.len() == 2 => Self::ConnAck { session_present: body[0] & 1 == 1, code: body[1] },
+            PUBLISH => Self::parse_publish(header, body)?,
+            PUBACK if body.len() == 2 => Self::PubAck(read_u16(body)?),
+            SUBACK if body.len() > 2 => Self::SubAck { packet_id: read_u16(body)?, codes: &body[2..] },
+            UNSUBACK if body.len() == 2 => Self::UnsubAck(read_u16(body)?),
+            PINGRESP if body.is_empty() => Self::PingResp,
+            _ => return Err(MqttErr::InvalidPacket.into()),
+        };
+        Ok(packet)
+    }
+
+    /// parse publish packet
+    fn parse_publish(header: u8, body: &'a [u8]) -> SocketResult<Self> {
+        let qos = match (header >> 1) & 0x03 {
+            0 => QoS::AtMostOnce,
+            1 => QoS::AtLeastOnce,
+            _ => return Err(MqttErr::InvalidPacket.into()),
+        };
+
+        let topic_len = read_u16(body)? as usize;
+        let topic = body.get(2..2 + topic_len).ok_or(MqttErr::InvalidPacket)?;
+        let topic = core::str::from_utf8(topic).map_err(|_| MqttErr::InvalidPacket)?;
+        let mut used = 2 + topic_len;
+        let packet_id = match qos {
+            QoS::AtMostOnce => 0,
+            QoS::AtLeastOnce => {
+                let packet_id = read_u16(&body[used..])?;
+                used += 2;
+                packet_id
+            }
+        };
+
+        Ok(Self::Publish { topic, payload: &body[used..], qos, retain: header & 0x01 == 1, dup: header & 0x08 != 0, packet_id })
+    }
+}
+
+/// read big endian u16
+#[inline]
+fn read_u16(bytes: &[u8]) -> SocketResult<u16> {
+    match bytes {
+        [h, l, ..] => Ok(u16::from_be_bytes([*h, *l])),
+        _ => Err(MqttErr::InvalidPacket.into()),
+    }
+}
+
+/// encode remaining length to buf, returns the number of bytes used
+pub fn encode_len(mut len: usize, buf: &mut [u8; 4]) -> SocketResult<usize> {
+    if len > MAX_REMAINING_LEN { return Err(MqttErr::PacketTooLarge.into()); }
+
+    let mut used = 0;
+    loop {
+        let mut byte = (len % 128) as u8;
+        len /= 128;
+        if len > 0 { byte |= 0x80; }
+        buf[used] = byte;
+        used += 1;
+        if len == 0 { return Ok(used); }
+    }
+}
@@ -0,0 +1,172 @@
+use embassy_futures::select::{select, Either};
+use embassy_time::{Duration, Instant, Timer};
+use crate::channel::callback_enum::CallbackEnum;
+use crate::channel::read_channel::{ReadChannel, ReadRef};
+use crate::err::SocketErr;
+use crate::mqtt::callback::MqttCallBack;
+use crate::mqtt::client::MqttClient;
+use crate::mqtt::decoder::PacketDecoder;
+use crate::mqtt::packet::Packet;
+use crate::mqtt::{MqttErr, QoS};
+
+/// mqtt runner, replace [crate::tcp_client::callback_runner::CallbackRunner]<br />
+/// send connect after the tcp connection, send pingreq within keep alive, decode packets and call back<br />
+/// if the broker does not answer connect or pingreq within keep alive, the connection is closed and reconnected
+pub struct MqttRunner<'d, const RC_SZ: usize, const WC_SZ: usize, const F_SZ: usize, CB: MqttCallBack> {
+    /// read channel
+    channel: &'d ReadChannel<'d, RC_SZ>,
+    /// mqtt client
+    client: &'d MqttClient<'d, WC_SZ>,
+    /// packet decoder
+    decoder: PacketDecoder<F_SZ>,
+    /// tcp connected
+    tcp_connected: bool,
+    /// connect or pingreq send time, waiting for the answer of the broker
+    wait_since: Option<Instant>,
+    /// mqtt callback
+    cb: CB,
+}
+
+/// custom method
+impl<'d, const RC_SZ: usize, const WC_SZ: usize, const F_SZ: usize, CB: MqttCallBack> MqttRunner<'d, RC_SZ, WC_SZ, F_SZ, CB> {
+    /// create mqtt runner
+    #[inline]
+    pub fn new(channel: &'d ReadChannel<'d, RC_SZ>, client: &'d MqttClient<'d, WC_SZ>, cb: CB) -> Self {
+        Self {
+            channel,
+            client,
+            decoder: PacketDecoder::new(),
+            tcp_connected: false,
+            wait_since: None,
+            cb,
+        }
+    }
+
+    /// run mqtt runner
+    #[inline]
+    pub async fn run(&mut self) {
+        loop { self.run_logic().await; }
+    }
+
+    /// run logic, wait until there is msg or keep alive is due
+    async fn run_logic(&mut self) {
+        let deadline = self.deadline();
+        match select(self.channel.wait_read_ref(), Timer::at(deadline)).await {
+            Either::First(msg) => self.callback_logic(&msg).await,
+            Either::Second(_) => self.keep_alive_logic().await,
+        }
+    }
+
+    /// keep alive duration, None is disabled
+    #[inline]
+    fn keep_alive(&self) -> Option<Duration> {
+        match self.client.options().keep_alive {
+            0 => None,
+            secs => Some(Duration::from_secs(secs as u64)),
+        }
+    }
+
+    /// next keep alive deadline
+    fn deadline(&self) -> Instant {
+        let Some(keep_alive) = self.keep_alive() else { return Instant::MAX; };
+        if !self.tcp_connected { return Instant::MAX; }
+
+        match self.wait_since {
+            Some(since) => since + keep_alive,
+            None => self.client.last_send() + keep_alive,
+        }
+    }
+
+    /// send pingreq, or close the connection if the broker did not answer
+    async fn keep_alive_logic(&mut self) {
+        if self.wait_since.is_some() {
+            self.wait_since = None;
+            self.cb.err(MqttErr::Timeout.into()).await;
+            self.client.close().await;
+            return;
+        }
+
+        match self.client.ping().await {
+            Ok(_) => self.wait_since = Some(Instant::now()),
+            Err(e) => self.cb.err(e).await,
+        }
+    }
+
+    /// call back by socket msg, the msg is borrowed in place
+    async fn callback_logic(&mut self, msg: &ReadRef<'_, '_, RC_SZ>) {
+        match msg.callback_enum() {
+            CallbackEnum::Conn => {
+                self.decoder.reset();
+                self.tcp_connected = true;
+                self.wait_since = Some(Instant::now());
+                if let Err(e) = self.client.connect().await { self.cb.err(e).await; }
+            }
+            CallbackEnum::Disconnect => {
+                self.decoder.reset();
+                self.tcp_connected = false;
+                self.wait_since = None;
+                if self.client.is_connected() {
+                    self.client.set_connected(false);
+                    self.cb.dis_conn().await;
+                }
+            }
+            CallbackEnum::Recv => self.recv_logic(msg.as_bytes()).await,
+            CallbackEnum::Err(e) => self.cb.err(e).await,
+            CallbackEnum::Dropped(count) => {
+                // part of the byte stream has been lost, the packets can not be decoded any more
+                self.decoder.reset();
+                self.cb.dropped(count).await;
+                self.client.close().await;
+            }
+            CallbackEnum::Reconnect { attempt, delay } => self.cb.reconnect(attempt, delay).await,
+        }
+    }
+
+    /// decode recv data and call back every packet
+    async fn recv_logic(&mut self, mut buf: &[u8]) {
+        while !buf.is_empty() {
+            let (used, packet) = self.decoder.decode(buf);
+            buf = &buf[used..];
+            match packet {
+                Some(Ok(packet)) => Self::packet_logic(packet, self.channel, self.client, &mut self.cb, &mut self.wait_since).await,
+                Some(Err(SocketErr::FrameTooLarge)) => self.cb.err(SocketErr::FrameTooLarge).await,
+                Some(Err(e)) => {
+                    // invalid packet, the rest of the stream can not be trusted
+                    self.decoder.reset();
+                    self.cb.err(e).await;
+                    self.client.close().await;
+                    return;
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// call back one packet, pingresp is recorded as rtt sample
+    async fn packet_logic(packet: Packet<'_>, channel: &ReadChannel<'_, RC_SZ>, client: &MqttClient<'_, WC_SZ>, cb: &mut CB, wait_since: &mut Option<Instant>) {
+        match packet {
+            Packet::ConnAck { session_present, code: 0 } => {
+                *wait_since = None;
+                client.set_connected(true);
+                cb.conn(session_present, client).await;
+            }
+            Packet::ConnAck { code, .. } => {
+                *wait_since = None;
+                cb.err(MqttErr::ConnectRefused(code).into()).await;
+                client.close().await;
+            }
+            Packet::Publish { topic, payload, qos, packet_id, .. } => {
+                if qos == QoS::AtLeastOnce && let Err(e) = client.puback(packet_id).await {
+                    cb.err(e).await;
+                }
+                cb.publish(topic, payload, qos, client).await;
+            }
+            Packet::PubAck(packet_id) => cb.puback(packet_id).await,
+            Packet::SubAck { packet_id, codes } => cb.suback(packet_id, codes).await,
+            Packet::UnsubAck(packet_id) => cb.unsuback(packet_id).await,
+            Packet::PingResp => {
+                if let Some(since) = wait_since.take() { channel.rtt_sample(Instant::now() - since); }
+            }
+        }
+    }
+}
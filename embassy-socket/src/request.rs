@@ -0,0 +1,249 @@
+use core::cell::RefCell;
+use core::future::poll_fn;
+use core::task::{Poll, Waker};
+use embassy_futures::select::{select, Either};
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_time::{Duration, Timer};
+use crate::channel::write_channel::WriteChannel;
+use crate::err::{SocketErr, SocketResult};
+use crate::tcp_client::callback::TcpClientCallBack;
+
+/// response matcher of one request
+#[derive(Copy, Clone, Debug)]
+pub enum Matcher {
+    /// frames whose id equals the value, the id is extracted by the id function of [Requester]
+    Id(u32),
+    /// frames for which the function returns true
+    Fn(fn(&[u8]) -> bool),
+}
+
+/// response frame, F_SZ is max frame size
+#[derive(Copy, Clone, Debug)]
+pub struct Frame<const F_SZ: usize> {
+    /// frame bytes
+    bytes: [u8; F_SZ],
+    /// real len
+    len: usize,
+}
+
+/// custom method
+impl<const F_SZ: usize> Frame<F_SZ> {
+    /// copy frame, returns None if bytes is larger than F_SZ
+    #[inline]
+    fn copy_from(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() > F_SZ { return None; }
+
+        let mut frame = Self { bytes: [0; F_SZ], len: bytes.len() };
+        frame.bytes[..bytes.len()].copy_from_slice(bytes);
+        Some(frame)
+    }
+
+    /// real bytes data
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+/// one pending request
+struct Pending<const F_SZ: usize> {
+    /// response matcher, None is a free slot
+    matcher: Option<Matcher>,
+    /// response, set by [Requester::deliver]
+    result: Option<SocketResult<Frame<F_SZ>>>,
+    /// waker of the awaiting task
+    waker: Option<Waker>,
+}
+
+/// request/response correlation of one tcp client<br />
+/// P is max outstanding requests, F_SZ is max response frame size<br />
+/// frames are delivered by [RequestClient], wrap it with [crate::framing::framed::FramedClient]
+/// if responses can be split or merged by tcp
+pub struct Requester<'d, const WC_SZ: usize, const P: usize, const F_SZ: usize> {
+    /// write channel
+    wch: &'d WriteChannel<'d, WC_SZ>,
+    /// extract the id of a received frame, None if the frame has no id
+    id_of: fn(&[u8]) -> Option<u32>,
+    /// pending requests
+    pending: Mutex<CriticalSectionRawMutex, RefCell<[Pending<F_SZ>; P]>>,
+}
+
+/// custom method
+impl<'d, const WC_SZ: usize, const P: usize, const F_SZ: usize> Requester<'d, WC_SZ, P, F_SZ> {
+    /// create requester, id_of extracts the id of a received frame for [Matcher::Id]
+    #[inline]
+    pub fn new(wch: &'d WriteChannel<'d, WC_SZ>, id_of: fn(&[u8]) -> Option<u32>) -> Self {
+        let pending = core::array::from_fn(|_| Pending { matcher: None, result: None, waker: None });
+        Self { wch, id_of, pending: Mutex::new(RefCell::new(pending)) }
+    }
+
+    /// create requester without ids, only [Matcher::Fn] can be used
+    #[inline]
+    pub fn without_id(wch: &'d WriteChannel<'d, WC_SZ>) -> Self {
+        Self::new(wch, |_| None)
+    }
+
+    /// send payload and wait for the frame accepted by matcher<br />
+    /// the matcher is registered before sending, so a fast response is never missed<br />
+    /// returns [SocketErr::TooManyRequests] if P requests are outstanding,
+    /// the error of [WriteChannel::send_bytes] if the payload can not be queued,
+    /// [SocketErr::RequestAborted] if the connection is lost,
+    /// [SocketErr::RequestTimeout] if no response arrives within timeout
+    pub async fn request(&self, payload: &[u8], matcher: Matcher, timeout: Duration) -> SocketResult<Frame<F_SZ>> {
+        let slot = self.register(matcher).ok_or(SocketErr::TooManyRequests)?;
+        // the slot is freed when the request returns or is cancelled
+        let _guard = SlotGuard { requester: self, slot };
+
+        self.wch.send_bytes(payload).await?;
+        match select(self.wait(slot), Timer::after(timeout)).await {
+            Either::First(result) => result,
+            Either::Second(_) => Err(SocketErr::RequestTimeout),
+        }
+    }
+
+    /// request with id, more see [Requester::request]
+    #[inline]
+    pub async fn request_id(&self, payload: &[u8], id: u32, timeout: Duration) -> SocketResult<Frame<F_SZ>> {
+        self.request(payload, Matcher::Id(id), timeout).await
+    }
+
+    /// number of outstanding requests
+    pub fn outstanding(&self) -> usize {
+        self.pending.lock(|p| p.borrow().iter().filter(|p| p.matcher.is_some()).count())
+    }
+
+    /// register matcher to a free slot, returns the slot index
+    fn register(&self, matcher: Matcher) -> Option<usize> {
+        self.pending.lock(|p| {
+            let mut pending = p.borrow_mut();
+            let slot = pending.iter().position(|p| p.matcher.is_none())?;
+            pending[slot] = Pending { matcher: Some(matcher), result: None, waker: None };
+            Some(slot)
+        })
+    }
+
+    /// free slot
+    #[inline]
+    fn free(&self, slot: usize) {
+        self.pending.lock(|p| p.borrow_mut()[slot] = Pending { matcher: None, result: None, waker: None });
+    }
+
+    /// wait for the result of slot
+    async fn wait(&self, slot: usize) -> SocketResult<Frame<F_SZ>> {
+        poll_fn(|cx| self.pending.lock(|p| {
+            let pending = &mut p.borrow_mut()[slot];
+            match pending.result.take() {
+                Some(result) => Poll::Ready(result),
+                None => {
+                    pending.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })).await
+    }
+
+    /// set result of slot and wake the awaiting task
+    #[inline]
+    fn complete(pending: &mut Pending<F_SZ>, result: SocketResult<Frame<F_SZ>>) {
+        pending.result = Some(result);
+        if let Some(waker) = pending.waker.take() { waker.wake(); }
+    }
+
+    /// deliver received frame to the oldest matching request, returns false if no request matches<br />
+    /// a frame larger than F_SZ completes the request with [SocketErr::FrameTooLarge]
+    pub fn deliver(&self, frame: &[u8]) -> bool {
+        let id = (self.id_of)(frame);
+        self.pending.lock(|p| {
+            let mut pending = p.borrow_mut();
+            let matched = pending.iter_mut().find(|p| p.result.is_none() && match p.matcher {
+                Some(Matcher::Id(want)) => id == Some(want),
+                Some(Matcher::Fn(f)) => f(frame),
+                None => false,
+            });
+            let Some(pending) = matched else { return false; };
+
+            Self::complete(pending, Frame::copy_from(frame).ok_or(SocketErr::FrameTooLarge));
+            true
+        })
+    }
+
+    /// abort all outstanding requests with [SocketErr::RequestAborted], called when the connection is lost
+    pub fn abort(&self) {
+        self.pending.lock(|p| {
+            for pending in p.borrow_mut().iter_mut().filter(|p| p.matcher.is_some() && p.result.is_none()) {
+                Self::complete(pending, Err(SocketErr::RequestAborted));
+            }
+        });
+    }
+}
+
+/// free the slot when the request returns or is cancelled
+struct SlotGuard<'a, 'd, const WC_SZ: usize, const P: usize, const F_SZ: usize> {
+    /// requester
+    requester: &'a Requester<'d, WC_SZ, P, F_SZ>,
+    /// slot index
+    slot: usize,
+}
+
+/// support drop
+impl<const WC_SZ: usize, const P: usize, const F_SZ: usize> Drop for SlotGuard<'_, '_, WC_SZ, P, F_SZ> {
+    #[inline]
+    fn drop(&mut self) {
+        self.requester.free(self.slot);
+    }
+}
+
+/// tcp client callback with request/response correlation<br />
+/// received data matching an outstanding request is delivered to [Requester::request] instead of the inner callback,
+/// other data is passed to the inner callback, outstanding requests are aborted when the connection is lost
+pub struct RequestClient<'d, CB: TcpClientCallBack, const WC_SZ: usize, const P: usize, const F_SZ: usize> {
+    /// requester
+    requester: &'d Requester<'d, WC_SZ, P, F_SZ>,
+    /// inner callback
+    pub cb: CB,
+}
+
+/// custom method
+impl<'d, CB: TcpClientCallBack, const WC_SZ: usize, const P: usize, const F_SZ: usize> RequestClient<'d, CB, WC_SZ, P, F_SZ> {
+    /// create request client callback
+    #[inline]
+    pub const fn new(requester: &'d Requester<'d, WC_SZ, P, F_SZ>, cb: CB) -> Self {
+        Self { requester, cb }
+    }
+}
+
+/// support tcp client callback
+impl<CB: TcpClientCallBack, const WC_SZ: usize, const P: usize, const F_SZ: usize> TcpClientCallBack for RequestClient<'_, CB, WC_SZ, P, F_SZ> {
+    type T = CB::T;
+
+    #[inline]
+    async fn conn<const CN: usize>(&mut self, wch: &WriteChannel<'_, CN>, t: &mut Self::T) {
+        self.cb.conn(wch, t).await;
+    }
+
+    async fn dis_conn(&mut self, t: &mut Self::T) {
+        self.requester.abort();
+        self.cb.dis_conn(t).await;
+    }
+
+    async fn recv<const CN: usize>(&mut self, buf: &[u8], wch: &WriteChannel<'_, CN>, t: &mut Self::T) {
+        if !self.requester.deliver(buf) { self.cb.recv(buf, wch, t).await; }
+    }
+
+    #[inline]
+    async fn err(&mut self, err: SocketErr, t: &mut Self::T) {
+        self.cb.err(err, t).await;
+    }
+
+    #[inline]
+    async fn reconnect(&mut self, attempt: u32, delay: Duration) {
+        self.cb.reconnect(attempt, delay).await;
+    }
+
+    /// outstanding requests are kept, the response may still arrive
+    #[inline]
+    async fn dropped<const CN: usize>(&mut self, count: u32, wch: &WriteChannel<'_, CN>, t: &mut Self::T) {
+        self.cb.dropped(count, wch, t).await;
+    }
+}
@@ -0,0 +1,26 @@
+use crate::err::SocketErr;
+use crate::sntp::SntpSample;
+
+/// sntp callback, generally used to set a hardware rtc after every synchronization
+pub trait SntpCallBack {
+    /// the clock has been synchronized call this, the clock is already updated<br />
+    /// use `UtcDateTime::from_unix(sample.utc())` to get the calendar time, more see [crate::sntp::UtcDateTime]
+    async fn synced(&mut self, sample: &SntpSample);
+
+    /// socket err or sntp err will call this<br />
+    /// only error notification will be made, no blocking and exit will be made<br />
+    /// please do not use endless loops in this method
+    async fn err(&mut self, err: SocketErr);
+}
+
+/// sntp callback that does nothing, only the clock is updated
+pub struct NoHook;
+
+/// support sntp callback
+impl SntpCallBack for NoHook {
+    #[inline]
+    async fn synced(&mut self, _sample: &SntpSample) {}
+
+    #[inline]
+    async fn err(&mut self, _err: SocketErr) {}
+}
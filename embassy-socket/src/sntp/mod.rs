@@ -0,0 +1,236 @@
+use core::cell::Cell;
+use embassy_net::{IpAddress, IpEndpoint};
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_time::{Duration, Instant};
+
+pub mod packet;
+pub mod callback;
+pub mod runner;
+
+/// sntp server port
+pub const SNTP_PORT: u16 = 123;
+
+/// sntp error
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SntpErr {
+    /// the server did not answer within the timeout, the request is retried
+    Timeout,
+    /// the reply is not a valid server reply, or the server is not synchronized
+    InvalidReply,
+    /// the reply does not answer the outstanding request, it is ignored
+    Mismatch,
+    /// the server sent a kiss-o'-death, the next request waits for the poll interval
+    KissOfDeath,
+}
+
+/// sntp client config
+#[derive(Copy, Clone, Debug)]
+pub struct SntpConfig {
+    /// sntp server
+    pub server: IpEndpoint,
+    /// interval between successful synchronizations
+    pub poll: Duration,
+    /// time to wait for the reply
+    pub timeout: Duration,
+    /// interval before retrying a failed request
+    pub retry: Duration,
+}
+
+/// custom method
+impl SntpConfig {
+    /// create config of the server on port 123, poll every 15 minutes, retry after 30 seconds
+    #[inline]
+    pub const fn new(server: IpAddress) -> Self {
+        Self {
+            server: IpEndpoint::new(server, SNTP_PORT),
+            poll: Duration::from_secs(15 * 60),
+            timeout: Duration::from_secs(5),
+            retry: Duration::from_secs(30),
+        }
+    }
+
+    /// set poll interval
+    #[inline]
+    pub const fn poll(mut self, poll: Duration) -> Self {
+        self.poll = poll;
+        self
+    }
+
+    /// set reply timeout
+    #[inline]
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// set retry interval
+    #[inline]
+    pub const fn retry(mut self, retry: Duration) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+/// result of one synchronization
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SntpSample {
+    /// utc at [Instant] zero since the unix epoch, the utc of an instant is `offset + instant`
+    pub offset: Duration,
+    /// round-trip delay of the network, the server processing time is excluded
+    pub delay: Duration,
+    /// stratum of the server
+    pub stratum: u8,
+    /// local time of the synchronization
+    pub at: Instant,
+}
+
+/// custom method
+impl SntpSample {
+    /// calculate the sample from the request send time t1, server receive time t2,
+    /// server transmit time t3 and reply receive time t4<br />
+    /// the offset is corrected by half of the round-trip delay
+    pub fn new(t1: Instant, t2: Duration, t3: Duration, t4: Instant, stratum: u8) -> Self {
+        let (t1, t2, t3, t4) = (t1.as_micros() as i128, t2.as_micros() as i128, t3.as_micros() as i128, t4.as_micros() as i128);
+        let offset = ((t2 - t1) + (t3 - t4)) / 2;
+        let delay = (t4 - t1) - (t3 - t2);
+        Self {
+            offset: Duration::from_micros(offset.max(0) as u64),
+            delay: Duration::from_micros(delay.max(0) as u64),
+            stratum,
+            at: Instant::from_micros(t4 as u64),
+        }
+    }
+
+    /// utc of the synchronization since the unix epoch
+    #[inline]
+    pub fn utc(&self) -> Duration {
+        self.offset + Duration::from_micros(self.at.as_micros())
+    }
+}
+
+/// wall clock synchronized by [runner::SntpRunner], share it to other tasks by reference
+pub struct SntpClock {
+    /// last sample, None if never synchronized
+    sample: Mutex<CriticalSectionRawMutex, Cell<Option<SntpSample>>>,
+}
+
+/// support default
+impl Default for SntpClock {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// custom method
+impl SntpClock {
+    /// create clock that is not synchronized
+    #[inline]
+    pub const fn new() -> Self {
+        Self { sample: Mutex::new(Cell::new(None)) }
+    }
+
+    /// last sample, None if never synchronized
+    #[inline]
+    pub fn sample(&self) -> Option<SntpSample> {
+        self.sample.lock(|s| s.get())
+    }
+
+    /// set sample
+    #[inline]
+    pub(crate) fn set(&self, sample: SntpSample) {
+        self.sample.lock(|s| s.set(Some(sample)));
+    }
+
+    /// clock has been synchronized
+    #[inline]
+    pub fn is_synced(&self) -> bool {
+        self.sample().is_some()
+    }
+
+    /// utc at [Instant] zero since the unix epoch, None if never synchronized
+    #[inline]
+    pub fn offset(&self) -> Option<Duration> {
+        self.sample().map(|s| s.offset)
+    }
+
+    /// utc of the instant since the unix epoch, None if never synchronized
+    #[inline]
+    pub fn utc(&self, instant: Instant) -> Option<Duration> {
+        self.offset().map(|offset| offset + Duration::from_micros(instant.as_micros()))
+    }
+
+    /// current utc since the unix epoch, None if never synchronized
+    #[inline]
+    pub fn now(&self) -> Option<Duration> {
+        self.utc(Instant::now())
+    }
+
+    /// current utc date and time, None if never synchronized
+    #[inline]
+    pub fn date_time(&self) -> Option<UtcDateTime> {
+        self.now().map(UtcDateTime::from_unix)
+    }
+
+    /// instant of the utc since the unix epoch, None if never synchronized or the utc is before [Instant] zero
+    #[inline]
+    pub fn instant(&self, utc: Duration) -> Option<Instant> {
+        Some(Instant::from_micros(utc.as_micros().checked_sub(self.offset()?.as_micros())?))
+    }
+}
+
+/// utc calendar date and time, generally used to set a hardware rtc
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UtcDateTime {
+    /// year
+    pub year: u16,
+    /// month, 1 to 12
+    pub month: u8,
+    /// day of the month, 1 to 31
+    pub day: u8,
+    /// day of the week, 0 is sunday
+    pub weekday: u8,
+    /// hour, 0 to 23
+    pub hour: u8,
+    /// minute, 0 to 59
+    pub minute: u8,
+    /// second, 0 to 59
+    pub second: u8,
+    /// microsecond
+    pub micros: u32,
+}
+
+/// custom method
+impl UtcDateTime {
+    /// convert time since the unix epoch
+    pub const fn from_unix(time: Duration) -> Self {
+        let micros = time.as_micros();
+        let secs = micros / 1_000_000;
+        let days = secs / 86_400;
+        let rem = secs % 86_400;
+
+        // civil date from days, the year starts in march so that the leap day is the last day
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        Self {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            // 1970-01-01 is thursday
+            weekday: ((days + 4) % 7) as u8,
+            hour: (rem / 3600) as u8,
+            minute: (rem / 60 % 60) as u8,
+            second: (rem % 60) as u8,
+            micros: (micros % 1_000_000) as u32,
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use embassy_time::Duration;
+use crate::err::SocketResult;
+use crate::sntp::SntpErr;
+
+/// sntp packet len without extension fields and authenticator
+pub const PACKET_LEN: usize = 48;
+/// seconds from the ntp epoch 1900 to the unix epoch 1970
+pub const UNIX_OFFSET: u64 = 2_208_988_800;
+/// ntp version
+pub const VERSION: u8 = 4;
+/// client mode
+pub const MODE_CLIENT: u8 = 3;
+/// server mode
+pub const MODE_SERVER: u8 = 4;
+/// broadcast mode
+pub const MODE_BROADCAST: u8 = 5;
+/// leap indicator of an unsynchronized server
+pub const LEAP_UNSYNCHRONIZED: u8 = 3;
+
+/// checked server reply
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Reply {
+    /// stratum of the server, 1 is a primary reference
+    pub stratum: u8,
+    /// time the request arrived at the server, since the unix epoch
+    pub receive: Duration,
+    /// time the reply left the server, since the unix epoch
+    pub transmit: Duration,
+}
+
+/// encode client request, the transmit timestamp is only used to match the reply
+pub fn encode_request(transmit: [u8; 8]) -> [u8; PACKET_LEN] {
+    let mut packet = [0; PACKET_LEN];
+    packet[0] = VERSION << 3 | MODE_CLIENT;
+    packet[40..48].copy_from_slice(&transmit);
+    packet
+}
+
+/// parse and check the server reply of the request with transmit timestamp<br />
+/// returns [SntpErr::Mismatch] if the reply does not answer this request, such as a late reply of an earlier request
+pub fn parse_reply(bytes: &[u8], transmit: [u8; 8]) -> SocketResult<Reply> {
+    if bytes.len() < PACKET_LEN { return Err(SntpErr::InvalidReply.into()); }
+    let (leap, version, mode) = (bytes[0] >> 6, bytes[0] >> 3 & 0x07, bytes[0] & 0x07);
+    if version == 0 || !matches!(mode, MODE_SERVER | MODE_BROADCAST) { return Err(SntpErr::InvalidReply.into()); }
+    if bytes[24..32] != transmit { return Err(SntpErr::Mismatch.into()); }
+
+    // stratum 0 is a kiss-o'-death, the server asks the client to stop or slow down
+    let stratum = bytes[1];
+    if stratum == 0 { return Err(SntpErr::KissOfDeath.into()); }
+    if leap == LEAP_UNSYNCHRONIZED || stratum > 15 || bytes[40..48] == [0; 8] { return Err(SntpErr::InvalidReply.into()); }
+    Ok(Reply { stratum, receive: timestamp(&bytes[32..40]), transmit: timestamp(&bytes[40..48]) })
+}
+
+/// decode ntp timestamp to time since the unix epoch<br />
+/// seconds below 2^31 belong to the next era, which starts in 2036
+pub fn timestamp(bytes: &[u8]) -> Duration {
+    let secs = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64;
+    let frac = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as u64;
+    if secs == 0 && frac == 0 { return Duration::from_ticks(0); }
+
+    let secs = if secs < 1 << 31 { secs + (1 << 32) } else { secs };
+    Duration::from_micros(secs.saturating_sub(UNIX_OFFSET) * 1_000_000 + ((frac * 1_000_000) >> 32))
+}
+
+/// encode time since the unix epoch to ntp timestamp
+pub fn encode_timestamp(time: Duration) -> [u8; 8] {
+    let micros = time.as_micros();
+    let secs = (micros / 1_000_000 + UNIX_OFFSET) as u32;
+    let frac = (((micros % 1_000_000) << 32) / 1_000_000) as u32;
+    let mut bytes = [0; 8];
+    bytes[..4].copy_from_slice(&secs.to_be_bytes());
+    bytes[4..].copy_from_slice(&frac.to_be_bytes());
+    bytes
+}
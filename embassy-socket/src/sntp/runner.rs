@@ -0,0 +1,143 @@
+use embassy_futures::select::{select, Either};
+use embassy_time::{Duration, Instant, Timer};
+use crate::channel::callback_enum::CallbackEnum;
+use crate::channel::read_channel::ReadRef;
+use crate::channel::SocketChannel;
+use crate::err::SocketErr;
+use crate::sntp::callback::SntpCallBack;
+use crate::sntp::packet::{encode_request, parse_reply};
+use crate::sntp::{SntpClock, SntpConfig, SntpErr, SntpSample};
+
+/// outstanding request
+#[derive(Copy, Clone, Debug)]
+struct Pending {
+    /// local send time
+    sent: Instant,
+    /// transmit timestamp of the request
+    transmit: [u8; 8],
+}
+
+/// sntp runner, replace [crate::udp::callback_runner::CallbackRunner]<br />
+/// send a request after the udp socket is bound and then every poll interval, check the reply and update the clock<br />
+/// a request without reply within the timeout is retried after the retry interval
+pub struct SntpRunner<'d, const RC_SZ: usize, const WC_SZ: usize, CB: SntpCallBack> {
+    /// socket channel
+    socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
+    /// synchronized clock
+    clock: &'d SntpClock,
+    /// sntp config
+    config: SntpConfig,
+    /// udp socket bound
+    bound: bool,
+    /// outstanding request
+    pending: Option<Pending>,
+    /// next request time
+    next: Instant,
+    /// sntp callback
+    cb: CB,
+}
+
+/// custom method
+impl<'d, const RC_SZ: usize, const WC_SZ: usize, CB: SntpCallBack> SntpRunner<'d, RC_SZ, WC_SZ, CB> {
+    /// create sntp runner
+    #[inline]
+    pub fn new(socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>, clock: &'d SntpClock, config: SntpConfig, cb: CB) -> Self {
+        Self { socket_channel, clock, config, bound: false, pending: None, next: Instant::MAX, cb }
+    }
+
+    /// run sntp runner
+    #[inline]
+    pub async fn run(&mut self) {
+        loop { self.run_logic().await; }
+    }
+
+    /// run logic, wait until there is msg or the request is due or timed out
+    async fn run_logic(&mut self) {
+        match select(self.socket_channel.read_channel.wait_read_ref(), Timer::at(self.deadline())).await {
+            Either::First(msg) => self.callback_logic(&msg).await,
+            Either::Second(_) => self.timer_logic().await,
+        }
+    }
+
+    /// next deadline, the reply timeout or the next request
+    #[inline]
+    fn deadline(&self) -> Instant {
+        if !self.bound { return Instant::MAX; }
+
+        match self.pending {
+            Some(pending) => pending.sent + self.config.timeout,
+            None => self.next,
+        }
+    }
+
+    /// handle msg of the udp socket
+    async fn callback_logic(&mut self, msg: &ReadRef<'_, '_, RC_SZ>) {
+        match msg.callback_enum() {
+            CallbackEnum::Conn => {
+                self.bound = true;
+                self.next = Instant::now();
+            }
+            CallbackEnum::Disconnect => {
+                self.bound = false;
+                self.pending = None;
+            }
+            CallbackEnum::Recv => self.recv(msg.as_bytes(), msg.endpoint().addr == self.config.server.addr).await,
+            CallbackEnum::Err(e) => self.cb.err(e).await,
+            CallbackEnum::Dropped(_) | CallbackEnum::Reconnect { .. } => {}
+        }
+    }
+
+    /// send the request, or give up the request that timed out
+    async fn timer_logic(&mut self) {
+        if self.pending.take().is_some() {
+            self.retry(SntpErr::Timeout.into()).await;
+            return;
+        }
+
+        let sent = Instant::now();
+        // the local instant makes the transmit timestamp unique, the server copies it to the reply
+        let transmit = sent.as_micros().to_be_bytes();
+        match self.socket_channel.write_channel.send_bytes_to(&encode_request(transmit), self.config.server).await {
+            Ok(_) => self.pending = Some(Pending { sent, transmit }),
+            Err(e) => self.retry(e).await,
+        }
+    }
+
+    /// handle datagram, from_server is false if the source is not the server
+    async fn recv(&mut self, bytes: &[u8], from_server: bool) {
+        let Some(pending) = self.pending else { return; };
+        if !from_server { return; }
+
+        let received = Instant::now();
+        let reply = match parse_reply(bytes, pending.transmit) {
+            Ok(reply) => reply,
+            // late reply of an earlier request, keep waiting
+            Err(SocketErr::SntpError(SntpErr::Mismatch)) => return,
+            Err(e) => {
+                self.pending = None;
+                return match e {
+                    SocketErr::SntpError(SntpErr::KissOfDeath) => self.retry_after(e, self.config.poll).await,
+                    _ => self.retry(e).await,
+                };
+            }
+        };
+
+        self.pending = None;
+        self.next = received + self.config.poll;
+        let sample = SntpSample::new(pending.sent, reply.receive, reply.transmit, received, reply.stratum);
+        self.clock.set(sample);
+        self.cb.synced(&sample).await;
+    }
+
+    /// report err and retry after the retry interval
+    #[inline]
+    async fn retry(&mut self, err: SocketErr) {
+        self.retry_after(err, self.config.retry).await
+    }
+
+    /// report err and send the next request after delay
+    async fn retry_after(&mut self, err: SocketErr, delay: Duration) {
+        self.next = Instant::now() + delay;
+        self.cb.err(err).await;
+    }
+}
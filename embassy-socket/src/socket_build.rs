@@ -1,38 +1,43 @@
-use core::net::Ipv4Addr;
 use embassy_net::Stack;
 use crate::channel::SocketChannel;
-use crate::connection::socket_state::SocketState;
+use crate::connection::socket_state::TcpState;
 use crate::tcp_client;
 use crate::tcp_client::callback::TcpClientCallBack;
 use crate::tcp_client::callback_runner::CallbackRunner;
 use crate::tcp_client::read_runner::ReadRunner as TcpClientReadRunner;
+use crate::tcp_client::target::Target;
 use crate::tcp_server::callback::TcpServerCallBack;
 use crate::tcp_server::read_runner::ReadRunner as TcpServerReadRunner;
 use crate::tcp_server::TcpServer;
+use crate::udp;
+use crate::udp::callback::UdpCallBack;
+use crate::udp::callback_runner::CallbackRunner as UdpCallbackRunner;
+use crate::udp::read_runner::ReadRunner as UdpReadRunner;
+use crate::connection::udp_state::UdpState;
 
-/// socket builder trait, let stack build tcp client or tcp server
-pub trait SocketBuilderTrait<'d, const N: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize> {
+/// socket builder trait, let stack build tcp client, tcp server or udp
+pub trait SocketBuilderTrait<'d> {
     /// build tcp client
     fn build_tcp_client<CB: TcpClientCallBack, const RC_SZ: usize, const WC_SZ: usize>(
-        self, ip: Ipv4Addr,
+        self, target: impl Into<Target>,
         port: u16,
         cb: CB,
-        state: &'d SocketState<N, TX_SZ, RX_SZ, BUF_SIZE>,
+        state: &'d dyn TcpState,
         socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>)
-        -> (TcpClientReadRunner<'d, N, TX_SZ, RX_SZ, BUF_SIZE, RC_SZ, WC_SZ>, CallbackRunner<'d, RC_SZ, CB>);
+        -> (TcpClientReadRunner<'d, RC_SZ, WC_SZ>, CallbackRunner<'d, RC_SZ, WC_SZ, CB>);
 
     /// build tcp server
-    fn build_tcp_server<CB: TcpServerCallBack>(self, state: &'d SocketState<N, TX_SZ, RX_SZ, BUF_SIZE>, port: u16, cb: &'d CB,
-    ) -> TcpServer<'d, N, TX_SZ, RX_SZ, BUF_SIZE, CB>;
+    fn build_tcp_server<CB: TcpServerCallBack>(self, state: &'d dyn TcpState, port: u16, cb: &'d CB,
+    ) -> TcpServer<'d, CB>;
 
     /// build tcp client runner<br />
     /// more see [tcp_client::build_runner]
     fn build_tcp_client_runner<const RC_SZ: usize, const WC_SZ: usize>(
-        self, ip: Ipv4Addr,
+        self, target: impl Into<Target>,
         port: u16,
         socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
-        state: &'d SocketState<N, TX_SZ, RX_SZ, BUF_SIZE>)
-        -> TcpClientReadRunner<'d, N, TX_SZ, RX_SZ, BUF_SIZE, RC_SZ, WC_SZ>;
+        state: &'d dyn TcpState)
+        -> TcpClientReadRunner<'d, RC_SZ, WC_SZ>;
 
     /// build tcp server runner<br />
     /// more see [crate::tcp_server::build_runner]
@@ -40,43 +45,60 @@ pub trait SocketBuilderTrait<'d, const N: usize, const TX_SZ: usize, const RX_SZ
         self,
         port: u16,
         socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
-        state: &'d SocketState<N, TX_SZ, RX_SZ, BUF_SIZE>)
-        -> TcpServerReadRunner<'d, N, TX_SZ, RX_SZ, BUF_SIZE, RC_SZ, WC_SZ>;
+        state: &'d dyn TcpState)
+        -> TcpServerReadRunner<'d, RC_SZ, WC_SZ>;
+
+    /// build udp<br />
+    /// more see [udp::build]
+    fn build_udp<CB: UdpCallBack, const RC_SZ: usize, const WC_SZ: usize>(
+        self,
+        port: u16,
+        cb: CB,
+        state: &'d dyn UdpState,
+        socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>)
+        -> (UdpReadRunner<'d, RC_SZ, WC_SZ>, UdpCallbackRunner<'d, RC_SZ, WC_SZ, CB>);
+
+    /// build udp runner<br />
+    /// more see [udp::build_runner]
+    fn build_udp_runner<const RC_SZ: usize, const WC_SZ: usize>(
+        self,
+        port: u16,
+        socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
+        state: &'d dyn UdpState)
+        -> UdpReadRunner<'d, RC_SZ, WC_SZ>;
 }
 
 /// support socket to build tcp client/server
-impl<'d, const N: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize>
-SocketBuilderTrait<'d, N, TX_SZ, RX_SZ, BUF_SIZE>
-for Stack<'d> {
+impl<'d> SocketBuilderTrait<'d> for Stack<'d> {
     #[inline]
     fn build_tcp_client<CB: TcpClientCallBack, const RC_SZ: usize, const WC_SZ: usize>(
         self,
-        ip: Ipv4Addr,
+        target: impl Into<Target>,
         port: u16,
         cb: CB,
-        state: &'d SocketState<N, TX_SZ, RX_SZ, BUF_SIZE>,
+        state: &'d dyn TcpState,
         socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>)
-        -> (TcpClientReadRunner<'d, N, TX_SZ, RX_SZ, BUF_SIZE, RC_SZ, WC_SZ>, CallbackRunner<'d, RC_SZ, CB>) {
-        tcp_client::build(self, ip, port, socket_channel, state, cb)
+        -> (TcpClientReadRunner<'d, RC_SZ, WC_SZ>, CallbackRunner<'d, RC_SZ, WC_SZ, CB>) {
+        tcp_client::build(self, target, port, socket_channel, state, cb)
     }
 
     #[inline]
     fn build_tcp_server<CB: TcpServerCallBack>(
         self,
-        state: &'d SocketState<N, TX_SZ, RX_SZ, BUF_SIZE>,
+        state: &'d dyn TcpState,
         port: u16,
-        cb: &'d CB) -> TcpServer<'d, N, TX_SZ, RX_SZ, BUF_SIZE, CB> {
+        cb: &'d CB) -> TcpServer<'d, CB> {
         TcpServer::new(self, state, port, cb)
     }
 
     #[inline]
     fn build_tcp_client_runner<const RC_SZ: usize, const WC_SZ: usize>(
         self,
-        ip: Ipv4Addr,
+        target: impl Into<Target>,
         port: u16,
         socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
-        state: &'d SocketState<N, TX_SZ, RX_SZ, BUF_SIZE>) -> TcpClientReadRunner<'d, N, TX_SZ, RX_SZ, BUF_SIZE, RC_SZ, WC_SZ> {
-        TcpClientReadRunner::new(self, ip, port, socket_channel, state)
+        state: &'d dyn TcpState) -> TcpClientReadRunner<'d, RC_SZ, WC_SZ> {
+        TcpClientReadRunner::new(self, target, port, socket_channel, state)
     }
 
     #[inline]
@@ -84,7 +106,27 @@ for Stack<'d> {
         self,
         port: u16,
         socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
-        state: &'d SocketState<N, TX_SZ, RX_SZ, BUF_SIZE>) -> TcpServerReadRunner<'d, N, TX_SZ, RX_SZ, BUF_SIZE, RC_SZ, WC_SZ> {
+        state: &'d dyn TcpState) -> TcpServerReadRunner<'d, RC_SZ, WC_SZ> {
         TcpServerReadRunner::new(self, state, port, socket_channel)
     }
+
+    #[inline]
+    fn build_udp<CB: UdpCallBack, const RC_SZ: usize, const WC_SZ: usize>(
+        self,
+        port: u16,
+        cb: CB,
+        state: &'d dyn UdpState,
+        socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>)
+        -> (UdpReadRunner<'d, RC_SZ, WC_SZ>, UdpCallbackRunner<'d, RC_SZ, WC_SZ, CB>) {
+        udp::build(self, port, socket_channel, state, cb)
+    }
+
+    #[inline]
+    fn build_udp_runner<const RC_SZ: usize, const WC_SZ: usize>(
+        self,
+        port: u16,
+        socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
+        state: &'d dyn UdpState) -> UdpReadRunner<'d, RC_SZ, WC_SZ> {
+        UdpReadRunner::new(self, state, port, socket_channel)
+    }
 }
@@ -1,18 +1,32 @@
+use embassy_time::Duration;
+use crate::channel::write_channel::WriteChannel;
 use crate::err::SocketErr;
 
 /// tcp client callback
 pub trait TcpClientCallBack {
+    /// data processed independently on a single connection<br />
+    /// reset to default before every connection, so nothing is carried over after a reconnect
+    type T: Default;
+
     /// connection success call this
-    async fn conn(&mut self);
+    async fn conn<const CN: usize>(&mut self, wch: &WriteChannel<'_, CN>, t: &mut Self::T);
 
     /// connection lost call this
-    async fn dis_conn(&mut self);
+    async fn dis_conn(&mut self, t: &mut Self::T);
 
     /// recv tcp client data call this
-    async fn recv(&mut self, buf: &[u8]);
+    async fn recv<const CN: usize>(&mut self, buf: &[u8], wch: &WriteChannel<'_, CN>, t: &mut Self::T);
 
     /// socket err will call this<br />
     /// only error notification will be made, no blocking and exit will be made<br />
     /// please do not use endless loops in this method
-    async fn err(&mut self, err: SocketErr);
-}
\ No newline at end of file
+    async fn err(&mut self, err: SocketErr, t: &mut Self::T);
+
+    /// reconnect will call this before waiting delay, attempt starts from 1<br />
+    /// default do nothing
+    async fn reconnect(&mut self, _attempt: u32, _delay: Duration) {}
+
+    /// read data was dropped because the read channel was full, count is the number of dropped msg<br />
+    /// more see [crate::channel::overflow_policy::OverflowPolicy], default do nothing
+    async fn dropped<const CN: usize>(&mut self, _count: u32, _wch: &WriteChannel<'_, CN>, _t: &mut Self::T) {}
+}
@@ -1,33 +1,23 @@
-use embassy_time::{Duration, Timer};
 use crate::channel::callback_enum::CallbackEnum;
-use crate::channel::read_channel::ReadChannel;
-use crate::channel::socket_msg::SocketMsg;
+use crate::channel::SocketChannel;
 use crate::tcp_client::callback::TcpClientCallBack;
 
 /// tcp client callback runner
-pub struct CallbackRunner<'d, const RC_SZ: usize, CB: TcpClientCallBack> {
-    /// channel
-    channel: &'d ReadChannel<'d, RC_SZ>,
-    /// channel empty timeout, default is 100 millis
-    timeout: Duration,
-    /// msg cache
-    socket_msg: SocketMsg<RC_SZ>,
+pub struct CallbackRunner<'d, const RC_SZ: usize, const WC_SZ: usize, CB: TcpClientCallBack> {
+    /// socket channel
+    socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
     /// tcp client callback
     cb: CB,
+    /// data of the current connection
+    t: CB::T,
 }
 
 /// custom method
-impl<'d, const RC_SZ: usize, CB: TcpClientCallBack> CallbackRunner<'d, RC_SZ, CB> {
+impl<'d, const RC_SZ: usize, const WC_SZ: usize, CB: TcpClientCallBack> CallbackRunner<'d, RC_SZ, WC_SZ, CB> {
     /// create tcp client callback runner
     #[inline]
-    pub fn new(channel: &'d ReadChannel<'d, RC_SZ>, cb: CB) -> Self {
-        Self { channel, cb, timeout: Duration::from_millis(100), socket_msg: SocketMsg::default() }
-    }
-
-    /// set channel empty timeout
-    #[inline]
-    pub fn timeout(&mut self, timeout: Duration) {
-        self.timeout = timeout;
+    pub fn new(socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>, cb: CB) -> Self {
+        Self { socket_channel, cb, t: Default::default() }
     }
 
     /// run tcp client callback runner<br />
@@ -37,19 +27,21 @@ impl<'d, const RC_SZ: usize, CB: TcpClientCallBack> CallbackRunner<'d, RC_SZ, CB
         loop { self.run_logic().await; }
     }
 
-    /// run logic
+    /// run logic, wait until there is msg and then call back, the msg is borrowed in place until the callback returns<br />
+    /// connection data is reset before every connection
     async fn run_logic(&mut self) {
-        if self.channel.is_empty().await {
-            Timer::after(self.timeout).await;
-            return;
-        }
-
-        self.channel.read(&mut self.socket_msg).await;
-        match self.socket_msg.callback_enum {
-            CallbackEnum::Conn => self.cb.conn().await,
-            CallbackEnum::Disconnect => self.cb.dis_conn().await,
-            CallbackEnum::Recv => self.cb.recv(self.socket_msg.as_bytes()).await,
-            CallbackEnum::Err(e) => self.cb.err(e).await,
+        let wch = &self.socket_channel.write_channel;
+        let msg = self.socket_channel.read_channel.wait_read_ref().await;
+        match msg.callback_enum() {
+            CallbackEnum::Conn => {
+                self.t = Default::default();
+                self.cb.conn(wch, &mut self.t).await
+            }
+            CallbackEnum::Disconnect => self.cb.dis_conn(&mut self.t).await,
+            CallbackEnum::Recv => self.cb.recv(msg.as_bytes(), wch, &mut self.t).await,
+            CallbackEnum::Err(e) => self.cb.err(e, &mut self.t).await,
+            CallbackEnum::Dropped(count) => self.cb.dropped(count, wch, &mut self.t).await,
+            CallbackEnum::Reconnect { attempt, delay } => self.cb.reconnect(attempt, delay).await,
         }
     }
 }
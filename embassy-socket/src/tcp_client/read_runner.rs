@@ -1,56 +1,109 @@
-use core::net::Ipv4Addr;
-use embassy_net::Stack;
-use embassy_net::tcp::State;
-use embassy_time::{Duration, Timer};
+use core::cell::Cell;
+use embassy_futures::select::{select, select4, Either, Either4};
+use embassy_net::{IpEndpoint, Stack};
+#[cfg(feature = "dns")]
+use embassy_net::dns::{DnsQueryType, IpAddress};
+use embassy_time::{Duration, Instant, Timer};
+use embedded_io_async::Write;
+use crate::channel::callback_enum::CallbackEnum;
+use crate::channel::socket_msg::SocketMsg;
 use crate::channel::SocketChannel;
-use crate::connection::socket_state::SocketState;
-use crate::connection::TcpConnection;
-use crate::err::SocketResult;
+use crate::connection::socket_state::TcpState;
+use crate::connection::{TcpConnReader, TcpConnection};
+use crate::control::{Command, Control, State};
+use crate::control;
+use crate::err::{SocketErr, SocketResult};
+use crate::heartbeat::{Heartbeat, Supervisor};
+use crate::tcp_client::reconnect::ReconnectPolicy;
+use crate::tcp_client::target::Target;
+#[cfg(feature = "tls")]
+use core::cell::RefCell;
+#[cfg(feature = "tls")]
+use embedded_io_async::Read;
+#[cfg(feature = "tls")]
+use embedded_tls::{SplitConnectionState, TlsCipherSuite, TlsError, TlsVerifier};
+#[cfg(feature = "tls")]
+use rand_core::{CryptoRng, RngCore};
+#[cfg(feature = "tls")]
+use crate::tls::{TlsIo, TlsSession};
 
 /// tcp client read runner
-pub struct ReadRunner<'d, const N: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize, const RC_SZ: usize, const WC_SZ: usize> {
+pub struct ReadRunner<'d, const RC_SZ: usize, const WC_SZ: usize> {
     /// tcp stack
     stack: Stack<'d>,
     /// socket state, memory pool
-    state: &'d SocketState<N, TX_SZ, RX_SZ, BUF_SIZE>,
+    state: &'d dyn TcpState,
     /// socket timeout
     socket_timeout: Option<Duration>,
     /// socket keep alive<br />
     /// more see [embassy_net::tcp::TcpSocket::set_keep_alive]
     keep_alive: Option<Duration>,
-    /// read data timeout, default is 100 milliseconds
-    read_timeout: Duration,
-    /// tcp client connection ip
-    ip: Ipv4Addr,
+    /// tcp client connection target
+    target: Cell<Target>,
     /// tcp client connection port
-    port: u16,
+    port: Cell<u16>,
     /// socket channel
     socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
+    /// reconnect policy
+    reconnect_policy: ReconnectPolicy,
+    /// application-level heartbeat
+    heartbeat: Option<Heartbeat>,
+    /// reconnect attempts since the last connection that lasted the min uptime of the reconnect policy
+    attempt: Cell<u32>,
+    /// runtime control handle
+    control: Option<&'d Control>,
+    /// command received while connected
+    command: Cell<Option<Command>>,
+    /// resolved host ip and the time it expires
+    #[cfg(feature = "dns")]
+    dns_cache: Cell<Option<(IpAddress, Instant)>>,
+    /// dns cache ttl
+    #[cfg(feature = "dns")]
+    dns_ttl: Duration,
 }
 
 /// custom method
-impl<'d, const N: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize, const RC_SZ: usize, const WC_SZ: usize>
-ReadRunner<'d, N, TX_SZ, RX_SZ, BUF_SIZE, RC_SZ, WC_SZ> {
-    /// create tcp client read runner
+impl<'d, const RC_SZ: usize, const WC_SZ: usize>
+ReadRunner<'d, RC_SZ, WC_SZ> {
+    /// create tcp client read runner<br />
+    /// target can be ip or host name (with `dns` feature), more see [Target]
     #[inline]
     pub fn new(
         stack: Stack<'d>,
-        ip: Ipv4Addr,
+        target: impl Into<Target>,
         port: u16,
         socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
-        state: &'d SocketState<N, TX_SZ, RX_SZ, BUF_SIZE>) -> Self {
+        state: &'d dyn TcpState) -> Self {
         Self {
             stack,
             state,
             socket_timeout: Some(Duration::from_secs(1)),
             keep_alive: Some(Duration::from_millis(300)),
-            read_timeout: Duration::from_millis(100),
-            ip,
-            port,
+            target: Cell::new(target.into()),
+            port: Cell::new(port),
             socket_channel,
+            reconnect_policy: ReconnectPolicy::default(),
+            heartbeat: None,
+            attempt: Cell::new(0),
+            control: None,
+            command: Cell::new(None),
+            #[cfg(feature = "dns")]
+            dns_cache: Cell::new(None),
+            #[cfg(feature = "dns")]
+            dns_ttl: Duration::from_secs(300),
         }
     }
 
+    /// create tcp client read runner with ip endpoint
+    #[inline]
+    pub fn with_endpoint(
+        stack: Stack<'d>,
+        endpoint: IpEndpoint,
+        socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
+        state: &'d dyn TcpState) -> Self {
+        Self::new(stack, endpoint.addr, endpoint.port, socket_channel, state)
+    }
+
     /// set socket timeout<br />
     /// connection timeout and etc. <br />
     /// recommended not to set or set to None
@@ -59,22 +112,47 @@ ReadRunner<'d, N, TX_SZ, RX_SZ, BUF_SIZE, RC_SZ, WC_SZ> {
         self.keep_alive = timeout.map(|timeout| timeout.checked_div(10)).unwrap_or_default();
     }
 
-    /// set read timeout
+    /// set reconnect policy, default see [ReconnectPolicy::default]
+    #[inline]
+    pub fn reconnect_policy(&mut self, policy: ReconnectPolicy) {
+        self.reconnect_policy = policy;
+    }
+
+    /// set application-level heartbeat, default is None<br />
+    /// more see [Heartbeat]
+    #[inline]
+    pub fn heartbeat(&mut self, heartbeat: Option<Heartbeat>) {
+        self.heartbeat = heartbeat;
+    }
+
+    /// set runtime control handle, more see [Control]
+    #[inline]
+    pub fn control(&mut self, control: &'d Control) {
+        self.control = Some(control);
+    }
+
+    /// set how long a resolved host ip is reused, default is 5 minutes<br />
+    /// embassy-net does not expose the ttl of dns records, so the cache ttl is configured here<br />
+    /// the cache is dropped when connecting to the resolved ip fails, the host is resolved again on the next reconnect
+    #[cfg(feature = "dns")]
     #[inline]
-    pub fn read_timeout(&mut self, timeout: Duration) {
-        self.read_timeout = timeout;
+    pub fn dns_ttl(&mut self, ttl: Duration) {
+        self.dns_ttl = ttl;
     }
 
     /// run tcp client<br />
-    /// calling this method causes tcp to maintain a long connection and send data asynchronously over WriteChannel
+    /// calling this method causes tcp to maintain a long connection and send data asynchronously over WriteChannel<br />
+    /// this method returns only when the reconnect policy max attempts is exhausted or [Control::stop] is called
     #[inline]
     pub async fn run(&self) {
-        loop { self.run_logic().await; }
+        while self.run_logic().await {}
+        control::set_state(self.control, State::Stopped);
     }
 
-    /// run logic
-    async fn run_logic(&self) {
+    /// run logic, returns false if reconnect attempts are exhausted or stop is requested
+    async fn run_logic(&self) -> bool {
         // wait stack link and config up
+        control::set_state(self.control, State::Connecting);
         self.stack.wait_link_up().await;
         self.stack.wait_config_up().await;
 
@@ -82,54 +160,256 @@ ReadRunner<'d, N, TX_SZ, RX_SZ, BUF_SIZE, RC_SZ, WC_SZ> {
             Ok(conn) => conn,
             Err(e) => {
                 self.socket_channel.read_channel.err(e).await;
-                return;
+                return self.retry_wait().await;
             }
         };
 
+        let connected = Instant::now();
+        control::set_state(self.control, State::Connected);
         self.socket_channel.write_channel.enable().await;
         self.socket_channel.read_channel.conn().await;
-        while !self.read_logic(&mut conn).await {}
+        self.serve(&mut conn).await;
+        // close the connection before waiting for the command
+        drop(conn);
         self.socket_channel.write_channel.disable().await;
         self.socket_channel.read_channel.dis_conn().await;
+        self.after_serve(connected).await
     }
 
-    /// read tcp data logic
-    async fn read_logic(&self, conn: &mut TcpConnection<'d, N, TX_SZ, RX_SZ, BUF_SIZE>) -> bool {
-        if !conn.socket.can_recv() {
-            if let Err(e) = self.write_logic(conn).await { self.socket_channel.read_channel.err(e).await }
-            return matches!(conn.socket.state(), State::CloseWait|State::Closed);
-        }
+    /// run tcp client over tls<br />
+    /// the same as [ReadRunner::run], the tls handshake is done after every connection,
+    /// handshake failure is reported as [SocketErr::TlsError] and reconnected by the reconnect policy<br />
+    /// recv and write data in the socket channel are plaintext, so the callback does not need to change
+    #[cfg(feature = "tls")]
+    #[inline]
+    pub async fn run_tls<CS, RNG, V>(&self, session: &mut TlsSession<'_, CS, RNG, V>)
+    where
+        CS: TlsCipherSuite + 'static,
+        RNG: CryptoRng + RngCore,
+        V: for<'v> TlsVerifier<'v, CS> {
+        while self.run_tls_logic(session).await {}
+        control::set_state(self.control, State::Stopped);
+    }
+
+    /// run tls logic, returns false if reconnect attempts are exhausted or stop is requested
+    #[cfg(feature = "tls")]
+    async fn run_tls_logic<CS, RNG, V>(&self, session: &mut TlsSession<'_, CS, RNG, V>) -> bool
+    where
+        CS: TlsCipherSuite + 'static,
+        RNG: CryptoRng + RngCore,
+        V: for<'v> TlsVerifier<'v, CS> {
+        let Some(connected) = self.serve_tls(session).await else { return self.retry_wait().await; };
 
-        match conn.try_read().await {
-            Ok(bytes) => {
-                self.socket_channel.read_channel.recv(bytes).await;
-                false
+        self.socket_channel.write_channel.disable().await;
+        self.socket_channel.read_channel.dis_conn().await;
+        self.after_serve(connected).await
+    }
+
+    /// connect, handshake and serve, returns the time the handshake finished, None if connecting or the handshake failed<br />
+    /// the connection is closed when this method returns
+    #[cfg(feature = "tls")]
+    async fn serve_tls<CS, RNG, V>(&self, session: &mut TlsSession<'_, CS, RNG, V>) -> Option<Instant>
+    where
+        CS: TlsCipherSuite + 'static,
+        RNG: CryptoRng + RngCore,
+        V: for<'v> TlsVerifier<'v, CS> {
+        control::set_state(self.control, State::Connecting);
+        self.stack.wait_link_up().await;
+        self.stack.wait_config_up().await;
+
+        let mut conn = match self.try_conn().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                self.socket_channel.read_channel.err(e).await;
+                return None;
             }
+        };
+
+        let (socket, buf) = conn.socket_buf();
+        let (reader, writer) = socket.split();
+        let (reader, writer) = (RefCell::new(reader), RefCell::new(writer));
+        let tls = match session.open(TlsIo::new(&reader, &writer)).await {
+            Ok(tls) => tls,
             Err(e) => {
                 self.socket_channel.read_channel.err(e.into()).await;
-                true
+                return None;
+            }
+        };
+
+        let connected = Instant::now();
+        control::set_state(self.control, State::Connected);
+        self.socket_channel.write_channel.enable().await;
+        self.socket_channel.read_channel.conn().await;
+        let mut split_state = SplitConnectionState::default();
+        let (mut tls_reader, mut tls_writer) = tls.split_with(&mut split_state);
+        let supervisor = Supervisor::new(self.heartbeat);
+        let read = self.tls_read_logic(&mut tls_reader, buf, &supervisor);
+        let either = select4(read, self.write_logic(&mut tls_writer), supervisor.run(&self.socket_channel.write_channel), self.control_logic()).await;
+        self.supervise(either).await;
+        Some(connected)
+    }
+
+    /// read tls plaintext data logic, returns when the connection is closed
+    #[cfg(feature = "tls")]
+    async fn tls_read_logic<R: Read<Error=TlsError>>(&self, reader: &mut R, buf: &mut [u8], supervisor: &Supervisor) {
+        loop {
+            match reader.read(buf).await {
+                Ok(0) => return,
+                Ok(len) => {
+                    supervisor.recv(&buf[..len]);
+                    self.socket_channel.read_channel.recv(&buf[..len]).await;
+                }
+                Err(e) => {
+                    self.socket_channel.read_channel.err(e.into()).await;
+                    return;
+                }
             }
         }
     }
 
-    /// write logic
-    async fn write_logic(&self, conn: &mut TcpConnection<'d, N, TX_SZ, RX_SZ, BUF_SIZE>) -> SocketResult<()> {
-        // if channel is empty, just sleep and return let continue
-        if self.socket_channel.write_channel.is_empty().await {
-            Timer::after(self.read_timeout).await;
-            return Ok(());
+    /// wait before the next reconnect, returns false if reconnect attempts are exhausted or stop is requested
+    async fn retry_wait(&self) -> bool {
+        let attempt = self.attempt.get().saturating_add(1);
+        self.attempt.set(attempt);
+        let Some(delay) = self.reconnect_policy.delay(attempt) else {
+            self.socket_channel.read_channel.err(SocketErr::ReconnectExhausted).await;
+            return false;
+        };
+
+        control::set_state(self.control, State::Backoff);
+        self.socket_channel.read_channel.reconnect(attempt, delay).await;
+        match select(Timer::after(delay), control::wait(self.control)).await {
+            Either::First(_) => true,
+            Either::Second(command) => self.command_logic(command).await,
+        }
+    }
+
+    /// handle the command received while connected, returns false if reconnect attempts are exhausted or stop is requested<br />
+    /// a connection shorter than the min uptime of the reconnect policy counts as a failed attempt and waits the backoff delay,
+    /// otherwise the attempts are reset and the client reconnects at once
+    async fn after_serve(&self, connected: Instant) -> bool {
+        if let Some(command) = self.command.take() { return self.command_logic(command).await; }
+        if connected.elapsed() < self.reconnect_policy.min_uptime { return self.retry_wait().await; }
+
+        self.attempt.set(0);
+        true
+    }
+
+    /// handle command while not connected, returns false if stop is requested<br />
+    /// disconnect waits here until connect, reconnect or stop
+    async fn command_logic(&self, mut command: Command) -> bool {
+        loop {
+            match command {
+                Command::Stop => return false,
+                Command::Connect => break,
+                Command::ReconnectTo(target, port) => {
+                    self.target.set(target);
+                    self.port.set(port);
+                    #[cfg(feature = "dns")]
+                    self.dns_cache.set(None);
+                    break;
+                }
+                Command::Disconnect => {
+                    control::set_state(self.control, State::Paused);
+                    command = control::wait(self.control).await;
+                }
+            }
+        }
+        self.attempt.set(0);
+        true
+    }
+
+    /// handle commands while connected, returns when the connection should be closed now
+    #[inline]
+    async fn control_logic(&self) {
+        control::serve(self.control, &self.socket_channel.write_channel, &self.command, true).await
+    }
+
+    /// read and write concurrently until the connection is closed or the heartbeat times out
+    async fn serve(&self, conn: &mut TcpConnection<'d>) {
+        let (mut reader, mut writer) = conn.split();
+        let supervisor = Supervisor::new(self.heartbeat);
+        let read = self.read_logic(&mut reader, &supervisor);
+        let either = select4(read, self.write_logic(&mut writer), supervisor.run(&self.socket_channel.write_channel), self.control_logic()).await;
+        self.supervise(either).await;
+    }
+
+    /// report heartbeat timeout
+    #[inline]
+    async fn supervise(&self, either: Either4<(), (), (), ()>) {
+        if let Either4::Third(_) = either { self.socket_channel.read_channel.err(SocketErr::HeartbeatTimeout).await; }
+    }
+
+    /// read tcp data logic, returns when the connection is closed
+    /// socket data is copied to the read channel in place, one read is one msg
+    async fn read_logic(&self, reader: &mut TcpConnReader<'_>, supervisor: &Supervisor) {
+        let read_channel = &self.socket_channel.read_channel;
+        loop {
+            let result = reader.read_with(|bytes| {
+                let len = read_channel.try_recv(bytes);
+                supervisor.recv(&bytes[..len]);
+                len
+            }).await;
+            match result {
+                Ok(None) => return,
+                Ok(Some(0)) => read_channel.wait_room().await,
+                Ok(Some(_)) => {}
+                Err(e) => {
+                    self.socket_channel.read_channel.err(e.into()).await;
+                    return;
+                }
+            }
         }
+    }
 
-        self.socket_channel.write_channel.try_tcp_write(conn).await?;
-        Ok(())
+    /// write logic, write data as soon as it is queued, returns when close is requested
+    async fn write_logic<W: Write>(&self, writer: &mut W)
+    where
+        W::Error: Into<SocketErr> {
+        let mut msg = SocketMsg::default();
+        loop {
+            if let Err(e) = self.socket_channel.write_channel.try_write(writer, &mut msg).await {
+                self.socket_channel.read_channel.err(e.into()).await;
+            }
+            if matches!(msg.callback_enum, CallbackEnum::Disconnect) { return; }
+        }
     }
 
     /// try connection
-    async fn try_conn(&self) -> SocketResult<TcpConnection<'d, N, TX_SZ, RX_SZ, BUF_SIZE>> {
+    async fn try_conn(&self) -> SocketResult<TcpConnection<'d>> {
+        self.socket_channel.read_channel.connect_attempt();
         let mut conn = TcpConnection::new(self.stack, self.state)?;
         conn.socket.set_timeout(self.socket_timeout);
         conn.socket.set_keep_alive(self.keep_alive);
-        conn.socket.connect((self.ip, self.port)).await?;
+        // only one variant without the dns feature
+        #[cfg_attr(not(feature = "dns"), allow(clippy::infallible_destructuring_match))]
+        let ip = match self.target.get() {
+            Target::Ip(ip) => ip,
+            #[cfg(feature = "dns")]
+            Target::Host(host) => self.resolve(host).await?,
+        };
+        if let Err(e) = conn.socket.connect((ip, self.port.get())).await {
+            #[cfg(feature = "dns")]
+            self.dns_cache.set(None);
+            return Err(e.into());
+        }
         Ok(conn)
     }
+
+    /// resolve host ip, the cached ip is used until it expires<br />
+    /// ipv4 is preferred, ipv6 is queried when the host has no ipv4 (with `proto-ipv6` feature)
+    #[cfg(feature = "dns")]
+    async fn resolve(&self, host: &str) -> SocketResult<IpAddress> {
+        if let Some((ip, expires)) = self.dns_cache.get() && Instant::now() < expires { return Ok(ip); }
+
+        let addrs = self.stack.dns_query(host, DnsQueryType::A).await;
+        #[cfg(feature = "proto-ipv6")]
+        let addrs = match addrs {
+            Ok(addrs) if !addrs.is_empty() => Ok(addrs),
+            _ => self.stack.dns_query(host, DnsQueryType::Aaaa).await,
+        };
+        let ip = addrs?.first().copied().ok_or(embassy_net::dns::Error::Failed)?;
+        self.dns_cache.set(Some((ip, Instant::now() + self.dns_ttl)));
+        Ok(ip)
+    }
 }
@@ -0,0 +1,120 @@
+use embassy_time::{Duration, Instant};
+
+/// reconnect backoff
+#[derive(Copy, Clone, Debug)]
+pub enum Backoff {
+    /// reconnect immediately, no delay
+    Immediately,
+    /// reconnect after a fixed delay
+    Fixed(Duration),
+    /// reconnect after base * 2^(attempt - 1), the delay will not exceed max
+    Exponential {
+        /// first delay
+        base: Duration,
+        /// max delay
+        max: Duration,
+    },
+}
+
+/// tcp client reconnect policy<br />
+/// default is exponential backoff from 1 second up to 30 seconds, no jitter, unlimited attempts, 10 seconds min uptime
+#[derive(Copy, Clone, Debug)]
+pub struct ReconnectPolicy {
+    /// reconnect backoff
+    pub backoff: Backoff,
+    /// jitter percent, 0~100<br />
+    /// the delay will be randomly reduced by up to this percent,
+    /// avoid many devices reconnecting at the same time
+    pub jitter: u8,
+    /// max reconnect attempts, None is unlimited
+    pub max_attempts: Option<u32>,
+    /// a connection closed within this time counts as a failed attempt and is reconnected after the backoff delay,
+    /// so a server that accepts and closes at once is not reconnected in a tight loop<br />
+    /// the attempts are reset only after a connection lasted this long
+    pub min_uptime: Duration,
+}
+
+/// support default
+impl Default for ReconnectPolicy {
+    #[inline]
+    fn default() -> Self {
+        Self::exponential(Duration::from_secs(1), Duration::from_secs(30))
+    }
+}
+
+/// custom method
+impl ReconnectPolicy {
+    /// create reconnect policy, no jitter, unlimited attempts, 10 seconds min uptime
+    #[inline]
+    pub const fn new(backoff: Backoff) -> Self {
+        Self { backoff, jitter: 0, max_attempts: None, min_uptime: Duration::from_secs(10) }
+    }
+
+    /// reconnect immediately, no delay
+    #[inline]
+    pub const fn immediately() -> Self {
+        Self::new(Backoff::Immediately)
+    }
+
+    /// reconnect after a fixed delay
+    #[inline]
+    pub const fn fixed(delay: Duration) -> Self {
+        Self::new(Backoff::Fixed(delay))
+    }
+
+    /// reconnect with exponential delay, the delay will not exceed max
+    #[inline]
+    pub const fn exponential(base: Duration, max: Duration) -> Self {
+        Self::new(Backoff::Exponential { base, max })
+    }
+
+    /// set jitter percent, values greater than 100 are treated as 100
+    #[inline]
+    pub const fn jitter(mut self, percent: u8) -> Self {
+        self.jitter = if percent > 100 { 100 } else { percent };
+        self
+    }
+
+    /// set max reconnect attempts
+    #[inline]
+    pub const fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// set min uptime, more see [ReconnectPolicy::min_uptime]
+    #[inline]
+    pub const fn min_uptime(mut self, min_uptime: Duration) -> Self {
+        self.min_uptime = min_uptime;
+        self
+    }
+
+    /// get the delay before the attempt, attempt starts from 1<br />
+    /// returns None if the max attempts is exceeded, then stop reconnecting
+    pub fn delay(&self, attempt: u32) -> Option<Duration> {
+        if self.max_attempts.is_some_and(|max| attempt > max) { return None; }
+
+        let delay = match self.backoff {
+            Backoff::Immediately => Duration::from_ticks(0),
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { base, max } => {
+                let factor = 1u64.checked_shl(attempt.saturating_sub(1)).unwrap_or(u64::MAX);
+                Duration::from_ticks(base.as_ticks().saturating_mul(factor).min(max.as_ticks()))
+            }
+        };
+        Some(self.apply_jitter(delay, attempt))
+    }
+
+    /// randomly reduce delay by up to jitter percent
+    fn apply_jitter(&self, delay: Duration, attempt: u32) -> Duration {
+        if self.jitter == 0 { return delay; }
+
+        // xorshift, the current time is random enough for jitter
+        let mut x = (Instant::now().as_ticks() ^ ((attempt as u64) << 32)) | 1;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        let percent = x % (self.jitter as u64 + 1);
+        Duration::from_ticks(delay.as_ticks() - delay.as_ticks() / 100 * percent)
+    }
+}
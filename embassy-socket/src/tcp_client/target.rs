@@ -0,0 +1,59 @@
+use core::net::Ipv4Addr;
+#[cfg(feature = "proto-ipv6")]
+use core::net::{IpAddr, Ipv6Addr};
+use embassy_net::IpAddress;
+
+/// tcp client connection target<br />
+/// `Ipv4Addr`, `IpAddress`, `Ipv6Addr` and `IpAddr` (with `proto-ipv6` feature)
+/// and host name (with `dns` feature) can be converted into target directly
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// fixed ip
+    Ip(IpAddress),
+    /// host name, resolved by dns before connecting
+    #[cfg(feature = "dns")]
+    Host(&'static str),
+}
+
+/// support ip address to target
+impl From<IpAddress> for Target {
+    #[inline]
+    fn from(value: IpAddress) -> Self {
+        Self::Ip(value)
+    }
+}
+
+/// support ip addr to target
+#[cfg(feature = "proto-ipv6")]
+impl From<IpAddr> for Target {
+    #[inline]
+    fn from(value: IpAddr) -> Self {
+        Self::Ip(value.into())
+    }
+}
+
+/// support ipv4 addr to target
+impl From<Ipv4Addr> for Target {
+    #[inline]
+    fn from(value: Ipv4Addr) -> Self {
+        Self::Ip(IpAddress::Ipv4(value))
+    }
+}
+
+/// support ipv6 addr to target
+#[cfg(feature = "proto-ipv6")]
+impl From<Ipv6Addr> for Target {
+    #[inline]
+    fn from(value: Ipv6Addr) -> Self {
+        Self::Ip(IpAddress::Ipv6(value))
+    }
+}
+
+/// support host name to target
+#[cfg(feature = "dns")]
+impl From<&'static str> for Target {
+    #[inline]
+    fn from(value: &'static str) -> Self {
+        Self::Host(value)
+    }
+}
@@ -20,4 +20,10 @@ pub trait TcpServerCallBack {
     /// only error notification will be made, no blocking and exit will be made<br />
     /// please do not use endless loops in this method
     async fn err(&self, err: SocketErr, t: &mut Self::T);
+
+    /// read data was dropped because the read channel was full, count is the number of dropped msg<br />
+    /// the multi-connection runner calls this for every connection, because the dropped data may belong to any of them,
+    /// other tcp server runners do not know the endpoint<br />
+    /// more see [crate::channel::overflow_policy::OverflowPolicy], default do nothing
+    async fn dropped<const CN: usize>(&self, _endpoint: IpEndpoint, _count: u32, _wch: &WriteChannel<'_, CN>, _t: &mut Self::T) {}
 }
\ No newline at end of file
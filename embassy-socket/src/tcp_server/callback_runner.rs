@@ -1,6 +1,4 @@
-use embassy_time::{Duration, Timer};
 use crate::channel::callback_enum::CallbackEnum;
-use crate::channel::socket_msg::SocketMsg;
 use crate::channel::SocketChannel;
 use crate::tcp_server::callback::TcpServerCallBack;
 
@@ -8,10 +6,6 @@ use crate::tcp_server::callback::TcpServerCallBack;
 pub struct CallbackRunner<'d, const RC_SZ: usize, const WC_SZ: usize, CB: TcpServerCallBack> {
     /// socket channel
     socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
-    /// channel empty timeout, default is 100 millis
-    timeout: Duration,
-    /// msg cache
-    socket_msg: SocketMsg<RC_SZ>,
     /// tcp client callback
     cb: &'d CB,
 }
@@ -21,13 +15,7 @@ impl<'d, const RC_SZ: usize, const WC_SZ: usize, CB: TcpServerCallBack> Callback
     /// create tcp server callback runner
     #[inline]
     pub fn new(socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>, cb: &'d CB) -> Self {
-        Self { socket_channel, cb, timeout: Duration::from_millis(100), socket_msg: SocketMsg::default() }
-    }
-
-    /// set channel empty timeout
-    #[inline]
-    pub fn timeout(&mut self, timeout: Duration) {
-        self.timeout = timeout;
+        Self { socket_channel, cb }
     }
 
     /// run tcp client callback runner<br />
@@ -37,19 +25,17 @@ impl<'d, const RC_SZ: usize, const WC_SZ: usize, CB: TcpServerCallBack> Callback
         loop { self.run_logic(t).await; }
     }
 
-    /// run logic
+    /// run logic, wait until there is msg and then call back, the msg is borrowed in place until the callback returns
     async fn run_logic(&mut self, t: &mut CB::T) {
-        if self.socket_channel.read_channel.is_empty().await {
-            Timer::after(self.timeout).await;
-            return;
-        }
-
-        self.socket_channel.read_channel.read_addr(&mut self.socket_msg).await;
-        match self.socket_msg.callback_enum {
-            CallbackEnum::Conn => self.cb.conn(self.socket_msg.endpoint, &self.socket_channel.write_channel, t).await,
-            CallbackEnum::Disconnect => self.cb.dis_conn(self.socket_msg.endpoint, t).await,
-            CallbackEnum::Recv => self.cb.recv(self.socket_msg.endpoint, self.socket_msg.as_bytes(), &self.socket_channel.write_channel, t).await,
+        let msg = self.socket_channel.read_channel.wait_read_ref().await;
+        match msg.callback_enum() {
+            CallbackEnum::Conn => self.cb.conn(msg.endpoint(), &self.socket_channel.write_channel, t).await,
+            CallbackEnum::Disconnect => self.cb.dis_conn(msg.endpoint(), t).await,
+            CallbackEnum::Recv => self.cb.recv(msg.endpoint(), msg.as_bytes(), &self.socket_channel.write_channel, t).await,
             CallbackEnum::Err(e) => self.cb.err(e, t).await,
+            CallbackEnum::Dropped(count) => self.cb.dropped(msg.endpoint(), count, &self.socket_channel.write_channel, t).await,
+            // only tcp client will reconnect
+            CallbackEnum::Reconnect { .. } => {}
         }
     }
 }
@@ -1,20 +1,24 @@
 use embassy_net::Stack;
 use crate::channel::SocketChannel;
-use crate::connection::socket_state::SocketState;
+use crate::connection::socket_state::TcpState;
 use crate::tcp_server::callback::TcpServerCallBack;
 use crate::tcp_server::callback_runner::CallbackRunner;
+use crate::tcp_server::multi_callback_runner::MultiCallbackRunner;
+use crate::tcp_server::multi_runner::MultiReadRunner;
 use crate::tcp_server::read_runner::ReadRunner;
 
 pub mod callback;
 pub mod read_runner;
 pub mod callback_runner;
+pub mod multi_runner;
+pub mod multi_callback_runner;
 
 /// tcp server
-pub struct TcpServer<'d, const N: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize, CB: TcpServerCallBack> {
+pub struct TcpServer<'d, CB: TcpServerCallBack> {
     /// tcp stack
     stack: Stack<'d>,
     /// socket state, memory pool
-    state: &'d SocketState<N, TX_SZ, RX_SZ, BUF_SIZE>,
+    state: &'d dyn TcpState,
     /// listener port
     port: u16,
     /// socket callback
@@ -22,11 +26,11 @@ pub struct TcpServer<'d, const N: usize, const TX_SZ: usize, const RX_SZ: usize,
 }
 
 /// custom method
-impl<'d, const N: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize, CB: TcpServerCallBack>
-TcpServer<'d, N, TX_SZ, RX_SZ, BUF_SIZE, CB> {
+impl<'d, CB: TcpServerCallBack>
+TcpServer<'d, CB> {
     /// create tcp server
     #[inline]
-    pub fn new(stack: Stack<'d>, state: &'d SocketState<N, TX_SZ, RX_SZ, BUF_SIZE>, port: u16, cb: &'d CB) -> Self {
+    pub fn new(stack: Stack<'d>, state: &'d dyn TcpState, port: u16, cb: &'d CB) -> Self {
         Self { stack, state, port, cb }
     }
 
@@ -35,10 +39,22 @@ TcpServer<'d, N, TX_SZ, RX_SZ, BUF_SIZE, CB> {
     #[inline]
     pub fn create<const RC_SZ: usize, const WC_SZ: usize>(
         &self, socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>)
-        -> (ReadRunner<'d, N, TX_SZ, RX_SZ, BUF_SIZE, RC_SZ, WC_SZ>, CallbackRunner<'d, RC_SZ, WC_SZ, CB>) {
+        -> (ReadRunner<'d, RC_SZ, WC_SZ>, CallbackRunner<'d, RC_SZ, WC_SZ, CB>) {
         (ReadRunner::new(self.stack, self.state, self.port, socket_channel),
          CallbackRunner::new(socket_channel, self.cb))
     }
+
+    /// create multi-connection runner<br />
+    /// one runner accepts up to N connections, N should not exceed the connection number of the state and all connections share the socket channel,
+    /// so only one pair of tasks is needed<br />
+    /// every connection has its own callback data `T`, more see [MultiReadRunner] and [MultiCallbackRunner]
+    #[inline]
+    pub fn create_multi<const N: usize, const RC_SZ: usize, const WC_SZ: usize>(
+        &self, socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>)
+        -> (MultiReadRunner<'d, N, RC_SZ, WC_SZ>, MultiCallbackRunner<'d, N, RC_SZ, WC_SZ, CB>) {
+        (MultiReadRunner::new(self.stack, self.state, self.port, socket_channel),
+         MultiCallbackRunner::new(socket_channel, self.cb))
+    }
 }
 
 /// just create runner<br />
@@ -48,10 +64,21 @@ TcpServer<'d, N, TX_SZ, RX_SZ, BUF_SIZE, CB> {
 /// only enable runner, reduce the number of `embassy_executor::task`,
 /// and then call read yourself to read data to save resources
 #[inline]
-pub fn build_runner<'d, const N: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize, const RC_SZ: usize, const WC_SZ: usize>(
+pub fn build_runner<'d, const RC_SZ: usize, const WC_SZ: usize>(
     stack: Stack<'d>,
     port: u16,
     socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
-    state: &'d SocketState<N, TX_SZ, RX_SZ, BUF_SIZE>) -> ReadRunner<'d, N, TX_SZ, RX_SZ, BUF_SIZE, RC_SZ, WC_SZ> {
+    state: &'d dyn TcpState) -> ReadRunner<'d, RC_SZ, WC_SZ> {
     ReadRunner::new(stack, state, port, socket_channel)
 }
+
+/// just create multi-connection runner<br />
+/// more see [MultiReadRunner]
+#[inline]
+pub fn build_multi_runner<'d, const N: usize, const RC_SZ: usize, const WC_SZ: usize>(
+    stack: Stack<'d>,
+    port: u16,
+    socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
+    state: &'d dyn TcpState) -> MultiReadRunner<'d, N, RC_SZ, WC_SZ> {
+    MultiReadRunner::new(stack, state, port, socket_channel)
+}
@@ -0,0 +1,79 @@
+use embassy_net::IpEndpoint;
+use crate::channel::callback_enum::CallbackEnum;
+use crate::channel::SocketChannel;
+use crate::err::SocketErr;
+use crate::tcp_server::callback::TcpServerCallBack;
+
+/// multi-connection tcp server callback runner<br />
+/// every connection table slot has its own callback data `T`, the msg of a connection is called back with the `T` of its slot,
+/// so the callback data of one connection is never seen by another connection<br />
+/// N is the table len, it should be the same as the N of [crate::tcp_server::multi_runner::MultiReadRunner],
+/// a connection that does not fit in the table is closed and [SocketErr::TooManyConnections] is reported with the server callback data
+pub struct MultiCallbackRunner<'d, const N: usize, const RC_SZ: usize, const WC_SZ: usize, CB: TcpServerCallBack> {
+    /// socket channel
+    socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
+    /// tcp server callback
+    cb: &'d CB,
+    /// endpoint of the connection of each slot
+    endpoints: [Option<IpEndpoint>; N],
+}
+
+/// custom method
+impl<'d, const N: usize, const RC_SZ: usize, const WC_SZ: usize, CB: TcpServerCallBack> MultiCallbackRunner<'d, N, RC_SZ, WC_SZ, CB> {
+    /// create multi-connection tcp server callback runner
+    #[inline]
+    pub fn new(socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>, cb: &'d CB) -> Self {
+        Self { socket_channel, cb, endpoints: [None; N] }
+    }
+
+    /// run multi-connection tcp server callback runner<br />
+    /// conns is the callback data of each slot, it is taken by a connection on connect and reused after it is disconnected,
+    /// reset it in [TcpServerCallBack::conn] if needed<br />
+    /// server is the callback data of the errors not belonging to any connection, such as accept errors
+    #[inline]
+    pub async fn run(&mut self, server: &mut CB::T, conns: &mut [CB::T; N]) {
+        loop { self.run_logic(server, conns).await; }
+    }
+
+    /// run logic, wait until there is msg and then call back with the callback data of its connection
+    async fn run_logic(&mut self, server: &mut CB::T, conns: &mut [CB::T; N]) {
+        let msg = self.socket_channel.read_channel.wait_read_ref().await;
+        let wch = &self.socket_channel.write_channel;
+        let endpoint = msg.endpoint();
+        match msg.callback_enum() {
+            CallbackEnum::Conn => {
+                // the runner never has more than N connections, a full table is only possible with a smaller N here
+                let Some(index) = self.endpoints.iter().position(Option::is_none) else {
+                    wch.close(Some(endpoint)).await.ok();
+                    self.cb.err(SocketErr::TooManyConnections, server).await;
+                    return;
+                };
+                self.endpoints[index] = Some(endpoint);
+                self.cb.conn(endpoint, wch, &mut conns[index]).await;
+            }
+            CallbackEnum::Disconnect => if let Some(index) = self.index(endpoint) {
+                self.endpoints[index] = None;
+                self.cb.dis_conn(endpoint, &mut conns[index]).await;
+            },
+            CallbackEnum::Recv => if let Some(index) = self.index(endpoint) {
+                self.cb.recv(endpoint, msg.as_bytes(), wch, &mut conns[index]).await;
+            },
+            CallbackEnum::Err(e) => match self.index(endpoint) {
+                Some(index) => self.cb.err(e, &mut conns[index]).await,
+                None => self.cb.err(e, server).await,
+            },
+            // the dropped msg may belong to any connection
+            CallbackEnum::Dropped(count) => for (index, t) in conns.iter_mut().enumerate() {
+                if let Some(endpoint) = self.endpoints[index] { self.cb.dropped(endpoint, count, wch, t).await; }
+            },
+            // only tcp client will reconnect
+            CallbackEnum::Reconnect { .. } => {}
+        }
+    }
+
+    /// slot index of the connection of the endpoint
+    #[inline]
+    fn index(&self, endpoint: IpEndpoint) -> Option<usize> {
+        self.endpoints.iter().position(|e| *e == Some(endpoint))
+    }
+}
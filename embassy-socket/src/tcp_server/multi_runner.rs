@@ -0,0 +1,404 @@
+use core::cell::Cell;
+use core::future::pending;
+use core::pin::pin;
+use embassy_futures::select::{select, select3, select4, select_array, Either, Either3, Either4};
+use embassy_net::{IpEndpoint, Stack};
+use embassy_net::tcp::TcpWriter;
+use embassy_time::{Duration, Instant, Timer};
+use crate::channel::callback_enum::CallbackEnum;
+use crate::channel::socket_msg::SocketMsg;
+use crate::channel::SocketChannel;
+use crate::connection::socket_state::TcpState;
+use crate::connection::{TcpConnReader, TcpConnection};
+use crate::control::{Command, Control, State};
+use crate::control;
+use crate::err::SocketErr;
+use crate::heartbeat::{Action, Heartbeat, Supervisor};
+
+/// one connection of the connection table
+struct Conn<'d> {
+    /// tcp connection
+    conn: TcpConnection<'d>,
+    /// remote endpoint
+    endpoint: IpEndpoint,
+    /// heartbeat supervisor
+    supervisor: Supervisor,
+    /// bytes of the current write msg already written, None if the connection is not a target of it
+    written: Option<usize>,
+    /// the current write msg must be written before this, only used while written is Some
+    write_deadline: Instant,
+    /// ping bytes not written yet
+    ping: &'static [u8],
+}
+
+/// custom method
+impl Conn<'_> {
+    /// earliest of the heartbeat deadline and the write deadline
+    #[inline]
+    fn deadline(&self) -> Instant {
+        match self.written {
+            Some(_) => self.supervisor.deadline().min(self.write_deadline),
+            None => self.supervisor.deadline(),
+        }
+    }
+}
+
+/// connection table slot
+type Slot<'d> = Option<Conn<'d>>;
+
+/// event of one connection
+enum ConnEvent {
+    /// the current write msg was written
+    Written,
+    /// the connection was closed, with the error if it was not closed by the remote side
+    Closed(Option<SocketErr>),
+}
+
+/// multi-connection tcp server runner<br />
+/// accept up to N connections in one runner, also limited by the connection number of the socket state, all connections share one socket channel<br />
+/// every msg in the read channel carries the endpoint of the connection,
+/// use [crate::tcp_server::multi_callback_runner::MultiCallbackRunner] to keep callback data per connection<br />
+/// use [crate::channel::write_channel::WriteChannel::send_bytes_to] to write to one connection,
+/// [crate::channel::write_channel::WriteChannel::send_bytes] to write to all connections,
+/// and [crate::channel::write_channel::WriteChannel::close] to close one connection<br />
+/// connections are read and written independently, a connection waiting for room in the read channel
+/// or for its peer to read does not stop the others, the next write msg is taken when every target connection was written,
+/// a connection that does not take the msg within the write timeout is closed, so one stalled peer can not stop the others
+pub struct MultiReadRunner<'d, const N: usize, const RC_SZ: usize, const WC_SZ: usize> {
+    /// tcp stack
+    stack: Stack<'d>,
+    /// socket state, memory pool
+    state: &'d dyn TcpState,
+    /// listener port
+    port: u16,
+    /// socket timeout
+    socket_timeout: Option<Duration>,
+    /// socket keep alive<br />
+    /// more see [embassy_net::tcp::TcpSocket::set_keep_alive]
+    keep_alive: Option<Duration>,
+    /// socket channel
+    socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
+    /// application-level heartbeat, every connection is supervised independently
+    heartbeat: Option<Heartbeat>,
+    /// time one connection may take to write one msg
+    write_timeout: Option<Duration>,
+    /// runtime control handle
+    control: Option<&'d Control>,
+    /// command received while serving
+    command: Cell<Option<Command>>,
+}
+
+/// custom method
+impl<'d, const N: usize, const RC_SZ: usize, const WC_SZ: usize> MultiReadRunner<'d, N, RC_SZ, WC_SZ> {
+    /// create multi-connection read runner
+    #[inline]
+    pub fn new(stack: Stack<'d>, state: &'d dyn TcpState, port: u16, socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>) -> Self {
+        Self {
+            stack,
+            state,
+            port,
+            socket_timeout: Some(Duration::from_secs(1)),
+            keep_alive: Some(Duration::from_millis(300)),
+            socket_channel,
+            heartbeat: None,
+            write_timeout: Some(Duration::from_secs(5)),
+            control: None,
+            command: Cell::new(None),
+        }
+    }
+
+    /// set socket timeout<br />
+    /// connection timeout and etc. <br />
+    /// recommended not to set or set to None
+    pub fn socket_timeout(&mut self, timeout: Option<Duration>) {
+        self.socket_timeout = timeout;
+        self.keep_alive = timeout.map(|timeout| timeout.checked_div(10)).unwrap_or_default();
+    }
+
+    /// set application-level heartbeat, default is None<br />
+    /// the ping is written to the connection directly, between two write msg, more see [Heartbeat]
+    #[inline]
+    pub fn heartbeat(&mut self, heartbeat: Option<Heartbeat>) {
+        self.heartbeat = heartbeat;
+    }
+
+    /// set the time one connection may take to write one msg and flush it, default is 5 seconds<br />
+    /// the next msg is only taken when every target connection was written, a connection that misses the timeout
+    /// is closed and [SocketErr::WriteTimeout] is reported, None waits for the peer forever
+    #[inline]
+    pub fn write_timeout(&mut self, timeout: Option<Duration>) {
+        self.write_timeout = timeout;
+    }
+
+    /// set runtime control handle, more see [Control]<br />
+    /// disconnect closes all connections and accepts again, connect and reconnect are ignored,
+    /// the state is connected while there is any connection
+    #[inline]
+    pub fn control(&mut self, control: &'d Control) {
+        self.control = Some(control);
+    }
+
+    /// run tcp server<br />
+    /// this method returns only when [Control::stop] is called
+    pub async fn run(&self) {
+        loop {
+            control::set_state(self.control, State::Connecting);
+            let up = async {
+                self.stack.wait_link_up().await;
+                self.stack.wait_config_up().await;
+            };
+            // there is no connection to close yet
+            match select(up, control::wait(self.control)).await {
+                Either::First(_) => {}
+                Either::Second(Command::Stop) => break,
+                Either::Second(_) => continue,
+            }
+
+            let mut conns: [Slot<'d>; N] = core::array::from_fn(|_| None);
+            self.socket_channel.write_channel.enable().await;
+            self.serve(&mut conns).await;
+            self.socket_channel.write_channel.disable().await;
+            for conn in conns.iter_mut().filter_map(Option::take) {
+                self.socket_channel.read_channel.dis_conn_addr(conn.endpoint).await;
+            }
+            if let Some(Command::Stop) = self.command.take() { break; }
+        }
+        control::set_state(self.control, State::Stopped);
+    }
+
+    /// accept, read and write until the network link is down, disconnect is requested,
+    /// or stop is requested and the queued data was written
+    async fn serve(&self, conns: &mut [Slot<'d>; N]) {
+        let mut msg = SocketMsg::default();
+        // stop queues a close request of all connections, it must not be dropped before it is queued
+        let mut control = pin!(control::serve(self.control, &self.socket_channel.write_channel, &self.command, false));
+        // a write msg is taken and not all target connections were written
+        let mut writing = false;
+        // any target connection of the current write msg failed
+        let mut failed = false;
+        // stop is requested and the queued data was written
+        let mut stopped = false;
+        'listen: loop {
+            // the listener also occupies the pool, so it is only created when there is free memory and a free table slot
+            let mut listener = match conns.iter().any(Option::is_none) {
+                true => self.try_listen(),
+                false => None,
+            };
+            let listening = listener.is_some();
+
+            // the accept future lives until it resolves, dropping it while the handshake is in progress would reset the peer
+            let result = {
+                let mut accept = pin!(async {
+                    match listener.as_mut() {
+                        Some(conn) => conn.socket.accept(self.port).await,
+                        None => pending().await,
+                    }
+                });
+                loop {
+                    let state = if conns.iter().any(Option::is_some) { State::Connected } else { State::Connecting };
+                    control::set_state(self.control, state);
+                    let event = {
+                        let deadline = conns.iter().flatten().map(Conn::deadline).min().unwrap_or(Instant::MAX);
+                        let bytes = msg.as_bytes();
+                        let mut iter = conns.iter_mut();
+                        let serves: [_; N] = core::array::from_fn(|_| {
+                            let slot = iter.next().and_then(Option::as_mut);
+                            async move {
+                                match slot {
+                                    Some(conn) => self.serve_conn(conn, bytes).await,
+                                    None => pending().await,
+                                }
+                            }
+                        });
+                        // the next msg is only taken when the current one is done, take it after the select so that msg is not borrowed
+                        let wait_msg = async {
+                            match writing {
+                                true => pending().await,
+                                false => self.socket_channel.write_channel.wait().await,
+                            }
+                        };
+                        let others = select3(self.stack.wait_link_down(), Timer::at(deadline), control.as_mut());
+                        select4(accept.as_mut(), select_array(serves), wait_msg, others).await
+                    };
+
+                    match event {
+                        Either4::First(result) => break result,
+                        Either4::Second((ConnEvent::Written, _)) => {}
+                        Either4::Second((ConnEvent::Closed(e), index)) => {
+                            failed |= self.remove(&mut conns[index], e).await;
+                        }
+                        Either4::Third(_) => {
+                            self.socket_channel.write_channel.take(&mut msg).await;
+                            writing = true;
+                            failed = self.write_logic(&msg, conns).await;
+                            // the close request queued by stop closed all connections
+                            let close_all = !msg.has_endpoint() && matches!(msg.callback_enum, CallbackEnum::Disconnect);
+                            stopped = close_all && matches!(self.command.get(), Some(Command::Stop));
+                        }
+                        Either4::Fourth(Either3::First(_)) | Either4::Fourth(Either3::Third(_)) => return,
+                        Either4::Fourth(Either3::Second(_)) => failed |= self.deadline_logic(conns).await,
+                    }
+
+                    if writing && !conns.iter().flatten().any(|conn| conn.written.is_some()) {
+                        writing = false;
+                        self.socket_channel.write_channel.write_done(!failed);
+                    }
+                    if stopped { return; }
+                    // a slot or the pool may be free again, try to listen
+                    if !listening && conns.iter().any(Option::is_none) { continue 'listen; }
+                }
+            };
+            self.accept_logic(listener.take(), result, conns).await;
+        }
+    }
+
+    /// try create a listener, returns None if the pool is exhausted
+    fn try_listen(&self) -> Option<TcpConnection<'d>> {
+        let mut conn = TcpConnection::new(self.stack, self.state).ok()?;
+        conn.socket.set_timeout(self.socket_timeout);
+        conn.socket.set_keep_alive(self.keep_alive);
+        Some(conn)
+    }
+
+    /// put the accepted connection into the connection table
+    async fn accept_logic(
+        &self,
+        listener: Option<TcpConnection<'d>>,
+        result: Result<(), embassy_net::tcp::AcceptError>,
+        conns: &mut [Slot<'d>; N]) {
+        self.socket_channel.read_channel.connect_attempt();
+        let result = result.map_err(SocketErr::from)
+            .and_then(|_| listener.ok_or_else(SocketErr::no_route))
+            .and_then(|conn| conn.socket.remote_endpoint().map(|endpoint| (conn, endpoint)).ok_or_else(SocketErr::no_route));
+        let (conn, endpoint) = match result {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                self.socket_channel.read_channel.err(e).await;
+                return;
+            }
+        };
+
+        // the listener is only created when there is a free slot
+        if let Some(slot) = conns.iter_mut().find(|slot| slot.is_none()) {
+            *slot = Some(Conn { conn, endpoint, supervisor: Supervisor::new(self.heartbeat), written: None, write_deadline: Instant::MAX, ping: &[] });
+            self.socket_channel.read_channel.conn_addr(endpoint).await;
+        }
+    }
+
+    /// read and write one connection until it is closed or the current write msg was written
+    async fn serve_conn(&self, conn: &mut Conn<'d>, msg: &[u8]) -> ConnEvent {
+        let Conn { conn, endpoint, supervisor, written, ping, .. } = conn;
+        let (mut reader, mut writer) = conn.split();
+        match select(self.read_conn(&mut reader, *endpoint, supervisor), self.write_conn(&mut writer, msg, written, ping)).await {
+            Either::First(e) => ConnEvent::Closed(e),
+            Either::Second(event) => event,
+        }
+    }
+
+    /// read one connection in place until it is closed
+    async fn read_conn(&self, reader: &mut TcpConnReader<'_>, endpoint: IpEndpoint, supervisor: &Supervisor) -> Option<SocketErr> {
+        let read_channel = &self.socket_channel.read_channel;
+        loop {
+            let result = reader.read_with(|bytes| {
+                let len = read_channel.try_recv_addr(bytes, Some(endpoint));
+                supervisor.recv(&bytes[..len]);
+                len
+            }).await;
+            match result {
+                // the read channel is full, only this connection waits, the data stays in the socket until there is room
+                Ok(Some(0)) => read_channel.wait_room().await,
+                Ok(Some(_)) => {}
+                // remote side closed the connection, it is not an error
+                Ok(None) => return None,
+                Err(e) => return Some(e.into()),
+            }
+        }
+    }
+
+    /// write the ping and the current write msg to one connection, returns when the msg was written and flushed<br />
+    /// a msg that was started is finished before the ping and the other way round, so they do not interleave
+    async fn write_conn(&self, writer: &mut TcpWriter<'_>, msg: &[u8], written: &mut Option<usize>, ping: &mut &'static [u8]) -> ConnEvent {
+        loop {
+            let to_ping = !ping.is_empty() && written.is_none_or(|len| len == 0);
+            let bytes = match (to_ping, *written) {
+                (true, _) => *ping,
+                // flush before the msg is done, so that WriteChannel::flush returns after the data was sent
+                (false, Some(len)) if len == msg.len() => {
+                    if let Err(e) = writer.flush().await { return ConnEvent::Closed(Some(e.into())); }
+                    *written = None;
+                    return ConnEvent::Written;
+                }
+                (false, Some(len)) => &msg[len..],
+                (false, None) => return pending().await,
+            };
+            let len = match writer.write(bytes).await {
+                Ok(len) => len,
+                Err(e) => return ConnEvent::Closed(Some(e.into())),
+            };
+            self.socket_channel.write_channel.add_written(len);
+
+            if to_ping {
+                *ping = &ping[len..];
+                continue;
+            }
+            *written = Some(written.map_or(len, |written| written + len));
+        }
+    }
+
+    /// remove one connection, returns true if the current write msg was not written to it
+    async fn remove(&self, slot: &mut Slot<'d>, e: Option<SocketErr>) -> bool {
+        let Some(conn) = slot.take() else { return false; };
+        if let Some(e) = e { self.socket_channel.read_channel.err_addr(e, conn.endpoint).await; }
+        self.socket_channel.read_channel.dis_conn_addr(conn.endpoint).await;
+        conn.written.is_some()
+    }
+
+    /// start writing msg to one connection or all connections, or close one connection<br />
+    /// returns true if the msg already failed
+    async fn write_logic(&self, msg: &SocketMsg<WC_SZ>, conns: &mut [Slot<'d>; N]) -> bool {
+        let broadcast = !msg.has_endpoint();
+        let write_deadline = self.write_timeout.map_or(Instant::MAX, |timeout| Instant::now() + timeout);
+        let mut found = false;
+        for slot in conns.iter_mut() {
+            let Some(conn) = slot else { continue; };
+            if !broadcast && conn.endpoint != msg.endpoint { continue; }
+
+            found = true;
+            match msg.callback_enum {
+                CallbackEnum::Disconnect => { self.remove(slot, None).await; }
+                // empty msg has nothing to write
+                _ if msg.len > 0 => {
+                    conn.written = Some(0);
+                    conn.write_deadline = write_deadline;
+                }
+                _ => {}
+            }
+        }
+
+        if !broadcast && !found {
+            self.socket_channel.read_channel.err(SocketErr::no_route()).await;
+            return true;
+        }
+        false
+    }
+
+    /// close the connections that missed the write deadline, queue ping or close the connections whose heartbeat deadline is reached<br />
+    /// returns true if a closed connection was a target of the current write msg
+    async fn deadline_logic(&self, conns: &mut [Slot<'d>; N]) -> bool {
+        let mut failed = false;
+        for slot in conns.iter_mut() {
+            let Some(conn) = slot else { continue; };
+            if conn.written.is_some() && conn.write_deadline <= Instant::now() {
+                failed |= self.remove(slot, Some(SocketErr::WriteTimeout)).await;
+                continue;
+            }
+            match conn.supervisor.poll() {
+                // the previous ping may still be being written
+                Some(Action::Ping(ping)) if conn.ping.is_empty() => conn.ping = ping,
+                Some(Action::Timeout) => failed |= self.remove(slot, Some(SocketErr::HeartbeatTimeout)).await,
+                _ => {}
+            }
+        }
+        failed
+    }
+}
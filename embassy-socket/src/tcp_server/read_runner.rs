@@ -1,17 +1,24 @@
+use core::cell::Cell;
+use embassy_futures::select::{select, select4, Either, Either4};
 use embassy_net::{IpEndpoint, Stack};
-use embassy_net::tcp::State;
-use embassy_time::{Duration, Timer};
+use embassy_net::tcp::TcpWriter;
+use embassy_time::Duration;
+use crate::channel::callback_enum::CallbackEnum;
+use crate::channel::socket_msg::SocketMsg;
 use crate::channel::SocketChannel;
-use crate::connection::socket_state::SocketState;
-use crate::connection::TcpConnection;
+use crate::connection::socket_state::TcpState;
+use crate::connection::{TcpConnReader, TcpConnection};
+use crate::control::{Command, Control, State};
+use crate::control;
 use crate::err::{SocketErr, SocketResult};
+use crate::heartbeat::{Heartbeat, Supervisor};
 
 /// tcp server read runner
-pub struct ReadRunner<'d, const N: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize, const RC_SZ: usize, const WC_SZ: usize> {
+pub struct ReadRunner<'d, const RC_SZ: usize, const WC_SZ: usize> {
     /// tcp stack
     stack: Stack<'d>,
     /// socket state, memory pool
-    state: &'d SocketState<N, TX_SZ, RX_SZ, BUF_SIZE>,
+    state: &'d dyn TcpState,
     /// listener port
     port: u16,
     /// socket timeout
@@ -19,25 +26,31 @@ pub struct ReadRunner<'d, const N: usize, const TX_SZ: usize, const RX_SZ: usize
     /// socket keep alive<br />
     /// more see [embassy_net::tcp::TcpSocket::set_keep_alive]
     keep_alive: Option<Duration>,
-    /// read data timeout, default is 100 milliseconds
-    read_timeout: Duration,
     /// socket channel
     socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
+    /// application-level heartbeat
+    heartbeat: Option<Heartbeat>,
+    /// runtime control handle
+    control: Option<&'d Control>,
+    /// command received while accepting or connected
+    command: Cell<Option<Command>>,
 }
 
 /// custom method
-impl<'d, const N: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE: usize, const RC_SZ: usize, const WC_SZ: usize> ReadRunner<'d, N, TX_SZ, RX_SZ, BUF_SIZE, RC_SZ, WC_SZ> {
+impl<'d, const RC_SZ: usize, const WC_SZ: usize> ReadRunner<'d, RC_SZ, WC_SZ> {
     /// create one read runner
     #[inline]
-    pub fn new(stack: Stack<'d>, state: &'d SocketState<N, TX_SZ, RX_SZ, BUF_SIZE>, port: u16, socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>) -> Self {
+    pub fn new(stack: Stack<'d>, state: &'d dyn TcpState, port: u16, socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>) -> Self {
         Self {
             stack,
             state,
             port,
             socket_timeout: Some(Duration::from_secs(1)),
             keep_alive: Some(Duration::from_millis(300)),
-            read_timeout: Duration::from_millis(100),
             socket_channel,
+            heartbeat: None,
+            control: None,
+            command: Cell::new(None),
         }
     }
 
@@ -49,67 +62,99 @@ impl<'d, const N: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE:
         self.keep_alive = timeout.map(|timeout| timeout.checked_div(10)).unwrap_or_default();
     }
 
-    /// set read timeout
+    /// set application-level heartbeat, default is None<br />
+    /// more see [Heartbeat]
     #[inline]
-    pub fn read_timeout(&mut self, timeout: Duration) {
-        self.read_timeout = timeout;
+    pub fn heartbeat(&mut self, heartbeat: Option<Heartbeat>) {
+        self.heartbeat = heartbeat;
     }
 
-    /// run tcp server
+    /// set runtime control handle, more see [Control]<br />
+    /// disconnect closes the current connection, connect and reconnect are ignored
+    #[inline]
+    pub fn control(&mut self, control: &'d Control) {
+        self.control = Some(control);
+    }
+
+    /// run tcp server<br />
+    /// this method returns only when [Control::stop] is called
     pub async fn run(&self) {
         loop {
             if let Err(e) = self.run_logic().await {
                 self.socket_channel.read_channel.err(e).await;
             }
+            if let Some(Command::Stop) = self.command.take() { break; }
         }
+        control::set_state(self.control, State::Stopped);
     }
 
     /// run logic
     async fn run_logic(&self) -> SocketResult<()> {
-        let mut conn = self.try_accept().await?;
+        control::set_state(self.control, State::Connecting);
+        let mut conn = match select(self.try_accept(), control::wait(self.control)).await {
+            Either::First(conn) => conn?,
+            Either::Second(command) => {
+                self.command.set(Some(command));
+                return Ok(());
+            }
+        };
         let endpoint = conn.socket.remote_endpoint().ok_or_else(SocketErr::no_route)?;
 
+        control::set_state(self.control, State::Connected);
         self.socket_channel.write_channel.enable().await;
         self.socket_channel.read_channel.conn_addr(endpoint).await;
-        while !self.read_logic(&mut conn, endpoint).await {}
+        self.serve(&mut conn, endpoint).await;
         self.socket_channel.write_channel.disable().await;
         self.socket_channel.read_channel.dis_conn_addr(endpoint).await;
         Ok(())
     }
 
-    /// read logic
-    async fn read_logic(&self, conn: &mut TcpConnection<'d, N, TX_SZ, RX_SZ, BUF_SIZE>, endpoint: IpEndpoint) -> bool {
-        if !conn.socket.can_recv() {
-            if let Err(e) = self.write_logic(conn).await { self.socket_channel.read_channel.err(e).await; }
-            return matches!(conn.socket.state(), State::CloseWait|State::Closed);
+    /// read and write concurrently until the connection is closed or the heartbeat times out
+    async fn serve(&self, conn: &mut TcpConnection<'d>, endpoint: IpEndpoint) {
+        let (mut reader, mut writer) = conn.split();
+        let supervisor = Supervisor::new(self.heartbeat);
+        let read = self.read_logic(&mut reader, endpoint, &supervisor);
+        let control = control::serve(self.control, &self.socket_channel.write_channel, &self.command, false);
+        if let Either4::Third(_) = select4(read, self.write_logic(&mut writer), supervisor.run(&self.socket_channel.write_channel), control).await {
+            self.socket_channel.read_channel.err(SocketErr::HeartbeatTimeout).await;
         }
+    }
 
-        match conn.try_read().await {
-            Ok(bytes) => {
-                self.socket_channel.read_channel.recv_addr(bytes, endpoint).await;
-                false
-            }
-            Err(e) => {
-                self.socket_channel.read_channel.err(e.into()).await;
-                true
+    /// read logic, returns when the connection is closed
+    /// socket data is copied to the read channel in place, one read is one msg
+    async fn read_logic(&self, reader: &mut TcpConnReader<'_>, endpoint: IpEndpoint, supervisor: &Supervisor) {
+        let read_channel = &self.socket_channel.read_channel;
+        loop {
+            let result = reader.read_with(|bytes| {
+                let len = read_channel.try_recv_addr(bytes, Some(endpoint));
+                supervisor.recv(&bytes[..len]);
+                len
+            }).await;
+            match result {
+                Ok(None) => return,
+                Ok(Some(0)) => read_channel.wait_room().await,
+                Ok(Some(_)) => {}
+                Err(e) => {
+                    self.socket_channel.read_channel.err(e.into()).await;
+                    return;
+                }
             }
         }
     }
 
-    /// write logic
-    async fn write_logic(&self, conn: &mut TcpConnection<'d, N, TX_SZ, RX_SZ, BUF_SIZE>) -> SocketResult<()> {
-        // if channel is empty, just sleep and return let continue
-        if self.socket_channel.write_channel.is_empty().await {
-            Timer::after(self.read_timeout).await;
-            return Ok(());
+    /// write logic, write data as soon as it is queued, returns when close is requested
+    async fn write_logic(&self, writer: &mut TcpWriter<'_>) {
+        let mut msg = SocketMsg::default();
+        loop {
+            if let Err(e) = self.socket_channel.write_channel.try_write(writer, &mut msg).await {
+                self.socket_channel.read_channel.err(e.into()).await;
+            }
+            if matches!(msg.callback_enum, CallbackEnum::Disconnect) { return; }
         }
-
-        self.socket_channel.write_channel.try_tcp_write(conn).await?;
-        Ok(())
     }
 
     /// try accept connection
-    async fn try_accept(&self) -> SocketResult<TcpConnection<'d, N, TX_SZ, RX_SZ, BUF_SIZE>> {
+    async fn try_accept(&self) -> SocketResult<TcpConnection<'d>> {
         self.stack.wait_link_up().await;
         self.stack.wait_config_up().await;
 
@@ -117,6 +162,7 @@ impl<'d, const N: usize, const TX_SZ: usize, const RX_SZ: usize, const BUF_SIZE:
         conn.socket.set_timeout(self.socket_timeout);
         conn.socket.set_keep_alive(self.keep_alive);
         conn.socket.accept(self.port).await?;
+        self.socket_channel.read_channel.connect_attempt();
         Ok(conn)
     }
 }
@@ -0,0 +1,107 @@
+use core::cell::RefCell;
+use core::marker::PhantomData;
+use embassy_net::tcp::{Error, TcpReader, TcpWriter};
+use embedded_io_async::{ErrorType, Read, Write};
+use embedded_tls::{NoVerify, TlsCipherSuite, TlsConfig, TlsConnection, TlsContext, TlsError, TlsVerifier};
+use rand_core::{CryptoRng, RngCore};
+
+// export dependency library
+pub use embedded_tls;
+pub use rand_core;
+
+/// tls session, tls 1.3 client config, rng and record buffers supplied by the user<br />
+/// CS is the cipher suite, such as [embedded_tls::Aes128GcmSha256]<br />
+/// V is the certificate verifier, default [NoVerify], it is enough when the server is verified by psk<br />
+/// the read record buf should fit an encrypted tls record, the maximum record is 16640 bytes,
+/// more see [TlsConnection::new]<br />
+/// use [crate::tcp_client::read_runner::ReadRunner::run_tls] to run tcp client over tls
+pub struct TlsSession<'c, CS: TlsCipherSuite + 'static, RNG: CryptoRng + RngCore, V = NoVerify> {
+    /// tls config, psk, ca, server name and etc.
+    config: TlsConfig<'c, CS>,
+    /// random number generator
+    rng: RNG,
+    /// encrypted read record buf
+    read_record: &'c mut [u8],
+    /// encrypted write record buf
+    write_record: &'c mut [u8],
+    /// certificate verifier
+    _verifier: PhantomData<V>,
+}
+
+/// custom method
+impl<'c, CS: TlsCipherSuite + 'static, RNG: CryptoRng + RngCore, V> TlsSession<'c, CS, RNG, V>
+where
+    V: for<'v> TlsVerifier<'v, CS> {
+    /// create tls session
+    #[inline]
+    pub fn new(config: TlsConfig<'c, CS>, rng: RNG, read_record: &'c mut [u8], write_record: &'c mut [u8]) -> Self {
+        Self { config, rng, read_record, write_record, _verifier: PhantomData }
+    }
+
+    /// get tls config
+    #[inline]
+    pub fn config(&self) -> &TlsConfig<'c, CS> {
+        &self.config
+    }
+
+    /// set tls config, used by the next handshake
+    #[inline]
+    pub fn set_config(&mut self, config: TlsConfig<'c, CS>) {
+        self.config = config;
+    }
+
+    /// create tls connection and handshake
+    pub(crate) async fn open<'s>(&'s mut self, io: TlsIo<'s>) -> Result<TlsConnection<'s, TlsIo<'s>, CS>, TlsError> {
+        let mut tls = TlsConnection::new(io, self.read_record, self.write_record);
+        tls.open::<RNG, V>(TlsContext::new(&self.config, &mut self.rng)).await?;
+        Ok(tls)
+    }
+}
+
+/// tls io, tcp reader and writer shared by the tls reader and writer halves
+#[derive(Copy, Clone)]
+pub(crate) struct TlsIo<'a> {
+    /// tcp reader
+    reader: &'a RefCell<TcpReader<'a>>,
+    /// tcp writer
+    writer: &'a RefCell<TcpWriter<'a>>,
+}
+
+/// custom method
+impl<'a> TlsIo<'a> {
+    /// create tls io
+    #[inline]
+    pub(crate) fn new(reader: &'a RefCell<TcpReader<'a>>, writer: &'a RefCell<TcpWriter<'a>>) -> Self {
+        Self { reader, writer }
+    }
+}
+
+/// support embedded io error type
+impl ErrorType for TlsIo<'_> {
+    type Error = Error;
+}
+
+// after split, the tcp reader is only used by the tls reader half and the tcp writer by the tls writer half,
+// the handshake reads and writes in turn, so a borrow is never shared
+/// support embedded io read
+#[allow(clippy::await_holding_refcell_ref)]
+impl Read for TlsIo<'_> {
+    #[inline]
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.reader.borrow_mut().read(buf).await
+    }
+}
+
+/// support embedded io write
+#[allow(clippy::await_holding_refcell_ref)]
+impl Write for TlsIo<'_> {
+    #[inline]
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.writer.borrow_mut().write(buf).await
+    }
+
+    #[inline]
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.writer.borrow_mut().flush().await
+    }
+}
@@ -0,0 +1,25 @@
+use embassy_net::IpEndpoint;
+use crate::channel::write_channel::WriteChannel;
+use crate::err::SocketErr;
+
+/// udp callback
+pub trait UdpCallBack {
+    /// udp socket bind success call this<br />
+    /// use [WriteChannel::send_bytes_to] to send datagram
+    async fn bind<const CN: usize>(&mut self, wch: &WriteChannel<'_, CN>);
+
+    /// udp socket closed call this, generally because the network link is down
+    async fn close(&mut self);
+
+    /// recv udp datagram call this, endpoint is datagram source endpoint
+    async fn recv<const CN: usize>(&mut self, endpoint: IpEndpoint, buf: &[u8], wch: &WriteChannel<'_, CN>);
+
+    /// socket err will call this<br />
+    /// only error notification will be made, no blocking and exit will be made<br />
+    /// please do not use endless loops in this method
+    async fn err(&mut self, err: SocketErr);
+
+    /// read datagram was dropped because the read channel was full, count is the number of dropped msg<br />
+    /// more see [crate::channel::overflow_policy::OverflowPolicy], default do nothing
+    async fn dropped(&mut self, _count: u32) {}
+}
@@ -0,0 +1,41 @@
+use crate::channel::callback_enum::CallbackEnum;
+use crate::channel::SocketChannel;
+use crate::udp::callback::UdpCallBack;
+
+/// udp callback runner
+pub struct CallbackRunner<'d, const RC_SZ: usize, const WC_SZ: usize, CB: UdpCallBack> {
+    /// socket channel
+    socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
+    /// udp callback
+    cb: CB,
+}
+
+/// custom method
+impl<'d, const RC_SZ: usize, const WC_SZ: usize, CB: UdpCallBack> CallbackRunner<'d, RC_SZ, WC_SZ, CB> {
+    /// create udp callback runner
+    #[inline]
+    pub fn new(socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>, cb: CB) -> Self {
+        Self { socket_channel, cb }
+    }
+
+    /// run udp callback runner<br />
+    /// this method is used for async callbacks
+    #[inline]
+    pub async fn run(&mut self) {
+        loop { self.run_logic().await; }
+    }
+
+    /// run logic, wait until there is msg and then call back, the msg is borrowed in place until the callback returns
+    async fn run_logic(&mut self) {
+        let msg = self.socket_channel.read_channel.wait_read_ref().await;
+        match msg.callback_enum() {
+            CallbackEnum::Conn => self.cb.bind(&self.socket_channel.write_channel).await,
+            CallbackEnum::Disconnect => self.cb.close().await,
+            CallbackEnum::Recv => self.cb.recv(msg.endpoint(), msg.as_bytes(), &self.socket_channel.write_channel).await,
+            CallbackEnum::Err(e) => self.cb.err(e).await,
+            CallbackEnum::Dropped(count) => self.cb.dropped(count).await,
+            // only tcp client will reconnect
+            CallbackEnum::Reconnect { .. } => {}
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use embassy_net::Stack;
+use crate::channel::SocketChannel;
+use crate::connection::udp_state::UdpState;
+use crate::udp::callback::UdpCallBack;
+use crate::udp::callback_runner::CallbackRunner;
+use crate::udp::read_runner::ReadRunner;
+
+pub mod callback;
+pub mod read_runner;
+pub mod callback_runner;
+
+/// build udp runner<br />
+/// port is local bind port, the udp client can use 0 to allocate a dynamic port<br />
+/// received datagram will be passed to the callback together with the source endpoint,
+/// reply through [crate::channel::write_channel::WriteChannel::send_bytes_to]
+#[inline]
+pub fn build<'d, const RC_SZ: usize, const WC_SZ: usize, CB: UdpCallBack>(
+    stack: Stack<'d>,
+    port: u16,
+    socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
+    state: &'d dyn UdpState,
+    cb: CB)
+    -> (ReadRunner<'d, RC_SZ, WC_SZ>, CallbackRunner<'d, RC_SZ, WC_SZ, CB>) {
+    (ReadRunner::new(stack, state, port, socket_channel), CallbackRunner::new(socket_channel, cb))
+}
+
+/// just create runner<br />
+/// read udp datagram use `socket_channel.read_channel.read_addr`<br />
+/// write udp datagram use `socket_channel.write_channel.send_bytes_to`
+#[inline]
+pub fn build_runner<'d, const RC_SZ: usize, const WC_SZ: usize>(
+    stack: Stack<'d>,
+    port: u16,
+    socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
+    state: &'d dyn UdpState) -> ReadRunner<'d, RC_SZ, WC_SZ> {
+    ReadRunner::new(stack, state, port, socket_channel)
+}
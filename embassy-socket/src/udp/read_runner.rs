@@ -0,0 +1,137 @@
+use core::cell::Cell;
+use embassy_futures::select::{select, select4, Either};
+use embassy_net::Stack;
+use embassy_net::udp::UdpSocket;
+use crate::channel::callback_enum::CallbackEnum;
+use crate::channel::socket_msg::SocketMsg;
+use crate::channel::SocketChannel;
+use crate::connection::udp_connection::UdpConnection;
+use crate::connection::udp_state::UdpState;
+use crate::control::{Command, Control, State};
+use crate::control;
+use crate::err::SocketResult;
+
+/// udp read runner
+pub struct ReadRunner<'d, const RC_SZ: usize, const WC_SZ: usize> {
+    /// net stack
+    stack: Stack<'d>,
+    /// udp state, memory pool
+    state: &'d dyn UdpState,
+    /// bind port, 0 means a dynamic port is allocated
+    port: u16,
+    /// socket channel
+    socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>,
+    /// runtime control handle
+    control: Option<&'d Control>,
+    /// command received while binding or bound
+    command: Cell<Option<Command>>,
+}
+
+/// custom method
+impl<'d, const RC_SZ: usize, const WC_SZ: usize> ReadRunner<'d, RC_SZ, WC_SZ> {
+    /// create udp read runner
+    #[inline]
+    pub fn new(stack: Stack<'d>, state: &'d dyn UdpState, port: u16, socket_channel: &'d SocketChannel<'d, RC_SZ, WC_SZ>) -> Self {
+        Self { stack, state, port, socket_channel, control: None, command: Cell::new(None) }
+    }
+
+    /// set runtime control handle, more see [Control]<br />
+    /// disconnect unbinds the socket and waits for connect, reconnect binds the socket again and the target is ignored,
+    /// the state is connected while the socket is bound
+    #[inline]
+    pub fn control(&mut self, control: &'d Control) {
+        self.control = Some(control);
+    }
+
+    /// run udp<br />
+    /// calling this method causes udp to keep the socket bound and send datagram asynchronously over WriteChannel<br />
+    /// this method returns only when [Control::stop] is called
+    pub async fn run(&self) {
+        loop {
+            if let Err(e) = self.run_logic().await {
+                self.socket_channel.read_channel.err(e).await;
+            }
+            if !self.command_logic().await { break; }
+        }
+        control::set_state(self.control, State::Stopped);
+    }
+
+    /// run logic
+    async fn run_logic(&self) -> SocketResult<()> {
+        control::set_state(self.control, State::Connecting);
+        let mut conn = match select(self.try_bind(), control::wait(self.control)).await {
+            Either::First(conn) => conn?,
+            Either::Second(command) => {
+                self.command.set(Some(command));
+                return Ok(());
+            }
+        };
+
+        control::set_state(self.control, State::Connected);
+        self.socket_channel.write_channel.enable().await;
+        self.socket_channel.read_channel.conn().await;
+        self.serve(&mut conn).await;
+        // unbind before waiting for the command
+        drop(conn);
+        self.socket_channel.write_channel.disable().await;
+        self.socket_channel.read_channel.dis_conn().await;
+        Ok(())
+    }
+
+    /// handle the command received while binding or bound, returns false if stop is requested
+    async fn command_logic(&self) -> bool {
+        let Some(mut command) = self.command.take() else { return true; };
+        loop {
+            match command {
+                Command::Stop => return false,
+                Command::Connect | Command::ReconnectTo(..) => return true,
+                Command::Disconnect => {
+                    control::set_state(self.control, State::Paused);
+                    command = control::wait(self.control).await;
+                }
+            }
+        }
+    }
+
+    /// read and write concurrently until the network link is down, disconnect is requested,
+    /// or stop is requested and the queued datagrams were sent
+    async fn serve(&self, conn: &mut UdpConnection<'d>) {
+        let (socket, buf) = conn.split();
+        let control = control::serve(self.control, &self.socket_channel.write_channel, &self.command, true);
+        select4(self.read_logic(socket, buf), self.write_logic(socket), self.stack.wait_link_down(), control).await;
+    }
+
+    /// read logic, pass datagram as soon as it is received
+    async fn read_logic(&self, socket: &UdpSocket<'_>, buf: &mut [u8]) {
+        loop {
+            match socket.recv_from(buf).await {
+                Ok((len, meta)) => self.socket_channel.read_channel.recv_addr(&buf[..len], meta.endpoint).await,
+                Err(e) => self.socket_channel.read_channel.err(e.into()).await,
+            }
+        }
+    }
+
+    /// write logic, send datagram as soon as it is queued, returns when the close request queued by stop is taken
+    async fn write_logic(&self, socket: &UdpSocket<'_>) {
+        let mut msg = SocketMsg::default();
+        loop {
+            if let Err(e) = self.socket_channel.write_channel.try_send_to(socket, &mut msg).await {
+                self.socket_channel.read_channel.err(e.into()).await;
+            }
+            // other close requests are ignored, udp has no connection to close
+            let stop = matches!(self.command.get(), Some(Command::Stop));
+            if stop && matches!(msg.callback_enum, CallbackEnum::Disconnect) { return; }
+        }
+    }
+
+    /// try bind udp socket
+    async fn try_bind(&self) -> SocketResult<UdpConnection<'d>> {
+        self.stack.wait_link_up().await;
+        self.stack.wait_config_up().await;
+
+        self.socket_channel.read_channel.connect_attempt();
+        let mut conn = UdpConnection::new(self.stack, self.state)?;
+        conn.socket.bind(self.port)?;
+        Ok(conn)
+    }
+}
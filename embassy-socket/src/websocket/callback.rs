@@ -0,0 +1,37 @@
+use crate::err::SocketErr;
+use crate::http::request::Request;
+use crate::websocket::sender::WsSender;
+use crate::websocket::Message;
+
+/// websocket callback, shared by [crate::websocket::server::WsServer] and [crate::websocket::client::WsClient]<br />
+/// methods take `&self` because the server callback is shared by all connection runners,
+/// keep the data of one connection in T
+pub trait WsCallBack {
+    /// data processed independently on a single websocket connection, reset to default on every connection
+    type T: Default;
+
+    /// server only, check the handshake request before accepting it, for example the path or the origin<br />
+    /// returns false to answer 403 and close the connection, default accept all
+    async fn accept(&self, _req: &Request<'_>, _t: &mut Self::T) -> bool {
+        true
+    }
+
+    /// handshake finished call this, messages can be sent from now on
+    async fn open<const CN: usize>(&self, ws: &WsSender<'_, '_, CN>, t: &mut Self::T);
+
+    /// recv whole message call this, fragments have been reassembled
+    async fn message<const CN: usize>(&self, msg: Message<'_>, ws: &WsSender<'_, '_, CN>, t: &mut Self::T);
+
+    /// websocket closed call this, only called if open was called<br />
+    /// code is the status code of the close frame of the peer,
+    /// None if the close frame has no status code, the connection was lost or the websocket failed
+    async fn close(&self, code: Option<u16>, t: &mut Self::T);
+
+    /// socket err or websocket err will call this<br />
+    /// only error notification will be made, no blocking and exit will be made<br />
+    /// please do not use endless loops in this method
+    async fn err(&self, err: SocketErr, t: &mut Self::T);
+
+    /// recv pong call this, ping is answered automatically, default do nothing
+    async fn pong<const CN: usize>(&self, _payload: &[u8], _ws: &WsSender<'_, '_, CN>, _t: &mut Self::T) {}
+}
@@ -0,0 +1,97 @@
+use crate::channel::overflow_policy::OverflowPolicy;
+use crate::channel::write_channel::WriteChannel;
+use crate::err::SocketErr;
+use crate::tcp_client::callback::TcpClientCallBack;
+use crate::websocket::callback::WsCallBack;
+use crate::websocket::handshake::{accept_key, check_response, client_key};
+use crate::websocket::sender::WsSender;
+use crate::websocket::{Phase, WsErr, WsState};
+
+/// websocket client callback<br />
+/// send the http upgrade handshake after every tcp connection, decode frames and pass whole messages to [WsCallBack::message]<br />
+/// frames are written in pieces, so the write channel is set to [OverflowPolicy::Block] when connected,
+/// set the read channel to [OverflowPolicy::Block] too, lost data breaks the frame stream and closes the connection<br />
+/// F_SZ is max message size, larger messages will be passed to [WsCallBack::err] as [SocketErr::FrameTooLarge]
+pub struct WsClient<CB: WsCallBack, const F_SZ: usize> {
+    /// `Host` header, add the port if it is not 80, for example `192.168.1.2:8080`
+    host: &'static str,
+    /// request path, for example `/ws`
+    path: &'static str,
+    /// masking key seed, use a random number if there is a rng
+    seed: u32,
+    /// websocket callback
+    pub cb: CB,
+}
+
+/// custom method
+impl<CB: WsCallBack, const F_SZ: usize> WsClient<CB, F_SZ> {
+    /// create websocket client callback
+    #[inline]
+    pub const fn new(host: &'static str, path: &'static str, seed: u32, cb: CB) -> Self {
+        Self { host, path, seed, cb }
+    }
+}
+
+/// support tcp client callback
+impl<CB: WsCallBack, const F_SZ: usize> TcpClientCallBack for WsClient<CB, F_SZ> {
+    type T = WsState<CB::T, F_SZ>;
+
+    async fn conn<const CN: usize>(&mut self, wch: &WriteChannel<'_, CN>, t: &mut Self::T) {
+        wch.overflow_policy(OverflowPolicy::Block);
+        t.reset(false, self.seed);
+        let ws = t.sender(WsSender::client(wch, 0));
+        let mut random = [0; 16];
+        for chunk in random.chunks_mut(4) {
+            chunk.copy_from_slice(&ws.next_mask());
+        }
+        let key = client_key(random);
+        t.accept = accept_key(&key);
+
+        let result = ws.raw(&[
+            b"GET ", self.path.as_bytes(), b" HTTP/1.1\r\nHost: ", self.host.as_bytes(),
+            b"\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: ", &key,
+            b"\r\nSec-WebSocket-Version: 13\r\n\r\n",
+        ]).await;
+        t.sync(&ws);
+        if let Err(e) = result { t.fail(&self.cb, e, &ws).await; }
+    }
+
+    #[inline]
+    async fn dis_conn(&mut self, t: &mut Self::T) {
+        t.dis_conn(&self.cb).await;
+    }
+
+    async fn recv<const CN: usize>(&mut self, mut buf: &[u8], wch: &WriteChannel<'_, CN>, t: &mut Self::T) {
+        let ws = t.sender(WsSender::client(wch, 0));
+        if t.phase == Phase::Handshake {
+            let (used, head) = t.decoder.decode_head(buf);
+            buf = &buf[used..];
+            let accepted = match head {
+                None => return,
+                Some(Ok(head)) => check_response(head, &t.accept),
+                Some(Err(_)) => false,
+            };
+            if !accepted {
+                t.fail(&self.cb, WsErr::HandshakeFailed.into(), &ws).await;
+                return;
+            }
+            t.open(&self.cb, &ws).await;
+        }
+
+        t.recv_frames(&self.cb, buf, &ws).await;
+        t.sync(&ws);
+    }
+
+    #[inline]
+    async fn err(&mut self, err: SocketErr, t: &mut Self::T) {
+        self.cb.err(err, &mut t.t).await;
+    }
+
+    /// part of the frame stream has been lost, the websocket is closed with [crate::websocket::CLOSE_INTERNAL_ERROR] and the tcp connection is closed,
+    /// the tcp client reconnects by its reconnect policy
+    async fn dropped<const CN: usize>(&mut self, _count: u32, wch: &WriteChannel<'_, CN>, t: &mut Self::T) {
+        let ws = t.sender(WsSender::client(wch, 0));
+        t.dropped(&self.cb, &ws).await;
+        t.sync(&ws);
+    }
+}
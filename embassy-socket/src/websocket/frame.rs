@@ -0,0 +1,373 @@
+use crate::err::{SocketErr, SocketResult};
+use crate::websocket::{Message, WsErr};
+
+/// max payload len of control frames
+pub const MAX_CONTROL: usize = 125;
+/// max frame header len
+pub const MAX_HEADER: usize = 14;
+
+/// websocket frame opcode
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Opcode {
+    /// continuation of a fragmented message
+    Continuation = 0x0,
+    /// text message, utf-8
+    Text = 0x1,
+    /// binary message
+    Binary = 0x2,
+    /// close
+    Close = 0x8,
+    /// ping
+    Ping = 0x9,
+    /// pong
+    Pong = 0xA,
+}
+
+/// custom method
+impl Opcode {
+    /// parse opcode, returns None for reserved opcodes
+    pub const fn parse(opcode: u8) -> Option<Self> {
+        let opcode = match opcode {
+            0x0 => Self::Continuation,
+            0x1 => Self::Text,
+            0x2 => Self::Binary,
+            0x8 => Self::Close,
+            0x9 => Self::Ping,
+            0xA => Self::Pong,
+            _ => return None,
+        };
+        Some(opcode)
+    }
+
+    /// control opcode, control frames can not be fragmented and are at most [MAX_CONTROL] bytes
+    #[inline]
+    pub const fn is_control(&self) -> bool {
+        matches!(self, Self::Close | Self::Ping | Self::Pong)
+    }
+}
+
+/// decoded websocket event
+#[derive(Copy, Clone, Debug)]
+pub enum Event<'a> {
+    /// whole message, fragments are reassembled
+    Message(Message<'a>),
+    /// ping, it should be answered with a pong of the same payload
+    Ping(&'a [u8]),
+    /// pong
+    Pong(&'a [u8]),
+    /// close, code is None if the close frame has no status code
+    Close(Option<u16>),
+}
+
+/// encode frame header, returns the header len<br />
+/// mask is the masking key, client frames must be masked, server frames must not
+pub fn encode_header(fin: bool, opcode: Opcode, len: usize, mask: Option<[u8; 4]>, out: &mut [u8; MAX_HEADER]) -> usize {
+    out[0] = ((fin as u8) << 7) | opcode as u8;
+    let masked = (mask.is_some() as u8) << 7;
+    let mut used = match len {
+        0..=125 => {
+            out[1] = masked | len as u8;
+            2
+        }
+        126..=0xFFFF => {
+            out[1] = masked | 126;
+            out[2..4].copy_from_slice(&(len as u16).to_be_bytes());
+            4
+        }
+        _ => {
+            out[1] = masked | 127;
+            out[2..10].copy_from_slice(&(len as u64).to_be_bytes());
+            10
+        }
+    };
+    if let Some(mask) = mask {
+        out[used..used + 4].copy_from_slice(&mask);
+        used += 4;
+    }
+    used
+}
+
+/// header of the frame being decoded
+#[derive(Copy, Clone, Debug)]
+struct Header {
+    /// final fragment
+    fin: bool,
+    /// opcode
+    opcode: Opcode,
+    /// masking key
+    mask: Option<[u8; 4]>,
+    /// payload len
+    len: usize,
+}
+
+/// websocket frame decoder, unmask frames and reassemble fragments into whole messages<br />
+/// N is max message size, larger messages will be reported as [SocketErr::FrameTooLarge] and discarded<br />
+/// control frames may be interleaved with fragments, they are decoded into their own buf<br />
+/// a protocol violation is reported as [WsErr::Protocol] or [WsErr::InvalidText], the rest of the stream can not be decoded
+pub struct WsDecoder<const N: usize> {
+    /// message buf, also the handshake head buf
+    buf: [u8; N],
+    /// message buf len
+    len: usize,
+    /// control frame payload buf
+    control: [u8; MAX_CONTROL],
+    /// header cache
+    header: [u8; MAX_HEADER],
+    /// header cache len
+    header_len: usize,
+    /// header of the frame being decoded, None is reading the header
+    frame: Option<Header>,
+    /// payload bytes of the frame decoded
+    offset: usize,
+    /// opcode of the fragmented message being reassembled
+    message: Option<Opcode>,
+    /// discard the message being reassembled, it is too large
+    discard: bool,
+    /// decode frames sent by the client, which must be masked
+    server: bool,
+    /// a protocol violation happened, nothing can be decoded until reset
+    failed: bool,
+    /// the message in buf has been returned, and buf should be cleared on next decode
+    ready: bool,
+}
+
+/// custom method
+impl<const N: usize> WsDecoder<N> {
+    /// create websocket decoder, server decodes frames sent by the client
+    #[inline]
+    pub const fn new(server: bool) -> Self {
+        Self {
+            buf: [0; N],
+            len: 0,
+            control: [0; MAX_CONTROL],
+            header: [0; MAX_HEADER],
+            header_len: 0,
+            frame: None,
+            offset: 0,
+            message: None,
+            discard: false,
+            server,
+            failed: false,
+            ready: false,
+        }
+    }
+
+    /// drop the incomplete frame and message, generally called when connection changes
+    pub fn reset(&mut self, server: bool) {
+        self.len = 0;
+        self.header_len = 0;
+        self.frame = None;
+        self.offset = 0;
+        self.message = None;
+        self.discard = false;
+        self.server = server;
+        self.failed = false;
+        self.ready = false;
+    }
+
+    /// decode http head of the handshake, returns the number of bytes used and the head without the empty line<br />
+    /// if the head is None, all bytes have been used, the head larger than N is reported as [SocketErr::FrameTooLarge]
+    pub fn decode_head(&mut self, bytes: &[u8]) -> (usize, Option<SocketResult<&[u8]>>) {
+        self.clear_ready();
+        for (i, b) in bytes.iter().enumerate() {
+            if self.len == N { return (i, Some(Err(SocketErr::FrameTooLarge))); }
+
+            self.buf[self.len] = *b;
+            self.len += 1;
+            if self.buf[..self.len].ends_with(b"\r\n\r\n") {
+                self.ready = true;
+                return (i + 1, Some(Ok(&self.buf[..self.len - 4])));
+            }
+        }
+        (bytes.len(), None)
+    }
+
+    /// decode bytes, returns the number of bytes used and the decoded event<br />
+    /// if the event is None, all bytes have been used<br />
+    /// call this method repeatedly with the remaining bytes until all bytes have been used
+    pub fn decode(&mut self, bytes: &[u8]) -> (usize, Option<SocketResult<Event<'_>>>) {
+        self.clear_ready();
+        if self.failed { return (bytes.len(), None); }
+
+        let mut used = 0;
+        let (opcode, len) = loop {
+            let frame = match self.frame {
+                Some(frame) => frame,
+                None => match self.read_header(bytes, &mut used) {
+                    None => return (used, None),
+                    Some(Ok(frame)) => {
+                        self.frame = Some(frame);
+                        frame
+                    }
+                    // the payload of the too large message is skipped
+                    Some(Err(SocketErr::FrameTooLarge)) => return (used, Some(Err(SocketErr::FrameTooLarge))),
+                    Some(Err(e)) => {
+                        self.failed = true;
+                        return (bytes.len(), Some(Err(e)));
+                    }
+                },
+            };
+
+            let copy = (frame.len - self.offset).min(bytes.len() - used);
+            self.copy_payload(&frame, &bytes[used..used + copy]);
+            self.offset += copy;
+            used += copy;
+            if self.offset < frame.len { return (used, None); }
+
+            self.frame = None;
+            self.offset = 0;
+            // the payload of a data message is in buf, only control frames have len
+            let len = if frame.opcode.is_control() { frame.len } else { 0 };
+            if let Some(opcode) = self.frame_done(&frame) { break (opcode, len); }
+        };
+        (used, Some(self.event(opcode, len)))
+    }
+
+    /// clear the returned message
+    #[inline]
+    fn clear_ready(&mut self) {
+        if self.ready {
+            self.ready = false;
+            self.len = 0;
+        }
+    }
+
+    /// read and check frame header, returns None if the header is incomplete<br />
+    /// a frame starting a message larger than N is reported here, and the message is discarded
+    fn read_header(&mut self, bytes: &[u8], used: &mut usize) -> Option<SocketResult<Header>> {
+        loop {
+            let need = self.header_need();
+            let copy = (need - self.header_len).min(bytes.len() - *used);
+            self.header[self.header_len..self.header_len + copy].copy_from_slice(&bytes[*used..*used + copy]);
+            self.header_len += copy;
+            *used += copy;
+            if self.header_len < need { return None; }
+            if self.header_need() == need { break; }
+        }
+
+        let len = core::mem::take(&mut self.header_len);
+        Some(self.parse_header(len))
+    }
+
+    /// header len, the whole len is known after the first 2 bytes
+    #[inline]
+    fn header_need(&self) -> usize {
+        if self.header_len < 2 { return 2; }
+
+        let ext = match self.header[1] & 0x7F {
+            126 => 2,
+            127 => 8,
+            _ => 0,
+        };
+        2 + ext + (self.header[1] >> 7) as usize * 4
+    }
+
+    /// parse and check the cached header of len
+    fn parse_header(&mut self, len: usize) -> SocketResult<Header> {
+        let header = &self.header[..len];
+        let protocol = SocketErr::WsError(WsErr::Protocol);
+        // no extension is negotiated, so the reserved bits must be 0
+        if header[0] & 0x70 != 0 { return Err(protocol); }
+        let opcode = Opcode::parse(header[0] & 0x0F).ok_or(protocol)?;
+        let fin = header[0] >> 7 == 1;
+        let masked = header[1] >> 7 == 1;
+        if masked != self.server { return Err(protocol); }
+
+        let (payload_len, mask_at) = match header[1] & 0x7F {
+            126 => (u16::from_be_bytes([header[2], header[3]]) as u64, 4),
+            127 => (u64::from_be_bytes(header[2..10].try_into().unwrap_or_default()), 10),
+            len => (len as u64, 2),
+        };
+        if payload_len >> 63 == 1 { return Err(protocol); }
+        let mask = masked.then(|| [header[mask_at], header[mask_at + 1], header[mask_at + 2], header[mask_at + 3]]);
+        let payload_len = usize::try_from(payload_len).unwrap_or(usize::MAX);
+
+        if opcode.is_control() {
+            if !fin || payload_len > MAX_CONTROL { return Err(protocol); }
+            return Ok(Header { fin, opcode, mask, len: payload_len });
+        }
+
+        // continuation must follow an unfinished message, a new message must not
+        match (opcode, self.message) {
+            (Opcode::Continuation, None) => return Err(protocol),
+            (Opcode::Continuation, Some(_)) => {}
+            (_, Some(_)) => return Err(protocol),
+            (_, None) => {
+                self.message = Some(opcode);
+                self.len = 0;
+                self.discard = false;
+            }
+        }
+
+        let frame = Header { fin, opcode, mask, len: payload_len };
+        if !self.discard && payload_len > N - self.len {
+            self.discard = true;
+            self.frame = Some(frame);
+            return Err(SocketErr::FrameTooLarge);
+        }
+        Ok(frame)
+    }
+
+    /// unmask and copy payload bytes of frame
+    fn copy_payload(&mut self, frame: &Header, bytes: &[u8]) {
+        let dst = match frame.opcode.is_control() {
+            true => &mut self.control[self.offset..self.offset + bytes.len()],
+            false if self.discard => return,
+            false => &mut self.buf[self.len..self.len + bytes.len()],
+        };
+        dst.copy_from_slice(bytes);
+        if let Some(mask) = frame.mask {
+            for (i, b) in dst.iter_mut().enumerate() {
+                *b ^= mask[(self.offset + i) % 4];
+            }
+        }
+        if !frame.opcode.is_control() { self.len += bytes.len(); }
+    }
+
+    /// the whole frame payload is decoded, returns the opcode of the event if there is one<br />
+    /// close payload and text message are checked here, the decoder fails if they are invalid
+    fn frame_done(&mut self, frame: &Header) -> Option<Opcode> {
+        if frame.opcode.is_control() {
+            if frame.opcode == Opcode::Close { self.failed = Self::close_code(&self.control[..frame.len]).is_err(); }
+            return Some(frame.opcode);
+        }
+        if !frame.fin { return None; }
+
+        let opcode = self.message.take()?;
+        if core::mem::take(&mut self.discard) {
+            self.len = 0;
+            return None;
+        }
+
+        self.ready = true;
+        if opcode == Opcode::Text { self.failed = core::str::from_utf8(&self.buf[..self.len]).is_err(); }
+        Some(opcode)
+    }
+
+    /// event of the decoded frame, len is the payload len of control frames
+    fn event(&self, opcode: Opcode, len: usize) -> SocketResult<Event<'_>> {
+        let control = &self.control[..len];
+        let event = match opcode {
+            Opcode::Ping => Event::Ping(control),
+            Opcode::Pong => Event::Pong(control),
+            Opcode::Close => Event::Close(Self::close_code(control)?),
+            _ if self.failed => return Err(SocketErr::WsError(WsErr::InvalidText)),
+            // the text has been checked by frame_done
+            Opcode::Text => Event::Message(Message::Text(unsafe { core::str::from_utf8_unchecked(&self.buf[..self.len]) })),
+            _ => Event::Message(Message::Binary(&self.buf[..self.len])),
+        };
+        Ok(event)
+    }
+
+    /// decode close payload, status code and utf-8 reason, returns the status code
+    fn close_code(payload: &[u8]) -> SocketResult<Option<u16>> {
+        match payload {
+            [] => Ok(None),
+            [_] => Err(SocketErr::WsError(WsErr::Protocol)),
+            [hi, lo, reason @ ..] => {
+                if core::str::from_utf8(reason).is_err() { return Err(SocketErr::WsError(WsErr::InvalidText)); }
+                Ok(Some(u16::from_be_bytes([*hi, *lo])))
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+/// websocket guid appended to the key, RFC 6455 section 1.3
+const GUID: &[u8] = b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// base64 alphabet
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// `Sec-WebSocket-Accept` value of the `Sec-WebSocket-Key`, base64 of sha-1 of key and guid
+pub fn accept_key(key: &[u8]) -> [u8; 28] {
+    let mut accept = [0; 28];
+    base64(&sha1(&[key, GUID]), &mut accept);
+    accept
+}
+
+/// client `Sec-WebSocket-Key`, base64 of 16 random bytes
+pub(crate) fn client_key(random: [u8; 16]) -> [u8; 24] {
+    let mut key = [0; 24];
+    base64(&random, &mut key);
+    key
+}
+
+/// get header value of a http head, name is case-insensitive<br />
+/// the first line of the head is the request or status line and is skipped
+pub(crate) fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.split("\r\n").skip(1).find_map(|line| {
+        let (n, v) = line.split_once(':')?;
+        n.trim().eq_ignore_ascii_case(name).then(|| v.trim())
+    })
+}
+
+/// header value is a comma separated list containing the token, case-insensitive
+pub(crate) fn has_token(value: Option<&str>, token: &str) -> bool {
+    value.is_some_and(|value| value.split(',').any(|t| t.trim().eq_ignore_ascii_case(token)))
+}
+
+/// check the handshake response of the server, returns true if the websocket is accepted
+pub(crate) fn check_response(head: &[u8], accept: &[u8; 28]) -> bool {
+    let Ok(head) = core::str::from_utf8(head) else { return false; };
+    let mut status = head.split("\r\n").next().unwrap_or_default().split(' ');
+    if !matches!((status.next(), status.next()), (Some("HTTP/1.1"), Some("101"))) { return false; }
+
+    header(head, "Upgrade").is_some_and(|v| v.eq_ignore_ascii_case("websocket"))
+        && has_token(header(head, "Connection"), "upgrade")
+        && header(head, "Sec-WebSocket-Accept").is_some_and(|v| v.as_bytes() == accept)
+}
+
+/// base64 encode with padding, out len must be `(bytes.len() + 2) / 3 * 4`
+fn base64(bytes: &[u8], out: &mut [u8]) {
+    for (chunk, out) in bytes.chunks(3).zip(out.chunks_mut(4)) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - i * 8));
+        for (i, o) in out.iter_mut().enumerate() {
+            *o = if i <= chunk.len() { BASE64[(n >> (18 - i * 6)) as usize & 0x3F] } else { b'=' };
+        }
+    }
+}
+
+/// sha-1 digest of the concatenated parts
+fn sha1(parts: &[&[u8]]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut block = [0; 64];
+    let mut len = 0;
+    let mut total = 0u64;
+    for part in parts {
+        for b in part.iter() {
+            block[len] = *b;
+            len += 1;
+            if len == 64 {
+                sha1_block(&mut h, &block);
+                len = 0;
+            }
+        }
+        total += part.len() as u64;
+    }
+
+    // padding, 0x80, zeros and the bit len
+    block[len] = 0x80;
+    block[len + 1..].fill(0);
+    if len >= 56 {
+        sha1_block(&mut h, &block);
+        block.fill(0);
+    }
+    block[56..].copy_from_slice(&(total * 8).to_be_bytes());
+    sha1_block(&mut h, &block);
+
+    let mut digest = [0; 20];
+    for (d, h) in digest.chunks_mut(4).zip(h) {
+        d.copy_from_slice(&h.to_be_bytes());
+    }
+    digest
+}
+
+/// sha-1 process one 64 bytes block
+fn sha1_block(h: &mut [u32; 5], block: &[u8; 64]) {
+    let mut w = [0u32; 80];
+    for (w, b) in w.iter_mut().zip(block.chunks(4)) {
+        *w = u32::from_be_bytes([b[0], b[1], b[2], b[3]]);
+    }
+    for i in 16..80 {
+        w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e] = *h;
+    for (i, w) in w.iter().enumerate() {
+        let (f, k) = match i {
+            0..20 => ((b & c) | (!b & d), 0x5A827999),
+            20..40 => (b ^ c ^ d, 0x6ED9EBA1),
+            40..60 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+            _ => (b ^ c ^ d, 0xCA62C1D6),
+        };
+        let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(*w);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = temp;
+    }
+
+    for (h, v) in h.iter_mut().zip([a, b, c, d, e]) {
+        *h = h.wrapping_add(v);
+    }
+}
@@ -0,0 +1,199 @@
+use embassy_time::Instant;
+use crate::err::SocketErr;
+use crate::websocket::callback::WsCallBack;
+use crate::websocket::frame::{Event, WsDecoder};
+use crate::websocket::sender::WsSender;
+
+pub mod handshake;
+pub mod frame;
+pub mod sender;
+pub mod callback;
+pub mod server;
+pub mod client;
+
+/// normal closure status code
+pub const CLOSE_NORMAL: u16 = 1000;
+/// going away status code, for example the server is shutting down
+pub const CLOSE_GOING_AWAY: u16 = 1001;
+/// protocol error status code
+pub const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+/// invalid payload data status code, for example text message is not utf-8
+pub const CLOSE_INVALID_DATA: u16 = 1007;
+/// internal error status code, for example read data was dropped
+pub const CLOSE_INTERNAL_ERROR: u16 = 1011;
+
+/// websocket error
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WsErr {
+    /// handshake request or response is invalid, or the server refused the upgrade, the connection will be closed
+    HandshakeFailed,
+    /// frame violates the websocket protocol, the connection will be closed
+    Protocol,
+    /// text message is not utf-8, the connection will be closed
+    InvalidText,
+    /// close frame has been sent, nothing can be sent any more
+    Closed,
+}
+
+/// custom method
+impl WsErr {
+    /// status code of the close frame sent because of the error
+    #[inline]
+    pub const fn close_code(&self) -> u16 {
+        match self {
+            Self::InvalidText => CLOSE_INVALID_DATA,
+            _ => CLOSE_PROTOCOL_ERROR,
+        }
+    }
+}
+
+/// websocket message
+#[derive(Copy, Clone, Debug)]
+pub enum Message<'a> {
+    /// text message
+    Text(&'a str),
+    /// binary message
+    Binary(&'a [u8]),
+}
+
+/// websocket connection phase
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Phase {
+    /// waiting for the handshake
+    Handshake,
+    /// handshake finished, frames are exchanged
+    Open,
+    /// close frame is exchanged or the connection failed, waiting for the tcp connection to be closed
+    Closed,
+}
+
+/// websocket connection data, hold the frame decoder of the connection and the callback data<br />
+/// F_SZ is max message size, also the max handshake head size
+pub struct WsState<T, const F_SZ: usize> {
+    /// frame decoder
+    decoder: WsDecoder<F_SZ>,
+    /// connection phase
+    phase: Phase,
+    /// masking key generator, client only
+    rng: u32,
+    /// close frame has been sent
+    close_sent: bool,
+    /// expected `Sec-WebSocket-Accept`, client only
+    accept: [u8; 28],
+    /// callback data, reset to default on every connection
+    pub t: T,
+}
+
+/// support default
+impl<T: Default, const F_SZ: usize> Default for WsState<T, F_SZ> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// custom method
+impl<T: Default, const F_SZ: usize> WsState<T, F_SZ> {
+    /// create websocket connection data
+    #[inline]
+    pub fn new() -> Self {
+        Self { decoder: WsDecoder::new(true), phase: Phase::Handshake, rng: 0, close_sent: false, accept: [0; 28], t: T::default() }
+    }
+
+    /// websocket handshake finished and not closed
+    #[inline]
+    pub fn is_open(&self) -> bool {
+        self.phase == Phase::Open
+    }
+
+    /// reset for a new connection, seed is the masking key seed of the client
+    fn reset(&mut self, server: bool, seed: u32) {
+        self.decoder.reset(server);
+        self.phase = Phase::Handshake;
+        // the tick makes keys differ between connections, xorshift state must not be 0
+        self.rng = (seed ^ Instant::now().as_ticks() as u32) | 1;
+        self.close_sent = false;
+        self.t = T::default();
+    }
+
+    /// create sender of the connection, the close flag and masking key generator are written back by [WsState::sync]
+    #[inline]
+    fn sender<'a, 'd, const CN: usize>(&self, ws: WsSender<'a, 'd, CN>) -> WsSender<'a, 'd, CN> {
+        ws.with_state(self.rng, self.close_sent)
+    }
+
+    /// write back the state changed by the sender
+    #[inline]
+    fn sync<const CN: usize>(&mut self, ws: &WsSender<'_, '_, CN>) {
+        (self.rng, self.close_sent) = ws.state();
+    }
+
+    /// handshake finished, call back open
+    async fn open<CB: WsCallBack<T = T>, const CN: usize>(&mut self, cb: &CB, ws: &WsSender<'_, '_, CN>) {
+        self.phase = Phase::Open;
+        cb.open(ws, &mut self.t).await;
+    }
+
+    /// decode frames and call back until all bytes have been used or the websocket is closed<br />
+    /// ping is answered automatically, close is answered and the tcp connection is closed by the server
+    async fn recv_frames<CB: WsCallBack<T = T>, const CN: usize>(&mut self, cb: &CB, mut buf: &[u8], ws: &WsSender<'_, '_, CN>) {
+        while !buf.is_empty() && self.phase == Phase::Open {
+            let (used, event) = self.decoder.decode(buf);
+            buf = &buf[used..];
+            match event {
+                Some(Ok(Event::Message(msg))) => cb.message(msg, ws, &mut self.t).await,
+                Some(Ok(Event::Ping(payload))) => { ws.pong(payload).await.ok(); }
+                Some(Ok(Event::Pong(payload))) => cb.pong(payload, ws, &mut self.t).await,
+                Some(Ok(Event::Close(code))) => {
+                    // answer the close of the peer with the same code
+                    if !ws.is_closed() { ws.close_frame(code, "").await.ok(); }
+                    self.close(cb, code, ws).await;
+                }
+                Some(Err(SocketErr::FrameTooLarge)) => cb.err(SocketErr::FrameTooLarge, &mut self.t).await,
+                Some(Err(e)) => self.fail(cb, e, ws).await,
+                None => {}
+            }
+        }
+    }
+
+    /// the websocket failed, send close frame if it is open and close the tcp connection
+    async fn fail<CB: WsCallBack<T = T>, const CN: usize>(&mut self, cb: &CB, err: SocketErr, ws: &WsSender<'_, '_, CN>) {
+        cb.err(err, &mut self.t).await;
+        let code = match err {
+            SocketErr::WsError(e) => e.close_code(),
+            _ => CLOSE_PROTOCOL_ERROR,
+        };
+        self.abort(cb, code, ws).await;
+    }
+
+    /// part of the frame stream has been lost, the frames can not be decoded any more, close like [WsState::fail]
+    async fn dropped<CB: WsCallBack<T = T>, const CN: usize>(&mut self, cb: &CB, ws: &WsSender<'_, '_, CN>) {
+        // a connection already closed must not close the next one
+        if self.phase == Phase::Closed { return; }
+        self.abort(cb, CLOSE_INTERNAL_ERROR, ws).await;
+    }
+
+    /// send close frame with the code if it is open, call back close and close the tcp connection
+    async fn abort<CB: WsCallBack<T = T>, const CN: usize>(&mut self, cb: &CB, code: u16, ws: &WsSender<'_, '_, CN>) {
+        if self.phase == Phase::Open {
+            if !ws.is_closed() { ws.close_frame(Some(code), "").await.ok(); }
+            cb.close(None, &mut self.t).await;
+        }
+        self.phase = Phase::Closed;
+        ws.close_tcp().await;
+    }
+
+    /// the close frame is exchanged, call back close<br />
+    /// the server closes the tcp connection, the client leaves it to the server
+    async fn close<CB: WsCallBack<T = T>, const CN: usize>(&mut self, cb: &CB, code: Option<u16>, ws: &WsSender<'_, '_, CN>) {
+        self.phase = Phase::Closed;
+        cb.close(code, &mut self.t).await;
+        if !ws.is_client() { ws.close_tcp().await; }
+    }
+
+    /// the tcp connection is lost, call back close if the websocket is open
+    async fn dis_conn<CB: WsCallBack<T = T>>(&mut self, cb: &CB) {
+        if self.phase == Phase::Open { cb.close(None, &mut self.t).await; }
+        self.phase = Phase::Closed;
+    }
+}
@@ -0,0 +1,212 @@
+use core::cell::Cell;
+use embassy_net::IpEndpoint;
+use crate::channel::write_channel::WriteChannel;
+use crate::err::{SocketErr, SocketResult};
+use crate::websocket::frame::{encode_header, Opcode, MAX_CONTROL, MAX_HEADER};
+use crate::websocket::WsErr;
+
+/// websocket sender, frame messages and write them through the write channel of the connection<br />
+/// frames are packed into msg of N bytes, a frame larger than N is written in several msg,
+/// so only one task should send large frames to a connection at a time<br />
+/// the senders passed to [crate::websocket::callback::WsCallBack] know whether close has been sent,
+/// senders created by other tasks do not
+pub struct WsSender<'a, 'd, const N: usize> {
+    /// write channel
+    wch: &'a WriteChannel<'d, N>,
+    /// connection endpoint, None is all connections of the multi-connection tcp server
+    endpoint: Option<IpEndpoint>,
+    /// client frames are masked
+    client: bool,
+    /// masking key generator, xorshift
+    rng: Cell<u32>,
+    /// close frame has been sent
+    close_sent: Cell<bool>,
+}
+
+/// custom method
+impl<'a, 'd, const N: usize> WsSender<'a, 'd, N> {
+    /// create server sender, frames are not masked<br />
+    /// endpoint is the connection of the multi-connection tcp server, None is all connections,
+    /// other tcp runners ignore the endpoint
+    #[inline]
+    pub const fn server(wch: &'a WriteChannel<'d, N>, endpoint: Option<IpEndpoint>) -> Self {
+        Self { wch, endpoint, client: false, rng: Cell::new(1), close_sent: Cell::new(false) }
+    }
+
+    /// create client sender, frames are masked with keys generated from seed
+    #[inline]
+    pub const fn client(wch: &'a WriteChannel<'d, N>, seed: u32) -> Self {
+        Self { wch, endpoint: None, client: true, rng: Cell::new(seed | 1), close_sent: Cell::new(false) }
+    }
+
+    /// set masking key generator and close flag of the connection
+    #[inline]
+    pub(crate) fn with_state(self, rng: u32, close_sent: bool) -> Self {
+        self.rng.set(rng);
+        self.close_sent.set(close_sent);
+        self
+    }
+
+    /// masking key generator and close flag
+    #[inline]
+    pub(crate) fn state(&self) -> (u32, bool) {
+        (self.rng.get(), self.close_sent.get())
+    }
+
+    /// connection endpoint
+    #[inline]
+    pub fn endpoint(&self) -> Option<IpEndpoint> {
+        self.endpoint
+    }
+
+    /// client sender
+    #[inline]
+    pub fn is_client(&self) -> bool {
+        self.client
+    }
+
+    /// close frame has been sent
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.close_sent.get()
+    }
+
+    /// write channel
+    #[inline]
+    pub fn write_channel(&self) -> &'a WriteChannel<'d, N> {
+        self.wch
+    }
+
+    /// send text message
+    #[inline]
+    pub async fn text(&self, text: &str) -> SocketResult<()> {
+        self.frame(Opcode::Text, true, text.as_bytes()).await
+    }
+
+    /// send binary message
+    #[inline]
+    pub async fn binary(&self, bytes: &[u8]) -> SocketResult<()> {
+        self.frame(Opcode::Binary, true, bytes).await
+    }
+
+    /// send ping, payload is at most 125 bytes
+    #[inline]
+    pub async fn ping(&self, payload: &[u8]) -> SocketResult<()> {
+        self.frame(Opcode::Ping, true, payload).await
+    }
+
+    /// send pong, payload is at most 125 bytes
+    #[inline]
+    pub async fn pong(&self, payload: &[u8]) -> SocketResult<()> {
+        self.frame(Opcode::Pong, true, payload).await
+    }
+
+    /// send close with status code and reason, the reason is at most 123 bytes<br />
+    /// the tcp connection is closed after the peer answers the close
+    #[inline]
+    pub async fn close(&self, code: u16, reason: &str) -> SocketResult<()> {
+        self.close_frame(Some(code), reason).await
+    }
+
+    /// send close frame, the status code is optional
+    pub(crate) async fn close_frame(&self, code: Option<u16>, reason: &str) -> SocketResult<()> {
+        let mut payload = [0; MAX_CONTROL];
+        let len = match code {
+            Some(code) => {
+                let reason = reason.as_bytes();
+                if reason.len() > MAX_CONTROL - 2 { return Err(SocketErr::PayloadTooLarge); }
+                payload[..2].copy_from_slice(&code.to_be_bytes());
+                payload[2..2 + reason.len()].copy_from_slice(reason);
+                2 + reason.len()
+            }
+            None => 0,
+        };
+        self.frame(Opcode::Close, true, &payload[..len]).await?;
+        self.close_sent.set(true);
+        Ok(())
+    }
+
+    /// send one frame, a message can be sent in fragments:
+    /// the first frame is [Opcode::Text] or [Opcode::Binary], the rest are [Opcode::Continuation],
+    /// and only the last frame is fin<br />
+    /// control frames can not be fragmented and are at most 125 bytes, otherwise returns [SocketErr::PayloadTooLarge]
+    pub async fn frame(&self, opcode: Opcode, fin: bool, payload: &[u8]) -> SocketResult<()> {
+        if self.close_sent.get() { return Err(WsErr::Closed.into()); }
+        if opcode.is_control() && (!fin || payload.len() > MAX_CONTROL) { return Err(SocketErr::PayloadTooLarge); }
+
+        let mask = self.client.then(|| self.next_mask());
+        let mut header = [0; MAX_HEADER];
+        let len = encode_header(fin, opcode, payload.len(), mask, &mut header);
+        let mut out = Out { sender: self, buf: [0; N], len: 0 };
+        out.put(&header[..len], None).await?;
+        out.put(payload, mask).await?;
+        out.flush().await
+    }
+
+    /// send raw bytes, used by the handshake
+    pub(crate) async fn raw(&self, parts: &[&[u8]]) -> SocketResult<()> {
+        let mut out = Out { sender: self, buf: [0; N], len: 0 };
+        for part in parts {
+            out.put(part, None).await?;
+        }
+        out.flush().await
+    }
+
+    /// request the runner to close the tcp connection
+    #[inline]
+    pub(crate) async fn close_tcp(&self) {
+        self.wch.close(self.endpoint).await.ok();
+    }
+
+    /// next masking key, also used as random bytes of the handshake key
+    pub(crate) fn next_mask(&self) -> [u8; 4] {
+        let mut x = self.rng.get();
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.rng.set(x);
+        x.to_be_bytes()
+    }
+
+    /// send bytes to the connection
+    #[inline]
+    async fn send(&self, bytes: &[u8]) -> SocketResult<()> {
+        match self.endpoint {
+            Some(endpoint) => self.wch.send_bytes_to(bytes, endpoint).await,
+            None => self.wch.send_bytes(bytes).await,
+        }
+    }
+}
+
+/// write cache of one frame, the cache is sent when full
+struct Out<'s, 'a, 'd, const N: usize> {
+    /// sender
+    sender: &'s WsSender<'a, 'd, N>,
+    /// write cache
+    buf: [u8; N],
+    /// write cache len
+    len: usize,
+}
+
+/// custom method
+impl<const N: usize> Out<'_, '_, '_, N> {
+    /// write bytes to the cache, masked if the mask is some
+    async fn put(&mut self, bytes: &[u8], mask: Option<[u8; 4]>) -> SocketResult<()> {
+        for (i, b) in bytes.iter().enumerate() {
+            self.buf[self.len] = match mask {
+                Some(mask) => b ^ mask[i % 4],
+                None => *b,
+            };
+            self.len += 1;
+            if self.len == N { self.flush().await?; }
+        }
+        Ok(())
+    }
+
+    /// send the cached data
+    async fn flush(&mut self) -> SocketResult<()> {
+        let len = core::mem::take(&mut self.len);
+        if len == 0 { return Ok(()); }
+        self.sender.send(&self.buf[..len]).await
+    }
+}
@@ -0,0 +1,99 @@
+use embassy_net::IpEndpoint;
+use crate::channel::overflow_policy::OverflowPolicy;
+use crate::channel::write_channel::WriteChannel;
+use crate::err::SocketErr;
+use crate::http::request::{Method, Request};
+use crate::http::response::Response;
+use crate::tcp_server::callback::TcpServerCallBack;
+use crate::websocket::callback::WsCallBack;
+use crate::websocket::handshake::{accept_key, has_token};
+use crate::websocket::sender::WsSender;
+use crate::websocket::{Phase, WsErr, WsState};
+
+/// websocket server callback<br />
+/// answer the http upgrade handshake, decode frames and pass whole messages to [WsCallBack::message]<br />
+/// every connection needs its own [WsState], the multi-connection runner keeps one per connection,
+/// more see [crate::tcp_server::multi_callback_runner::MultiCallbackRunner]<br />
+/// frames are written in pieces, so the write channel is set to [OverflowPolicy::Block] when connected,
+/// set the read channel to [OverflowPolicy::Block] too, lost data breaks the frame stream and closes the connection<br />
+/// F_SZ is max message size, larger messages will be passed to [WsCallBack::err] as [SocketErr::FrameTooLarge]
+pub struct WsServer<CB: WsCallBack, const F_SZ: usize> {
+    /// websocket callback
+    pub cb: CB,
+}
+
+/// custom method
+impl<CB: WsCallBack, const F_SZ: usize> WsServer<CB, F_SZ> {
+    /// create websocket server callback
+    #[inline]
+    pub const fn new(cb: CB) -> Self {
+        Self { cb }
+    }
+
+    /// check the handshake request and answer 101, returns the status code if the request is refused
+    async fn handshake<const CN: usize>(&self, head: &[u8], ws: &WsSender<'_, '_, CN>, t: &mut CB::T) -> Result<(), u16> {
+        let req = Request::parse(head).map_err(|e| e.status())?;
+        let upgrade = req.method == Method::Get
+            && req.minor_version == 1
+            && req.header("Upgrade").is_some_and(|v| v.eq_ignore_ascii_case("websocket"))
+            && has_token(req.header("Connection"), "upgrade")
+            && req.header("Sec-WebSocket-Version") == Some("13");
+        let Some(key) = req.header("Sec-WebSocket-Key").filter(|key| upgrade && key.len() == 24) else { return Err(400); };
+        if !self.cb.accept(&req, t).await { return Err(403); }
+
+        let accept = accept_key(key.as_bytes());
+        ws.raw(&[b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: ", &accept, b"\r\n\r\n"])
+            .await
+            .map_err(|_| 500)
+    }
+}
+
+/// support tcp server callback
+impl<CB: WsCallBack, const F_SZ: usize> TcpServerCallBack for WsServer<CB, F_SZ> {
+    type T = WsState<CB::T, F_SZ>;
+
+    async fn conn<const CN: usize>(&self, _endpoint: IpEndpoint, wch: &WriteChannel<'_, CN>, t: &mut Self::T) {
+        wch.overflow_policy(OverflowPolicy::Block);
+        t.reset(true, 0);
+    }
+
+    #[inline]
+    async fn dis_conn(&self, _endpoint: IpEndpoint, t: &mut Self::T) {
+        t.dis_conn(&self.cb).await;
+    }
+
+    async fn recv<const CN: usize>(&self, endpoint: IpEndpoint, mut buf: &[u8], wch: &WriteChannel<'_, CN>, t: &mut Self::T) {
+        let ws = t.sender(WsSender::server(wch, Some(endpoint)));
+        if t.phase == Phase::Handshake {
+            let (used, head) = t.decoder.decode_head(buf);
+            buf = &buf[used..];
+            let result = match head {
+                None => return,
+                Some(Ok(head)) => self.handshake(head, &ws, &mut t.t).await,
+                Some(Err(_)) => Err(431),
+            };
+            if let Err(status) = result {
+                Response::new(wch, endpoint, false, false).send_status(status).await;
+                t.fail(&self.cb, WsErr::HandshakeFailed.into(), &ws).await;
+                return;
+            }
+            t.open(&self.cb, &ws).await;
+        }
+
+        t.recv_frames(&self.cb, buf, &ws).await;
+        t.sync(&ws);
+    }
+
+    #[inline]
+    async fn err(&self, err: SocketErr, t: &mut Self::T) {
+        self.cb.err(err, &mut t.t).await;
+    }
+
+    /// part of the frame stream has been lost, the websocket is closed with [crate::websocket::CLOSE_INTERNAL_ERROR] and the tcp connection is closed<br />
+    /// the multi-connection runner closes every connection, the lost data may belong to any of them
+    async fn dropped<const CN: usize>(&self, endpoint: IpEndpoint, _count: u32, wch: &WriteChannel<'_, CN>, t: &mut Self::T) {
+        let ws = t.sender(WsSender::server(wch, Some(endpoint)));
+        t.dropped(&self.cb, &ws).await;
+        t.sync(&ws);
+    }
+}
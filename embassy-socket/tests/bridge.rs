@@ -0,0 +1,177 @@
+//! serial bridge and RFC 2217 telnet decoder over the in-memory link
+#![cfg(feature = "bridge")]
+
+mod harness;
+
+use embassy_futures::select::{select, select4, Either, Either4};
+use embassy_socket::bridge::rfc2217::{Item, TelnetDecoder, COM_PORT_OPTION, DO, IAC, SB, SE, SET_BAUDRATE, WILL};
+use embassy_socket::bridge::{BridgeConfig, SerialBridge, SerialControl};
+use embassy_socket::channel::callback_enum::CallbackEnum;
+use embassy_socket::channel::SocketChannel;
+use embassy_socket::connection::socket_state::SocketState;
+use embassy_socket::tcp_client;
+use embassy_socket::tcp_server;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::pipe::Pipe;
+use embassy_time::{Duration, Timer};
+use embedded_io_async::{ErrorKind, ErrorType, Read};
+use harness::{leak, read_exact, wait_event, SERVER_IP};
+
+/// socket state of the tests
+type State = SocketState<1, 4096, 4096, 1024>;
+/// socket channel of the tests
+type Channel = SocketChannel<'static, 1024, 1024>;
+/// in-memory serial port direction
+type Serial = Pipe<CriticalSectionRawMutex, 256>;
+
+/// decode all bytes, returns the items with data copied
+fn decode_all(decoder: &mut TelnetDecoder, mut bytes: &[u8]) -> Vec<String> {
+    let mut items = Vec::new();
+    while !bytes.is_empty() {
+        let (used, item) = decoder.decode(bytes);
+        bytes = &bytes[used..];
+        if let Some(item) = item { items.push(format!("{item:?}")); }
+    }
+    items
+}
+
+#[test]
+fn telnet_decoder() {
+    let mut decoder = TelnetDecoder::new();
+    let items = decode_all(&mut decoder, &[b'a', IAC, IAC, b'b', IAC, WILL, COM_PORT_OPTION, b'c']);
+    assert_eq!(items, ["Data([97])", "Data([255])", "Data([98])", "Negotiate(251, 44)", "Data([99])"]);
+
+    // set baud rate split over msg, 0xFF in the value is escaped
+    let cmd = [IAC, SB, COM_PORT_OPTION, SET_BAUDRATE, 0, 0, IAC, IAC, IAC, IAC, IAC, SE];
+    let (first, second) = cmd.split_at(5);
+    assert!(decode_all(&mut decoder, first).is_empty());
+    let (used, item) = decoder.decode(second);
+    assert_eq!(used, second.len());
+    let Some(Item::ComPort(com_port)) = item else { panic!("com port command expected") };
+    assert_eq!((com_port.command, com_port.value_u32()), (SET_BAUDRATE, Some(0xFFFF)));
+}
+
+/// serial control of the test, every baud rate is accepted
+struct Baud(Vec<u32>);
+
+/// support serial control
+impl SerialControl for Baud {
+    async fn set_baud_rate(&mut self, baud: u32) -> Option<u32> {
+        self.0.push(baud);
+        Some(baud)
+    }
+}
+
+/// run a bridge behind a tcp server, body gets the client channel, the serial input and the serial output
+fn bridge<F: Future>(config: BridgeConfig, body: impl FnOnce(&'static Channel, &'static Serial, &'static Serial, &'static SerialBridge<'static, 1024, 1024>) -> F) -> Vec<u32> {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch) = (harness::channel::<1024, 1024>(8, 8), harness::channel::<1024, 1024>(8, 8));
+        let client = tcp_client::build_runner(client_stack, SERVER_IP, 1234, cch, leak(State::new()));
+        let server = tcp_server::build_runner(server_stack, 1234, sch, leak(State::new()));
+        let (serial_in, serial_out): (&'static Serial, &'static Serial) = (leak(Pipe::new()), leak(Pipe::new()));
+        let bridge: &'static SerialBridge<'static, 1024, 1024> = leak(SerialBridge::new(sch, config));
+
+        let (mut rx, mut tx, mut baud) = (serial_in, serial_out, Baud(Vec::new()));
+        let run = bridge.run_with_control(&mut rx, &mut tx, &mut baud);
+        match select4(client.run(), server.run(), run, body(cch, serial_in, serial_out, bridge)).await {
+            Either4::Fourth(_) => {}
+            _ => panic!("runner returned"),
+        }
+        baud.0
+    })
+}
+
+/// read exactly len bytes from the serial output
+async fn serial_read(serial: &Serial, len: usize) -> Vec<u8> {
+    let mut data = vec![0; len];
+    let mut read = 0;
+    while read < len { read += serial.read(&mut data[read..]).await; }
+    data
+}
+
+#[test]
+fn raw_bridge() {
+    bridge(BridgeConfig::new(Duration::from_millis(20)), |cch, serial_in, serial_out, bridge| async move {
+        assert!(matches!(wait_event(&cch.read_channel).await, CallbackEnum::Conn));
+
+        cch.write_channel.send_bytes(b"hello\xFF").await.unwrap();
+        assert_eq!(serial_read(serial_out, 6).await, b"hello\xFF");
+
+        // bytes written to the serial port within the gap are sent as one packet
+        serial_in.write_all(b"wor").await;
+        serial_in.write_all(b"ld").await;
+        let msg = cch.read_channel.wait_read_ref().await;
+        assert_eq!(msg.as_bytes(), b"world");
+        drop(msg);
+
+        let stats = bridge.stats();
+        assert_eq!((stats.serial_in, stats.serial_out, stats.packets, stats.discarded), (5, 6, 1, 0));
+    });
+}
+
+#[test]
+fn rfc2217_bridge() {
+    let baud = bridge(BridgeConfig::new(Duration::from_millis(20)).rfc2217(), |cch, serial_in, serial_out, bridge| async move {
+        assert!(matches!(wait_event(&cch.read_channel).await, CallbackEnum::Conn));
+
+        cch.write_channel.send_bytes(&[IAC, WILL, COM_PORT_OPTION]).await.unwrap();
+        assert_eq!(read_exact(&cch.read_channel, 3).await, [IAC, DO, COM_PORT_OPTION]);
+
+        let mut cmd = vec![IAC, SB, COM_PORT_OPTION, SET_BAUDRATE];
+        cmd.extend(115_200u32.to_be_bytes());
+        cmd.extend([IAC, SE, b'a', IAC, IAC]);
+        cch.write_channel.send_bytes(&cmd).await.unwrap();
+        let mut answer = vec![IAC, SB, COM_PORT_OPTION, SET_BAUDRATE + 100];
+        answer.extend(115_200u32.to_be_bytes());
+        answer.extend([IAC, SE]);
+        assert_eq!(read_exact(&cch.read_channel, answer.len()).await, answer);
+        assert_eq!(serial_read(serial_out, 2).await, b"a\xFF");
+
+        // 0xFF from the serial port is escaped
+        serial_in.write_all(b"\xFFb").await;
+        assert_eq!(read_exact(&cch.read_channel, 3).await, [IAC, IAC, b'b']);
+        assert_eq!(bridge.stats().baud_rate, Some(115_200));
+    });
+    assert_eq!(baud, [115_200]);
+}
+
+/// serial port that is closed or fails at once, counts the reads
+struct Broken {
+    /// fail instead of returning 0 bytes
+    err: bool,
+    /// read count
+    reads: u32,
+}
+
+/// support error type
+impl ErrorType for Broken {
+    type Error = ErrorKind;
+}
+
+/// support read
+impl Read for Broken {
+    async fn read(&mut self, _buf: &mut [u8]) -> Result<usize, ErrorKind> {
+        self.reads += 1;
+        // a spinning bridge would never yield, stop it here so that the test fails instead of hanging
+        if self.reads > 100 { core::future::pending::<()>().await; }
+        if self.err { Err(ErrorKind::BrokenPipe) } else { Ok(0) }
+    }
+}
+
+#[test]
+fn broken_serial() {
+    for err in [false, true] {
+        let ch = harness::channel::<1024, 1024>(8, 8);
+        let bridge = SerialBridge::new(ch, BridgeConfig::new(Duration::from_millis(20)));
+        let (mut rx, mut tx) = (Broken { err, reads: 0 }, leak(Serial::new()));
+        match harness::block_on(select(bridge.run(&mut rx, &mut tx), Timer::after_millis(100))) {
+            Either::Second(_) => {}
+            Either::First(_) => panic!("bridge returned"),
+        }
+        // one read per gap
+        assert!((2..=8).contains(&rx.reads), "{} reads", rx.reads);
+        let stats = bridge.stats();
+        assert_eq!((stats.serial_in, stats.packets), (0, 0));
+        assert_eq!(stats.serial_errors, if err { rx.reads } else { 0 });
+    }
+}
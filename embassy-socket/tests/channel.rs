@@ -0,0 +1,210 @@
+//! read and write channels without the network
+
+mod harness;
+
+use std::cell::Cell;
+use embassy_futures::join::join;
+use embassy_futures::yield_now;
+use embassy_socket::channel::callback_enum::CallbackEnum;
+use embassy_socket::channel::overflow_policy::OverflowPolicy;
+use embassy_socket::channel::socket_msg::SocketMsg;
+use embassy_socket::err::SocketErr;
+use harness::block_on;
+
+#[test]
+fn recv_is_one_msg() {
+    let ch = harness::channel::<16, 4>(4, 1);
+    block_on(async {
+        // one recv is one msg however large it is, only bytes larger than the whole channel are split
+        ch.read_channel.recv(b"0123456789").await;
+        let msg = ch.read_channel.wait_read_ref().await;
+        assert!(matches!(msg.callback_enum(), CallbackEnum::Recv));
+        assert_eq!(msg.as_bytes(), b"0123456789");
+        drop(msg);
+        assert!(ch.read_channel.is_empty().await);
+    });
+}
+
+#[test]
+fn try_recv_is_one_msg() {
+    let ch = harness::channel::<8, 4>(2, 1);
+    block_on(async {
+        assert_eq!(ch.read_channel.try_recv(b"0123"), 4);
+        // only the largest free part is used, the rest stays in the socket
+        assert_eq!(ch.read_channel.try_recv(b"456789"), 4);
+        // full, block policy uses nothing, drop newest drops the data
+        ch.read_channel.overflow_policy(OverflowPolicy::Block);
+        assert_eq!(ch.read_channel.try_recv(b"ab"), 0);
+        ch.read_channel.overflow_policy(OverflowPolicy::DropNewest);
+        assert_eq!(ch.read_channel.try_recv(b"ab"), 2);
+        assert_eq!(ch.read_channel.dropped(), 1);
+        assert!(matches!(ch.read_channel.wait_read_ref().await.callback_enum(), CallbackEnum::Dropped(1)));
+
+        let msg = ch.read_channel.wait_read_ref().await;
+        assert_eq!(msg.as_bytes(), b"0123");
+        drop(msg);
+        // the freed start of the channel is reused, the msg data stays contiguous
+        assert_eq!(ch.read_channel.try_recv(b"abcdef"), 4);
+        let mut msg = SocketMsg::<8>::default();
+        for expected in [&b"4567"[..], b"abcd"] {
+            assert!(ch.read_channel.read(&mut msg).await);
+            assert_eq!(msg.as_bytes(), expected);
+        }
+        assert!(ch.read_channel.is_empty().await);
+        assert_eq!(ch.read_channel.try_recv(b"01234567"), 8);
+    });
+}
+
+#[test]
+fn borrowed_msg_while_sending() {
+    let ch = harness::channel::<16, 4>(4, 1);
+    block_on(async {
+        ch.read_channel.recv(b"0123").await;
+        let msg = ch.read_channel.wait_read_ref().await;
+        // the free part of the channel is filled while the front msg is borrowed
+        assert_eq!(ch.read_channel.try_recv(b"456789"), 6);
+        ch.read_channel.recv(b"abcdef").await;
+        assert_eq!(msg.as_bytes(), b"0123");
+        drop(msg);
+
+        // the freed start is filled while the next msg is borrowed
+        let msg = ch.read_channel.wait_read_ref().await;
+        assert_eq!(ch.read_channel.try_recv(b"ghij"), 4);
+        assert_eq!(msg.as_bytes(), b"456789");
+        drop(msg);
+        for expected in [&b"abcdef"[..], b"ghij"] {
+            assert_eq!(ch.read_channel.wait_read_ref().await.as_bytes(), expected);
+        }
+    });
+}
+
+#[test]
+fn msg_count_limit() {
+    let ch = harness::channel::<64, 4>(2, 1);
+    ch.read_channel.overflow_policy(OverflowPolicy::Block);
+    block_on(async {
+        assert_eq!(ch.read_channel.try_recv(b"a"), 1);
+        assert_eq!(ch.read_channel.try_recv(b"b"), 1);
+        // there is room for bytes but not for another msg
+        assert!(ch.read_channel.is_full().await);
+        assert_eq!(ch.read_channel.try_recv(b"c"), 0);
+    });
+}
+
+#[test]
+fn drop_oldest() {
+    let ch = harness::channel::<4, 4>(2, 1);
+    ch.read_channel.overflow_policy(OverflowPolicy::DropOldest);
+    block_on(async {
+        ch.read_channel.recv(b"a").await;
+        ch.read_channel.recv(b"b").await;
+        ch.read_channel.recv(b"c").await;
+
+        // the dropped count is reported first, then the newest data
+        let mut msg = SocketMsg::<4>::new([0; 4], 0);
+        ch.read_channel.wait_read(&mut msg).await;
+        assert!(matches!(msg.callback_enum, CallbackEnum::Dropped(1)));
+        ch.read_channel.wait_read(&mut msg).await;
+        assert_eq!(msg.as_bytes(), b"b");
+        ch.read_channel.wait_read(&mut msg).await;
+        assert_eq!(msg.as_bytes(), b"c");
+    });
+}
+
+#[test]
+fn borrowed_msg_blocks_copy_read() {
+    let ch = harness::channel::<4, 4>(2, 1);
+    block_on(async {
+        ch.read_channel.recv(b"a").await;
+        ch.read_channel.recv(b"b").await;
+        let mut msg = SocketMsg::<4>::new([0; 4], 0);
+        let borrowed = ch.read_channel.wait_read_ref().await;
+        assert!(!ch.read_channel.read(&mut msg).await);
+        assert_eq!(borrowed.as_bytes(), b"a");
+
+        drop(borrowed);
+        assert!(ch.read_channel.read(&mut msg).await);
+        assert_eq!(msg.as_bytes(), b"b");
+    });
+}
+
+#[test]
+fn write_channel_enable() {
+    let ch = harness::channel::<4, 4>(1, 4);
+    block_on(async {
+        assert!(matches!(ch.write_channel.send_str("a").await, Err(SocketErr::NotConnected)));
+        ch.write_channel.enable().await;
+        assert!(ch.write_channel.send_str("a").await.is_ok());
+        assert!(!ch.write_channel.is_empty().await);
+
+        // disable discards the queued data
+        ch.write_channel.disable().await;
+        assert!(ch.write_channel.is_empty().await);
+        assert!(matches!(ch.write_channel.send_str("a").await, Err(SocketErr::NotConnected)));
+    });
+}
+
+#[test]
+fn write_channel_errors() {
+    let ch = harness::channel::<4, 4>(1, 2);
+    block_on(async {
+        ch.write_channel.enable().await;
+        // the channel holds 2 msg of 4 bytes, larger payload is not queued at all
+        assert!(matches!(ch.write_channel.send_bytes(&[0; 9]).await, Err(SocketErr::PayloadTooLarge)));
+        assert!(ch.write_channel.is_empty().await);
+        assert!(ch.write_channel.send_bytes(&[0; 8]).await.is_ok());
+        assert!(matches!(ch.write_channel.send_bytes(&[0; 1]).await, Err(SocketErr::QueueFull)));
+        assert_eq!(ch.write_channel.dropped(), 1);
+    });
+}
+
+#[test]
+fn flush_waits_for_write() {
+    let ch = harness::channel::<4, 4>(1, 4);
+    block_on(async {
+        ch.write_channel.enable().await;
+        assert!(ch.write_channel.flush().await.is_ok());
+
+        ch.write_channel.send_bytes(b"abcdef").await.unwrap();
+        let flushed = Cell::new(false);
+        let flush = async {
+            let result = ch.write_channel.flush().await;
+            flushed.set(true);
+            result
+        };
+        // the runner writes two msg
+        let runner = async {
+            let mut msg = SocketMsg::default();
+            for _ in 0..2 {
+                ch.write_channel.take(&mut msg).await;
+                yield_now().await;
+                assert!(!flushed.get());
+                ch.write_channel.write_done(true);
+            }
+        };
+        assert!(join(flush, runner).await.0.is_ok());
+    });
+}
+
+#[test]
+fn flush_reports_failure() {
+    let ch = harness::channel::<4, 4>(1, 4);
+    block_on(async {
+        ch.write_channel.enable().await;
+        ch.write_channel.send_bytes(b"a").await.unwrap();
+        let runner = async {
+            ch.write_channel.take(&mut SocketMsg::default()).await;
+            ch.write_channel.write_done(false);
+        };
+        assert!(matches!(join(ch.write_channel.flush(), runner).await.0, Err(SocketErr::NotConnected)));
+
+        // disable discards the queued data, it will never be written
+        ch.write_channel.send_bytes(b"b").await.unwrap();
+        let disable = async {
+            yield_now().await;
+            ch.write_channel.disable().await;
+        };
+        assert!(matches!(join(ch.write_channel.flush(), disable).await.0, Err(SocketErr::NotConnected)));
+        assert!(matches!(ch.write_channel.flush().await, Err(SocketErr::NotConnected)));
+    });
+}
@@ -0,0 +1,52 @@
+//! frame decoder
+#![cfg(feature = "framing")]
+
+use embassy_socket::err::SocketErr;
+use embassy_socket::framing::decoder::FrameDecoder;
+use embassy_socket::framing::Framing;
+
+/// decode every chunk, returns the frames in order, None is a frame too large
+fn decode_all<const N: usize>(decoder: &mut FrameDecoder<N>, chunks: &[&[u8]]) -> Vec<Option<Vec<u8>>> {
+    let mut frames = Vec::new();
+    for chunk in chunks {
+        let mut bytes = *chunk;
+        while !bytes.is_empty() {
+            let (used, frame) = decoder.decode(bytes);
+            match frame {
+                Some(Ok(frame)) => frames.push(Some(frame.to_vec())),
+                Some(Err(SocketErr::FrameTooLarge)) => frames.push(None),
+                Some(Err(e)) => panic!("unexpected error {e:?}"),
+                None => {}
+            }
+            bytes = &bytes[used..];
+        }
+    }
+    frames
+}
+
+#[test]
+fn delimiter_boundary() {
+    let mut decoder = FrameDecoder::<8>::new(Framing::crlf());
+    // a frame of exactly N bytes fits, the delimiter is not stored
+    let frames = decode_all(&mut decoder, &[b"1234567\r\n", b"12345678\r\n", b"123456789\r\n", b"ok\r\n"]);
+    assert_eq!(frames, [Some(b"1234567".to_vec()), Some(b"12345678".to_vec()), None, Some(b"ok".to_vec())]);
+}
+
+#[test]
+fn delimiter_split() {
+    let mut decoder = FrameDecoder::<8>::new(Framing::crlf());
+    // the delimiter split across reads, and a lone `\r` is frame data
+    let frames = decode_all(&mut decoder, &[b"ab\r", b"\nc\rd\r", b"\r\n"]);
+    assert_eq!(frames, [Some(b"ab".to_vec()), Some(b"c\rd\r".to_vec())]);
+
+    // partial matches of an overlapping delimiter are frame data
+    let mut decoder = FrameDecoder::<8>::new(Framing::Delimiter(b"aab"));
+    let frames = decode_all(&mut decoder, &[b"xaa", b"ab", b"aaaab"]);
+    assert_eq!(frames, [Some(b"xa".to_vec()), Some(b"aa".to_vec())]);
+}
+
+#[test]
+#[should_panic(expected = "empty delimiter")]
+fn delimiter_empty() {
+    FrameDecoder::<8>::new(Framing::Delimiter(b""));
+}
@@ -0,0 +1,197 @@
+//! std test harness<br />
+//! two embassy-net stacks connected by an in-memory ethernet link, driven by a single thread executor,
+//! so that the tcp runners can be tested on the host with `cargo test`
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::net::Ipv4Addr;
+use std::pin::pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use embassy_futures::select::{select, Either};
+use embassy_net::{Config, Ipv4Cidr, Runner, Stack, StackResources, StaticConfigV4};
+use embassy_net_driver::{Capabilities, Driver, HardwareAddress, LinkState, RxToken, TxToken};
+use embassy_socket::channel::read_channel::{MsgHead, ReadChannel};
+use embassy_socket::channel::write_channel::WriteChannel;
+use embassy_socket::channel::socket_msg::SocketMsg;
+use embassy_socket::channel::SocketChannel;
+use embassy_socket::channel::callback_enum::CallbackEnum;
+use embassy_time::{with_timeout, Duration};
+
+/// client stack ip
+pub const CLIENT_IP: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+/// server stack ip
+pub const SERVER_IP: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 2);
+/// every test must finish within this time
+pub const TEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// one direction of the link
+#[derive(Default)]
+struct Queue {
+    /// ethernet frames in flight
+    frames: VecDeque<Vec<u8>>,
+    /// waker of the receiving stack
+    waker: Option<Waker>,
+}
+
+/// shared queue
+type Link = Arc<Mutex<Queue>>;
+
+/// in-memory ethernet driver, frames sent by one end are received by the other end
+pub struct PipeDriver {
+    /// mac address
+    mac: [u8; 6],
+    /// frames to receive
+    rx: Link,
+    /// frames to send
+    tx: Link,
+}
+
+/// create two connected drivers
+fn pipe() -> (PipeDriver, PipeDriver) {
+    let (a, b) = (Link::default(), Link::default());
+    let client = PipeDriver { mac: [0x02, 0, 0, 0, 0, 1], rx: a.clone(), tx: b.clone() };
+    let server = PipeDriver { mac: [0x02, 0, 0, 0, 0, 2], rx: b, tx: a };
+    (client, server)
+}
+
+/// received frame
+pub struct PipeRx(Vec<u8>);
+
+/// frame sender
+pub struct PipeTx(Link);
+
+/// support rx token
+impl RxToken for PipeRx {
+    fn consume<R, F: FnOnce(&mut [u8]) -> R>(mut self, f: F) -> R {
+        f(&mut self.0)
+    }
+}
+
+/// support tx token
+impl TxToken for PipeTx {
+    fn consume<R, F: FnOnce(&mut [u8]) -> R>(self, len: usize, f: F) -> R {
+        let mut frame = vec![0; len];
+        let r = f(&mut frame);
+        let mut queue = self.0.lock().unwrap();
+        queue.frames.push_back(frame);
+        if let Some(waker) = queue.waker.take() { waker.wake(); }
+        r
+    }
+}
+
+/// support embassy-net driver
+impl Driver for PipeDriver {
+    type RxToken<'a> = PipeRx;
+    type TxToken<'a> = PipeTx;
+
+    fn receive(&mut self, cx: &mut Context) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        let mut queue = self.rx.lock().unwrap();
+        match queue.frames.pop_front() {
+            Some(frame) => Some((PipeRx(frame), PipeTx(self.tx.clone()))),
+            None => {
+                queue.waker = Some(cx.waker().clone());
+                None
+            }
+        }
+    }
+
+    fn transmit(&mut self, _cx: &mut Context) -> Option<Self::TxToken<'_>> {
+        Some(PipeTx(self.tx.clone()))
+    }
+
+    fn link_state(&mut self, _cx: &mut Context) -> LinkState {
+        LinkState::Up
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        let mut capabilities = Capabilities::default();
+        capabilities.max_transmission_unit = 1514;
+        capabilities
+    }
+
+    fn hardware_address(&self) -> HardwareAddress {
+        HardwareAddress::Ethernet(self.mac)
+    }
+}
+
+/// leak value to get the static lifetime required by the runners
+pub fn leak<T>(t: T) -> &'static mut T {
+    Box::leak(Box::new(t))
+}
+
+/// create socket channel with `RC_SZ` read bytes shared by `r` read msg and `w` write msg
+pub fn channel<const RC_SZ: usize, const WC_SZ: usize>(r: usize, w: usize) -> &'static SocketChannel<'static, RC_SZ, WC_SZ> {
+    let read_channel = ReadChannel::from_parts(leak([0; RC_SZ]), leak(vec![MsgHead::default(); r]));
+    let write_buf = leak(vec![SocketMsg::new([0; WC_SZ], 0); w]);
+    leak(SocketChannel { read_channel, write_channel: WriteChannel::new(write_buf) })
+}
+
+/// create stack with static ip
+fn stack(driver: PipeDriver, ip: Ipv4Addr, seed: u64) -> (Stack<'static>, Runner<'static, PipeDriver>) {
+    let address = Ipv4Cidr::new(ip, 24);
+    let config = Config::ipv4_static(StaticConfigV4 { address, gateway: None, dns_servers: Default::default() });
+    embassy_net::new(driver, config, leak(StackResources::<8>::new()), seed)
+}
+
+/// thread waker, unpark the executor thread
+struct ThreadWaker(Thread);
+
+/// support wake
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// run future on the current thread until it is ready
+pub fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = pin!(fut);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(r) = fut.as_mut().poll(&mut cx) { return r; }
+        thread::park();
+    }
+}
+
+/// create the client and server stacks, run the network and the test body<br />
+/// panics if the body does not finish within [TEST_TIMEOUT]
+pub fn run<F: Future>(body: impl FnOnce(Stack<'static>, Stack<'static>) -> F) -> F::Output {
+    let (client_driver, server_driver) = pipe();
+    let (client, mut client_runner) = stack(client_driver, CLIENT_IP, 1);
+    let (server, mut server_runner) = stack(server_driver, SERVER_IP, 2);
+
+    let net = select(client_runner.run(), server_runner.run());
+    match block_on(select(net, with_timeout(TEST_TIMEOUT, body(client, server)))) {
+        Either::Second(Ok(r)) => r,
+        Either::Second(Err(_)) => panic!("test timed out"),
+    }
+}
+
+/// wait for the next msg that is not data, returns its callback logic
+pub async fn wait_event<const N: usize>(rch: &ReadChannel<'_, N>) -> CallbackEnum {
+    loop {
+        let msg = rch.wait_read_ref().await;
+        if !matches!(msg.callback_enum(), CallbackEnum::Recv) { return msg.callback_enum(); }
+    }
+}
+
+/// read exactly len bytes, panics on any other event<br />
+/// every msg is checked to be no larger than N
+pub async fn read_exact<const N: usize>(rch: &ReadChannel<'_, N>, len: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(len);
+    while data.len() < len {
+        let msg = rch.wait_read_ref().await;
+        match msg.callback_enum() {
+            CallbackEnum::Recv => {
+                assert!(msg.as_bytes().len() <= N);
+                data.extend_from_slice(msg.as_bytes());
+            }
+            other => panic!("unexpected event {other:?} after {} bytes", data.len()),
+        }
+    }
+    data
+}
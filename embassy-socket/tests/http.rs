@@ -0,0 +1,242 @@
+//! http request parser, and the http server over the in-memory link
+#![cfg(feature = "http")]
+
+mod harness;
+
+use embassy_futures::select::{select4, Either4};
+use embassy_socket::channel::callback_enum::CallbackEnum;
+use embassy_socket::channel::read_channel::ReadChannel;
+use embassy_socket::channel::SocketChannel;
+use embassy_socket::connection::socket_state::SocketState;
+use embassy_socket::http::request::{Method, ParseErr, Request};
+use embassy_socket::http::response::Response;
+use embassy_socket::http::{HttpHandler, HttpServer, HttpState, Route};
+use embassy_socket::tcp_client;
+use embassy_socket::tcp_server;
+use embassy_socket::tcp_server::callback_runner::CallbackRunner;
+use embassy_time::Timer;
+use harness::{leak, wait_event, SERVER_IP};
+
+/// socket state of the tests
+type State = SocketState<1, 4096, 4096, 1024>;
+/// socket channel of the tests
+type Channel = SocketChannel<'static, 512, 512>;
+/// server port
+const PORT: u16 = 80;
+/// max request size of the server
+const REQ_SZ: usize = 256;
+
+/// pages of the test server
+#[derive(Copy, Clone)]
+enum Page {
+    /// fixed text
+    Index,
+    /// respond the request body
+    Echo,
+    /// respond the request path
+    Api,
+}
+
+/// route table of the test server
+static ROUTES: [Route<Page>; 3] = [
+    Route::new(Method::Get, "/", Page::Index),
+    Route::new(Method::Post, "/echo", Page::Echo),
+    Route::prefix(Method::Get, "/api/", Page::Api),
+];
+
+/// test handler
+struct Handler;
+
+/// support http handler
+impl HttpHandler for Handler {
+    type Route = Page;
+
+    async fn handle<const CN: usize>(&self, route: Page, req: &Request<'_>, resp: &mut Response<'_, '_, CN>) {
+        match route {
+            Page::Index => resp.send(200, "text/plain", b"hello").await,
+            Page::Echo => resp.send(200, "application/octet-stream", req.body).await,
+            Page::Api => resp.send(200, "text/plain", req.path.as_bytes()).await,
+        };
+    }
+}
+
+/// raw http client, keeps the data after the last response
+struct Client {
+    /// read channel of the tcp client
+    rch: &'static ReadChannel<'static, 512>,
+    /// data received but not returned yet
+    buf: Vec<u8>,
+}
+
+/// custom method
+impl Client {
+    /// read one response, returns the head without the empty line and the body<br />
+    /// the response of HEAD has no body, panics on any event other than data
+    async fn response(&mut self, head_only: bool) -> (String, Vec<u8>) {
+        let head_len = loop {
+            if let Some(pos) = self.buf.windows(4).position(|w| w == b"\r\n\r\n") { break pos; }
+            self.recv().await;
+        };
+        let head = String::from_utf8(self.buf[..head_len].to_vec()).unwrap();
+        let body_len = match head_only {
+            true => 0,
+            false => head.lines()
+                .find_map(|line| line.strip_prefix("Content-Length: "))
+                .map_or(0, |len| len.parse().unwrap()),
+        };
+        while self.buf.len() < head_len + 4 + body_len { self.recv().await; }
+        let body = self.buf[head_len + 4..head_len + 4 + body_len].to_vec();
+        self.buf.drain(..head_len + 4 + body_len);
+        (head, body)
+    }
+
+    /// receive data to the buf
+    async fn recv(&mut self) {
+        let msg = self.rch.wait_read_ref().await;
+        match msg.callback_enum() {
+            CallbackEnum::Recv => self.buf.extend_from_slice(msg.as_bytes()),
+            other => panic!("unexpected event {other:?}"),
+        }
+    }
+
+    /// wait until the server closes the connection and the client connects again
+    async fn reconnect(&mut self) {
+        assert!(self.buf.is_empty());
+        while !matches!(wait_event(self.rch).await, CallbackEnum::Disconnect) {}
+        while !matches!(wait_event(self.rch).await, CallbackEnum::Conn) {}
+    }
+}
+
+/// run the http server, a raw tcp client and the test body until the body returns
+async fn with_server<R, F: Future<Output=R>>(client_stack: embassy_net::Stack<'static>,
+                                             server_stack: embassy_net::Stack<'static>,
+                                             body: impl FnOnce(&'static Channel, Client) -> F) -> R {
+    let (cch, sch): (&Channel, &Channel) = (harness::channel(8, 8), harness::channel(8, 8));
+    let client = tcp_client::build_runner(client_stack, SERVER_IP, PORT, cch, leak(State::new()));
+    let server = tcp_server::build_runner(server_stack, PORT, sch, leak(State::new()));
+    let mut callback = CallbackRunner::new(sch, leak(HttpServer::<_, REQ_SZ>::new(&ROUTES, Handler)));
+    let mut state = HttpState::new();
+    let body = async {
+        assert!(matches!(wait_event(&cch.read_channel).await, CallbackEnum::Conn));
+        body(cch, Client { rch: &cch.read_channel, buf: Vec::new() }).await
+    };
+    match select4(client.run(), server.run(), callback.run(&mut state), body).await {
+        Either4::Fourth(r) => r,
+        _ => panic!("runner returned"),
+    }
+}
+
+#[test]
+fn parse_request() {
+    let req = Request::parse(b"GET /api/led?on=1&name=a%20b&flag HTTP/1.1\r\nHost: 10.0.0.2\r\ncontent-length:  12 \r\nConnection: Close").unwrap();
+    assert_eq!(req.method, Method::Get);
+    assert_eq!((req.path, req.query, req.minor_version), ("/api/led", Some("on=1&name=a%20b&flag"), 1));
+    assert_eq!(req.headers(), [("Host", "10.0.0.2"), ("content-length", "12"), ("Connection", "Close")]);
+    // header names are case-insensitive, query values are not decoded
+    assert_eq!(req.header("Content-Length"), Some("12"));
+    assert_eq!(req.content_length(), Ok(12));
+    assert_eq!((req.query_param("on"), req.query_param("name"), req.query_param("flag"), req.query_param("off")), (Some("1"), Some("a%20b"), Some(""), None));
+    assert!(!req.keep_alive());
+
+    // HTTP/1.1 keeps alive by default, HTTP/1.0 only with keep-alive
+    let req = Request::parse(b"HEAD / HTTP/1.1").unwrap();
+    assert_eq!((req.method, req.query, req.content_length()), (Method::Head, None, Ok(0)));
+    assert!(req.keep_alive());
+    assert!(!Request::parse(b"GET / HTTP/1.0").unwrap().keep_alive());
+    assert!(Request::parse(b"GET / HTTP/1.0\r\nConnection: keep-alive").unwrap().keep_alive());
+}
+
+#[test]
+fn parse_request_err() {
+    let err = |head: &[u8]| Request::parse(head).err();
+    assert_eq!(err(b"GET /"), Some(ParseErr::BadRequest));
+    assert_eq!(err(b"GET / HTTP/1.1 x"), Some(ParseErr::BadRequest));
+    assert_eq!(err(b"GET / HTTP/1.1\r\nno colon"), Some(ParseErr::BadRequest));
+    assert_eq!(err(b"GET /\xFF HTTP/1.1"), Some(ParseErr::BadRequest));
+    assert_eq!(err(b"BREW / HTTP/1.1"), Some(ParseErr::NotImplemented));
+    assert_eq!(err(b"GET / HTTP/2.0"), Some(ParseErr::VersionNotSupported));
+    assert_eq!(Request::parse(b"POST / HTTP/1.1\r\nContent-Length: -1").unwrap().content_length(), Err(ParseErr::BadRequest));
+
+    // 16 headers at most
+    let mut head = b"GET / HTTP/1.1".to_vec();
+    for i in 0..16 { head.extend_from_slice(format!("\r\nX-{i}: {i}").as_bytes()); }
+    assert_eq!(Request::parse(&head).unwrap().headers().len(), 16);
+    head.extend_from_slice(b"\r\nX-16: 16");
+    assert_eq!(err(&head), Some(ParseErr::TooManyHeaders));
+}
+
+#[test]
+fn routes() {
+    harness::run(|client_stack, server_stack| with_server(client_stack, server_stack, |cch, mut client| async move {
+        let wch = &cch.write_channel;
+        wch.send_bytes(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+        let (head, body) = client.response(false).await;
+        assert_eq!(head, "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 5\r\nConnection: keep-alive");
+        assert_eq!(body, b"hello");
+
+        // prefix route, and the query is not part of the path
+        wch.send_bytes(b"GET /api/led?on=1 HTTP/1.1\r\n\r\n").await.unwrap();
+        assert_eq!(client.response(false).await.1, b"/api/led");
+
+        // unknown path, and the path matched with another method, the connection is kept
+        wch.send_bytes(b"GET /missing HTTP/1.1\r\n\r\n").await.unwrap();
+        assert!(client.response(false).await.0.starts_with("HTTP/1.1 404 "));
+        wch.send_bytes(b"DELETE / HTTP/1.1\r\n\r\n").await.unwrap();
+        assert!(client.response(false).await.0.starts_with("HTTP/1.1 405 "));
+
+        // HEAD is answered by the GET route without body, the next response follows the head directly
+        wch.send_bytes(b"HEAD / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\n\r\n").await.unwrap();
+        let (head, _) = client.response(true).await;
+        assert!(head.starts_with("HTTP/1.1 200 ") && head.contains("Content-Length: 5"));
+        assert_eq!(client.response(false).await, ("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 5\r\nConnection: keep-alive".into(), b"hello".to_vec()));
+
+        // HTTP/1.0 closes after the response
+        wch.send_bytes(b"GET / HTTP/1.0\r\n\r\n").await.unwrap();
+        assert!(client.response(false).await.0.ends_with("Connection: close"));
+        client.reconnect().await;
+    }));
+}
+
+#[test]
+fn request_too_large() {
+    harness::run(|client_stack, server_stack| with_server(client_stack, server_stack, |cch, mut client| async move {
+        let wch = &cch.write_channel;
+        // the body does not fit in the request buf, answered before the body is received
+        wch.send_bytes(b"POST /echo HTTP/1.1\r\nContent-Length: 1000\r\n\r\n").await.unwrap();
+        let (head, _) = client.response(false).await;
+        assert!(head.starts_with("HTTP/1.1 413 ") && head.ends_with("Connection: close"));
+        client.reconnect().await;
+
+        // the request buf is full before the end of the head
+        let mut big = b"GET / HTTP/1.1\r\nX-Big: ".to_vec();
+        big.resize(REQ_SZ, b'a');
+        wch.send_bytes(&big).await.unwrap();
+        let (head, _) = client.response(false).await;
+        assert!(head.starts_with("HTTP/1.1 431 ") && head.ends_with("Connection: close"));
+        client.reconnect().await;
+
+        // the new connection starts with an empty request buf
+        wch.send_bytes(b"POST /echo HTTP/1.1\r\nContent-Length: 3\r\n\r\nabc").await.unwrap();
+        assert_eq!(client.response(false).await.1, b"abc");
+    }));
+}
+
+#[test]
+fn pipelined_split() {
+    harness::run(|client_stack, server_stack| with_server(client_stack, server_stack, |cch, mut client| async move {
+        let wch = &cch.write_channel;
+        // two pipelined requests, split inside the head and inside the body of the second one
+        let parts: [&[u8]; 3] = [b"GET / HTTP/1.1\r\nHost: a\r\n\r\nPOST /echo HTTP/1.1\r\nContent-Le", b"ngth: 4\r\n\r\nab", b"cd"];
+        for part in parts {
+            wch.send_bytes(part).await.unwrap();
+            wch.flush().await.unwrap();
+            Timer::after_millis(20).await;
+        }
+        assert_eq!(client.response(false).await.1, b"hello");
+        assert_eq!(client.response(false).await.1, b"abcd");
+
+        // the connection is still alive
+        wch.send_bytes(b"GET /api/x HTTP/1.1\r\n\r\n").await.unwrap();
+        assert_eq!(client.response(false).await.1, b"/api/x");
+    }));
+}
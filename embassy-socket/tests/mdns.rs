@@ -0,0 +1,137 @@
+//! mdns message, records answers and mdns responder over the in-memory link
+#![cfg(feature = "mdns")]
+
+mod harness;
+
+use embassy_futures::select::{select3, Either3};
+use embassy_net::{IpAddress, IpEndpoint, Ipv4Address};
+use embassy_socket::channel::callback_enum::CallbackEnum;
+use embassy_socket::connection::udp_state::UdpSocketState;
+use embassy_socket::mdns::message::{read_u16, Name, Writer, CLASS_FLAG, CLASS_IN, HEADER_LEN, TYPE_A, TYPE_PTR};
+use embassy_socket::mdns::{MdnsResponder, Records, Service, MDNS_PORT, SERVICES_NAME};
+use embassy_socket::udp;
+use harness::{leak, SERVER_IP};
+
+/// udp state of the tests
+type State = UdpSocketState<1, 4, 1024, 1024, 512>;
+/// host address of the pure tests
+const ADDR: Ipv4Address = Ipv4Address::new(10, 0, 0, 2);
+/// services of the tests
+const SERVICES: &[Service] = &[
+    Service::new("_http._tcp", 80).txt(&["path=/"]),
+    Service::new("_modbus._tcp", 502).instance("plc"),
+];
+
+/// query with one question
+fn query(id: u16, name: &[&str], qtype: u16, unicast: bool) -> Vec<u8> {
+    let mut buf = [0; 256];
+    let mut w = Writer::new(&mut buf);
+    w.name(name);
+    w.u16(qtype);
+    w.u16(if unicast { CLASS_IN | CLASS_FLAG } else { CLASS_IN });
+    w.header(id, 0, [1, 0, 0]);
+    let len = w.len();
+    buf[..len].to_vec()
+}
+
+/// header counts, questions, answers and additional records
+fn counts(msg: &[u8]) -> [u16; 3] {
+    [read_u16(msg, 4).unwrap(), read_u16(msg, 6).unwrap(), read_u16(msg, 10).unwrap()]
+}
+
+#[test]
+fn name_matches() {
+    // `device.local` then `_http._tcp` with a pointer to `local`
+    let msg = [6, b'D', b'e', b'v', b'i', b'c', b'e', 5, b'l', b'o', b'c', b'a', b'l', 0, 5, b'_', b'h', b't', b't', b'p', 4, b'_', b't', b'c', b'p', 0xC0, 7];
+    assert!(Name::new(&msg, 0).matches(&["device", "local"]));
+    assert!(!Name::new(&msg, 0).matches(&["device"]));
+    assert!(Name::new(&msg, 14).matches(&["_http._tcp", "local"]));
+    assert_eq!(Name::new(&msg, 14).end(), Some(msg.len()));
+    // pointer loop
+    assert!(!Name::new(&[0xC0, 0], 0).matches(&["local"]));
+}
+
+#[test]
+fn records_answer() {
+    let records = Records::new("device", SERVICES);
+    let mut out = [0; 512];
+
+    let (len, unicast) = records.answer(&query(0, &["DEVICE", "local"], TYPE_A, false), ADDR, false, &mut out).unwrap();
+    assert!(!unicast);
+    assert_eq!(counts(&out[..len]), [0, 1, 0]);
+    assert!(Name::new(&out, HEADER_LEN).matches(&["device", "local"]));
+    assert_eq!(out[len - 4..len], ADDR.octets());
+
+    // the pointer is answered with srv, txt and address as additional records
+    let (len, unicast) = records.answer(&query(0, &["_http._tcp", "local"], TYPE_PTR, true), ADDR, false, &mut out).unwrap();
+    assert!(unicast);
+    assert_eq!(counts(&out[..len]), [0, 1, 3]);
+
+    let (len, _) = records.answer(&query(0, &[SERVICES_NAME], TYPE_PTR, false), ADDR, false, &mut out).unwrap();
+    assert_eq!(counts(&out[..len]), [0, 2, 0]);
+
+    // legacy query, id and question are repeated, the ttl is limited
+    let legacy = query(0x1234, &["device", "local"], TYPE_A, false);
+    let (len, unicast) = records.answer(&legacy, ADDR, true, &mut out).unwrap();
+    assert!(unicast);
+    assert_eq!(read_u16(&out, 0), Some(0x1234));
+    assert_eq!(counts(&out[..len]), [1, 1, 0]);
+    assert_eq!(out[HEADER_LEN..legacy.len()], legacy[HEADER_LEN..]);
+    // the record name `device.local` is 14 bytes, then type, class and ttl
+    let record = legacy.len() + 14;
+    assert_eq!(read_u16(&out, record + 2), Some(CLASS_IN));
+    assert_eq!(out[record + 4..record + 8], 10u32.to_be_bytes());
+
+    // other hosts and responses are not answered
+    assert!(records.answer(&query(0, &["other", "local"], TYPE_A, false), ADDR, false, &mut out).is_none());
+    let mut response = query(0, &["device", "local"], TYPE_A, false);
+    response[2] = 0x84;
+    assert!(records.answer(&response, ADDR, false, &mut out).is_none());
+}
+
+#[test]
+fn records_announce() {
+    let records = Records::new("device", SERVICES);
+    let mut out = [0; 512];
+    // address, two service types, and pointer, srv and txt of each service
+    let len = records.announce(ADDR, &mut out);
+    assert_eq!(counts(&out[..len]), [0, 9, 0]);
+
+    // the records that do not fit are dropped
+    let len = records.announce(ADDR, &mut out[..64]);
+    assert_eq!(counts(&out[..len]), [0, 1, 0]);
+}
+
+#[test]
+fn responder_legacy_query() {
+    harness::run(|client_stack, server_stack| async move {
+        let cch = harness::channel::<512, 512>(4, 4);
+        let client = udp::build_runner(client_stack, 0, cch, leak(State::new()));
+        let responder = MdnsResponder::<512>::new(server_stack, leak(State::new()), Records::new("device", SERVICES));
+        let server = IpEndpoint::new(IpAddress::Ipv4(SERVER_IP), MDNS_PORT);
+
+        let body = async {
+            let mut answers = Vec::new();
+            for (id, name, qtype) in [(1, &["device", "local"][..], TYPE_A), (2, &["_modbus._tcp", "local"][..], TYPE_PTR)] {
+                cch.write_channel.send_bytes_to(&query(id, name, qtype, false), server).await.unwrap();
+                loop {
+                    let msg = cch.read_channel.wait_read_ref().await;
+                    if !matches!(msg.callback_enum(), CallbackEnum::Recv) { continue; }
+                    answers.push(msg.as_bytes().to_vec());
+                    break;
+                }
+            }
+            answers
+        };
+        let answers = match select3(client.run(), responder.run(), body).await {
+            Either3::Third(answers) => answers,
+            _ => panic!("runner returned"),
+        };
+
+        assert_eq!((read_u16(&answers[0], 0), counts(&answers[0])), (Some(1), [1, 1, 0]));
+        assert_eq!(answers[0][answers[0].len() - 4..], SERVER_IP.octets());
+        // pointer with srv, txt and address, the srv port is the modbus port
+        assert_eq!((read_u16(&answers[1], 0), counts(&answers[1])), (Some(2), [1, 1, 3]));
+        assert!(answers[1].windows(6).any(|w| w == [0, 0, 0, 0, 0x01, 0xF6]));
+    });
+}
@@ -0,0 +1,275 @@
+//! modbus adu decoder, server function codes and client over the in-memory link
+#![cfg(feature = "modbus")]
+
+mod harness;
+
+use std::cell::RefCell;
+use std::ops::Range;
+use embassy_futures::select::{select4, Either4};
+use embassy_socket::channel::callback_enum::CallbackEnum;
+use embassy_socket::channel::SocketChannel;
+use embassy_socket::connection::socket_state::SocketState;
+use embassy_socket::err::SocketErr;
+use embassy_socket::modbus::adu::{Adu, AduDecoder};
+use embassy_socket::modbus::server::{ModbusServer, ModbusState, RegisterMap};
+use embassy_socket::modbus::{Coils, Exception, ModbusErr, MAX_ADU};
+use embassy_socket::tcp_client;
+use embassy_socket::tcp_server;
+use embassy_socket::tcp_server::callback_runner::CallbackRunner;
+use embassy_socket::modbus;
+use embassy_time::{Duration, Timer};
+use harness::{leak, read_exact, wait_event, SERVER_IP};
+
+/// socket state of the tests
+type State = SocketState<1, 4096, 4096, 1024>;
+/// socket channel of the tests
+type Channel = SocketChannel<'static, 512, 512>;
+/// server port
+const PORT: u16 = 502;
+
+/// encode adu
+fn adu(transaction_id: u16, pdu: &[u8]) -> Vec<u8> {
+    let mut buf = [0; MAX_ADU];
+    let len = Adu::encode(transaction_id, 1, pdu, &mut buf);
+    buf[..len].to_vec()
+}
+
+/// decode every chunk, returns the transaction id and pdu of the adu in order, None is an invalid frame
+fn decode_all(decoder: &mut AduDecoder, chunks: &[&[u8]]) -> Vec<Option<(u16, Vec<u8>)>> {
+    let mut adus = Vec::new();
+    for chunk in chunks {
+        let mut bytes = *chunk;
+        while !bytes.is_empty() {
+            let (used, adu) = decoder.decode(bytes);
+            bytes = &bytes[used..];
+            match adu {
+                Some(Ok(adu)) => adus.push(Some((adu.transaction_id, adu.pdu.to_vec()))),
+                Some(Err(SocketErr::InvalidFrame)) => {
+                    adus.push(None);
+                    break;
+                }
+                Some(Err(e)) => panic!("unexpected error {e:?}"),
+                None => {}
+            }
+        }
+    }
+    adus
+}
+
+/// modbus error of the result
+fn modbus_err(result: Result<(), SocketErr>) -> ModbusErr {
+    match result {
+        Err(SocketErr::ModbusError(e)) => e,
+        other => panic!("unexpected result {other:?}"),
+    }
+}
+
+/// register map of the tests, 16 coils and 16 holding registers from address 0<br />
+/// discrete inputs are the inverted coils, input registers are not supported
+#[derive(Default)]
+struct Map {
+    /// coils
+    coils: RefCell<[bool; 16]>,
+    /// holding registers
+    regs: RefCell<[u16; 16]>,
+}
+
+/// address range of the map
+fn range(addr: u16, len: usize) -> Result<Range<usize>, Exception> {
+    let start = addr as usize;
+    if start + len > 16 { return Err(Exception::IllegalDataAddress); }
+    Ok(start..start + len)
+}
+
+/// support register map
+impl RegisterMap for Map {
+    async fn read_coils(&self, _unit_id: u8, addr: u16, coils: &mut Coils<'_>) -> Result<(), Exception> {
+        for (i, coil) in self.coils.borrow()[range(addr, coils.len())?].iter().enumerate() { coils.set(i, *coil); }
+        Ok(())
+    }
+
+    async fn read_discrete_inputs(&self, _unit_id: u8, addr: u16, inputs: &mut Coils<'_>) -> Result<(), Exception> {
+        for (i, coil) in self.coils.borrow()[range(addr, inputs.len())?].iter().enumerate() { inputs.set(i, !*coil); }
+        Ok(())
+    }
+
+    async fn read_holding_registers(&self, _unit_id: u8, addr: u16, regs: &mut [u16]) -> Result<(), Exception> {
+        regs.copy_from_slice(&self.regs.borrow()[range(addr, regs.len())?]);
+        Ok(())
+    }
+
+    async fn write_coils(&self, _unit_id: u8, addr: u16, coils: &Coils<'_>) -> Result<(), Exception> {
+        let range = range(addr, coils.len())?;
+        for (i, coil) in self.coils.borrow_mut()[range].iter_mut().enumerate() { *coil = coils.get(i); }
+        Ok(())
+    }
+
+    async fn write_registers(&self, _unit_id: u8, addr: u16, regs: &[u16]) -> Result<(), Exception> {
+        self.regs.borrow_mut()[range(addr, regs.len())?].copy_from_slice(regs);
+        Ok(())
+    }
+}
+
+/// run the modbus server with the map and the test body until the body returns
+async fn with_server<R>(server_stack: embassy_net::Stack<'static>, client: impl Future<Output=()>, body: impl Future<Output=R>) -> R {
+    let sch: &Channel = harness::channel(8, 8);
+    let server = tcp_server::build_runner(server_stack, PORT, sch, leak(State::new()));
+    let mut callback = CallbackRunner::new(sch, leak(ModbusServer::new(Map::default())));
+    let mut state = ModbusState::new();
+    match select4(client, server.run(), callback.run(&mut state), body).await {
+        Either4::Fourth(r) => r,
+        _ => panic!("runner returned"),
+    }
+}
+
+#[test]
+fn adu_decoder() {
+    let mut decoder = AduDecoder::new();
+    // adu split across reads, and two adu in one read
+    let one = adu(1, &[3, 0, 0, 0, 1]);
+    let two = [adu(2, &[6, 0, 1, 0, 2]), adu(3, &[1, 0, 0, 0, 8])].concat();
+    let adus = decode_all(&mut decoder, &[&one[..4], &one[4..], &two]);
+    assert_eq!(adus, [Some((1, vec![3, 0, 0, 0, 1])), Some((2, vec![6, 0, 1, 0, 2])), Some((3, vec![1, 0, 0, 0, 8]))]);
+
+    // protocol id is not 0
+    let mut bad = adu(4, &[3, 0, 0, 0, 1]);
+    bad[3] = 1;
+    assert_eq!(decode_all(&mut decoder, &[&bad]), [None]);
+
+    // the length must cover the unit id and the function code, and fit in the max adu
+    let mut short = adu(5, &[3, 0, 0, 0, 1]);
+    short[4..6].copy_from_slice(&1u16.to_be_bytes());
+    assert_eq!(decode_all(&mut decoder, &[&short]), [None]);
+    let mut long = adu(6, &[3, 0, 0, 0, 1]);
+    long[4..6].copy_from_slice(&255u16.to_be_bytes());
+    assert_eq!(decode_all(&mut decoder, &[&long]), [None]);
+
+    // the decoder starts over after the error
+    assert_eq!(decode_all(&mut decoder, &[&one]), [Some((1, vec![3, 0, 0, 0, 1]))]);
+}
+
+#[test]
+fn server_function_codes() {
+    harness::run(|client_stack, server_stack| async move {
+        let cch: &Channel = harness::channel(8, 8);
+        let (client_runner, mut client) = modbus::build_client(client_stack, SERVER_IP, PORT, cch, leak(State::new()));
+        with_server(server_stack, client_runner.run(), async {
+            // the client reads the channel, so poll the statistics to wait for the connection
+            while cch.stats().uptime.is_none() { Timer::after_millis(5).await; }
+            // fc 5, 15 and 1
+            client.write_single_coil(3, true).await.unwrap();
+            client.write_multiple_coils(8, &[true, false, true]).await.unwrap();
+            let mut coils = [false; 11];
+            client.read_coils(0, &mut coils).await.unwrap();
+            assert_eq!(coils, [false, false, false, true, false, false, false, false, true, false, true]);
+            // fc 2
+            let mut inputs = [false; 2];
+            client.read_discrete_inputs(3, &mut inputs).await.unwrap();
+            assert_eq!(inputs, [false, true]);
+
+            // fc 6, 16 and 3
+            client.write_single_register(1, 0x1234).await.unwrap();
+            client.write_multiple_registers(4, &[5, 6]).await.unwrap();
+            let mut regs = [0; 6];
+            client.read_holding_registers(0, &mut regs).await.unwrap();
+            assert_eq!(regs, [0, 0x1234, 0, 0, 5, 6]);
+
+            // fc 4 is not supported by the map, and addresses out of the map
+            assert_eq!(modbus_err(client.read_input_registers(0, &mut [0; 1]).await), ModbusErr::Exception(Exception::IllegalFunction));
+            assert_eq!(modbus_err(client.read_holding_registers(15, &mut [0; 2]).await), ModbusErr::Exception(Exception::IllegalDataAddress));
+            assert_eq!(modbus_err(client.write_multiple_coils(15, &[true; 2]).await), ModbusErr::Exception(Exception::IllegalDataAddress));
+            // the request is checked before sending
+            assert_eq!(modbus_err(client.read_coils(0, &mut []).await), ModbusErr::InvalidRequest);
+        }).await
+    });
+}
+
+#[test]
+fn server_exceptions() {
+    harness::run(|client_stack, server_stack| async move {
+        let cch: &Channel = harness::channel(8, 8);
+        let client = tcp_client::build_runner(client_stack, SERVER_IP, PORT, cch, leak(State::new()));
+        with_server(server_stack, client.run(), async {
+            assert!(matches!(wait_event(&cch.read_channel).await, CallbackEnum::Conn));
+            let cases: [(&[u8], [u8; 2]); 7] = [
+                // unknown function code
+                (&[0x2B, 0x0E, 0x01, 0x00], [0xAB, 0x01]),
+                // wrong pdu len
+                (&[1, 0, 0, 0, 1, 0], [0x81, 0x03]),
+                // quantity 0 and too many registers
+                (&[3, 0, 0, 0, 0], [0x83, 0x03]),
+                (&[3, 0, 0, 0, 126], [0x83, 0x03]),
+                // single coil value must be 0xFF00 or 0
+                (&[5, 0, 0, 0x12, 0x34], [0x85, 0x03]),
+                // byte count does not match the quantity
+                (&[15, 0, 0, 0, 9, 1, 0xFF], [0x8F, 0x03]),
+                // address range overflows
+                (&[16, 0xFF, 0xFF, 0, 2, 4, 0, 1, 0, 2], [0x90, 0x02]),
+            ];
+            for (i, (pdu, exception)) in cases.iter().enumerate() {
+                cch.write_channel.send_bytes(&adu(i as u16, pdu)).await.unwrap();
+                assert_eq!(read_exact(&cch.read_channel, 9).await, adu(i as u16, exception), "case {i}");
+            }
+
+            // two requests in one segment, and one request split across segments
+            let two = [adu(10, &[6, 0, 0, 0, 7]), adu(11, &[3, 0, 0, 0, 1])].concat();
+            cch.write_channel.send_bytes(&two).await.unwrap();
+            assert_eq!(read_exact(&cch.read_channel, 12 + 11).await, [adu(10, &[6, 0, 0, 0, 7]), adu(11, &[3, 2, 0, 7])].concat());
+            let one = adu(12, &[3, 0, 0, 0, 1]);
+            cch.write_channel.send_bytes(&one[..5]).await.unwrap();
+            cch.write_channel.flush().await.unwrap();
+            Timer::after_millis(20).await;
+            cch.write_channel.send_bytes(&one[5..]).await.unwrap();
+            assert_eq!(read_exact(&cch.read_channel, 11).await, adu(12, &[3, 2, 0, 7]));
+        }).await
+    });
+}
+
+#[test]
+fn client_transaction() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch): (&Channel, &Channel) = (harness::channel(8, 8), harness::channel(8, 8));
+        let (client_runner, mut client) = modbus::build_client(client_stack, SERVER_IP, PORT, cch, leak(State::new()));
+        client.timeout(Duration::from_millis(100));
+        let server = tcp_server::build_runner(server_stack, PORT, sch, leak(State::new()));
+
+        // read one register, the response carries the transaction id, then the next one is answered
+        let server_logic = async {
+            let mut decoder = AduDecoder::new();
+            let mut requests = 0;
+            loop {
+                let msg = sch.read_channel.wait_read_ref().await;
+                if !matches!(msg.callback_enum(), CallbackEnum::Recv) { continue; }
+                for request in decode_all(&mut decoder, &[msg.as_bytes()]) {
+                    let (tid, _) = request.unwrap();
+                    requests += 1;
+                    match requests {
+                        // wrong transaction id first, then the right one
+                        1 => sch.write_channel.send_bytes(&[adu(tid + 1, &[3, 2, 0, 9]), adu(tid, &[3, 2, 0, 1])].concat()).await.unwrap(),
+                        // not answered, the client times out and the server closes the connection
+                        2 => sch.write_channel.close(None).await.unwrap(),
+                        // the late response of request 2 is discarded
+                        _ => sch.write_channel.send_bytes(&[adu(tid - 1, &[3, 2, 0, 8]), adu(tid, &[3, 2, 0, 4])].concat()).await.unwrap(),
+                    }
+                }
+            }
+        };
+        let body = async {
+            while cch.stats().uptime.is_none() { Timer::after_millis(5).await; }
+            let mut reg = [0; 1];
+            client.read_holding_registers(0, &mut reg).await.unwrap();
+            assert_eq!(reg, [1]);
+            assert_eq!(modbus_err(client.read_holding_registers(0, &mut reg).await), ModbusErr::Timeout);
+
+            // the client reconnects, the disconnect and conn events queued before the next request do not fail it
+            while cch.stats().connect_successes < 2 { Timer::after_millis(5).await; }
+            Timer::after_millis(20).await;
+            client.read_holding_registers(0, &mut reg).await.unwrap();
+            assert_eq!(reg, [4]);
+        };
+        match select4(client_runner.run(), server.run(), server_logic, body).await {
+            Either4::Fourth(_) => {}
+            _ => panic!("runner returned"),
+        }
+    });
+}
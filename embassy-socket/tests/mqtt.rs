@@ -0,0 +1,251 @@
+//! mqtt packet decoder, and the mqtt runner against a raw broker over the in-memory link
+#![cfg(feature = "mqtt")]
+
+mod harness;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use embassy_futures::select::{select4, Either4};
+use embassy_socket::channel::callback_enum::CallbackEnum;
+use embassy_socket::channel::read_channel::ReadChannel;
+use embassy_socket::channel::SocketChannel;
+use embassy_socket::connection::socket_state::SocketState;
+use embassy_socket::err::SocketErr;
+use embassy_socket::mqtt::callback::MqttCallBack;
+use embassy_socket::mqtt::client::MqttClient;
+use embassy_socket::mqtt::decoder::PacketDecoder;
+use embassy_socket::mqtt::packet::{encode_len, Packet, MAX_REMAINING_LEN};
+use embassy_socket::mqtt::{MqttErr, MqttOptions, QoS};
+use embassy_socket::tcp_client::reconnect::ReconnectPolicy;
+use embassy_socket::tcp_server;
+use embassy_socket::mqtt;
+use embassy_time::{Duration, Instant, Timer};
+use harness::{leak, read_exact, wait_event, SERVER_IP};
+
+/// socket state of the tests
+type State = SocketState<1, 4096, 4096, 1024>;
+/// socket channel of the tests
+type Channel = SocketChannel<'static, 512, 512>;
+/// broker port
+const PORT: u16 = 1883;
+
+/// mqtt error of the result
+fn mqtt_err<T>(result: Result<T, SocketErr>) -> Option<MqttErr> {
+    match result {
+        Err(SocketErr::MqttError(e)) => Some(e),
+        _ => None,
+    }
+}
+
+#[test]
+fn parse_packet() {
+    assert!(matches!(Packet::parse(0x20, &[1, 0]), Ok(Packet::ConnAck { session_present: true, code: 0 })));
+    assert!(matches!(Packet::parse(0x40, &[0, 7]), Ok(Packet::PubAck(7))));
+    assert!(matches!(Packet::parse(0x90, &[0, 8, 1, 0x80]), Ok(Packet::SubAck { packet_id: 8, codes: [1, 0x80] })));
+    assert!(matches!(Packet::parse(0xB0, &[0, 9]), Ok(Packet::UnsubAck(9))));
+    assert!(matches!(Packet::parse(0xD0, &[]), Ok(Packet::PingResp)));
+
+    // flags are taken from the header, qos 0 publish has no packet id
+    let Ok(Packet::Publish { topic, payload, qos, retain, dup, packet_id }) = Packet::parse(0x3B, b"\x00\x03a/b\x00\x05hi") else { panic!("not publish"); };
+    assert_eq!((topic, payload, qos, retain, dup, packet_id), ("a/b", &b"hi"[..], QoS::AtLeastOnce, true, true, 5));
+    let Ok(Packet::Publish { payload, qos, packet_id, .. }) = Packet::parse(0x30, b"\x00\x01th") else { panic!("not publish"); };
+    assert_eq!((payload, qos, packet_id), (&b"h"[..], QoS::AtMostOnce, 0));
+
+    // wrong body len, qos 2, topic out of the body, invalid utf8 topic, and packets sent to the broker
+    let invalid: [(u8, &[u8]); 8] = [
+        (0x20, &[0, 0, 0]),
+        (0x90, &[0, 8]),
+        (0xD0, &[0]),
+        (0x34, b"\x00\x01a\x00\x01"),
+        (0x30, b"\x00\x05a"),
+        (0x32, b"\x00\x01a\x00"),
+        (0x30, b"\x00\x01\xFF"),
+        (0xC0, &[]),
+    ];
+    for (i, (header, body)) in invalid.iter().enumerate() {
+        assert_eq!(mqtt_err(Packet::parse(*header, body)), Some(MqttErr::InvalidPacket), "case {i}");
+    }
+}
+
+#[test]
+fn remaining_len() {
+    let mut buf = [0; 4];
+    assert_eq!((encode_len(127, &mut buf).unwrap(), buf[0]), (1, 0x7F));
+    assert_eq!((encode_len(200, &mut buf).unwrap(), &buf[..2]), (2, &[0xC8, 0x01][..]));
+    assert_eq!((encode_len(MAX_REMAINING_LEN, &mut buf).unwrap(), buf), (4, [0xFF, 0xFF, 0xFF, 0x7F]));
+    assert_eq!(mqtt_err(encode_len(MAX_REMAINING_LEN + 1, &mut buf)), Some(MqttErr::PacketTooLarge));
+}
+
+#[test]
+fn decoder_split() {
+    let mut decoder = PacketDecoder::<256>::new();
+
+    // connack fed byte by byte
+    let connack = [0x20, 2, 0, 0];
+    for b in &connack[..3] { assert_eq!(decoder.decode(&[*b]).0, 1); }
+    assert!(matches!(decoder.decode(&connack[3..]), (1, Some(Ok(Packet::ConnAck { session_present: false, code: 0 })))));
+
+    // remaining len 200 is split between its two bytes, the pingresp follows in the same read
+    let mut publish = vec![0x32, 0xC8, 0x01, 0, 3, b'a', b'/', b'b', 0, 7];
+    publish.resize(3 + 200, b'x');
+    publish.extend([0xD0, 0]);
+    assert!(matches!(decoder.decode(&publish[..2]), (2, None)));
+    let (used, packet) = decoder.decode(&publish[2..]);
+    let Some(Ok(Packet::Publish { topic, payload, qos, packet_id, .. })) = packet else { panic!("not publish"); };
+    assert_eq!((topic, payload.len(), qos, packet_id), ("a/b", 193, QoS::AtLeastOnce, 7));
+    assert_eq!(used, 201);
+    assert!(matches!(decoder.decode(&publish[203..]), (2, Some(Ok(Packet::PingResp)))));
+
+    // remaining len longer than 4 bytes, the decoder starts over after the error
+    assert!(matches!(decoder.decode(&[0x30, 0xFF, 0xFF, 0xFF, 0xFF]), (5, Some(Err(SocketErr::MqttError(MqttErr::InvalidPacket))))));
+    assert!(matches!(decoder.decode(&[0xD0, 0]), (2, Some(Ok(Packet::PingResp)))));
+}
+
+#[test]
+fn decoder_oversize() {
+    let mut decoder = PacketDecoder::<16>::new();
+
+    // the body of 40 bytes is skipped across reads, the pingresp after it is decoded
+    let mut bytes = vec![0x30, 40];
+    bytes.resize(2 + 40, b'x');
+    bytes.extend([0xD0, 0]);
+    assert!(matches!(decoder.decode(&bytes[..10]), (10, Some(Err(SocketErr::FrameTooLarge)))));
+    assert!(matches!(decoder.decode(&bytes[10..30]), (20, None)));
+    assert!(matches!(decoder.decode(&bytes[30..]), (14, Some(Ok(Packet::PingResp)))));
+
+    // a body of exactly N bytes fits, reset drops the skip state
+    let mut fit = vec![0x30, 16, 0, 1, b't'];
+    fit.resize(2 + 16, b'x');
+    assert!(matches!(decoder.decode(&fit), (18, Some(Ok(Packet::Publish { topic: "t", .. })))));
+    assert!(matches!(decoder.decode(&[0x30, 17, 0]), (3, Some(Err(SocketErr::FrameTooLarge)))));
+    decoder.reset();
+    assert!(matches!(decoder.decode(&[0xD0, 0]), (2, Some(Ok(Packet::PingResp)))));
+}
+
+/// mqtt callback event
+#[derive(Debug, PartialEq)]
+enum Event {
+    /// connected
+    Conn,
+    /// disconnected
+    DisConn,
+    /// publish topic and payload
+    Publish(String, Vec<u8>),
+    /// mqtt error
+    Err(MqttErr),
+}
+
+/// record every event, tcp errors of the reconnects are not recorded
+struct Recorder(Rc<RefCell<Vec<Event>>>);
+
+/// support mqtt callback
+impl MqttCallBack for Recorder {
+    async fn conn<const CN: usize>(&mut self, _session_present: bool, _client: &MqttClient<'_, CN>) {
+        self.0.borrow_mut().push(Event::Conn);
+    }
+
+    async fn dis_conn(&mut self) {
+        self.0.borrow_mut().push(Event::DisConn);
+    }
+
+    async fn publish<const CN: usize>(&mut self, topic: &str, payload: &[u8], _qos: QoS, _client: &MqttClient<'_, CN>) {
+        self.0.borrow_mut().push(Event::Publish(topic.into(), payload.to_vec()));
+    }
+
+    async fn err(&mut self, err: SocketErr) {
+        if let SocketErr::MqttError(e) = err { self.0.borrow_mut().push(Event::Err(e)); }
+    }
+}
+
+/// read one packet of the client, the remaining len must be one byte<br />
+/// buf keeps the data after the packet, one read may contain several packets
+async fn broker_read(rch: &ReadChannel<'_, 512>, buf: &mut Vec<u8>) -> Vec<u8> {
+    while buf.len() < 2 || buf.len() < 2 + buf[1] as usize {
+        buf.extend(read_exact(rch, 1).await);
+        assert!(buf.len() < 2 || buf[1] < 0x80);
+    }
+    buf.drain(..2 + buf[1] as usize).collect()
+}
+
+#[test]
+fn keep_alive() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch): (&Channel, &Channel) = (harness::channel(8, 8), harness::channel(8, 8));
+        let client = leak(MqttClient::new(&cch.write_channel, MqttOptions::new("dev").keep_alive(1)));
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let (mut client_runner, mut mqtt_runner) = mqtt::build::<512, 512, 256, _>(client_stack, SERVER_IP, PORT, cch, leak(State::new()), client, Recorder(events.clone()));
+        client_runner.reconnect_policy(ReconnectPolicy::immediately());
+        let server = tcp_server::build_runner(server_stack, PORT, sch, leak(State::new()));
+
+        let broker = async {
+            let (rch, mut buf) = (&sch.read_channel, Vec::new());
+            assert!(matches!(wait_event(rch).await, CallbackEnum::Conn));
+            let connect = broker_read(rch, &mut buf).await;
+            assert_eq!((connect[0], &connect[2..10]), (0x10, &b"\x00\x04MQTT\x04\x02"[..]));
+            assert_eq!(&connect[10..], b"\x00\x01\x00\x03dev");
+            let connected = Instant::now();
+            sch.write_channel.send_bytes(&[0x20, 2, 0, 0]).await.unwrap();
+
+            // qos 1 publish is acknowledged by the client
+            sch.write_channel.send_bytes(b"\x32\x07\x00\x01t\x00\x09hi").await.unwrap();
+            assert_eq!(broker_read(rch, &mut buf).await, [0x40, 2, 0, 9]);
+
+            // pingreq is sent a keep alive after the last packet, and answered
+            assert_eq!(broker_read(rch, &mut buf).await, [0xC0, 0]);
+            assert!(connected.elapsed() >= Duration::from_millis(900));
+            sch.write_channel.send_bytes(&[0xD0, 0]).await.unwrap();
+
+            // the next pingreq is not answered, the client closes the connection a keep alive later
+            assert_eq!(broker_read(rch, &mut buf).await, [0xC0, 0]);
+            let pinged = Instant::now();
+            // the client may reset the connection when it closes
+            while !matches!(wait_event(rch).await, CallbackEnum::Disconnect) {}
+            assert!(pinged.elapsed() >= Duration::from_millis(900));
+
+            // the client connects again
+            assert!(matches!(wait_event(rch).await, CallbackEnum::Conn));
+            assert_eq!(broker_read(rch, &mut buf).await[0], 0x10);
+        };
+        match select4(client_runner.run(), server.run(), mqtt_runner.run(), broker).await {
+            Either4::Fourth(_) => {}
+            _ => panic!("runner returned"),
+        }
+        assert_eq!(*events.borrow(), [
+            Event::Conn,
+            Event::Publish("t".into(), b"hi".to_vec()),
+            Event::Err(MqttErr::Timeout),
+            Event::DisConn,
+        ]);
+    });
+}
+
+#[test]
+fn republish() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch): (&Channel, &Channel) = (harness::channel(8, 8), harness::channel(8, 8));
+        let client = leak(MqttClient::new(&cch.write_channel, MqttOptions::new("dev").clean_session(false)));
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let (client_runner, mut mqtt_runner) = mqtt::build::<512, 512, 256, _>(client_stack, SERVER_IP, PORT, cch, leak(State::new()), client, Recorder(events.clone()));
+        let server = tcp_server::build_runner(server_stack, PORT, sch, leak(State::new()));
+
+        let broker = async {
+            let (rch, mut buf) = (&sch.read_channel, Vec::new());
+            assert!(matches!(wait_event(rch).await, CallbackEnum::Conn));
+            assert_eq!(broker_read(rch, &mut buf).await[0], 0x10);
+            // the session is present
+            sch.write_channel.send_bytes(&[0x20, 2, 1, 0]).await.unwrap();
+            while !client.is_connected() { Timer::after_millis(10).await; }
+
+            // the puback is not sent, the message is sent again with the dup flag and the same packet id
+            let packet_id = client.publish("t", b"a", QoS::AtLeastOnce, false).await.unwrap();
+            assert_eq!(broker_read(rch, &mut buf).await, b"\x32\x06\x00\x01t\x00\x01a");
+            client.republish(packet_id, "t", b"a", false).await.unwrap();
+            assert_eq!(broker_read(rch, &mut buf).await, b"\x3A\x06\x00\x01t\x00\x01a");
+        };
+        match select4(client_runner.run(), server.run(), mqtt_runner.run(), broker).await {
+            Either4::Fourth(_) => {}
+            _ => panic!("runner returned"),
+        }
+        assert_eq!(*events.borrow(), [Event::Conn]);
+    });
+}
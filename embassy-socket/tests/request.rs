@@ -0,0 +1,115 @@
+//! request/response correlation over the in-memory link
+#![cfg(feature = "request")]
+
+mod harness;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use embassy_futures::join::join;
+use embassy_futures::select::{select3, select4, Either4};
+use embassy_socket::channel::callback_enum::CallbackEnum;
+use embassy_socket::channel::write_channel::WriteChannel;
+use embassy_socket::connection::socket_state::SocketState;
+use embassy_socket::err::SocketErr;
+use embassy_socket::framing::framed::FramedClient;
+use embassy_socket::framing::Framing;
+use embassy_socket::request::{Matcher, RequestClient, Requester};
+use embassy_socket::tcp_client;
+use embassy_socket::tcp_client::callback::TcpClientCallBack;
+use embassy_socket::tcp_client::callback_runner::CallbackRunner;
+use embassy_socket::tcp_server;
+use embassy_time::{Duration, Timer};
+use harness::{leak, SERVER_IP};
+
+/// socket state of the tests
+type State = SocketState<1, 4096, 4096, 1024>;
+
+/// id is the leading number of the frame
+fn id_of(frame: &[u8]) -> Option<u32> {
+    let end = frame.iter().position(|b| !b.is_ascii_digit()).unwrap_or(frame.len());
+    core::str::from_utf8(&frame[..end]).ok()?.parse().ok()
+}
+
+/// client callback, records frames that are not responses
+struct Unsolicited(Rc<RefCell<Vec<Vec<u8>>>>);
+
+/// support tcp client callback
+impl TcpClientCallBack for Unsolicited {
+    type T = ();
+
+    async fn conn<const CN: usize>(&mut self, _wch: &WriteChannel<'_, CN>, _t: &mut ()) {}
+
+    async fn dis_conn(&mut self, _t: &mut ()) {}
+
+    async fn recv<const CN: usize>(&mut self, buf: &[u8], _wch: &WriteChannel<'_, CN>, _t: &mut ()) {
+        self.0.borrow_mut().push(buf.to_vec());
+    }
+
+    async fn err(&mut self, _err: SocketErr, _t: &mut ()) {}
+}
+
+#[test]
+fn out_of_order_responses() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch) = (harness::channel::<256, 256>(8, 8), harness::channel::<256, 256>(8, 8));
+        let client = tcp_client::build_runner(client_stack, SERVER_IP, 1234, cch, leak(State::new()));
+        let server = tcp_server::build_runner(server_stack, 1234, sch, leak(State::new()));
+        let requester = leak(Requester::<256, 4, 16>::new(&cch.write_channel, id_of));
+        let unsolicited = Rc::new(RefCell::new(Vec::new()));
+        let cb = FramedClient::<_, 16>::new(Framing::Delimiter(b"\n"), RequestClient::new(requester, Unsolicited(unsolicited.clone())));
+        let mut callback = CallbackRunner::new(cch, cb);
+
+        // answer request 1 and 2 in reverse order after both are received, never answer 9
+        let server_logic = async {
+            let mut buf = Vec::new();
+            loop {
+                let msg = sch.read_channel.wait_read_ref().await;
+                if !matches!(msg.callback_enum(), CallbackEnum::Recv) { continue; }
+                buf.extend_from_slice(msg.as_bytes());
+                if buf.windows(2).any(|w| w == b"1\n") && buf.windows(2).any(|w| w == b"2\n") {
+                    sch.write_channel.send_str("push\n2:b\n1:a\n").await.unwrap();
+                    buf.clear();
+                }
+            }
+        };
+        let body = async {
+            // the callback runner reads the client channel, so poll the statistics to wait for the connection
+            while cch.stats().uptime.is_none() { Timer::after_millis(5).await; }
+            let timeout = Duration::from_secs(2);
+            let (one, two) = join(requester.request_id(b"1\n", 1, timeout), requester.request_id(b"2\n", 2, timeout)).await;
+            assert_eq!(one.unwrap().as_bytes(), b"1:a");
+            assert_eq!(two.unwrap().as_bytes(), b"2:b");
+            assert_eq!(*unsolicited.borrow(), [b"push".to_vec()]);
+
+            let err = requester.request(b"9\n", Matcher::Fn(|f| f.starts_with(b"9")), Duration::from_millis(100)).await;
+            assert!(matches!(err, Err(SocketErr::RequestTimeout)));
+            assert_eq!(requester.outstanding(), 0);
+        };
+        match select4(client.run(), server.run(), select3(callback.run(), server_logic, body), core::future::pending::<()>()).await {
+            Either4::Third(_) => {}
+            _ => panic!("runner returned"),
+        }
+    });
+}
+
+#[test]
+fn too_many_and_aborted() {
+    let ch = harness::channel::<16, 16>(1, 4);
+    let requester = Requester::<16, 1, 16>::without_id(&ch.write_channel);
+    harness::block_on(async {
+        // the write channel is disabled, so the request can not be sent
+        let err = requester.request(b"a", Matcher::Fn(|_| true), Duration::from_millis(10)).await;
+        assert!(matches!(err, Err(SocketErr::NotConnected)));
+
+        ch.write_channel.enable().await;
+        let first = requester.request(b"a", Matcher::Fn(|_| true), Duration::from_secs(1));
+        let second = async {
+            let err = requester.request(b"b", Matcher::Fn(|_| true), Duration::from_secs(1)).await;
+            assert!(matches!(err, Err(SocketErr::TooManyRequests)));
+            requester.abort();
+        };
+        let (first, _) = join(first, second).await;
+        assert!(matches!(first, Err(SocketErr::RequestAborted)));
+        assert_eq!(requester.outstanding(), 0);
+    });
+}
@@ -0,0 +1,127 @@
+//! sntp packet, calendar conversion and sntp runner over the in-memory link
+#![cfg(feature = "sntp")]
+
+mod harness;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use embassy_futures::select::{select4, Either4};
+use embassy_net::IpAddress;
+use embassy_socket::channel::callback_enum::CallbackEnum;
+use embassy_socket::connection::udp_state::UdpSocketState;
+use embassy_socket::err::SocketErr;
+use embassy_socket::sntp::callback::SntpCallBack;
+use embassy_socket::sntp::packet::{encode_request, encode_timestamp, parse_reply, timestamp, PACKET_LEN};
+use embassy_socket::sntp::runner::SntpRunner;
+use embassy_socket::sntp::{SntpClock, SntpConfig, SntpErr, SntpSample, UtcDateTime, SNTP_PORT};
+use embassy_socket::udp;
+use embassy_time::{Duration, Instant, Timer};
+use harness::{leak, SERVER_IP};
+
+/// udp state of the tests
+type State = UdpSocketState<1, 4, 1024, 1024, 512>;
+/// server time of the tests, 2023-11-14 22:13:20 utc
+const SERVER_TIME: Duration = Duration::from_secs(1_700_000_000);
+
+/// server reply to the request
+fn reply(request: &[u8], stratum: u8, time: Duration) -> [u8; PACKET_LEN] {
+    let mut reply = [0; PACKET_LEN];
+    reply[0] = 4 << 3 | 4;
+    reply[1] = stratum;
+    reply[24..32].copy_from_slice(&request[40..48]);
+    reply[32..40].copy_from_slice(&encode_timestamp(time));
+    reply[40..48].copy_from_slice(&encode_timestamp(time));
+    reply
+}
+
+#[test]
+fn timestamp_round_trip() {
+    let time = SERVER_TIME + Duration::from_micros(250_000);
+    assert_eq!(timestamp(&encode_timestamp(time)), time);
+    // 2036-02-07 06:28:16 utc is the start of the next ntp era
+    assert_eq!(timestamp(&[0, 0, 0, 1, 0, 0, 0, 0]), Duration::from_secs(2_085_978_497));
+}
+
+#[test]
+fn reply_check() {
+    let request = encode_request([1, 2, 3, 4, 5, 6, 7, 8]);
+    let ok = parse_reply(&reply(&request, 2, SERVER_TIME), [1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+    assert_eq!((ok.stratum, ok.receive, ok.transmit), (2, SERVER_TIME, SERVER_TIME));
+
+    let err = |result: Result<_, SocketErr>| match result {
+        Err(SocketErr::SntpError(e)) => e,
+        other => panic!("sntp error expected, got {other:?}"),
+    };
+    assert_eq!(err(parse_reply(&reply(&request, 2, SERVER_TIME), [0; 8])), SntpErr::Mismatch);
+    assert_eq!(err(parse_reply(&reply(&request, 0, SERVER_TIME), [1, 2, 3, 4, 5, 6, 7, 8])), SntpErr::KissOfDeath);
+    assert_eq!(err(parse_reply(&request, [1, 2, 3, 4, 5, 6, 7, 8])), SntpErr::InvalidReply);
+}
+
+#[test]
+fn sample_and_date_time() {
+    // the request takes 10ms to the server, the server takes 5ms, the reply takes 10ms
+    let t1 = Instant::from_secs(100);
+    let sample = SntpSample::new(t1, SERVER_TIME, SERVER_TIME + Duration::from_millis(5), t1 + Duration::from_millis(25), 1);
+    assert_eq!(sample.delay, Duration::from_millis(20));
+    assert_eq!(sample.utc(), SERVER_TIME + Duration::from_millis(15));
+
+    let date_time = UtcDateTime::from_unix(SERVER_TIME);
+    assert_eq!(date_time, UtcDateTime { year: 2023, month: 11, day: 14, weekday: 2, hour: 22, minute: 13, second: 20, micros: 0 });
+    let leap_day = UtcDateTime::from_unix(Duration::from_secs(951_782_400));
+    assert_eq!((leap_day.year, leap_day.month, leap_day.day), (2000, 2, 29));
+}
+
+/// sntp callback of the tests, records events
+struct Hook(Rc<RefCell<Vec<String>>>);
+
+/// support sntp callback
+impl SntpCallBack for Hook {
+    async fn synced(&mut self, sample: &SntpSample) {
+        self.0.borrow_mut().push(format!("synced {}", sample.stratum));
+    }
+
+    async fn err(&mut self, err: SocketErr) {
+        self.0.borrow_mut().push(format!("{err:?}"));
+    }
+}
+
+#[test]
+fn sync_with_retry() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch) = (harness::channel::<512, 512>(4, 4), harness::channel::<512, 512>(4, 4));
+        let client = udp::build_runner(client_stack, 0, cch, leak(State::new()));
+        let server = udp::build_runner(server_stack, SNTP_PORT, sch, leak(State::new()));
+        let clock: &'static SntpClock = leak(SntpClock::new());
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let config = SntpConfig::new(IpAddress::Ipv4(SERVER_IP)).timeout(Duration::from_millis(100)).retry(Duration::from_millis(50));
+        let mut runner = SntpRunner::new(cch, clock, config, Hook(events.clone()));
+
+        // the first request is not answered, the retry is answered
+        let body = async {
+            let mut requests = 0;
+            loop {
+                let msg = sch.read_channel.wait_read_ref().await;
+                if !matches!(msg.callback_enum(), CallbackEnum::Recv) { continue; }
+                requests += 1;
+                if requests == 1 { continue; }
+
+                let reply = reply(msg.as_bytes(), 1, SERVER_TIME);
+                let endpoint = msg.endpoint();
+                drop(msg);
+                sch.write_channel.send_bytes_to(&reply, endpoint).await.unwrap();
+                break;
+            }
+            while !clock.is_synced() { Timer::after(Duration::from_millis(5)).await; }
+        };
+        match select4(client.run(), server.run(), runner.run(), body).await {
+            Either4::Fourth(_) => {}
+            _ => panic!("runner returned"),
+        }
+
+        assert_eq!(*events.borrow(), ["SntpError(Timeout)", "synced 1"]);
+        let now = clock.now().unwrap();
+        assert!(now >= SERVER_TIME && now < SERVER_TIME + Duration::from_secs(1));
+        assert_eq!(clock.date_time().unwrap().year, 2023);
+        assert!(clock.instant(SERVER_TIME).unwrap() <= Instant::now());
+    });
+}
@@ -0,0 +1,35 @@
+//! socket state memory
+
+use embassy_socket::connection::slab_state::{SlabPool, SlabState};
+use embassy_socket::connection::socket_state::TcpState;
+
+#[test]
+fn slab_state_alloc() {
+    let mut slab = [0; 300];
+    assert!(SlabState::new(&mut slab, 4, 64, 64, 16).is_none());
+    let state = SlabState::new(&mut slab, 2, 64, 64, 16).unwrap();
+
+    let first = state.alloc().unwrap();
+    let second = state.alloc().unwrap();
+    assert!(state.alloc().is_none());
+    assert_eq!(state.used(), 2);
+
+    state.free(&first);
+    assert_eq!(state.used(), 1);
+    assert!(state.alloc().is_some());
+    state.free(&second);
+}
+
+#[test]
+fn slab_pool_carve() {
+    let mut slab = [0; 1000];
+    let pool = SlabPool::new(&mut slab);
+    assert!(pool.state(0, 64, 64, 64).is_none());
+    let small = pool.state(2, 100, 100, 50).unwrap();
+    assert_eq!(pool.remaining(), 500);
+    assert!(pool.state(1, 400, 100, 1).is_none());
+    assert_eq!(pool.remaining(), 500);
+    let large = pool.state(1, 200, 200, 100).unwrap();
+    assert_eq!(pool.remaining(), 0);
+    assert_eq!((small.count(), large.count()), (2, 1));
+}
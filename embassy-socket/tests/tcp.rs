@@ -0,0 +1,697 @@
+//! tcp client and server runners over the in-memory link
+
+mod harness;
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use embassy_futures::join::join;
+use embassy_futures::select::{select, select3, Either, Either3};
+use embassy_net::{IpAddress, IpEndpoint, Stack};
+use embassy_socket::channel::callback_enum::CallbackEnum;
+use embassy_socket::channel::overflow_policy::OverflowPolicy;
+use embassy_socket::channel::read_channel::ReadChannel;
+use embassy_socket::channel::write_channel::WriteChannel;
+use embassy_socket::channel::SocketChannel;
+use embassy_socket::connection::slab_state::SlabPool;
+use embassy_socket::connection::socket_state::{SocketState, TcpState};
+use embassy_socket::control::{Control, State as RunnerState};
+use embassy_socket::err::SocketErr;
+use embassy_socket::heartbeat::Heartbeat;
+use embassy_socket::tcp_client;
+use embassy_socket::tcp_client::callback::TcpClientCallBack;
+use embassy_socket::tcp_client::read_runner::ReadRunner as ClientRunner;
+use embassy_socket::tcp_client::reconnect::ReconnectPolicy;
+use embassy_socket::tcp_server;
+use embassy_socket::tcp_server::callback::TcpServerCallBack;
+use embassy_socket::tcp_server::multi_callback_runner::MultiCallbackRunner;
+use embassy_socket::tcp_server::read_runner::ReadRunner as ServerRunner;
+use embassy_socket::tcp_server::TcpServer;
+use embassy_time::{Duration, Instant, Timer};
+use harness::{leak, read_exact, wait_event, CLIENT_IP, SERVER_IP};
+
+/// server port
+const PORT: u16 = 1234;
+/// read msg size
+const RC_SZ: usize = 512;
+/// write msg size
+const WC_SZ: usize = 1024;
+
+/// socket state of the tests
+type State = SocketState<1, 4096, 4096, 1024>;
+/// socket channel of the tests
+type Channel = SocketChannel<'static, RC_SZ, WC_SZ>;
+
+/// create client runner, reconnect immediately
+fn client(stack: Stack<'static>, channel: &'static Channel) -> ClientRunner<'static, RC_SZ, WC_SZ> {
+    client_with(stack, channel, leak(State::new()))
+}
+
+/// create client runner with state, reconnect immediately
+fn client_with(stack: Stack<'static>, channel: &'static Channel, state: &'static dyn TcpState) -> ClientRunner<'static, RC_SZ, WC_SZ> {
+    let mut runner = tcp_client::build_runner(stack, SERVER_IP, PORT, channel, state);
+    runner.reconnect_policy(ReconnectPolicy::immediately());
+    runner
+}
+
+/// create server runner
+fn server(stack: Stack<'static>, channel: &'static Channel) -> ServerRunner<'static, RC_SZ, WC_SZ> {
+    tcp_server::build_runner(stack, PORT, channel, leak(State::new()))
+}
+
+/// run client, server and test body until the body returns
+async fn serve<R>(client: &ClientRunner<'static, RC_SZ, WC_SZ>,
+                  server: &ServerRunner<'static, RC_SZ, WC_SZ>,
+                  body: impl Future<Output=R>) -> R {
+    match select3(client.run(), server.run(), body).await {
+        Either3::Third(r) => r,
+        _ => panic!("runner returned"),
+    }
+}
+
+#[test]
+fn connect() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch) = (harness::channel(4, 4), harness::channel(4, 4));
+        let (client, server) = (client(client_stack, cch), server(server_stack, sch));
+        serve(&client, &server, async {
+            assert!(matches!(wait_event(&cch.read_channel).await, CallbackEnum::Conn));
+            let msg = sch.read_channel.wait_read_ref().await;
+            assert!(matches!(msg.callback_enum(), CallbackEnum::Conn));
+            assert_eq!(msg.endpoint().addr, IpAddress::Ipv4(CLIENT_IP));
+
+            let stats = cch.stats();
+            assert_eq!(stats.connect_attempts, 1);
+            assert_eq!(stats.connect_successes, 1);
+            assert!(stats.uptime.is_some());
+        }).await
+    });
+}
+
+#[test]
+fn echo() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch) = (harness::channel(4, 4), harness::channel(4, 4));
+        let (client, server) = (client(client_stack, cch), server(server_stack, sch));
+        let echo = async {
+            loop {
+                let msg = sch.read_channel.wait_read_ref().await;
+                if matches!(msg.callback_enum(), CallbackEnum::Recv) {
+                    sch.write_channel.send_bytes(msg.as_bytes()).await.unwrap();
+                }
+            }
+        };
+        let body = async {
+            assert!(matches!(wait_event(&cch.read_channel).await, CallbackEnum::Conn));
+            assert!(cch.write_channel.send_str("hello").await.is_ok());
+            assert_eq!(read_exact(&cch.read_channel, 5).await, b"hello");
+            assert!(cch.write_channel.send_str("world").await.is_ok());
+            assert_eq!(read_exact(&cch.read_channel, 5).await, b"world");
+        };
+        serve(&client, &server, select3(echo, body, core::future::pending::<()>())).await;
+    });
+}
+
+#[test]
+fn slab_state() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch) = (harness::channel(4, 32), harness::channel(8, 4));
+        sch.read_channel.overflow_policy(OverflowPolicy::Block);
+        // client and server states with different sizes from one slab
+        let pool = leak(SlabPool::new(leak([0; 12288])));
+        let small = leak(pool.state(1, 1024, 1024, 256).unwrap());
+        let large = leak(pool.state(1, 4096, 4096, 1024).unwrap());
+        assert!(pool.state(1, 1024, 1024, 256).is_none());
+        let client = client_with(client_stack, cch, small);
+        let server = tcp_server::build_runner(server_stack, PORT, sch, large);
+        let payload: Vec<u8> = (0..8_000).map(|i| (i % 251) as u8).collect();
+        serve(&client, &server, async {
+            assert!(matches!(wait_event(&sch.read_channel).await, CallbackEnum::Conn));
+            assert!(matches!(wait_event(&cch.read_channel).await, CallbackEnum::Conn));
+            assert_eq!((small.used(), large.used()), (1, 1));
+            let (sent, recv) = join(cch.write_channel.send_bytes(&payload), read_exact(&sch.read_channel, payload.len())).await;
+            assert!(sent.is_ok());
+            assert_eq!(recv, payload);
+        }).await
+    });
+}
+
+#[test]
+fn large_payload() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch) = (harness::channel(4, 32), harness::channel(8, 4));
+        sch.read_channel.overflow_policy(OverflowPolicy::Block);
+        let (client, server) = (client(client_stack, cch), server(server_stack, sch));
+        let payload: Vec<u8> = (0..20_000).map(|i| (i % 251) as u8).collect();
+        serve(&client, &server, async {
+            assert!(matches!(wait_event(&sch.read_channel).await, CallbackEnum::Conn));
+            assert!(matches!(wait_event(&cch.read_channel).await, CallbackEnum::Conn));
+            let (sent, recv) = join(cch.write_channel.send_bytes(&payload), read_exact(&sch.read_channel, payload.len())).await;
+            assert!(sent.is_ok());
+            assert_eq!(recv, payload);
+            assert_eq!(sch.stats().bytes_in, payload.len() as u64);
+            assert_eq!(sch.stats().read_dropped, 0);
+        }).await
+    });
+}
+
+/// event recorded by the client callback
+#[derive(Debug, PartialEq)]
+enum Event {
+    /// connected
+    Conn,
+    /// disconnected
+    DisConn,
+    /// data received, with the number of recv calls of this connection
+    Recv(u32),
+    /// socket error in debug format
+    Err(String),
+}
+
+/// client callback, records events and counts recv calls per connection
+struct Recorder(Rc<RefCell<Vec<Event>>>);
+
+/// support tcp client callback
+impl TcpClientCallBack for Recorder {
+    type T = u32;
+
+    async fn conn<const CN: usize>(&mut self, _wch: &WriteChannel<'_, CN>, _t: &mut u32) {
+        self.0.borrow_mut().push(Event::Conn);
+    }
+
+    async fn dis_conn(&mut self, _t: &mut u32) {
+        self.0.borrow_mut().push(Event::DisConn);
+    }
+
+    async fn recv<const CN: usize>(&mut self, _buf: &[u8], _wch: &WriteChannel<'_, CN>, t: &mut u32) {
+        *t += 1;
+        self.0.borrow_mut().push(Event::Recv(*t));
+    }
+
+    async fn err(&mut self, _err: SocketErr, _t: &mut u32) {}
+}
+
+/// wait until the recorder has len events
+async fn wait_events(events: &RefCell<Vec<Event>>, len: usize) {
+    while events.borrow().len() < len { Timer::after(Duration::from_millis(5)).await; }
+}
+
+#[test]
+fn server_reset() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch) = (harness::channel(4, 4), harness::channel(4, 4));
+        let client = client(client_stack, cch);
+        let mut server = server(server_stack, sch);
+        let control = leak(Control::new());
+        server.control(control);
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut callback = tcp_client::callback_runner::CallbackRunner::new(cch, Recorder(events.clone()));
+        let body = async {
+            assert!(matches!(wait_event(&sch.read_channel).await, CallbackEnum::Conn));
+            sch.write_channel.send_str("a").await.unwrap();
+            wait_events(&events, 2).await;
+
+            // server closes the connection, the client detects it and reconnects
+            control.disconnect();
+            assert!(matches!(wait_event(&sch.read_channel).await, CallbackEnum::Disconnect));
+            assert!(matches!(wait_event(&sch.read_channel).await, CallbackEnum::Conn));
+            sch.write_channel.send_str("b").await.unwrap();
+            wait_events(&events, 5).await;
+        };
+        serve(&client, &server, select3(callback.run(), body, core::future::pending::<()>())).await;
+
+        // the connection state is reset, so the recv count starts from 1 again
+        assert_eq!(*events.borrow(), [Event::Conn, Event::Recv(1), Event::DisConn, Event::Conn, Event::Recv(1)]);
+    });
+}
+
+#[test]
+fn reconnect_policy() {
+    let ms = Duration::from_millis;
+    let policy = ReconnectPolicy::exponential(ms(100), ms(1000)).max_attempts(6);
+    let delays: Vec<_> = (1..=7).map(|attempt| policy.delay(attempt)).collect();
+    assert_eq!(delays, [Some(ms(100)), Some(ms(200)), Some(ms(400)), Some(ms(800)), Some(ms(1000)), Some(ms(1000)), None]);
+    // the exponential factor saturates, fixed and immediately do not grow
+    assert_eq!(ReconnectPolicy::exponential(ms(1), ms(5)).delay(100), Some(ms(5)));
+    assert_eq!(ReconnectPolicy::fixed(ms(300)).delay(u32::MAX), Some(ms(300)));
+    assert_eq!(ReconnectPolicy::immediately().delay(1), Some(Duration::from_ticks(0)));
+
+    let default = ReconnectPolicy::default();
+    assert_eq!((default.delay(1), default.delay(10), default.max_attempts), (Some(ms(1000)), Some(ms(30000)), None));
+    assert_eq!(default.min_uptime, ms(10000));
+
+    // jitter only reduces the delay, up to the percent
+    let jittered = ReconnectPolicy::fixed(ms(1000)).jitter(20);
+    assert!((1..100).map(|attempt| jittered.delay(attempt).unwrap()).all(|delay| delay >= ms(800) && delay <= ms(1000)));
+    assert_eq!(ReconnectPolicy::fixed(ms(1)).jitter(200).jitter, 100);
+}
+
+#[test]
+fn reconnect_exhausted() {
+    harness::run(|client_stack, _server_stack| async move {
+        let cch: &Channel = harness::channel(8, 4);
+        // nothing listens on the server, every connect is refused
+        let mut client = tcp_client::build_runner(client_stack, SERVER_IP, PORT, cch, leak(State::new()));
+        client.reconnect_policy(ReconnectPolicy::exponential(Duration::from_millis(50), Duration::from_secs(1)).max_attempts(2));
+        let start = Instant::now();
+        client.run().await;
+        assert!(start.elapsed() >= Duration::from_millis(150));
+
+        let mut events = Vec::new();
+        while !cch.read_channel.is_empty().await { events.push(wait_event(&cch.read_channel).await); }
+        let [
+            CallbackEnum::Err(SocketErr::ConnectError(_)),
+            CallbackEnum::Reconnect { attempt: 1, delay: first },
+            CallbackEnum::Err(SocketErr::ConnectError(_)),
+            CallbackEnum::Reconnect { attempt: 2, delay: second },
+            CallbackEnum::Err(SocketErr::ConnectError(_)),
+            CallbackEnum::Err(SocketErr::ReconnectExhausted),
+        ] = events[..] else { panic!("unexpected events {events:?}") };
+        assert_eq!((first, second), (Duration::from_millis(50), Duration::from_millis(100)));
+    });
+}
+
+#[test]
+fn reconnect_backoff() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch) = (harness::channel(8, 4), harness::channel(4, 4));
+        let mut client = tcp_client::build_runner(client_stack, SERVER_IP, PORT, cch, leak(State::new()));
+        let policy = ReconnectPolicy::exponential(Duration::from_millis(20), Duration::from_secs(1));
+        client.reconnect_policy(policy.min_uptime(Duration::from_millis(500)));
+        let server = server(server_stack, sch);
+        // the server closes every connection at once, or after the min uptime once it is told to
+        let stable = Cell::new(false);
+        let closer = async {
+            loop {
+                if !matches!(wait_event(&sch.read_channel).await, CallbackEnum::Conn) { continue; }
+                if stable.get() { Timer::after(Duration::from_millis(700)).await; }
+                sch.write_channel.close(None).await.unwrap();
+            }
+        };
+
+        let body = async {
+            // a connection closed at once counts as a failed attempt, the delay grows
+            let mut delays = Vec::new();
+            while delays.len() < 3 {
+                if let CallbackEnum::Reconnect { attempt, delay } = wait_event(&cch.read_channel).await {
+                    assert_eq!(attempt as usize, delays.len() + 1);
+                    delays.push(delay.as_millis());
+                }
+            }
+            assert_eq!(delays, [20, 40, 80]);
+
+            // a connection that lasted the min uptime resets the attempts, the client reconnects at once
+            stable.set(true);
+            while !matches!(wait_event(&cch.read_channel).await, CallbackEnum::Conn) {}
+            while !matches!(wait_event(&cch.read_channel).await, CallbackEnum::Disconnect) {}
+            stable.set(false);
+            loop {
+                match wait_event(&cch.read_channel).await {
+                    CallbackEnum::Conn => break,
+                    CallbackEnum::Reconnect { .. } => panic!("a stable connection is reconnected with backoff"),
+                    _ => {}
+                }
+            }
+            while !matches!(wait_event(&cch.read_channel).await, CallbackEnum::Reconnect { attempt: 1, .. }) {}
+        };
+        serve(&client, &server, select(closer, body)).await;
+    });
+}
+
+#[test]
+fn channel_overflow() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch) = (harness::channel(4, 8), harness::channel(4, 4));
+        let (client, server) = (client(client_stack, cch), server(server_stack, sch));
+        serve(&client, &server, async {
+            assert!(matches!(wait_event(&cch.read_channel).await, CallbackEnum::Conn));
+            // nobody reads the server channel, conn and 3 msg fill it, the rest is dropped
+            for _ in 0..8 {
+                assert!(cch.write_channel.send_bytes(&[7; 100]).await.is_ok());
+                Timer::after(Duration::from_millis(20)).await;
+            }
+            Timer::after(Duration::from_millis(100)).await;
+
+            let CallbackEnum::Dropped(dropped) = sch.read_channel.wait_read_ref().await.callback_enum() else { panic!("dropped is not reported first") };
+            assert_eq!(dropped, 5);
+            assert_eq!(sch.stats().read_dropped, 5);
+            assert!(matches!(sch.read_channel.wait_read_ref().await.callback_enum(), CallbackEnum::Conn));
+            assert_eq!(read_exact(&sch.read_channel, 300).await, [7; 300]);
+        }).await
+    });
+}
+
+/// heartbeat of the tests, ping after 200ms idle, the response must be pong within 200ms
+const HEARTBEAT: Heartbeat = Heartbeat::ping(Duration::from_millis(200), Duration::from_millis(200), b"ping").matcher(|bytes| bytes == b"pong");
+
+#[test]
+fn client_heartbeat() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch) = (harness::channel(4, 4), harness::channel(4, 4));
+        let (mut client, server) = (client(client_stack, cch), server(server_stack, sch));
+        client.heartbeat(Some(HEARTBEAT));
+        serve(&client, &server, async {
+            assert!(matches!(wait_event(&cch.read_channel).await, CallbackEnum::Conn));
+            assert!(matches!(wait_event(&sch.read_channel).await, CallbackEnum::Conn));
+            let connected = Instant::now();
+
+            // the ping is sent after idle, the matching response is accepted
+            assert_eq!(read_exact(&sch.read_channel, 4).await, b"ping");
+            assert!(connected.elapsed() >= Duration::from_millis(200));
+            sch.write_channel.send_str("pong").await.unwrap();
+            let answered = Instant::now();
+
+            // the next ping follows an idle after the response, other data is not the response
+            assert_eq!(read_exact(&sch.read_channel, 4).await, b"ping");
+            assert!(answered.elapsed() >= Duration::from_millis(200));
+            sch.write_channel.send_str("nope").await.unwrap();
+            let pinged = Instant::now();
+
+            // the client closes the connection a timeout after the ping, the ping was sent a little before it was read
+            assert!(matches!(wait_event(&cch.read_channel).await, CallbackEnum::Err(SocketErr::HeartbeatTimeout)));
+            assert!(matches!(wait_event(&cch.read_channel).await, CallbackEnum::Disconnect));
+            assert!(pinged.elapsed() >= Duration::from_millis(150));
+        }).await
+    });
+}
+
+#[test]
+fn server_heartbeat() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch) = (harness::channel(4, 4), harness::channel(4, 4));
+        let (client, mut server) = (client(client_stack, cch), server(server_stack, sch));
+        server.heartbeat(Some(HEARTBEAT));
+        serve(&client, &server, async {
+            assert!(matches!(wait_event(&cch.read_channel).await, CallbackEnum::Conn));
+            assert!(matches!(wait_event(&sch.read_channel).await, CallbackEnum::Conn));
+            let connected = Instant::now();
+
+            // the ping is sent after idle, the matching response is accepted
+            assert_eq!(read_exact(&cch.read_channel, 4).await, b"ping");
+            assert!(connected.elapsed() >= Duration::from_millis(200));
+            cch.write_channel.send_str("pong").await.unwrap();
+            let answered = Instant::now();
+
+            // the next ping is not answered, the server closes the connection a timeout after it,
+            // the ping was sent a little before it was read
+            assert_eq!(read_exact(&cch.read_channel, 4).await, b"ping");
+            assert!(answered.elapsed() >= Duration::from_millis(200));
+            let pinged = Instant::now();
+            assert!(matches!(wait_event(&sch.read_channel).await, CallbackEnum::Err(SocketErr::HeartbeatTimeout)));
+            assert!(matches!(wait_event(&sch.read_channel).await, CallbackEnum::Disconnect));
+            assert!(pinged.elapsed() >= Duration::from_millis(150));
+        }).await
+    });
+}
+
+#[test]
+fn client_control() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch1, sch2) = (harness::channel(4, 4), harness::channel(4, 4), harness::channel(4, 4));
+        let mut client = client(client_stack, cch);
+        let server1 = server(server_stack, sch1);
+        let server2 = tcp_server::build_runner::<RC_SZ, WC_SZ>(server_stack, PORT + 1, sch2, leak(State::new()));
+        let control = leak(Control::new());
+        client.control(control);
+
+        let body = async {
+            assert!(matches!(wait_event(&cch.read_channel).await, CallbackEnum::Conn));
+            assert!(matches!(wait_event(&sch1.read_channel).await, CallbackEnum::Conn));
+            assert_eq!(control.state(), RunnerState::Connected);
+
+            // the connection is closed and the client pauses
+            control.disconnect();
+            assert!(matches!(wait_event(&cch.read_channel).await, CallbackEnum::Disconnect));
+            while !matches!(wait_event(&sch1.read_channel).await, CallbackEnum::Disconnect) {}
+            while control.state() != RunnerState::Paused { Timer::after_millis(10).await; }
+
+            // the client connects to the new port
+            control.reconnect_to(SERVER_IP, PORT + 1);
+            assert!(matches!(wait_event(&cch.read_channel).await, CallbackEnum::Conn));
+            assert!(matches!(wait_event(&sch2.read_channel).await, CallbackEnum::Conn));
+            assert_eq!(control.state(), RunnerState::Connected);
+
+            // the queued data is written before the runner returns
+            cch.write_channel.send_str("bye").await.unwrap();
+            control.stop();
+            assert_eq!(read_exact(&sch2.read_channel, 3).await, b"bye");
+        };
+        match select(join(server1.run(), server2.run()), join(client.run(), body)).await {
+            Either::Second(_) => assert_eq!(control.state(), RunnerState::Stopped),
+            Either::First(_) => panic!("runner returned"),
+        }
+    });
+}
+
+#[test]
+fn flush() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch) = (harness::channel(4, 4), harness::channel(4, 4));
+        let (client, server) = (client(client_stack, cch), server(server_stack, sch));
+        serve(&client, &server, async {
+            assert!(matches!(wait_event(&sch.read_channel).await, CallbackEnum::Conn));
+            assert!(matches!(wait_event(&cch.read_channel).await, CallbackEnum::Conn));
+            assert!(cch.write_channel.send_str("hello").await.is_ok());
+            assert!(cch.write_channel.flush().await.is_ok());
+            assert!(cch.write_channel.is_empty().await);
+            assert_eq!(cch.stats().bytes_out, 5);
+            assert_eq!(read_exact(&sch.read_channel, 5).await, b"hello");
+        }).await
+    });
+
+    // the multi-connection runner flushes every target connection, the data is not lost when the runner is dropped right after flush
+    harness::run(|client_stack, server_stack| async move {
+        let (cch1, cch2, sch) = (harness::channel(4, 4), harness::channel(4, 4), harness::channel(8, 4));
+        let (client1, client2) = (client(client_stack, cch1), client(client_stack, cch2));
+        let runner = tcp_server::build_multi_runner::<2, RC_SZ, WC_SZ>(server_stack, PORT, sch, leak(SocketState::<3, 4096, 4096, 1024>::new()));
+
+        let body = async {
+            let write = async {
+                wait_conn(&cch1.read_channel).await;
+                wait_conn(&cch2.read_channel).await;
+                sch.write_channel.send_bytes(&[7; 200]).await.unwrap();
+                assert!(sch.write_channel.flush().await.is_ok());
+                assert_eq!(sch.stats().bytes_out, 400);
+            };
+            assert!(matches!(select(runner.run(), write).await, Either::Second(_)));
+            assert_eq!(read_exact(&cch1.read_channel, 200).await, [7; 200]);
+            assert_eq!(read_exact(&cch2.read_channel, 200).await, [7; 200]);
+        };
+        match select(join(client1.run(), client2.run()), body).await {
+            Either::Second(_) => {}
+            Either::First(_) => panic!("runner returned"),
+        }
+    });
+}
+
+/// server callback, every connection echoes all data it received so far
+struct Accumulator(RefCell<Vec<Event>>);
+
+/// support tcp server callback
+impl TcpServerCallBack for Accumulator {
+    type T = Vec<u8>;
+
+    async fn conn<const CN: usize>(&self, _endpoint: IpEndpoint, _wch: &WriteChannel<'_, CN>, t: &mut Vec<u8>) {
+        t.clear();
+        self.0.borrow_mut().push(Event::Conn);
+    }
+
+    async fn dis_conn(&self, _endpoint: IpEndpoint, _t: &mut Vec<u8>) {
+        self.0.borrow_mut().push(Event::DisConn);
+    }
+
+    async fn recv<const CN: usize>(&self, endpoint: IpEndpoint, buf: &[u8], wch: &WriteChannel<'_, CN>, t: &mut Vec<u8>) {
+        t.extend_from_slice(buf);
+        wch.send_bytes_to(t, endpoint).await.unwrap();
+    }
+
+    async fn err(&self, err: SocketErr, _t: &mut Vec<u8>) {
+        self.0.borrow_mut().push(Event::Err(format!("{err:?}")));
+    }
+}
+
+/// wait until the client is connected<br />
+/// the server has one listener, a client connecting before it is created again is reset and reconnects
+async fn wait_conn(rch: &ReadChannel<'_, RC_SZ>) {
+    while !matches!(wait_event(rch).await, CallbackEnum::Conn) {}
+}
+
+#[test]
+fn multi_conn_state() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch1, cch2, sch) = (harness::channel(4, 4), harness::channel(4, 4), harness::channel(8, 8));
+        let (client1, client2) = (client(client_stack, cch1), client(client_stack, cch2));
+        let cb = leak(Accumulator(RefCell::new(Vec::new())));
+        let server = TcpServer::new(server_stack, leak(SocketState::<3, 4096, 4096, 1024>::new()), PORT, cb);
+        let (runner, mut callback) = server.create_multi::<2, RC_SZ, WC_SZ>(sch);
+        let (mut server_t, mut conns_t) = (Vec::new(), [Vec::new(), Vec::new()]);
+
+        let body = async {
+            wait_conn(&cch1.read_channel).await;
+            wait_conn(&cch2.read_channel).await;
+            // every connection only sees its own data
+            cch1.write_channel.send_str("a").await.unwrap();
+            assert_eq!(read_exact(&cch1.read_channel, 1).await, b"a");
+            cch2.write_channel.send_str("b").await.unwrap();
+            assert_eq!(read_exact(&cch2.read_channel, 1).await, b"b");
+            cch1.write_channel.send_str("c").await.unwrap();
+            assert_eq!(read_exact(&cch1.read_channel, 2).await, b"ac");
+
+            // the remote close is detected, the reconnected client gets a reset slot
+            cch1.write_channel.close(None).await.unwrap();
+            assert!(matches!(wait_event(&cch1.read_channel).await, CallbackEnum::Disconnect));
+            wait_conn(&cch1.read_channel).await;
+            cch1.write_channel.send_str("d").await.unwrap();
+            assert_eq!(read_exact(&cch1.read_channel, 1).await, b"d");
+            cch2.write_channel.send_str("e").await.unwrap();
+            assert_eq!(read_exact(&cch2.read_channel, 2).await, b"be");
+        };
+        let server = join(runner.run(), callback.run(&mut server_t, &mut conns_t));
+        match select3(join(client1.run(), client2.run()), server, body).await {
+            Either3::Third(_) => {}
+            _ => panic!("runner returned"),
+        }
+        // the closed connection may be reported as reset
+        let events: Vec<_> = cb.0.borrow_mut().drain(..).filter(|e| !matches!(e, Event::Err(_))).collect();
+        assert_eq!(events, [Event::Conn, Event::Conn, Event::DisConn, Event::Conn]);
+    });
+}
+
+#[test]
+fn multi_read_full() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch1, cch2, sch) = (harness::channel(4, 4), harness::channel(4, 4), harness::channel(3, 4));
+        sch.read_channel.overflow_policy(OverflowPolicy::Block);
+        let (client1, client2) = (client(client_stack, cch1), client(client_stack, cch2));
+        let runner = tcp_server::build_multi_runner::<2, RC_SZ, WC_SZ>(server_stack, PORT, sch, leak(SocketState::<3, 4096, 4096, 1024>::new()));
+
+        let body = async {
+            wait_conn(&cch1.read_channel).await;
+            wait_conn(&cch2.read_channel).await;
+            // nobody reads the server channel, two conn and one msg fill it, the next msg waits in the socket
+            cch1.write_channel.send_str("x").await.unwrap();
+            Timer::after(Duration::from_millis(50)).await;
+            cch1.write_channel.send_str("y").await.unwrap();
+            Timer::after(Duration::from_millis(50)).await;
+
+            // the waiting connection does not stop writing
+            sch.write_channel.send_str("hi").await.unwrap();
+            assert!(sch.write_channel.flush().await.is_ok());
+            assert_eq!(read_exact(&cch1.read_channel, 2).await, b"hi");
+            assert_eq!(read_exact(&cch2.read_channel, 2).await, b"hi");
+
+            assert!(matches!(wait_event(&sch.read_channel).await, CallbackEnum::Conn));
+            assert!(matches!(wait_event(&sch.read_channel).await, CallbackEnum::Conn));
+            assert_eq!(read_exact(&sch.read_channel, 2).await, b"xy");
+        };
+        match select3(join(client1.run(), client2.run()), runner.run(), body).await {
+            Either3::Third(_) => {}
+            _ => panic!("runner returned"),
+        }
+    });
+}
+
+#[test]
+fn multi_stalled_peer() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch1, cch2, sch) = (harness::channel(4, 4), harness::channel(4, 4), harness::channel(8, 4));
+        // nobody reads the first client, its socket stops taking data
+        cch1.read_channel.overflow_policy(OverflowPolicy::Block);
+        cch2.read_channel.overflow_policy(OverflowPolicy::Block);
+        sch.write_channel.overflow_policy(OverflowPolicy::Block);
+        let (client1, client2) = (client(client_stack, cch1), client(client_stack, cch2));
+        let mut runner = tcp_server::build_multi_runner::<2, RC_SZ, WC_SZ>(server_stack, PORT, sch, leak(SocketState::<3, 4096, 4096, 1024>::new()));
+        runner.write_timeout(Some(Duration::from_millis(200)));
+
+        let body = async {
+            wait_conn(&cch1.read_channel).await;
+            wait_conn(&cch2.read_channel).await;
+            while !matches!(wait_event(&sch.read_channel).await, CallbackEnum::Conn) {}
+            while !matches!(wait_event(&sch.read_channel).await, CallbackEnum::Conn) {}
+
+            // the stalled connection is closed, the other one gets all data
+            let write = async {
+                for _ in 0..20 { sch.write_channel.send_bytes(&[7; 1000]).await.unwrap(); }
+            };
+            let (_, data) = join(write, read_exact(&cch2.read_channel, 20000)).await;
+            assert_eq!(data, [7; 20000]);
+            assert!(matches!(wait_event(&sch.read_channel).await, CallbackEnum::Err(SocketErr::WriteTimeout)));
+            assert!(matches!(wait_event(&sch.read_channel).await, CallbackEnum::Disconnect));
+        };
+        match select3(join(client1.run(), client2.run()), runner.run(), body).await {
+            Either3::Third(_) => {}
+            _ => panic!("runner returned"),
+        }
+    });
+}
+
+#[test]
+fn multi_table_full() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch1, cch2, sch) = (harness::channel(4, 4), harness::channel(4, 4), harness::channel(8, 8));
+        let (client1, client2) = (client(client_stack, cch1), client(client_stack, cch2));
+        let cb = leak(Accumulator(RefCell::new(Vec::new())));
+        let runner = tcp_server::build_multi_runner::<2, RC_SZ, WC_SZ>(server_stack, PORT, sch, leak(SocketState::<3, 4096, 4096, 1024>::new()));
+        // the callback table is smaller than the connection table of the runner
+        let mut callback = MultiCallbackRunner::<1, RC_SZ, WC_SZ, _>::new(sch, cb);
+        let (mut server_t, mut conns_t) = (Vec::new(), [Vec::new()]);
+
+        let body = async {
+            wait_conn(&cch1.read_channel).await;
+            let second = async {
+                // the second connection does not fit in the table and is closed
+                wait_conn(&cch2.read_channel).await;
+                while !matches!(wait_event(&cch2.read_channel).await, CallbackEnum::Disconnect) {}
+
+                // the first connection is served as before
+                cch1.write_channel.send_str("a").await.unwrap();
+                assert_eq!(read_exact(&cch1.read_channel, 1).await, b"a");
+            };
+            match select(client2.run(), second).await {
+                Either::Second(_) => {}
+                Either::First(_) => panic!("runner returned"),
+            }
+        };
+        let server = join(runner.run(), callback.run(&mut server_t, &mut conns_t));
+        match select3(client1.run(), server, body).await {
+            Either3::Third(_) => {}
+            _ => panic!("runner returned"),
+        }
+        assert_eq!(cb.0.borrow()[..2], [Event::Conn, Event::Err(format!("{:?}", SocketErr::TooManyConnections))]);
+    });
+}
+
+#[test]
+fn multi_control() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch1, cch2, sch) = (harness::channel(4, 4), harness::channel(4, 4), harness::channel(8, 4));
+        let (client1, client2) = (client(client_stack, cch1), client(client_stack, cch2));
+        let mut runner = tcp_server::build_multi_runner::<2, RC_SZ, WC_SZ>(server_stack, PORT, sch, leak(SocketState::<3, 4096, 4096, 1024>::new()));
+        let control = leak(Control::new());
+        runner.control(control);
+
+        let body = async {
+            wait_conn(&cch1.read_channel).await;
+            wait_conn(&cch2.read_channel).await;
+            assert_eq!(control.state(), RunnerState::Connected);
+
+            // all connections are closed, the clients reconnect
+            control.disconnect();
+            // the closed socket is freed at once, so the client may see a reset first
+            while !matches!(wait_event(&cch1.read_channel).await, CallbackEnum::Disconnect) {}
+            while !matches!(wait_event(&cch2.read_channel).await, CallbackEnum::Disconnect) {}
+            wait_conn(&cch1.read_channel).await;
+            wait_conn(&cch2.read_channel).await;
+
+            // the queued data is written to all connections before the runner returns
+            sch.write_channel.send_str("bye").await.unwrap();
+            control.stop();
+            assert_eq!(read_exact(&cch1.read_channel, 3).await, b"bye");
+            assert_eq!(read_exact(&cch2.read_channel, 3).await, b"bye");
+        };
+        match select(join(client1.run(), client2.run()), join(runner.run(), body)).await {
+            Either::Second(_) => assert_eq!(control.state(), RunnerState::Stopped),
+            Either::First(_) => panic!("runner returned"),
+        }
+    });
+}
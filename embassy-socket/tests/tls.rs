@@ -0,0 +1,93 @@
+//! tls client handshake failure over the in-memory link
+#![cfg(feature = "tls")]
+
+mod harness;
+
+use embassy_futures::select::{select4, Either4};
+use embassy_socket::channel::callback_enum::CallbackEnum;
+use embassy_socket::channel::SocketChannel;
+use embassy_socket::connection::socket_state::SocketState;
+use embassy_socket::err::SocketErr;
+use embassy_socket::tcp_client;
+use embassy_socket::tcp_client::reconnect::ReconnectPolicy;
+use embassy_socket::tcp_server;
+use embassy_socket::tls::embedded_tls::{Aes128GcmSha256, TlsConfig};
+use embassy_socket::tls::rand_core::{CryptoRng, Error, RngCore};
+use embassy_socket::tls::TlsSession;
+use harness::{leak, wait_event, SERVER_IP};
+
+/// socket state of the tests
+type State = SocketState<1, 4096, 4096, 1024>;
+/// socket channel of the tests
+type Channel = SocketChannel<'static, 1024, 1024>;
+/// server port
+const PORT: u16 = 443;
+
+/// xorshift rng, only for tests
+struct TestRng(u64);
+
+/// support rng
+impl RngCore for TestRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for b in dest { *b = self.next_u64() as u8; }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// support crypto rng
+impl CryptoRng for TestRng {}
+
+#[test]
+fn handshake_err() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch): (&Channel, &Channel) = (harness::channel(8, 8), harness::channel(8, 8));
+        let mut client = tcp_client::build_runner(client_stack, SERVER_IP, PORT, cch, leak(State::new()));
+        client.reconnect_policy(ReconnectPolicy::immediately());
+        let server = tcp_server::build_runner(server_stack, PORT, sch, leak(State::new()));
+        let config = TlsConfig::<Aes128GcmSha256>::new().with_server_name("example.com");
+        let mut session: TlsSession<_, _> = TlsSession::new(config, TestRng(0x2545_F491_4F6C_DD1D), leak([0; 16640]), leak([0; 4096]));
+
+        // plain echo server, the client hello is echoed back
+        let echo = async {
+            loop {
+                let msg = sch.read_channel.wait_read_ref().await;
+                if matches!(msg.callback_enum(), CallbackEnum::Recv) { sch.write_channel.send_bytes(msg.as_bytes()).await.ok(); }
+            }
+        };
+
+        // every handshake fails and is reported, then the client reconnects, the connection is never reported
+        let body = async {
+            let mut handshakes = 0;
+            loop {
+                match wait_event(&cch.read_channel).await {
+                    CallbackEnum::Err(SocketErr::TlsError(_)) => handshakes += 1,
+                    // the first attempts may be refused before the server listens
+                    CallbackEnum::Err(SocketErr::ConnectError(_)) => {}
+                    CallbackEnum::Reconnect { .. } if handshakes == 2 => return,
+                    CallbackEnum::Reconnect { .. } => {}
+                    other => panic!("unexpected event {other:?}"),
+                }
+            }
+        };
+
+        match select4(client.run_tls(&mut session), server.run(), echo, body).await {
+            Either4::Fourth(_) => {}
+            _ => panic!("runner returned"),
+        }
+    });
+}
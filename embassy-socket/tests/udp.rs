@@ -0,0 +1,63 @@
+//! udp runner over the in-memory link
+
+mod harness;
+
+use embassy_futures::join::join;
+use embassy_futures::select::{select, Either};
+use embassy_net::{IpAddress, IpEndpoint};
+use embassy_socket::channel::callback_enum::CallbackEnum;
+use embassy_socket::connection::udp_state::UdpSocketState;
+use embassy_socket::control::{Control, State as RunnerState};
+use embassy_socket::udp;
+use embassy_time::{Duration, Timer};
+use harness::{leak, read_exact, wait_event, SERVER_IP};
+
+/// udp state of the tests
+type State = UdpSocketState<1, 4, 1024, 1024, 512>;
+/// server port
+const PORT: u16 = 7000;
+
+#[test]
+fn control() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch) = (harness::channel::<512, 512>(4, 4), harness::channel::<512, 512>(4, 4));
+        let client = udp::build_runner(client_stack, 0, cch, leak(State::new()));
+        let mut server = udp::build_runner(server_stack, PORT, sch, leak(State::new()));
+        let control = leak(Control::new());
+        server.control(control);
+        let endpoint = IpEndpoint::new(IpAddress::Ipv4(SERVER_IP), PORT);
+
+        let body = async {
+            assert!(matches!(wait_event(&cch.read_channel).await, CallbackEnum::Conn));
+            assert!(matches!(wait_event(&sch.read_channel).await, CallbackEnum::Conn));
+            assert_eq!(control.state(), RunnerState::Connected);
+            cch.write_channel.send_str_to("a", endpoint).await.unwrap();
+            assert_eq!(read_exact(&sch.read_channel, 1).await, b"a");
+
+            // the socket is unbound until connect, datagrams sent meanwhile are lost
+            control.disconnect();
+            assert!(matches!(wait_event(&sch.read_channel).await, CallbackEnum::Disconnect));
+            assert_eq!(control.state(), RunnerState::Paused);
+            cch.write_channel.send_str_to("b", endpoint).await.unwrap();
+            Timer::after(Duration::from_millis(50)).await;
+            control.connect();
+            assert!(matches!(wait_event(&sch.read_channel).await, CallbackEnum::Conn));
+            cch.write_channel.send_str_to("c", endpoint).await.unwrap();
+            let client_endpoint = {
+                let msg = sch.read_channel.wait_read_ref().await;
+                assert!(matches!(msg.callback_enum(), CallbackEnum::Recv));
+                assert_eq!(msg.as_bytes(), b"c");
+                msg.endpoint()
+            };
+
+            // the queued datagram is sent before the runner returns
+            sch.write_channel.send_str_to("d", client_endpoint).await.unwrap();
+            control.stop();
+            assert_eq!(read_exact(&cch.read_channel, 1).await, b"d");
+        };
+        match select(client.run(), join(server.run(), body)).await {
+            Either::Second(_) => assert_eq!(control.state(), RunnerState::Stopped),
+            Either::First(_) => panic!("runner returned"),
+        }
+    });
+}
@@ -0,0 +1,302 @@
+//! websocket handshake, frame codec, and client/server over the in-memory link
+#![cfg(feature = "websocket")]
+
+mod harness;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use embassy_futures::select::{select4, Either4};
+use embassy_socket::channel::overflow_policy::OverflowPolicy;
+use embassy_socket::connection::socket_state::SocketState;
+use embassy_socket::err::SocketErr;
+use embassy_socket::http::request::Request;
+use embassy_socket::tcp_client;
+use embassy_socket::tcp_client::callback_runner::CallbackRunner as ClientCallbackRunner;
+use embassy_socket::tcp_client::reconnect::ReconnectPolicy;
+use embassy_socket::tcp_server;
+use embassy_socket::tcp_server::callback_runner::CallbackRunner as ServerCallbackRunner;
+use embassy_socket::websocket::callback::WsCallBack;
+use embassy_socket::websocket::client::WsClient;
+use embassy_socket::websocket::frame::{encode_header, Event, Opcode, WsDecoder, MAX_HEADER};
+use embassy_socket::websocket::handshake::accept_key;
+use embassy_socket::websocket::sender::WsSender;
+use embassy_socket::websocket::server::WsServer;
+use embassy_socket::websocket::{Message, WsErr, WsState, CLOSE_INTERNAL_ERROR, CLOSE_NORMAL};
+use embassy_time::{Duration, Timer};
+use harness::{leak, SERVER_IP};
+
+/// socket state of the tests
+type State = SocketState<1, 4096, 4096, 1024>;
+
+/// decode all bytes, one byte at a time, returns the events in debug format
+fn decode_all<const N: usize>(decoder: &mut WsDecoder<N>, bytes: &[u8]) -> Vec<String> {
+    let mut events = Vec::new();
+    for b in bytes.chunks(1) {
+        let mut b = b;
+        while !b.is_empty() {
+            let (used, event) = decoder.decode(b);
+            b = &b[used..];
+            if let Some(event) = event { events.push(format!("{event:?}")); }
+        }
+    }
+    events
+}
+
+/// encode one frame
+fn frame(fin: bool, opcode: Opcode, payload: &[u8], mask: Option<[u8; 4]>) -> Vec<u8> {
+    let mut header = [0; MAX_HEADER];
+    let len = encode_header(fin, opcode, payload.len(), mask, &mut header);
+    let mut frame = header[..len].to_vec();
+    frame.extend(payload.iter().enumerate().map(|(i, b)| mask.map_or(*b, |mask| b ^ mask[i % 4])));
+    frame
+}
+
+#[test]
+fn accept_key_rfc() {
+    // RFC 6455 section 1.3
+    assert_eq!(&accept_key(b"dGhlIHNhbXBsZSBub25jZQ=="), b"s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+}
+
+#[test]
+fn masked_frame() {
+    // RFC 6455 section 5.7, a single-frame masked text message
+    let mut decoder = WsDecoder::<16>::new(true);
+    let events = decode_all(&mut decoder, &[0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58]);
+    assert_eq!(events, [r#"Ok(Message(Text("Hello")))"#]);
+}
+
+#[test]
+fn fragments_with_ping() {
+    let mut decoder = WsDecoder::<16>::new(false);
+    let mut bytes = frame(false, Opcode::Text, b"Hel", None);
+    bytes.extend(frame(true, Opcode::Ping, b"p", None));
+    bytes.extend(frame(true, Opcode::Continuation, b"lo", None));
+    bytes.extend(frame(true, Opcode::Close, &CLOSE_NORMAL.to_be_bytes(), None));
+    let events = decode_all(&mut decoder, &bytes);
+    assert_eq!(events, ["Ok(Ping([112]))", r#"Ok(Message(Text("Hello")))"#, "Ok(Close(Some(1000)))"]);
+}
+
+#[test]
+fn extended_len_round_trip() {
+    let mut decoder = WsDecoder::<512>::new(true);
+    let payload: Vec<u8> = (0..300).map(|i| i as u8).collect();
+    let bytes = frame(true, Opcode::Binary, &payload, Some([1, 2, 3, 4]));
+    let (used, event) = decoder.decode(&bytes);
+    assert_eq!(used, bytes.len());
+    let Some(Ok(Event::Message(Message::Binary(decoded)))) = event else { panic!("binary message expected") };
+    assert_eq!(decoded, payload);
+}
+
+#[test]
+fn too_large_and_protocol_error() {
+    let mut decoder = WsDecoder::<4>::new(false);
+    let mut bytes = frame(true, Opcode::Binary, b"12345", None);
+    bytes.extend(frame(true, Opcode::Text, b"ok", None));
+    let events = decode_all(&mut decoder, &bytes);
+    assert_eq!(events, ["Err(FrameTooLarge)", r#"Ok(Message(Text("ok")))"#]);
+
+    // server frames must not be masked, the rest of the stream is not decoded
+    let mut bytes = frame(true, Opcode::Text, b"ok", Some([1, 2, 3, 4]));
+    bytes.extend(frame(true, Opcode::Text, b"ok", None));
+    let events = decode_all(&mut decoder, &bytes);
+    assert_eq!(events, [format!("{:?}", Err::<(), _>(SocketErr::WsError(WsErr::Protocol)))]);
+}
+
+/// websocket event recorded by the callbacks
+#[derive(Debug, PartialEq)]
+enum WsEvent {
+    /// handshake finished
+    Open,
+    /// text message
+    Text(String),
+    /// binary message
+    Binary(Vec<u8>),
+    /// closed with code
+    Close(Option<u16>),
+    /// error in debug format
+    Err(String),
+}
+
+/// websocket callback, records events, the server echoes messages and the client says hello<br />
+/// the server holds the text `wait` for a while, so that the read channel fills up
+struct Recorder {
+    /// recorded events
+    events: Rc<RefCell<Vec<WsEvent>>>,
+    /// path accepted by the server
+    path: &'static str,
+}
+
+/// support websocket callback
+impl WsCallBack for Recorder {
+    type T = u32;
+
+    async fn accept(&self, req: &Request<'_>, _t: &mut u32) -> bool {
+        req.path == self.path
+    }
+
+    async fn open<const CN: usize>(&self, ws: &WsSender<'_, '_, CN>, _t: &mut u32) {
+        self.events.borrow_mut().push(WsEvent::Open);
+        if ws.is_client() { ws.text("hello").await.unwrap(); }
+    }
+
+    async fn message<const CN: usize>(&self, msg: Message<'_>, ws: &WsSender<'_, '_, CN>, t: &mut u32) {
+        *t += 1;
+        match msg {
+            Message::Text(text) => self.events.borrow_mut().push(WsEvent::Text(text.into())),
+            Message::Binary(bytes) => self.events.borrow_mut().push(WsEvent::Binary(bytes.into())),
+        }
+        if ws.is_client() { return; }
+        if let Message::Text("wait") = msg {
+            Timer::after(Duration::from_millis(200)).await;
+            return;
+        }
+
+        // echo, the text is answered with a large binary message
+        match msg {
+            Message::Text(text) => ws.binary(&text.as_bytes().repeat(100)).await.unwrap(),
+            Message::Binary(bytes) => ws.binary(bytes).await.unwrap(),
+        }
+    }
+
+    async fn close(&self, code: Option<u16>, _t: &mut u32) {
+        self.events.borrow_mut().push(WsEvent::Close(code));
+    }
+
+    async fn err(&self, err: SocketErr, _t: &mut u32) {
+        self.events.borrow_mut().push(WsEvent::Err(format!("{err:?}")));
+    }
+}
+
+/// wait until events has len events
+async fn wait_events(events: &RefCell<Vec<WsEvent>>, len: usize) {
+    while events.borrow().len() < len { Timer::after(Duration::from_millis(5)).await; }
+}
+
+/// websocket events without the socket errors, the tcp connection closed by the peer may be reported as reset
+fn ws_events(events: &RefCell<Vec<WsEvent>>) -> Vec<WsEvent> {
+    events.borrow_mut().drain(..).filter(|e| !matches!(e, WsEvent::Err(e) if e.starts_with("TcpError"))).collect()
+}
+
+#[test]
+fn client_server() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch) = (harness::channel::<256, 256>(8, 8), harness::channel::<256, 256>(8, 8));
+        cch.read_channel.overflow_policy(OverflowPolicy::Block);
+        sch.read_channel.overflow_policy(OverflowPolicy::Block);
+        let mut client = tcp_client::build_runner(client_stack, SERVER_IP, 1234, cch, leak(State::new()));
+        client.reconnect_policy(ReconnectPolicy::immediately());
+        let server = tcp_server::build_runner(server_stack, 1234, sch, leak(State::new()));
+
+        let (client_events, server_events) = (Rc::new(RefCell::new(Vec::new())), Rc::new(RefCell::new(Vec::new())));
+        let ws_client = WsClient::<_, 1024>::new("10.0.0.2:1234", "/ws", 7, Recorder { events: client_events.clone(), path: "" });
+        let mut client_cb = ClientCallbackRunner::new(cch, ws_client);
+        let ws_server = leak(WsServer::<_, 1024>::new(Recorder { events: server_events.clone(), path: "/ws" }));
+        let mut server_cb = ServerCallbackRunner::new(sch, ws_server);
+        let mut server_state = WsState::new();
+
+        let body = async {
+            wait_events(&client_events, 2).await;
+            assert_eq!(client_events.borrow()[1], WsEvent::Binary(b"hello".repeat(100)));
+
+            // close from the client, the server answers and closes the tcp connection, the client reconnects
+            WsSender::client(&cch.write_channel, 1).close(CLOSE_NORMAL, "bye").await.unwrap();
+            wait_events(&client_events, 5).await;
+            while !matches!(client_events.borrow().last(), Some(WsEvent::Binary(_))) { Timer::after(Duration::from_millis(5)).await; }
+        };
+        let callbacks = embassy_futures::join::join(client_cb.run(), server_cb.run(&mut server_state));
+        match select4(client.run(), server.run(), callbacks, body).await {
+            Either4::Fourth(_) => {}
+            _ => panic!("runner returned"),
+        }
+
+        assert_eq!(ws_events(&client_events), [
+            WsEvent::Open,
+            WsEvent::Binary(b"hello".repeat(100)),
+            WsEvent::Close(Some(CLOSE_NORMAL)),
+            WsEvent::Open,
+            WsEvent::Binary(b"hello".repeat(100)),
+        ]);
+        assert_eq!(ws_events(&server_events)[..3], [WsEvent::Open, WsEvent::Text("hello".into()), WsEvent::Close(Some(CLOSE_NORMAL))]);
+    });
+}
+
+#[test]
+fn dropped_closes() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch) = (harness::channel::<256, 256>(8, 8), harness::channel::<256, 256>(4, 8));
+        cch.read_channel.overflow_policy(OverflowPolicy::Block);
+        let mut client = tcp_client::build_runner(client_stack, SERVER_IP, 1234, cch, leak(State::new()));
+        client.reconnect_policy(ReconnectPolicy::immediately());
+        let server = tcp_server::build_runner(server_stack, 1234, sch, leak(State::new()));
+
+        let (client_events, server_events) = (Rc::new(RefCell::new(Vec::new())), Rc::new(RefCell::new(Vec::new())));
+        let ws_client = WsClient::<_, 1024>::new("10.0.0.2:1234", "/ws", 7, Recorder { events: client_events.clone(), path: "" });
+        let mut client_cb = ClientCallbackRunner::new(cch, ws_client);
+        let ws_server = leak(WsServer::<_, 1024>::new(Recorder { events: server_events.clone(), path: "/ws" }));
+        let mut server_cb = ServerCallbackRunner::new(sch, ws_server);
+        let mut server_state = WsState::new();
+
+        let body = async {
+            wait_events(&client_events, 2).await;
+            // the server holds `wait`, the frames after it do not fit in the server read channel and some are dropped
+            let ws = WsSender::client(&cch.write_channel, 1);
+            ws.text("wait").await.unwrap();
+            for _ in 0..8 {
+                ws.binary(&[7; 100]).await.unwrap();
+                Timer::after(Duration::from_millis(20)).await;
+            }
+
+            // the server closes the websocket and the tcp connection, the client reconnects and opens again
+            let closed = WsEvent::Close(Some(CLOSE_INTERNAL_ERROR));
+            while !client_events.borrow().iter().skip_while(|e| **e != closed).any(|e| *e == WsEvent::Open) {
+                Timer::after(Duration::from_millis(5)).await;
+            }
+        };
+        let callbacks = embassy_futures::join::join(client_cb.run(), server_cb.run(&mut server_state));
+        match select4(client.run(), server.run(), callbacks, body).await {
+            Either4::Fourth(_) => {}
+            _ => panic!("runner returned"),
+        }
+
+        assert!(sch.stats().read_dropped > 0);
+        let server_events = ws_events(&server_events);
+        assert_eq!(server_events[..3], [WsEvent::Open, WsEvent::Text("hello".into()), WsEvent::Text("wait".into())]);
+        // the lost frames are not a close of the peer
+        assert_eq!(server_events.iter().find(|e| matches!(e, WsEvent::Close(_))), Some(&WsEvent::Close(None)));
+    });
+}
+
+#[test]
+fn refused_path() {
+    harness::run(|client_stack, server_stack| async move {
+        let (cch, sch) = (harness::channel::<256, 256>(8, 8), harness::channel::<256, 256>(8, 8));
+        let client = tcp_client::build_runner(client_stack, SERVER_IP, 1234, cch, leak(State::new()));
+        let server = tcp_server::build_runner(server_stack, 1234, sch, leak(State::new()));
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut server_cb = ServerCallbackRunner::new(sch, leak(WsServer::<_, 1024>::new(Recorder { events: events.clone(), path: "/ws" })));
+        let mut server_state = WsState::new();
+
+        let body = async {
+            let mut head = Vec::new();
+            // the client is a raw tcp client, the handshake to an unknown path is answered with 403
+            loop {
+                let msg = cch.read_channel.wait_read_ref().await;
+                if matches!(msg.callback_enum(), embassy_socket::channel::callback_enum::CallbackEnum::Conn) {
+                    drop(msg);
+                    cch.write_channel.send_str("GET /other HTTP/1.1\r\nHost: x\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                        Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n").await.unwrap();
+                    continue;
+                }
+                head.extend_from_slice(msg.as_bytes());
+                if head.ends_with(b"\r\n\r\n") { break; }
+            }
+            assert!(head.starts_with(b"HTTP/1.1 403 Forbidden\r\n"));
+            wait_events(&events, 1).await;
+        };
+        match select4(client.run(), server.run(), server_cb.run(&mut server_state), body).await {
+            Either4::Fourth(_) => {}
+            _ => panic!("runner returned"),
+        }
+        assert_eq!(*events.borrow(), [WsEvent::Err(format!("{:?}", SocketErr::WsError(WsErr::HandshakeFailed)))]);
+    });
+}